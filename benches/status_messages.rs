@@ -0,0 +1,67 @@
+//! Compares reporting static and owned status messages through a `ProgressTrackedImValProm`.
+//! Run with `cargo bench --bench status_messages`
+use lazy_async_promise::{ImmediateValuePromise, Progress, ProgressTrackedImValProm, StringStatus};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const MESSAGES: usize = 100_000;
+const BATCH: usize = 64;
+const RUNS: usize = 10;
+
+fn report_and_poll(message: fn(usize) -> StringStatus, coalesce: bool) -> Duration {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    runtime.block_on(async {
+        let start = Instant::now();
+        let mut promise = ProgressTrackedImValProm::new(
+            |s| {
+                ImmediateValuePromise::new(async move {
+                    for i in 0..MESSAGES {
+                        s.send(message(i)).await.unwrap();
+                    }
+                    Ok(())
+                })
+            },
+            BATCH,
+        );
+        if coalesce {
+            promise = promise.with_progress_coalescing();
+        }
+        while promise.poll_state().is_updating() {
+            tokio::task::yield_now().await;
+        }
+        black_box(promise.status_history().len());
+        start.elapsed()
+    })
+}
+
+fn static_status(i: usize) -> StringStatus {
+    StringStatus::from_str(
+        Progress::from_fraction(i as f64, MESSAGES as f64),
+        "hashing",
+    )
+}
+
+fn owned_status(i: usize) -> StringStatus {
+    StringStatus::from_string(
+        Progress::from_fraction(i as f64, MESSAGES as f64),
+        "hashing".to_string(),
+    )
+}
+
+fn bench(name: &str, message: fn(usize) -> StringStatus, coalesce: bool) {
+    let fastest = (0..RUNS)
+        .map(|_| report_and_poll(message, coalesce))
+        .min()
+        .unwrap_or_default();
+    let per_message = fastest.as_nanos() as f64 / MESSAGES as f64;
+    println!("{name:<24} {per_message:>8.1} ns/message");
+}
+
+fn main() {
+    bench("static", static_status, false);
+    bench("owned", owned_status, false);
+    bench("static, coalesced", static_status, true);
+    bench("owned, coalesced", owned_status, true);
+}
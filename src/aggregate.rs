@@ -0,0 +1,66 @@
+//! Running aggregates over the items of a [`crate::LazyVecPromise`], see `with_aggregate`.
+//! The accumulator is type-erased, so the promise doesn't need another type parameter.
+use std::any::Any;
+
+type Fold<T> = Box<dyn FnMut(&mut dyn Any, &T) + Send>;
+
+pub(crate) struct Aggregate<T> {
+    init: Box<dyn Fn() -> Box<dyn Any + Send> + Send>,
+    value: Box<dyn Any + Send>,
+    fold: Fold<T>,
+    /// the data was changed in place, the value is recomputed by the next poll
+    stale: bool,
+}
+
+impl<T> Aggregate<T> {
+    pub(crate) fn new<A: Clone + Send + 'static>(
+        init: A,
+        mut fold: impl FnMut(&mut A, &T) + Send + 'static,
+    ) -> Self {
+        Aggregate {
+            value: Box::new(init.clone()),
+            init: Box::new(move || Box::new(init.clone())),
+            fold: Box::new(move |value, item| {
+                if let Some(value) = value.downcast_mut::<A>() {
+                    fold(value, item);
+                }
+            }),
+            stale: false,
+        }
+    }
+
+    /// The accumulator, `None` if it is not an `A`
+    pub(crate) fn get<A: 'static>(&self) -> Option<&A> {
+        self.value.downcast_ref()
+    }
+
+    pub(crate) fn add<'a>(&mut self, items: impl IntoIterator<Item = &'a T>)
+    where
+        T: 'a,
+    {
+        for item in items {
+            (self.fold)(self.value.as_mut(), item);
+        }
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.value = (self.init)();
+        self.stale = false;
+    }
+
+    pub(crate) fn recompute(&mut self, data: &[T]) {
+        self.reset();
+        self.add(data);
+    }
+
+    /// Recompute with the next [`Self::refresh`]
+    pub(crate) fn invalidate(&mut self) {
+        self.stale = true;
+    }
+
+    pub(crate) fn refresh(&mut self, data: &[T]) {
+        if self.stale {
+            self.recompute(data);
+        }
+    }
+}
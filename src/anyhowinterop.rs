@@ -0,0 +1,102 @@
+use std::error::Error;
+use std::fmt::{self, Debug, Display, Formatter};
+#[cfg(feature = "tokio")]
+use std::future::Future;
+
+use crate::BoxedSendError;
+#[cfg(feature = "tokio")]
+use crate::ImmediateValuePromise;
+
+/// Keeps the original anyhow error, including its context and backtrace
+struct AnyhowError(anyhow::Error);
+
+impl Debug for AnyhowError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&self.0, f)
+    }
+}
+
+impl Display for AnyhowError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+impl Error for AnyhowError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.0.source()
+    }
+}
+
+impl BoxedSendError {
+    /// Convert an [`anyhow::Error`], its context chain stays available via [`BoxedSendError::chain`].
+    /// A `From` implementation is not possible since it would collide with the blanket implementation for errors.
+    pub fn from_anyhow(error: anyhow::Error) -> Self {
+        BoxedSendError::new(Box::new(AnyhowError(error)))
+    }
+
+    /// Convert into an [`anyhow::Error`]. Errors created by [`BoxedSendError::from_anyhow`] are returned as they were,
+    /// for all others the chain is kept as context messages.
+    pub fn into_anyhow(self) -> anyhow::Error {
+        if self.0.is::<AnyhowError>() {
+            let Ok(original) = self.0.downcast::<AnyhowError>() else {
+                unreachable!();
+            };
+            return original.0;
+        }
+        let mut messages: Vec<_> = self.chain().map(|error| error.to_string()).collect();
+        let mut error = anyhow::Error::msg(messages.pop().unwrap_or_default());
+        while let Some(message) = messages.pop() {
+            error = error.context(message);
+        }
+        error
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<T: Send + 'static> ImmediateValuePromise<T> {
+    /// Creator for futures returning [`anyhow::Result`], see [`ImmediateValuePromise::new`]
+    pub fn new_anyhow<U: Future<Output = anyhow::Result<T>> + Send + 'static>(updater: U) -> Self {
+        ImmediateValuePromise::new(
+            async move { updater.await.map_err(BoxedSendError::from_anyhow) },
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::BoxedSendError;
+
+    #[test]
+    fn round_trip_keeps_chain() {
+        let error = anyhow::anyhow!("disk on fire").context("loading config failed");
+        let boxed = BoxedSendError::from_anyhow(error);
+        assert_eq!(boxed.full_message(), "loading config failed: disk on fire");
+        let error = boxed.into_anyhow();
+        assert_eq!(error.chain().count(), 2);
+
+        let boxed: BoxedSendError = std::io::Error::other("disk on fire").into();
+        let error = boxed.into_anyhow();
+        assert_eq!(error.to_string(), "disk on fire");
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn anyhow_futures() {
+        use crate::runtime::sleep;
+        use crate::{ImmediateValuePromise, ImmediateValueState};
+        use anyhow::Context;
+        use std::time::Duration;
+
+        let mut promise = ImmediateValuePromise::<i32>::new_anyhow(async {
+            std::fs::read_to_string("DOES_NOT_EXIST").context("reading settings")?;
+            Ok(34)
+        });
+        sleep(Duration::from_millis(50)).await;
+        let ImmediateValueState::Error(error) = promise.poll_state() else {
+            unreachable!();
+        };
+        assert_eq!(error.chain().count(), 2);
+        assert!(error.full_message().starts_with("reading settings: "));
+    }
+}
@@ -0,0 +1,221 @@
+use std::fmt::{self, Debug, Formatter};
+use std::pin::pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+use bytes::{Bytes, BytesMut};
+use futures_core::Stream;
+use futures_util::StreamExt;
+
+use crate::{
+    clock, BoxedSendError, DirectCacheAccess, ImmediateValuePromise, ImmediateValueState, Progress,
+};
+
+/// Upper bound for preallocating from the announced size, which may be wrong
+const MAX_PREALLOCATION: u64 = 16 * 1024 * 1024;
+
+/// # A promise collecting a byte stream, e.g. a download
+/// Created from a stream of chunks and the total size if known, like the content length of a response.
+/// The chunks are collected into one [`Bytes`], the progress is computed from the bytes received so far.
+/// If the stream fails, the error tells after how many bytes and [`Self::bytes_received`] keeps the count.
+/// ```rust
+/// # #[tokio::main]
+/// # async fn main() {
+/// use lazy_async_promise::{BytesPromise, BoxedSendError, ImmediateValueState};
+/// let chunks = vec![Ok::<_, BoxedSendError>(vec![1u8, 2]), Ok(vec![3, 4])];
+/// let mut promise = BytesPromise::new(futures_util::stream::iter(chunks), Some(4));
+/// tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+/// assert!(matches!(promise.poll_state(), ImmediateValueState::Success(bytes) if bytes.as_ref() == [1, 2, 3, 4]));
+/// assert_eq!(promise.bytes_received(), 4);
+/// assert_eq!(*promise.get_progress(), 1.0);
+/// # }
+/// ```
+pub struct BytesPromise {
+    promise: ImmediateValuePromise<Bytes>,
+    received: Arc<Received>,
+    total: Option<u64>,
+    started: Instant,
+}
+
+#[derive(Default)]
+struct Received {
+    bytes: AtomicU64,
+    /// time from the start until the stream ended
+    duration: OnceLock<Duration>,
+}
+
+impl BytesPromise {
+    /// Spawn collecting the chunks of `stream`. `total` is the expected size in bytes, without it the progress is
+    /// [`Progress::indeterminate`].
+    pub fn new<S, B, E>(stream: S, total: Option<u64>) -> Self
+    where
+        S: Stream<Item = Result<B, E>> + Send + 'static,
+        B: AsRef<[u8]>,
+        E: Into<BoxedSendError>,
+    {
+        let received = Arc::new(Received::default());
+        let started = clock::now();
+        let counter = received.clone();
+        let promise = ImmediateValuePromise::new(async move {
+            let capacity = total.unwrap_or(0).min(MAX_PREALLOCATION);
+            let mut buffer = BytesMut::with_capacity(capacity as usize);
+            let mut stream = pin!(stream);
+            let result = loop {
+                match stream.next().await {
+                    Some(Ok(chunk)) => {
+                        let chunk = chunk.as_ref();
+                        buffer.extend_from_slice(chunk);
+                        counter
+                            .bytes
+                            .fetch_add(chunk.len() as u64, Ordering::Relaxed);
+                    }
+                    Some(Err(error)) => {
+                        let received = buffer.len();
+                        let context = format!("byte stream failed after {received} bytes");
+                        break Err(error.into().context(context));
+                    }
+                    None => break Ok(buffer.freeze()),
+                }
+            };
+            let _ = counter.duration.set(clock::since(started));
+            result
+        });
+        BytesPromise {
+            promise,
+            received,
+            total,
+            started,
+        }
+    }
+
+    /// Poll the state, the value is the collected bytes once the stream ended
+    pub fn poll_state(&mut self) -> &ImmediateValueState<Bytes> {
+        self.promise.poll_state()
+    }
+
+    /// Get the current state without polling
+    pub fn get_state(&self) -> &ImmediateValueState<Bytes> {
+        self.promise.get_state()
+    }
+
+    /// The bytes received over the total size, [`Progress::indeterminate`] if the total is unknown
+    pub fn get_progress(&self) -> Progress {
+        match self.total {
+            Some(0) => Progress::from_percent(100),
+            Some(total) => Progress::from_fraction(self.bytes_received() as f64, total as f64),
+            None => Progress::indeterminate(),
+        }
+    }
+
+    /// How many bytes were received so far, also after the stream failed
+    pub fn bytes_received(&self) -> u64 {
+        self.received.bytes.load(Ordering::Relaxed)
+    }
+
+    /// The total size given on creation
+    pub fn total_bytes(&self) -> Option<u64> {
+        self.total
+    }
+
+    /// The average bytes per second since the promise was created, until the stream ended
+    pub fn throughput(&self) -> f64 {
+        let elapsed = match self.received.duration.get() {
+            Some(duration) => *duration,
+            None => clock::since(self.started),
+        };
+        if elapsed.is_zero() {
+            return 0.0;
+        }
+        self.bytes_received() as f64 / elapsed.as_secs_f64()
+    }
+
+    /// The user-facing error message if the stream failed, see [`ImmediateValuePromise::display_error`]
+    pub fn display_error(&self) -> Option<String> {
+        self.promise.display_error()
+    }
+}
+
+impl Debug for BytesPromise {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BytesPromise")
+            .field("promise", &self.promise)
+            .field("received", &self.bytes_received())
+            .field("total", &self.total)
+            .finish()
+    }
+}
+
+impl DirectCacheAccess<Bytes, BoxedSendError> for BytesPromise {
+    fn get_value_mut(&mut self) -> Option<&mut Bytes> {
+        self.promise.get_value_mut()
+    }
+    fn get_value(&self) -> Option<&Bytes> {
+        self.promise.get_value()
+    }
+    fn get_result(&self) -> Option<Result<&Bytes, &BoxedSendError>> {
+        self.promise.get_result()
+    }
+    fn get_result_mut(&mut self) -> Option<Result<&mut Bytes, &mut BoxedSendError>> {
+        self.promise.get_result_mut()
+    }
+    fn take_value(&mut self) -> Option<Bytes> {
+        self.promise.take_value()
+    }
+    fn take_result(&mut self) -> Option<Result<Bytes, BoxedSendError>> {
+        self.promise.take_result()
+    }
+    fn set_value(&mut self, value: Bytes) -> Option<Bytes> {
+        self.promise.set_value(value)
+    }
+    fn is_pending(&self) -> bool {
+        self.promise.is_pending()
+    }
+    fn take_error(&mut self) -> Option<BoxedSendError> {
+        self.promise.take_error()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use futures_util::{stream, StreamExt};
+
+    use super::BytesPromise;
+    use crate::runtime::sleep;
+    use crate::{BoxedSendError, DirectCacheAccess, ImmediateValueState};
+
+    #[tokio::test]
+    async fn partial_count_on_error() {
+        let chunks = vec![
+            Ok(vec![0u8; 300]),
+            Ok(vec![0u8; 200]),
+            Err(BoxedSendError::msg("connection reset")),
+        ];
+        let delayed = stream::iter(chunks).then(|chunk| async move {
+            sleep(Duration::from_millis(40)).await;
+            chunk
+        });
+        let mut promise = BytesPromise::new(delayed, Some(1000));
+        sleep(Duration::from_millis(60)).await;
+        assert!(matches!(
+            promise.poll_state(),
+            ImmediateValueState::Updating
+        ));
+        assert_eq!(promise.bytes_received(), 300);
+        assert_eq!(*promise.get_progress(), 0.3);
+        assert!(promise.throughput() > 0.0);
+
+        sleep(Duration::from_millis(150)).await;
+        promise.poll_state();
+        assert_eq!(
+            promise.take_error().unwrap().full_message(),
+            "byte stream failed after 500 bytes: connection reset"
+        );
+        assert_eq!(promise.bytes_received(), 500);
+
+        let unknown_size = BytesPromise::new(stream::iter([Ok::<_, BoxedSendError>([1u8])]), None);
+        assert!(unknown_size.get_progress().is_indeterminate());
+    }
+}
@@ -0,0 +1,117 @@
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::future::Future;
+use std::pin::Pin;
+
+use tokio_util::sync::CancellationToken;
+
+use crate::BoxedSendError;
+
+/// The error an [`crate::ImmediateValuePromise`] switches to once its [`CancellationToken`] is cancelled, see
+/// `ImmediateValuePromise::new_with_token`. Check for it with [`BoxedSendError::is_cancelled`]. Cancellations are
+/// neither retried nor reported to error hooks. The lazy promises switch to [`crate::DataState::Cancelled`] instead.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl Display for Cancelled {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "promise was cancelled")
+    }
+}
+
+impl Error for Cancelled {}
+
+impl BoxedSendError {
+    /// Is this the error of a cancelled promise?
+    pub fn is_cancelled(&self) -> bool {
+        self.0.is::<Cancelled>()
+    }
+}
+
+/// Stops the future as soon as the token is cancelled
+pub(crate) fn cancellable<F: Future<Output = ()> + Send + 'static>(
+    future: F,
+    token: Option<CancellationToken>,
+) -> Pin<Box<dyn Future<Output = ()> + Send + 'static>> {
+    match token {
+        Some(token) => Box::pin(async move {
+            token.run_until_cancelled(future).await;
+        }),
+        None => Box::pin(future),
+    }
+}
+
+/// Is the token of a promise cancelled?
+pub(crate) fn is_cancelled(token: &Option<CancellationToken>) -> bool {
+    token.as_ref().is_some_and(CancellationToken::is_cancelled)
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use tokio::sync::mpsc::Sender;
+
+    use super::*;
+    use crate::api_macros::*;
+    use crate::runtime::sleep;
+    use crate::{
+        DataState, DirectCacheAccess, ImmediateValuePromise, LazyVecPromise, Message, Promise,
+        RetryPolicy,
+    };
+
+    #[tokio::test]
+    async fn immediate_cancellation() {
+        let parent = CancellationToken::new();
+        let slow = || async {
+            sleep(Duration::from_secs(10)).await;
+            Ok(1)
+        };
+        let mut first = ImmediateValuePromise::new_with_token(slow(), &parent);
+        let mut second = ImmediateValuePromise::new_with_token(slow(), &parent);
+        first.cancel();
+        assert!(first
+            .poll_state()
+            .get_result()
+            .unwrap()
+            .unwrap_err()
+            .is_cancelled());
+        assert!(second.poll_state().is_updating());
+        parent.cancel();
+        assert!(second
+            .poll_state()
+            .get_result()
+            .unwrap()
+            .unwrap_err()
+            .is_cancelled());
+    }
+
+    #[tokio::test]
+    async fn lazy_cancellation() {
+        let token = CancellationToken::new();
+        let updater = |tx: Sender<Message<i32>>| async move {
+            send_data!(1, tx);
+            sleep(Duration::from_secs(10)).await;
+            set_finished!(tx);
+        };
+        let hook_calls = Arc::new(AtomicUsize::new(0));
+        let hook_calls_clone = hook_calls.clone();
+        let mut promise = LazyVecPromise::new(updater, 6)
+            .with_cancellation(&token)
+            .with_retry(RetryPolicy::new(3))
+            .with_on_error(move |_| {
+                hook_calls_clone.fetch_add(1, Ordering::SeqCst);
+            });
+        assert!(promise.poll_state().is_updating());
+        sleep(Duration::from_millis(20)).await;
+        token.cancel();
+        assert_eq!(promise.poll_state(), &DataState::Cancelled);
+        assert_eq!(promise.retries_done(), 0);
+        assert_eq!(hook_calls.load(Ordering::SeqCst), 0);
+        sleep(Duration::from_millis(20)).await;
+        assert!(promise.poll_state().is_cancelled());
+        assert_eq!(promise.poll_state().to_string(), "cancelled");
+    }
+}
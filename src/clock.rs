@@ -0,0 +1,16 @@
+//! The time source for timestamps and durations. With the `tokio` feature, this is tokio's clock, so
+//! `tokio::time::pause` and `advance` apply in tests. Outside a paused runtime, it is the same as [`Instant::now`].
+use std::time::{Duration, Instant};
+
+/// The current time, following tokio's paused clock if there is one
+pub(crate) fn now() -> Instant {
+    #[cfg(feature = "tokio")]
+    return tokio::time::Instant::now().into_std();
+    #[cfg(not(feature = "tokio"))]
+    Instant::now()
+}
+
+/// Time passed since `earlier`, see [`now`]
+pub(crate) fn since(earlier: Instant) -> Duration {
+    now().saturating_duration_since(earlier)
+}
@@ -0,0 +1,63 @@
+//! Control channels into running updaters, see `new_with_control` on the promises.
+//! The message type is erased, so the promises don't need another type parameter.
+use std::any::Any;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use tokio::sync::mpsc::error::SendError;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+/// Hands the receiver of the update just started to the updater
+pub(crate) type ControlSlot<C> = Arc<Mutex<Option<UnboundedReceiver<C>>>>;
+
+type Open = Box<dyn FnMut() -> Box<dyn Any + Send + Sync> + Send + Sync>;
+
+/// The sending side of the control channel of a promise, one channel per update
+pub(crate) struct Control {
+    open: Open,
+    sender: Option<Box<dyn Any + Send + Sync>>,
+}
+
+impl Control {
+    /// A control channel for messages of type `C`, the receivers are put into the returned slot by [`Self::open`]
+    pub(crate) fn new<C: Send + 'static>() -> (Self, ControlSlot<C>) {
+        let slot: ControlSlot<C> = Arc::default();
+        let receivers = slot.clone();
+        let open = move || -> Box<dyn Any + Send + Sync> {
+            let (sender, receiver) = unbounded_channel::<C>();
+            *receivers.lock().unwrap_or_else(PoisonError::into_inner) = Some(receiver);
+            Box::new(sender)
+        };
+        let control = Control {
+            open: Box::new(open),
+            sender: None,
+        };
+        (control, slot)
+    }
+
+    /// Start a new channel for the next update, messages for the previous one are no longer delivered
+    pub(crate) fn open(&mut self) {
+        self.sender = Some((self.open)());
+    }
+
+    /// Send `message` to the running update. Fails if the updater is done or dropped its receiver, if no update
+    /// was started yet, or if the channel is not for `C`.
+    pub(crate) fn send<C: 'static>(
+        control: &Option<Control>,
+        message: C,
+    ) -> Result<(), SendError<C>> {
+        let sender = control
+            .as_ref()
+            .and_then(|control| control.sender.as_ref())
+            .and_then(|sender| sender.downcast_ref::<UnboundedSender<C>>());
+        match sender {
+            Some(sender) => sender.send(message),
+            None => Err(SendError(message)),
+        }
+    }
+}
+
+/// The receiver put into the slot by [`Control::open`], or a closed one
+pub(crate) fn take_receiver<C>(slot: &ControlSlot<C>) -> UnboundedReceiver<C> {
+    let receiver = slot.lock().unwrap_or_else(PoisonError::into_inner).take();
+    receiver.unwrap_or_else(|| unbounded_channel().1)
+}
@@ -0,0 +1,113 @@
+//! Warnings about misused [`crate::ImmediateValuePromise`]s, e.g. one that was created but never stored.
+//! Only compiled into debug builds with the `log` feature, release builds don't carry any of it.
+use std::panic::Location;
+
+use tokio::sync::watch;
+
+use crate::StateTag;
+
+/// Polls after a taken value until the warning, the first one is expected e.g. from `get_or_spawn_with`
+const POLLS_AFTER_TAKEN: u32 = 2;
+
+/// Tracks the use of a promise, warning when it's dropped without ever being polled
+pub(crate) struct Diagnostics {
+    created: &'static Location<'static>,
+    tags: watch::Receiver<StateTag>,
+    polled: bool,
+    polls_after_taken: Option<u32>,
+}
+
+impl Diagnostics {
+    /// `tags` is the state watch of the promise, telling whether its future finished
+    #[track_caller]
+    pub(crate) fn new(tags: watch::Receiver<StateTag>) -> Self {
+        Diagnostics {
+            created: Location::caller(),
+            tags,
+            polled: false,
+            polls_after_taken: None,
+        }
+    }
+
+    /// For promises without a future, they are fine to drop unpolled
+    pub(crate) fn disarmed(tags: watch::Receiver<StateTag>) -> Self {
+        let mut diagnostics = Self::new(tags);
+        diagnostics.disarm();
+        diagnostics
+    }
+
+    /// For promises which are fine to drop unpolled from now on, e.g. replaced or detached ones
+    pub(crate) fn disarm(&mut self) {
+        self.polled = true;
+    }
+
+    pub(crate) fn polled(&mut self) {
+        self.polled = true;
+        let Some(polls) = &mut self.polls_after_taken else {
+            return;
+        };
+        *polls += 1;
+        if *polls == POLLS_AFTER_TAKEN {
+            log::warn!(
+                "ImmediateValuePromise created at {} is polled repeatedly after its value was taken, it stays \
+                 empty - start a new future with `set_future` or `get_or_spawn_with`",
+                self.created
+            );
+        }
+    }
+
+    pub(crate) fn taken(&mut self) {
+        self.polls_after_taken = Some(0);
+    }
+
+    /// The warning to log when the promise is dropped now
+    fn drop_warning(&self) -> Option<String> {
+        if self.polled {
+            return None;
+        }
+        let what = match *self.tags.borrow() {
+            StateTag::Success | StateTag::Error => {
+                "with a result nobody polled, the result is lost"
+            }
+            _ => "without ever being polled, its future is aborted",
+        };
+        Some(format!(
+            "ImmediateValuePromise created at {} was dropped {what} - was it stored?",
+            self.created
+        ))
+    }
+}
+
+impl Drop for Diagnostics {
+    fn drop(&mut self) {
+        if let Some(warning) = self.drop_warning() {
+            log::warn!("{warning}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use crate::runtime::sleep;
+    use crate::ImmediateValuePromise;
+
+    #[tokio::test]
+    async fn warns_about_unpolled_promises() {
+        let line = line!() + 1;
+        let unpolled = ImmediateValuePromise::new(async { Ok(34) });
+        let warning = unpolled.diagnostics.drop_warning().unwrap();
+        assert!(
+            warning.contains(&format!("{}:{line}", file!())),
+            "{warning}"
+        );
+        sleep(Duration::from_millis(20)).await;
+        let warning = unpolled.diagnostics.drop_warning().unwrap();
+        assert!(warning.contains("the result is lost"), "{warning}");
+
+        let mut polled = ImmediateValuePromise::new(async { Ok(34) });
+        polled.poll_state();
+        assert!(polled.diagnostics.drop_warning().is_none());
+    }
+}
@@ -0,0 +1,55 @@
+//! Counting the messages lost to full buffers, see `dropped_messages` on the promises.
+//! The counter of a promise is made available to the task running its future as a task-local, so the non-blocking
+//! send helpers can count failed sends without a wrapper around the tokio senders.
+use std::cell::RefCell;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+tokio::task_local! {
+    static DROPPED: DropCounter;
+}
+
+thread_local! {
+    /// The counter for promises spawned while a tracked promise runs its creator
+    static CREATING: RefCell<Option<DropCounter>> = const { RefCell::new(None) };
+}
+
+/// Shared between a promise and the task running its future
+#[derive(Clone, Default)]
+pub(crate) struct DropCounter(Arc<AtomicU64>);
+
+impl DropCounter {
+    pub(crate) fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Count the messages dropped while `future` runs
+    pub(crate) fn scope<F: Future>(&self, future: F) -> impl Future<Output = F::Output> {
+        DROPPED.scope(self.clone(), future)
+    }
+
+    /// Run `creator`, futures spawned by [`crate::ImmediateValuePromise`]s meanwhile count into this counter
+    pub(crate) fn while_creating<R>(&self, creator: impl FnOnce() -> R) -> R {
+        let previous = CREATING.with(|current| current.replace(Some(self.clone())));
+        let result = creator();
+        CREATING.with(|current| *current.borrow_mut() = previous);
+        result
+    }
+
+    /// The counter of the promise whose creator is running, see [`Self::while_creating`]
+    pub(crate) fn creating() -> Option<DropCounter> {
+        CREATING.with(|current| current.borrow().clone())
+    }
+}
+
+/// Count a message which couldn't be sent, if the current task belongs to a promise
+pub(crate) fn record_dropped() {
+    let _ = DROPPED.try_with(|counter| {
+        let _previous = counter.0.fetch_add(1, Ordering::Relaxed);
+        #[cfg(feature = "log")]
+        if _previous == 0 {
+            log::warn!("message dropped, the buffer of the promise is full - consider a bigger buffer size");
+        }
+    });
+}
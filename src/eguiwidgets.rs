@@ -0,0 +1,409 @@
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Display, Formatter};
+use std::sync::{Arc, Mutex};
+
+#[cfg(any(feature = "tokio", feature = "test-util"))]
+use crate::Promise;
+use crate::{
+    BoxedSendError, DirectCacheAccess, ImmediateValueState, Progress, ProgressTracked,
+    SnapshotPoll, StateSnapshot, ThreadPromise, TrackedPromise,
+};
+#[cfg(feature = "tokio")]
+use crate::{ImmediateValuePromise, LazyValuePromise, LazyVecPromise};
+
+/// Common bound for all promises which can be shown with [`PromiseUiExt::promise`]
+pub trait EguiPromise<T>: DirectCacheAccess<T, BoxedSendError> {
+    /// Can the promise be restarted after an error? Shows a "Retry" button if so.
+    const RESTARTABLE: bool = false;
+    /// apply pending updates
+    fn poll_for_ui(&mut self);
+    /// the user-facing error message if in error state
+    fn error_text(&self) -> Option<String>;
+    /// restart after an error, only called if [`EguiPromise::RESTARTABLE`]
+    fn restart(&mut self) {}
+}
+
+#[cfg(feature = "tokio")]
+impl<T: Send + 'static> EguiPromise<T> for ImmediateValuePromise<T> {
+    fn poll_for_ui(&mut self) {
+        self.poll_state();
+    }
+    fn error_text(&self) -> Option<String> {
+        self.display_error()
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<T: Debug + Send + 'static> EguiPromise<T> for LazyValuePromise<T> {
+    const RESTARTABLE: bool = true;
+    fn poll_for_ui(&mut self) {
+        self.poll_state();
+    }
+    fn error_text(&self) -> Option<String> {
+        self.display_error()
+    }
+    fn restart(&mut self) {
+        self.update();
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<T: Debug + Send + 'static> EguiPromise<Vec<T>> for LazyVecPromise<T> {
+    const RESTARTABLE: bool = true;
+    fn poll_for_ui(&mut self) {
+        self.poll_state();
+    }
+    fn error_text(&self) -> Option<String> {
+        self.display_error()
+    }
+    fn restart(&mut self) {
+        self.update();
+    }
+}
+
+impl<T: Send + 'static> EguiPromise<T> for ThreadPromise<T> {
+    fn poll_for_ui(&mut self) {
+        self.poll_state();
+    }
+    fn error_text(&self) -> Option<String> {
+        self.display_error()
+    }
+}
+
+impl<P: TrackedPromise, M> EguiPromise<P::Value> for ProgressTracked<P, M> {
+    fn poll_for_ui(&mut self) {
+        self.poll_state();
+    }
+    fn error_text(&self) -> Option<String> {
+        self.display_error()
+    }
+}
+
+#[cfg(feature = "test-util")]
+impl<T, M> EguiPromise<T> for crate::MockPromise<T, M> {
+    const RESTARTABLE: bool = true;
+    fn poll_for_ui(&mut self) {
+        self.poll_state();
+    }
+    fn error_text(&self) -> Option<String> {
+        self.get_result()
+            .and_then(Result::err)
+            .map(ToString::to_string)
+    }
+    fn restart(&mut self) {
+        self.update();
+    }
+}
+
+/// Extension trait for [`egui::Ui`] to show promises
+pub trait PromiseUiExt {
+    /// Polls the promise and shows a spinner while updating, the error text (with a "Retry" button for restartable
+    /// promises) on error and calls `add_contents` with the value once resolved. Returns what `add_contents` returned.
+    /// Shows nothing if the value was taken or the update was cancelled.
+    /// ```rust, no_run
+    /// use lazy_async_promise::{ThreadPromise, PromiseUiExt};
+    /// fn show(ui: &mut egui::Ui, promise: &mut ThreadPromise<String>) {
+    ///     ui.promise(promise, |ui, value| ui.label(value.as_str()));
+    /// }
+    /// ```
+    fn promise<T, P: EguiPromise<T>, R>(
+        &mut self,
+        promise: &mut P,
+        add_contents: impl FnOnce(&mut egui::Ui, &mut T) -> R,
+    ) -> Option<R>;
+}
+
+impl PromiseUiExt for egui::Ui {
+    fn promise<T, P: EguiPromise<T>, R>(
+        &mut self,
+        promise: &mut P,
+        add_contents: impl FnOnce(&mut egui::Ui, &mut T) -> R,
+    ) -> Option<R> {
+        promise.poll_for_ui();
+        if let Some(error) = promise.error_text() {
+            self.colored_label(self.visuals().error_fg_color, error);
+            if P::RESTARTABLE && self.button("Retry").clicked() {
+                promise.restart();
+            }
+            return None;
+        }
+        if promise.is_pending() {
+            self.spinner();
+            return None;
+        }
+        match promise.get_result_mut() {
+            Some(Ok(value)) => Some(add_contents(self, value)),
+            _ => None,
+        }
+    }
+}
+
+impl Progress {
+    /// An [`egui::ProgressBar`] showing this progress as percentage
+    pub fn to_progress_bar(&self) -> egui::ProgressBar {
+        egui::ProgressBar::new(self.as_f32()).show_percentage()
+    }
+}
+
+impl<P: TrackedPromise, M: Display> ProgressTracked<P, M> {
+    /// Polls the promise and renders a progress bar labeled with the latest status message.
+    /// Returns the current state, so the caller can show the result once finished.
+    /// ```rust, no_run
+    /// use lazy_async_promise::{ImmediateValueState, ProgressTrackedThreadPromise, StringStatus};
+    /// fn show(ui: &mut egui::Ui, promise: &mut ProgressTrackedThreadPromise<i32, std::borrow::Cow<'static, str>>) {
+    ///     if let ImmediateValueState::Success(value) = promise.show_progress(ui) {
+    ///         ui.label(format!("Result: {value}"));
+    ///     }
+    /// }
+    /// ```
+    pub fn show_progress(&mut self, ui: &mut egui::Ui) -> &ImmediateValueState<P::Value> {
+        self.poll_state();
+        let mut bar = self.get_progress().to_progress_bar();
+        if let Some(status) = self.last_status() {
+            bar = bar.text(status.message.to_string());
+        }
+        ui.add(bar);
+        self.get_state()
+    }
+}
+
+/// # Promises kept per [`egui::Id`], created on first use
+/// Immediate mode UIs often want "the promise of this widget" without keeping it in the app state. The store
+/// creates a promise the first time an id is used and drops it once the id wasn't used for `max_idle_frames`
+/// frames, which aborts its task. Own the store in the app, or keep it in the egui memory with
+/// [`Self::from_memory`].
+/// ```rust, no_run
+/// use lazy_async_promise::{DirectCacheAccess, ImmediateValuePromise, PromiseStore, StateTag};
+/// fn show(ui: &mut egui::Ui, store: &mut PromiseStore<ImmediateValuePromise<String>>, user: u32) {
+///     let id = ui.id().with(user);
+///     let state = store.poll_state(ui.ctx(), id, || {
+///         ImmediateValuePromise::new(async move { Ok(format!("user {user}")) })
+///     });
+///     if state.state == StateTag::Success {
+///         if let Some(name) = store.get(ui.ctx(), id).and_then(|promise| promise.get_value()) {
+///             ui.label(name.as_str());
+///         }
+///     }
+/// }
+/// ```
+pub struct PromiseStore<P> {
+    entries: HashMap<egui::Id, StoreEntry<P>>,
+    max_idle_frames: u64,
+    /// the pass the garbage was last collected in
+    collected: Option<u64>,
+}
+
+struct StoreEntry<P> {
+    promise: P,
+    last_used: u64,
+}
+
+impl<P> PromiseStore<P> {
+    /// An empty store, dropping promises not used for `max_idle_frames` frames
+    pub fn new(max_idle_frames: u64) -> Self {
+        PromiseStore {
+            entries: HashMap::new(),
+            max_idle_frames,
+            collected: None,
+        }
+    }
+
+    /// The store kept in the egui memory under `id`, created on first use. Lock it for the duration of a frame.
+    pub fn from_memory(ctx: &egui::Context, id: egui::Id, max_idle_frames: u64) -> Arc<Mutex<Self>>
+    where
+        P: Send + 'static,
+    {
+        ctx.data_mut(|data| {
+            let store = data.get_temp_mut_or_insert_with(id, || {
+                Arc::new(Mutex::new(PromiseStore::new(max_idle_frames)))
+            });
+            store.clone()
+        })
+    }
+
+    /// The promise of `id`, spawned with `spawn` on first use
+    pub fn get_or_spawn(
+        &mut self,
+        ctx: &egui::Context,
+        id: egui::Id,
+        spawn: impl FnOnce() -> P,
+    ) -> &mut P {
+        let frame = self.collect_garbage(ctx);
+        let entry = self.entries.entry(id).or_insert_with(|| StoreEntry {
+            promise: spawn(),
+            last_used: frame,
+        });
+        entry.last_used = frame;
+        &mut entry.promise
+    }
+
+    /// Poll the promise of `id`, spawned with `spawn` on first use, and take a snapshot of its state
+    pub fn poll_state(
+        &mut self,
+        ctx: &egui::Context,
+        id: egui::Id,
+        spawn: impl FnOnce() -> P,
+    ) -> StateSnapshot<P::Tag>
+    where
+        P: SnapshotPoll,
+    {
+        self.get_or_spawn(ctx, id, spawn).poll_snapshot()
+    }
+
+    /// The promise of `id` if there is one, counts as used
+    pub fn get(&mut self, ctx: &egui::Context, id: egui::Id) -> Option<&mut P> {
+        let frame = self.collect_garbage(ctx);
+        let entry = self.entries.get_mut(&id)?;
+        entry.last_used = frame;
+        Some(&mut entry.promise)
+    }
+
+    /// Remove the promise of `id`, e.g. to spawn it again
+    pub fn remove(&mut self, id: egui::Id) -> Option<P> {
+        self.entries.remove(&id).map(|entry| entry.promise)
+    }
+
+    /// How many promises are stored
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Are there no promises stored?
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Drop the idle promises once per frame, returns the current frame
+    fn collect_garbage(&mut self, ctx: &egui::Context) -> u64 {
+        let frame = ctx.cumulative_pass_nr();
+        if self.collected != Some(frame) {
+            self.collected = Some(frame);
+            let max_idle = self.max_idle_frames;
+            self.entries
+                .retain(|_, entry| frame.saturating_sub(entry.last_used) <= max_idle);
+        }
+        frame
+    }
+}
+
+impl<P> Debug for PromiseStore<P> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PromiseStore")
+            .field("promises", &self.entries.len())
+            .field("max_idle_frames", &self.max_idle_frames)
+            .finish()
+    }
+}
+
+#[cfg(all(test, feature = "tokio"))]
+mod test {
+    use std::time::Duration;
+
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    use super::{PromiseStore, PromiseUiExt};
+    use crate::api_macros::*;
+    use crate::runtime::sleep;
+    use crate::{
+        DataState, ImmediateValuePromise, LazyVecPromise, Message, Progress,
+        ProgressTrackedImValProm, Promise, StateTag, StringStatus,
+    };
+    use tokio::sync::mpsc::Sender;
+
+    pub(crate) fn run_ui(mut add_contents: impl FnMut(&mut egui::Ui)) {
+        let ctx = egui::Context::default();
+        let mut output = ctx.run_ui(egui::RawInput::default(), |ui| add_contents(ui));
+        output.textures_delta.clear();
+    }
+
+    #[tokio::test]
+    async fn show_progress() {
+        let mut promise = ProgressTrackedImValProm::new(
+            |s| {
+                ImmediateValuePromise::new(async move {
+                    s.send(StringStatus::from_str(
+                        Progress::from_percent(50),
+                        "halfway",
+                    ))
+                    .await
+                    .unwrap();
+                    Ok(34)
+                })
+            },
+            10,
+        );
+        sleep(Duration::from_millis(50)).await;
+        let mut finished = false;
+        run_ui(|ui| finished = promise.show_progress(ui).is_success());
+        assert!(finished);
+        assert_eq!(promise.last_status().unwrap().message, "halfway");
+    }
+
+    #[tokio::test]
+    async fn promise_ui() {
+        let mut promise = ImmediateValuePromise::new(async {
+            sleep(Duration::from_millis(10)).await;
+            Ok(34)
+        });
+        let mut shown = None;
+        run_ui(|ui| shown = ui.promise(&mut promise, |_, value| *value));
+        assert!(shown.is_none());
+        sleep(Duration::from_millis(50)).await;
+        run_ui(|ui| shown = ui.promise(&mut promise, |_, value| *value));
+        assert_eq!(shown, Some(34));
+
+        let error_maker = |tx: Sender<Message<i32>>| async move {
+            set_error!("bla", tx);
+        };
+        let mut lazy_promise = LazyVecPromise::new(error_maker, 6);
+        run_ui(|ui| assert!(ui.promise(&mut lazy_promise, |_, _| ()).is_none()));
+        sleep(Duration::from_millis(50)).await;
+        run_ui(|ui| assert!(ui.promise(&mut lazy_promise, |_, _| ()).is_none()));
+        assert!(matches!(lazy_promise.poll_state(), DataState::Error(_)));
+    }
+
+    #[tokio::test]
+    async fn promise_store_collects_idle_promises() {
+        let ctx = egui::Context::default();
+        let finished = Arc::new(AtomicBool::new(false));
+        let spawn = |delay: u64| {
+            let finished = finished.clone();
+            move || {
+                ImmediateValuePromise::new(async move {
+                    sleep(Duration::from_millis(delay)).await;
+                    finished.store(true, Ordering::SeqCst);
+                    Ok(delay)
+                })
+            }
+        };
+        let store = PromiseStore::from_memory(&ctx, egui::Id::new("store"), 1);
+        let frame = |show_slow: bool| {
+            let mut states = Vec::new();
+            let mut output = ctx.run_ui(egui::RawInput::default(), |ui| {
+                let mut store = store.lock().unwrap();
+                states.push(store.poll_state(ui.ctx(), egui::Id::new("fast"), spawn(0)));
+                if show_slow {
+                    store.get_or_spawn(ui.ctx(), egui::Id::new("slow"), spawn(100));
+                }
+            });
+            output.textures_delta.clear();
+            states
+        };
+        frame(true);
+        assert_eq!(store.lock().unwrap().len(), 2);
+        sleep(Duration::from_millis(20)).await;
+        assert_eq!(frame(false)[0].state, StateTag::Success);
+        finished.store(false, Ordering::SeqCst);
+        frame(false);
+        frame(false);
+        assert_eq!(store.lock().unwrap().len(), 1);
+        assert!(Arc::ptr_eq(
+            &store,
+            &PromiseStore::from_memory(&ctx, egui::Id::new("store"), 1)
+        ));
+        // the dropped slow promise was aborted
+        sleep(Duration::from_millis(120)).await;
+        assert!(!finished.load(Ordering::SeqCst));
+    }
+}
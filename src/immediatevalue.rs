@@ -1,10 +1,51 @@
+use std::fmt::{self, Debug, Display, Formatter};
+#[cfg(feature = "tokio")]
 use std::future::Future;
 use std::mem;
+#[cfg(feature = "tokio")]
+use std::pin::Pin;
+#[cfg(feature = "tokio")]
+use std::sync::atomic::{AtomicU8, Ordering};
+#[cfg(feature = "tokio")]
 use std::sync::Arc;
+#[cfg(feature = "tokio")]
+use std::task::{Context, Poll, Waker};
 
-use tokio::sync::Mutex;
+#[cfg(feature = "tokio")]
+use tokio::sync::{oneshot, watch};
+#[cfg(feature = "tokio-util")]
+use tokio_util::sync::CancellationToken;
 
-use crate::{BoxedSendError, DirectCacheAccess, FutureResult};
+#[cfg(feature = "tokio-util")]
+use crate::cancellation::is_cancelled;
+
+#[cfg(all(debug_assertions, feature = "log"))]
+use crate::diagnostics::Diagnostics;
+#[cfg(feature = "tokio")]
+use crate::dropcount::DropCounter;
+#[cfg(feature = "tokio")]
+use crate::immediatevaluebuilder::ImmediateValuePromiseBuilder;
+#[cfg(feature = "registry")]
+use crate::registry::Registration;
+#[cfg(feature = "tokio")]
+use crate::runtime::{self, AdoptedTask, Task};
+#[cfg(feature = "futures")]
+use crate::statechange::state_change_stream;
+#[cfg(feature = "tokio")]
+use crate::statechange::{StateChange, StateChangeReceiver};
+#[cfg(feature = "tokio")]
+use crate::transitionlog::{self, Transition, TransitionCause, TransitionLog};
+#[cfg(feature = "tokio-util")]
+use crate::Cancelled;
+#[cfg(feature = "tokio")]
+use crate::StateSnapshot;
+#[cfg(feature = "tokio")]
+use crate::{
+    display_error, report_error, updater_task_error, ErrorDisplay, ErrorHook, FutureResult,
+};
+#[cfg(feature = "tokio")]
+use crate::{panic_error, ErrorKind, Progress, ProgressSender};
+use crate::{BoxedSendError, DirectCacheAccess};
 
 /// # A promise which can be easily created and stored.
 /// ## Introduction
@@ -46,6 +87,10 @@ use crate::{BoxedSendError, DirectCacheAccess, FutureResult};
 ///     unreachable!();
 /// }
 /// ```
+/// ### Dropping
+/// Dropping the promise aborts its future at the next await point, so e.g. closing a window mid-load doesn't
+/// leave a request running with its side effects. Use [`ImmediateValuePromise::detach`] to let it run to
+/// completion instead.
 /// ### Modifying inner values or taking ownership
 /// ```rust, no_run
 /// use std::thread;
@@ -100,12 +145,58 @@ use crate::{BoxedSendError, DirectCacheAccess, FutureResult};
 /// assert_eq!(*value_opt.unwrap(), 34);
 /// ```
 ///
+#[cfg(feature = "tokio")]
 pub struct ImmediateValuePromise<T: Send> {
-    value_arc: Arc<Mutex<Option<FutureResult<T>>>>,
+    result_rx: Option<ResultSource<T>>,
     state: ImmediateValueState<T>,
+    pub(crate) on_error: Option<ErrorHook>,
+    pub(crate) error_display: Option<ErrorDisplay>,
+    #[cfg_attr(not(feature = "futures"), allow(dead_code))]
+    state_changes: StateChangeReceiver,
+    pub(crate) tags: watch::Receiver<StateTag>,
+    #[cfg(feature = "tokio-util")]
+    pub(crate) cancel_token: Option<CancellationToken>,
+    pub(crate) name: Option<String>,
+    pub(crate) on_settle: Option<SettleHook<T>>,
+    deferred: Option<Pin<Box<dyn Future<Output = ()> + Send>>>,
+    inline: bool,
+    task: Option<Task>,
+    adopted: Option<AdoptedTask>,
+    progress: Option<ProgressSender>,
+    /// one bit per promise of a zip which is still pending
+    zipped: Option<Arc<AtomicU8>>,
+    transitions: Option<Box<TransitionLog<ImmediateValueState<T>>>>,
+    #[cfg(all(debug_assertions, feature = "log"))]
+    pub(crate) diagnostics: Diagnostics,
+    #[cfg(feature = "registry")]
+    registration: Option<Registration>,
+}
+
+/// Callback invoked once with the final state of an [`ImmediateValuePromise`], see [`ImmediateValuePromise::with_on_settle`]
+#[cfg(feature = "tokio")]
+pub type SettleHook<T> = Box<dyn FnOnce(&ImmediateValueState<T>) + Send>;
+
+/// Where a pending [`ImmediateValuePromise`] gets its result from. Converted promises poll the source of the
+/// original promise and map the result once it arrives, without spawning another task.
+#[cfg(feature = "tokio")]
+enum ResultSource<T> {
+    Channel(oneshot::Receiver<FutureResult<T>>),
+    Mapped(Box<dyn FnMut() -> Option<FutureResult<T>> + Send>),
+}
+
+#[cfg(feature = "tokio")]
+impl<T> ResultSource<T> {
+    /// A closed channel means the future panicked, see [`ImmediateValuePromise::task_failure`]
+    fn try_take(&mut self) -> Option<FutureResult<T>> {
+        match self {
+            ResultSource::Channel(result_rx) => result_rx.try_recv().ok(),
+            ResultSource::Mapped(poll) => poll(),
+        }
+    }
 }
 
-/// The return state of a [`ImmediateValuePromise`], contains the error, the value or that it is still updating
+/// The return state of a [`ImmediateValuePromise`], contains the error, the value or that it is still updating.
+/// Implements [`DirectCacheAccess`] itself, so detached states can be used wherever a promise is expected.
 pub enum ImmediateValueState<T> {
     /// future is not yet resolved
     Updating,
@@ -117,6 +208,137 @@ pub enum ImmediateValueState<T> {
     Empty,
 }
 
+impl<T> ImmediateValueState<T> {
+    /// Is the state [`ImmediateValueState::Success`]?
+    pub fn is_success(&self) -> bool {
+        matches!(self, ImmediateValueState::Success(_))
+    }
+
+    /// Is the state [`ImmediateValueState::Error`]?
+    pub fn is_error(&self) -> bool {
+        matches!(self, ImmediateValueState::Error(_))
+    }
+
+    /// Is the state [`ImmediateValueState::Updating`]?
+    pub fn is_updating(&self) -> bool {
+        matches!(self, ImmediateValueState::Updating)
+    }
+
+    /// Is the state [`ImmediateValueState::Empty`]?
+    pub fn is_empty(&self) -> bool {
+        matches!(self, ImmediateValueState::Empty)
+    }
+
+    /// The value if the state is [`ImmediateValueState::Success`]
+    /// ```rust
+    /// use lazy_async_promise::ImmediateValueState;
+    /// assert_eq!(ImmediateValueState::Success(34).as_success(), Some(&34));
+    /// assert_eq!(ImmediateValueState::<i32>::Updating.as_success(), None);
+    /// ```
+    pub fn as_success(&self) -> Option<&T> {
+        match self {
+            ImmediateValueState::Success(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// The error if the state is [`ImmediateValueState::Error`]
+    /// ```rust
+    /// use lazy_async_promise::{BoxedSendError, ImmediateValueState};
+    /// let state = ImmediateValueState::<i32>::Error(BoxedSendError::msg("timeout"));
+    /// assert_eq!(state.as_error().unwrap().to_string(), "timeout");
+    /// assert!(ImmediateValueState::Success(34).as_error().is_none());
+    /// ```
+    pub fn as_error(&self) -> Option<&BoxedSendError> {
+        match self {
+            ImmediateValueState::Error(error) => Some(error),
+            _ => None,
+        }
+    }
+
+    /// Converts into the value if the state is [`ImmediateValueState::Success`], like [`Result::ok`]
+    /// ```rust
+    /// use lazy_async_promise::ImmediateValueState;
+    /// assert_eq!(ImmediateValueState::Success(34).ok(), Some(34));
+    /// assert_eq!(ImmediateValueState::<i32>::Empty.ok(), None);
+    /// ```
+    pub fn ok(self) -> Option<T> {
+        match self {
+            ImmediateValueState::Success(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Converts into the error if the state is [`ImmediateValueState::Error`], like [`Result::err`]
+    /// ```rust
+    /// use lazy_async_promise::{BoxedSendError, ImmediateValueState};
+    /// let state = ImmediateValueState::<i32>::Error(BoxedSendError::msg("timeout"));
+    /// assert_eq!(state.err().unwrap().to_string(), "timeout");
+    /// ```
+    pub fn err(self) -> Option<BoxedSendError> {
+        match self {
+            ImmediateValueState::Error(error) => Some(error),
+            _ => None,
+        }
+    }
+
+    /// Maps the value of [`ImmediateValueState::Success`], leaving the other states untouched
+    /// ```rust
+    /// use lazy_async_promise::ImmediateValueState;
+    /// let state = ImmediateValueState::Success("34").map_success(|value| value.len());
+    /// assert_eq!(state.as_success(), Some(&2));
+    /// assert!(ImmediateValueState::<&str>::Updating.map_success(|value| value.len()).is_updating());
+    /// ```
+    pub fn map_success<U>(self, f: impl FnOnce(T) -> U) -> ImmediateValueState<U> {
+        match self {
+            ImmediateValueState::Updating => ImmediateValueState::Updating,
+            ImmediateValueState::Success(value) => ImmediateValueState::Success(f(value)),
+            ImmediateValueState::Error(error) => ImmediateValueState::Error(error),
+            ImmediateValueState::Empty => ImmediateValueState::Empty,
+        }
+    }
+
+    /// The state without the payload
+    pub fn tag(&self) -> StateTag {
+        match self {
+            ImmediateValueState::Updating => StateTag::Updating,
+            ImmediateValueState::Success(_) => StateTag::Success,
+            ImmediateValueState::Error(_) => StateTag::Error,
+            ImmediateValueState::Empty => StateTag::Empty,
+        }
+    }
+}
+
+/// Human-readable state with the error message, the value itself is not printed
+/// ```rust
+/// use lazy_async_promise::{BoxedSendError, ImmediateValueState};
+/// assert_eq!(ImmediateValueState::Success(34).to_string(), "success");
+/// assert_eq!(ImmediateValueState::<i32>::Error(BoxedSendError::msg("timeout")).to_string(), "error: timeout");
+/// ```
+impl<T> Display for ImmediateValueState<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ImmediateValueState::Updating => f.write_str("updating"),
+            ImmediateValueState::Success(_) => f.write_str("success"),
+            ImmediateValueState::Error(error) => write!(f, "error: {error}"),
+            ImmediateValueState::Empty => f.write_str("empty"),
+        }
+    }
+}
+
+/// A copyable [`ImmediateValueState`] without the payload, see [`ImmediateValuePromise::state_watch`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StateTag {
+    /// See [`ImmediateValueState::Updating`]
+    Updating,
+    /// See [`ImmediateValueState::Success`]
+    Success,
+    /// See [`ImmediateValueState::Error`]
+    Error,
+    /// See [`ImmediateValueState::Empty`]
+    Empty,
+}
+
 impl<T> DirectCacheAccess<T, BoxedSendError> for ImmediateValueState<T> {
     /// gets a mutable reference to the local cache if existing
     fn get_value_mut(&mut self) -> Option<&mut T> {
@@ -144,6 +366,14 @@ impl<T> DirectCacheAccess<T, BoxedSendError> for ImmediateValueState<T> {
         }
     }
 
+    fn get_result_mut(&mut self) -> Option<Result<&mut T, &mut BoxedSendError>> {
+        match self {
+            ImmediateValueState::Success(inner) => Some(Ok(inner)),
+            ImmediateValueState::Error(error) => Some(Err(error)),
+            _ => None,
+        }
+    }
+
     /// Takes ownership of the inner value if ready, leaving self in state [`ImmediateValueState::Empty`].
     /// Does nothing if we are in any other state.
     fn take_value(&mut self) -> Option<T> {
@@ -157,8 +387,13 @@ impl<T> DirectCacheAccess<T, BoxedSendError> for ImmediateValueState<T> {
         None
     }
 
+    /// Takes ownership of the inner value or error if ready, leaving self in state [`ImmediateValueState::Empty`].
+    /// Does nothing if we are in any other state.
     fn take_result(&mut self) -> Option<Result<T, BoxedSendError>> {
-        if matches!(self, ImmediateValueState::Success(_)) {
+        if matches!(
+            self,
+            ImmediateValueState::Success(_) | ImmediateValueState::Error(_)
+        ) {
             let val = mem::replace(self, ImmediateValueState::Empty);
             return match val {
                 ImmediateValueState::Success(inner) => Some(Ok(inner)),
@@ -168,8 +403,21 @@ impl<T> DirectCacheAccess<T, BoxedSendError> for ImmediateValueState<T> {
         }
         None
     }
+
+    fn is_pending(&self) -> bool {
+        self.is_updating()
+    }
+
+    /// Replaces the state with [`ImmediateValueState::Success`], returning the previous value if there was one.
+    fn set_value(&mut self, value: T) -> Option<T> {
+        match mem::replace(self, ImmediateValueState::Success(value)) {
+            ImmediateValueState::Success(previous) => Some(previous),
+            _ => None,
+        }
+    }
 }
 
+#[cfg(feature = "tokio")]
 impl<T: Send + 'static> DirectCacheAccess<T, BoxedSendError> for ImmediateValuePromise<T> {
     fn get_value_mut(&mut self) -> Option<&mut T> {
         self.state.get_value_mut()
@@ -180,88 +428,781 @@ impl<T: Send + 'static> DirectCacheAccess<T, BoxedSendError> for ImmediateValueP
     fn get_result(&self) -> Option<Result<&T, &BoxedSendError>> {
         self.state.get_result()
     }
+    fn get_result_mut(&mut self) -> Option<Result<&mut T, &mut BoxedSendError>> {
+        self.state.get_result_mut()
+    }
     fn take_value(&mut self) -> Option<T> {
-        self.state.take_value()
+        let value = self.state.take_value();
+        self.record_transition(TransitionCause::Taken);
+        #[cfg(all(debug_assertions, feature = "log"))]
+        if value.is_some() {
+            self.diagnostics.taken();
+        }
+        value
     }
     fn take_result(&mut self) -> Option<Result<T, BoxedSendError>> {
-        self.state.take_result()
+        let result = self.state.take_result();
+        self.record_transition(TransitionCause::Taken);
+        #[cfg(all(debug_assertions, feature = "log"))]
+        if result.is_some() {
+            self.diagnostics.taken();
+        }
+        result
+    }
+    fn is_pending(&self) -> bool {
+        self.state.is_pending()
+    }
+    /// Injects a value into the cache. If the future is still running, its result will overwrite
+    /// the injected value once it lands during a later [`ImmediateValuePromise::poll_state`].
+    fn set_value(&mut self, value: T) -> Option<T> {
+        let previous = self.state.set_value(value);
+        self.record_transition(TransitionCause::ValueSet);
+        previous
     }
 }
 
+#[cfg(feature = "tokio")]
 impl<T: Send + 'static> ImmediateValuePromise<T> {
-    /// Creator, supply a future which returns `Result<T, Box<dyn Error + Send>`. Will be immediately spawned via tokio.
+    /// Creator, supply a future which returns `Result<T, Box<dyn Error + Send>`. Will be immediately spawned via tokio, or async-std with the `async-std` feature.
+    #[cfg_attr(all(debug_assertions, feature = "log"), track_caller)]
     pub fn new<U: Future<Output = Result<T, BoxedSendError>> + Send + 'static>(updater: U) -> Self {
-        let arc = Arc::new(Mutex::new(None));
-        let arc_clone = arc.clone();
-        tokio::spawn(async move {
-            let mut val = arc_clone.lock().await;
-            *val = Some(updater.await);
-        });
+        Self::builder().spawn(updater)
+    }
+
+    /// A builder for promises with more options, like timeouts, retries or starting lazily
+    pub fn builder() -> ImmediateValuePromiseBuilder<T> {
+        ImmediateValuePromiseBuilder::new()
+    }
+
+    /// Creator naming the spawned task, so it can be told apart in tokio-console. Requires building with
+    /// `--cfg tokio_unstable`, otherwise the task stays anonymous. The name is available via [`Self::name`] either way.
+    #[cfg_attr(all(debug_assertions, feature = "log"), track_caller)]
+    pub fn new_named<U: Future<Output = Result<T, BoxedSendError>> + Send + 'static>(
+        name: impl Into<String>,
+        updater: U,
+    ) -> Self {
+        Self::builder().name(name).spawn(updater)
+    }
+
+    /// Spawns the updater right away, or on the first [`Self::poll_state`] if `lazy` is set
+    #[cfg_attr(all(debug_assertions, feature = "log"), track_caller)]
+    pub(crate) fn spawn<U: Future<Output = Result<T, BoxedSendError>> + Send + 'static>(
+        updater: U,
+        name: Option<String>,
+        lazy: bool,
+    ) -> Self {
+        let (result_tx, result_rx) = oneshot::channel();
+        // the promise only keeps a receiver, so streams end if the future panics
+        let (state_changes_tx, state_changes) = watch::channel(Some(StateChange::Started));
+        let (tag_tx, tags) = watch::channel(StateTag::Updating);
+        #[cfg(all(debug_assertions, feature = "log"))]
+        let diagnostics = Diagnostics::new(tags.clone());
+        let dropped = DropCounter::creating();
+        let task = async move {
+            let result = match dropped {
+                Some(dropped) => dropped.scope(updater).await,
+                None => updater.await,
+            };
+            let summary = result.as_ref().map(|_| ()).map_err(ToString::to_string);
+            let tag = match result {
+                Ok(_) => StateTag::Success,
+                Err(_) => StateTag::Error,
+            };
+            // the promise may have been dropped already, nobody is interested in the result then
+            let _ = result_tx.send(result);
+            tag_tx.send_replace(tag);
+            state_changes_tx.send_replace(Some(StateChange::Finished(summary)));
+        };
+        let (deferred, task) = if lazy {
+            let deferred: Pin<Box<dyn Future<Output = ()> + Send>> = Box::pin(task);
+            (Some(deferred), None)
+        } else {
+            (None, Some(runtime::spawn_named(name.as_deref(), task)))
+        };
+        #[cfg(feature = "registry")]
+        let registration = name
+            .as_deref()
+            .map(|name| Registration::new(name, "ImmediateValuePromise", "Updating"));
         Self {
-            value_arc: arc,
+            result_rx: Some(ResultSource::Channel(result_rx)),
             state: ImmediateValueState::Updating,
+            on_error: None,
+            error_display: None,
+            state_changes,
+            tags,
+            #[cfg(feature = "tokio-util")]
+            cancel_token: None,
+            name,
+            on_settle: None,
+            deferred,
+            inline: false,
+            task,
+            adopted: None,
+            progress: None,
+            zipped: None,
+            transitions: TransitionLog::by_default(&ImmediateValueState::Updating),
+            #[cfg(all(debug_assertions, feature = "log"))]
+            diagnostics,
+            #[cfg(feature = "registry")]
+            registration,
+        }
+    }
+
+    /// Creator which doesn't spawn the future: it is stored in the promise and polled once by each call to
+    /// [`Self::poll_state`], so the execution order is deterministic and cheap futures don't need a task.
+    /// Only suitable for futures which can make progress without being woken by a runtime, or if the promise is
+    /// polled frequently, e.g. every frame: wakeups are ignored, the next poll comes from the next `poll_state`.
+    /// Runtime resources like timers work as long as `poll_state` is called inside the runtime.
+    /// [`Self::changed`] and [`Self::state_watch`] only see the result once it was polled.
+    #[cfg_attr(all(debug_assertions, feature = "log"), track_caller)]
+    pub fn new_inline<U: Future<Output = Result<T, BoxedSendError>> + Send + 'static>(
+        updater: U,
+    ) -> Self {
+        let mut promise = Self::spawn(updater, None, true);
+        promise.inline = true;
+        promise
+    }
+
+    /// Creator for a task spawned elsewhere, e.g. before the UI existed. Reads the result straight from the
+    /// handle, without a forwarding task. A panicking or aborted task is an error of kind
+    /// [`ErrorKind::Panicked`] or [`ErrorKind::Cancelled`]. Dropping the promise aborts the task,
+    /// unless it is detached. A `From` implementation would overlap with the one for futures.
+    /// ```rust
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use lazy_async_promise::{BoxedSendError, DirectCacheAccess, ImmediateValuePromise};
+    /// let handle = tokio::spawn(async { Ok::<_, BoxedSendError>(34) });
+    /// let mut promise = ImmediateValuePromise::from_join_handle(handle);
+    /// tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    /// assert!(promise.poll_state().is_success());
+    /// assert_eq!(promise.get_value(), Some(&34));
+    /// # }
+    /// ```
+    pub fn from_join_handle<E: Into<BoxedSendError> + Send + 'static>(
+        mut handle: tokio::task::JoinHandle<Result<T, E>>,
+    ) -> Self {
+        let (state_changes_tx, state_changes) = watch::channel(Some(StateChange::Started));
+        let (tag_tx, tags) = watch::channel(StateTag::Updating);
+        let adopted = AdoptedTask::new(handle.abort_handle());
+        let source = move || {
+            if !handle.is_finished() {
+                return None;
+            }
+            let mut cx = Context::from_waker(Waker::noop());
+            let result = match Pin::new(&mut handle).poll(&mut cx) {
+                Poll::Ready(Ok(result)) => result.map_err(Into::into),
+                Poll::Ready(Err(error)) if error.is_panic() => {
+                    Err(panic_error(error.into_panic().as_ref()))
+                }
+                Poll::Ready(Err(error)) => {
+                    Err(BoxedSendError::from(error).with_kind(ErrorKind::Cancelled))
+                }
+                Poll::Pending => return None,
+            };
+            let summary = result.as_ref().map(|_| ()).map_err(ToString::to_string);
+            tag_tx.send_replace(match result {
+                Ok(_) => StateTag::Success,
+                Err(_) => StateTag::Error,
+            });
+            state_changes_tx.send_replace(Some(StateChange::Finished(summary)));
+            Some(result)
+        };
+        let mut promise = Self::empty();
+        promise.result_rx = Some(ResultSource::Mapped(Box::new(source)));
+        promise.state = ImmediateValueState::Updating;
+        promise.state_changes = state_changes;
+        promise.tags = tags;
+        promise.adopted = Some(adopted);
+        promise
+    }
+
+    /// Creator for futures which only report a progress, without the channel and history of
+    /// [`crate::ProgressTrackedImValProm`]. The creator gets a [`ProgressSender`] to set the progress, which is
+    /// read with [`Self::get_progress`].
+    /// ```rust
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use lazy_async_promise::{ImmediateValuePromise, Progress};
+    /// let mut promise = ImmediateValuePromise::new_with_progress(|progress| async move {
+    ///     let mut sum = 0;
+    ///     for i in 1..=4 {
+    ///         sum += i;
+    ///         progress.set(Progress::from_fraction(i, 4));
+    ///     }
+    ///     Ok(sum)
+    /// });
+    /// tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    /// assert!(promise.poll_state().is_success());
+    /// assert_eq!(promise.get_progress(), Some(Progress::from_percent(100)));
+    /// # }
+    /// ```
+    #[cfg_attr(all(debug_assertions, feature = "log"), track_caller)]
+    pub fn new_with_progress<U: Future<Output = Result<T, BoxedSendError>> + Send + 'static>(
+        creator: impl FnOnce(ProgressSender) -> U,
+    ) -> Self {
+        let progress = ProgressSender::default();
+        let mut promise = Self::new(creator(progress.clone()));
+        promise.progress = Some(progress);
+        promise
+    }
+
+    /// Keep a log of the state transitions, see [`crate::LazyVecPromise::with_transition_log`]. Transitions before
+    /// this call are not logged, use [`crate::set_transition_log_default`] to log from the start.
+    pub fn with_transition_log(mut self) -> Self {
+        self.transitions
+            .get_or_insert_with(|| TransitionLog::enabled(&self.state));
+        self
+    }
+
+    /// The logged transitions, oldest first. Empty unless enabled by [`Self::with_transition_log`].
+    pub fn transition_log(&self) -> &[Transition] {
+        TransitionLog::entries(&self.transitions)
+    }
+
+    /// The error of a task which ended without sending its result because it panicked, or the result it sent
+    /// right before ending. `None` while the task runs.
+    fn task_failure(&mut self) -> Option<FutureResult<T>> {
+        if !self.task.as_mut()?.is_finished() {
+            return None;
+        }
+        let outcome = self.task.take()?.try_join()?;
+        match outcome {
+            Err(failure) => Some(Err(updater_task_error(Err(failure)))),
+            Ok(()) => self.result_rx.as_mut().and_then(ResultSource::try_take),
+        }
+    }
+
+    fn record_transition(&mut self, cause: TransitionCause) {
+        transitionlog::record(&mut self.transitions, &self.state, cause);
+    }
+
+    /// The progress set by the future of [`Self::new_with_progress`], `None` for other promises
+    pub fn get_progress(&self) -> Option<Progress> {
+        self.progress.as_ref().map(ProgressSender::get)
+    }
+
+    /// A promise in the [`ImmediateValueState::Empty`] state without any future, to be started later with
+    /// [`Self::set_future`]. Saves wrapping the promise in an `Option` when there is nothing to compute yet.
+    pub fn empty() -> Self {
+        let (_, state_changes) = watch::channel(None);
+        let (_, tags) = watch::channel(StateTag::Empty);
+        #[cfg(all(debug_assertions, feature = "log"))]
+        let diagnostics = Diagnostics::disarmed(tags.clone());
+        Self {
+            result_rx: None,
+            state: ImmediateValueState::Empty,
+            on_error: None,
+            error_display: None,
+            state_changes,
+            tags,
+            #[cfg(feature = "tokio-util")]
+            cancel_token: None,
+            name: None,
+            on_settle: None,
+            deferred: None,
+            inline: false,
+            task: None,
+            adopted: None,
+            progress: None,
+            zipped: None,
+            transitions: TransitionLog::by_default(&ImmediateValueState::Empty),
+            #[cfg(all(debug_assertions, feature = "log"))]
+            diagnostics,
+            #[cfg(feature = "registry")]
+            registration: None,
+        }
+    }
+
+    /// Spawn a new future, replacing the current one. A running future is aborted and its result is never applied,
+    /// an inline promise polls the new future inline.
+    /// the state is [`ImmediateValueState::Updating`] until the new result arrives.
+    /// The name and the error hooks are kept for the new future, cancellation and settle hooks are not.
+    #[cfg_attr(all(debug_assertions, feature = "log"), track_caller)]
+    pub fn set_future<U: Future<Output = Result<T, BoxedSendError>> + Send + 'static>(
+        &mut self,
+        updater: U,
+    ) {
+        let mut promise = Self::spawn(updater, self.name.take(), self.inline);
+        promise.inline = self.inline;
+        let mut previous = mem::replace(self, promise);
+        previous.disarm_diagnostics();
+        self.on_error = previous.on_error;
+        self.error_display = previous.error_display;
+        #[cfg(feature = "registry")]
+        {
+            self.registration = previous.registration;
+        }
+        if previous.transitions.is_some() {
+            self.transitions = previous.transitions;
+            self.record_transition(TransitionCause::Update);
+        }
+        if let Some(task) = previous.task {
+            task.abort();
         }
     }
 
+    /// Let the future run to completion without the promise, instead of aborting it like dropping the promise does.
+    /// A future which was not spawned yet, because of [`ImmediateValuePromiseBuilder::lazy`], is spawned now.
+    /// Inline futures are only polled by the promise, so they are dropped.
+    pub fn detach(mut self) {
+        self.disarm_diagnostics();
+        if !self.inline {
+            if let Some(task) = self.deferred.take() {
+                self.task = Some(runtime::spawn_named(self.name.as_deref(), task));
+            }
+        }
+        if let Some(task) = self.task.take() {
+            task.detach();
+        }
+        if let Some(adopted) = self.adopted.take() {
+            adopted.detach();
+        }
+    }
+
+    /// Don't warn when this promise is dropped without being polled, it's intended
+    pub(crate) fn disarm_diagnostics(&mut self) {
+        #[cfg(all(debug_assertions, feature = "log"))]
+        self.diagnostics.disarm();
+    }
+
+    /// The name given by [`Self::new_named`]
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Creator for futures which should stop once the token is cancelled. The future is dropped on cancellation and
+    /// the promise switches to [`ImmediateValueState::Error`] with a [`crate::Cancelled`] error, even if the task
+    /// didn't get to run. A child token is used, so [`ImmediateValuePromise::cancel`] only cancels this promise.
+    #[cfg(feature = "tokio-util")]
+    #[cfg_attr(all(debug_assertions, feature = "log"), track_caller)]
+    pub fn new_with_token<U: Future<Output = Result<T, BoxedSendError>> + Send + 'static>(
+        updater: U,
+        token: &CancellationToken,
+    ) -> Self {
+        Self::builder().cancellation(token).spawn(updater)
+    }
+
+    /// Cancel this promise, see [`ImmediateValuePromise::new_with_token`]. Does nothing for other promises.
+    #[cfg(feature = "tokio-util")]
+    pub fn cancel(&self) {
+        if let Some(token) = &self.cancel_token {
+            token.cancel();
+        }
+    }
+
+    /// Set a hook which is called once with the error if the future fails, during the `poll_state` observing it.
+    /// Replaces the default hook set by [`crate::set_default_on_error`] for this promise.
+    pub fn with_on_error(mut self, hook: impl FnMut(&BoxedSendError) + Send + 'static) -> Self {
+        self.on_error = Some(Box::new(hook));
+        self
+    }
+
+    /// Set a hook which is called once with the final state, success or error, during the `poll_state` observing it.
+    /// Called after the error hook.
+    pub fn with_on_settle(
+        mut self,
+        hook: impl FnOnce(&ImmediateValueState<T>) + Send + 'static,
+    ) -> Self {
+        self.on_settle = Some(Box::new(hook));
+        self
+    }
+
+    /// Set a mapper turning errors into user-facing messages for [`ImmediateValuePromise::display_error`]
+    pub fn with_error_display(
+        mut self,
+        mapper: impl Fn(&BoxedSendError) -> String + Send + Sync + 'static,
+    ) -> Self {
+        self.error_display = Some(Box::new(mapper));
+        self
+    }
+
+    /// The user-facing message if in error state. Without a mapper, this is the error's `Display` output.
+    /// The original error stays accessible via [`DirectCacheAccess::get_result`].
+    pub fn display_error(&self) -> Option<String> {
+        match &self.state {
+            ImmediateValueState::Error(error) => Some(display_error(&self.error_display, error)),
+            _ => None,
+        }
+    }
+
+    /// A stream of the state changes of this promise, starting with the current one and ending with
+    /// [`StateChange::Finished`]. Allows reacting to the result in async code without polling the promise.
+    #[cfg(feature = "futures")]
+    pub fn state_stream(&self) -> impl futures_core::Stream<Item = StateChange> {
+        state_change_stream(self.state_changes.clone(), false)
+    }
+
+    /// Wait until the future ended, without polling the promise. Nothing is applied yet, call [`Self::poll_state`]
+    /// afterwards to observe the result. Resolves right away if the future ended since the last call, and never
+    /// resolves again afterwards or if the future panicked. Cancellation safe, so it can be used in `select!`.
+    pub async fn changed(&mut self) {
+        if self.tags.changed().await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    }
+
+    /// A watch of the state the future ended in, set by the spawned task itself so observers don't depend on the owner
+    /// polling the promise. It shows [`StateTag::Updating`] until then and never [`StateTag::Empty`], since taking
+    /// the value happens on the owner's side. If the future panics, the watch is closed.
+    pub fn state_watch(&self) -> watch::Receiver<StateTag> {
+        self.tags.clone()
+    }
+
     /// Poll the state updating the internal state from the running thread if possible, will return the data or error if ready or updating otherwise.
     pub fn poll_state(&mut self) -> &ImmediateValueState<T> {
-        if matches!(self.state, ImmediateValueState::Updating) {
-            let value = self.value_arc.try_lock();
-            if let Ok(mut guard) = value {
-                if let Some(result) = guard.take() {
-                    match result {
-                        Ok(value) => self.state = ImmediateValueState::Success(value),
-                        Err(e) => self.state = ImmediateValueState::Error(e),
-                    };
+        #[cfg(all(debug_assertions, feature = "log"))]
+        self.diagnostics.polled();
+        if self.inline {
+            if let Some(task) = self.deferred.as_mut() {
+                let mut cx = Context::from_waker(Waker::noop());
+                if task.as_mut().poll(&mut cx).is_ready() {
+                    self.deferred = None;
                 }
             }
+        } else if let Some(task) = self.deferred.take() {
+            self.task = Some(runtime::spawn_named(self.name.as_deref(), task));
+        }
+        let mut result = self.result_rx.as_mut().and_then(ResultSource::try_take);
+        if result.is_none() && self.result_rx.is_some() {
+            result = self.task_failure();
+        }
+        #[cfg(feature = "tokio-util")]
+        if result.is_none() && self.result_rx.is_some() && is_cancelled(&self.cancel_token) {
+            result = Some(Err(Cancelled.into()));
+        }
+        if let Some(result) = result {
+            let cause = match &result {
+                #[cfg(feature = "tokio-util")]
+                Err(error) if error.is_cancelled() => TransitionCause::Cancelled,
+                Err(error) if error.kind() == ErrorKind::Panicked => TransitionCause::UpdaterEnded,
+                _ => TransitionCause::Updater,
+            };
+            match result {
+                Ok(value) => self.state = ImmediateValueState::Success(value),
+                Err(e) => {
+                    report_error(&mut self.on_error, &e);
+                    self.state = ImmediateValueState::Error(e)
+                }
+            };
+            self.record_transition(cause);
+            self.result_rx = None;
+            // a cancelled inline future is not polled anymore
+            self.deferred = None;
+            if let Some(hook) = self.on_settle.take() {
+                hook(&self.state);
+            }
+        }
+        #[cfg(feature = "registry")]
+        if let Some(registration) = &self.registration {
+            registration.record_immediate_state(&self.state, self.get_progress());
         }
         &self.state
     }
 
+    /// Poll and get the value, spawning the future created by `factory` if the value was taken before.
+    /// Returns `None` while updating without spawning, and after an error without retrying.
+    /// The name and the error hooks are kept for the new future, cancellation and settle hooks are not.
+    pub fn get_or_spawn_with<U: Future<Output = Result<T, BoxedSendError>> + Send + 'static>(
+        &mut self,
+        factory: impl FnOnce() -> U,
+    ) -> Option<&T> {
+        if self.poll_state().is_empty() {
+            self.set_future(factory());
+        }
+        self.state.get_value()
+    }
+
     /// Poll the state, return a mutable ref to to the state
     pub fn poll_state_mut(&mut self) -> &mut ImmediateValueState<T> {
         let _ = self.poll_state();
         &mut self.state
     }
 
+    /// Poll the state like `poll_state`, returning an owned [`StateSnapshot`] which doesn't borrow the promise
+    pub fn poll_snapshot(&mut self) -> StateSnapshot {
+        let state = self.poll_state().tag();
+        StateSnapshot {
+            state,
+            error: self.display_error(),
+            has_value: self.has_value(),
+        }
+    }
+
     /// Get the current state without pulling. No mutability required
     pub fn get_state(&self) -> &ImmediateValueState<T> {
         &self.state
     }
 }
 
-impl<T, V> From<T> for ImmediateValuePromise<V> 
+#[cfg(feature = "tokio")]
+impl<T: Send + 'static> ImmediateValuePromise<T> {
+    /// Converts the result once it arrives, or right away if the promise is already resolved. Hooks and
+    /// cancellation are kept, [`Self::state_watch`] and [`Self::state_stream`] keep following the original future.
+    fn map_settled<U: Send + 'static>(
+        self,
+        f: impl FnOnce(FutureResult<T>) -> FutureResult<U> + Send + 'static,
+    ) -> ImmediateValuePromise<U> {
+        let mut on_error = self.on_error;
+        let (state, result_rx) = match (self.state, self.result_rx) {
+            (ImmediateValueState::Updating, Some(mut source)) => {
+                let mut on_settle = self.on_settle;
+                let mut f = Some(f);
+                let poll = move || {
+                    let mut result = source.try_take()?;
+                    if let Some(hook) = on_settle.take() {
+                        let state = match result {
+                            Ok(value) => ImmediateValueState::Success(value),
+                            Err(error) => ImmediateValueState::Error(error),
+                        };
+                        hook(&state);
+                        result = match state {
+                            ImmediateValueState::Success(value) => Ok(value),
+                            ImmediateValueState::Error(error) => Err(error),
+                            _ => return None,
+                        };
+                    }
+                    f.take().map(|f| f(result))
+                };
+                let source: ResultSource<U> = ResultSource::Mapped(Box::new(poll));
+                (ImmediateValueState::Updating, Some(source))
+            }
+            (ImmediateValueState::Updating, None) => (ImmediateValueState::Updating, None),
+            (ImmediateValueState::Success(value), _) => match f(Ok(value)) {
+                Ok(value) => (ImmediateValueState::Success(value), None),
+                Err(error) => {
+                    report_error(&mut on_error, &error);
+                    (ImmediateValueState::Error(error), None)
+                }
+            },
+            (ImmediateValueState::Error(error), _) => match f(Err(error)) {
+                Ok(value) => (ImmediateValueState::Success(value), None),
+                Err(error) => (ImmediateValueState::Error(error), None),
+            },
+            (ImmediateValueState::Empty, _) => (ImmediateValueState::Empty, None),
+        };
+        ImmediateValuePromise {
+            result_rx,
+            state,
+            on_error,
+            error_display: self.error_display,
+            state_changes: self.state_changes,
+            tags: self.tags,
+            #[cfg(feature = "tokio-util")]
+            cancel_token: self.cancel_token,
+            name: self.name,
+            on_settle: None,
+            deferred: self.deferred,
+            inline: self.inline,
+            task: self.task,
+            adopted: self.adopted,
+            progress: self.progress,
+            zipped: self.zipped,
+            transitions: None,
+            #[cfg(all(debug_assertions, feature = "log"))]
+            diagnostics: self.diagnostics,
+            #[cfg(feature = "registry")]
+            registration: self.registration,
+        }
+    }
+
+    /// A promise resolving with both values once both promises resolved, e.g. to render a page needing two
+    /// requests. Polling it polls both promises. It fails as soon as one of them fails, the other promise is
+    /// dropped then, which aborts its future. [`Self::pending_parts`] tells which promise is still pending.
+    /// [`Self::state_watch`] and [`Self::state_stream`] only see the result once it was polled.
+    /// ```rust
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use lazy_async_promise::ImmediateValuePromise;
+    /// let profile = ImmediateValuePromise::new(async { Ok("ferris") });
+    /// let (permissions_tx, permissions_rx) = tokio::sync::oneshot::channel();
+    /// let permissions = ImmediateValuePromise::new(async { Ok(permissions_rx.await?) });
+    /// let mut page = ImmediateValuePromise::zip(profile, permissions);
+    /// tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    /// assert!(page.poll_state().is_updating());
+    /// assert_eq!(page.pending_parts(), [1]);
+    /// permissions_tx.send(vec!["read"]).unwrap();
+    /// tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    /// assert_eq!(page.poll_state().as_success(), Some(&("ferris", vec!["read"])));
+    /// # }
+    /// ```
+    pub fn zip<U: Send + 'static>(
+        first: Self,
+        second: ImmediateValuePromise<U>,
+    ) -> ImmediateValuePromise<(T, U)> {
+        let (mut first, mut second) = (first, second);
+        ImmediateValuePromise::combined(2, move |pending| {
+            let first_ready = poll_part(&mut first, 0, pending)?;
+            let second_ready = poll_part(&mut second, 1, pending)?;
+            if !(first_ready && second_ready) {
+                return Ok(None);
+            }
+            Ok(first.take_value().zip(second.take_value()))
+        })
+    }
+
+    /// Like [`Self::zip`] for three promises
+    pub fn zip3<U: Send + 'static, V: Send + 'static>(
+        first: Self,
+        second: ImmediateValuePromise<U>,
+        third: ImmediateValuePromise<V>,
+    ) -> ImmediateValuePromise<(T, U, V)> {
+        let (mut first, mut second, mut third) = (first, second, third);
+        ImmediateValuePromise::combined(3, move |pending| {
+            let first_ready = poll_part(&mut first, 0, pending)?;
+            let second_ready = poll_part(&mut second, 1, pending)?;
+            let third_ready = poll_part(&mut third, 2, pending)?;
+            if !(first_ready && second_ready && third_ready) {
+                return Ok(None);
+            }
+            let values = first.take_value().zip(second.take_value());
+            Ok(values
+                .zip(third.take_value())
+                .map(|((first, second), third)| (first, second, third)))
+        })
+    }
+
+    /// The indices of the zipped promises which are still pending, see [`Self::zip`]. Empty for other promises.
+    pub fn pending_parts(&self) -> Vec<usize> {
+        let pending = self
+            .zipped
+            .as_ref()
+            .map_or(0, |zipped| zipped.load(Ordering::Relaxed));
+        (0..u8::BITS as usize)
+            .filter(|index| pending & (1 << index) != 0)
+            .collect()
+    }
+
+    /// A promise resolving by polling `parts` other promises, the result is `None` while they are pending
+    fn combined(
+        parts: u8,
+        mut poll: impl FnMut(&AtomicU8) -> Result<Option<T>, BoxedSendError> + Send + 'static,
+    ) -> Self {
+        let zipped = Arc::new(AtomicU8::new((1 << parts) - 1));
+        let (state_changes_tx, state_changes) = watch::channel(Some(StateChange::Started));
+        let (tag_tx, tags) = watch::channel(StateTag::Updating);
+        let pending = zipped.clone();
+        let source = move || {
+            let result = poll(&pending).transpose()?;
+            let summary = result.as_ref().map(|_| ()).map_err(ToString::to_string);
+            tag_tx.send_replace(match result {
+                Ok(_) => StateTag::Success,
+                Err(_) => StateTag::Error,
+            });
+            state_changes_tx.send_replace(Some(StateChange::Finished(summary)));
+            Some(result)
+        };
+        let mut promise = Self::empty();
+        promise.result_rx = Some(ResultSource::Mapped(Box::new(source)));
+        promise.state = ImmediateValueState::Updating;
+        promise.state_changes = state_changes;
+        promise.tags = tags;
+        promise.zipped = Some(zipped);
+        promise
+    }
+}
+
+/// Poll one promise of a zip, whether it has a value. Clears its bit in `pending` once it settled.
+#[cfg(feature = "tokio")]
+fn poll_part<T: Send + 'static>(
+    promise: &mut ImmediateValuePromise<T>,
+    index: u8,
+    pending: &AtomicU8,
+) -> Result<bool, BoxedSendError> {
+    let result = match promise.poll_state().tag() {
+        StateTag::Updating => return Ok(false),
+        StateTag::Success => Ok(true),
+        StateTag::Error | StateTag::Empty => Err(promise.take_error().unwrap_or_else(|| {
+            BoxedSendError::msg("value was already taken").with_kind(ErrorKind::Runtime)
+        })),
+    };
+    pending.fetch_and(!(1 << index), Ordering::Relaxed);
+    result
+}
+
+#[cfg(feature = "tokio")]
+impl<T, E> ImmediateValuePromise<Result<T, E>>
+where
+    T: Send + 'static,
+    E: Into<BoxedSendError> + Send + 'static,
+{
+    /// Turns an inner `Err` into the error state of the promise, so the value doesn't have to be unwrapped twice.
+    /// Happens when the result arrives, or right away if the promise is already resolved.
+    /// [`Self::state_watch`] and [`Self::state_stream`] keep reporting the outer result.
+    /// ```rust, no_run
+    /// use lazy_async_promise::ImmediateValuePromise;
+    /// let promise: ImmediateValuePromise<String> =
+    ///     ImmediateValuePromise::new(async { Ok(std::fs::read_to_string("config.toml")) }).flatten_result();
+    /// ```
+    pub fn flatten_result(self) -> ImmediateValuePromise<T> {
+        self.map_settled(|result| result.and_then(|inner| inner.map_err(Into::into)))
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<T, E> ImmediateValuePromise<Option<Result<T, E>>>
+where
+    T: Send + 'static,
+    E: Into<BoxedSendError> + Send + 'static,
+{
+    /// Like [`Option::transpose`], turns an inner `Some(Err)` into the error state of the promise, see
+    /// [`ImmediateValuePromise::flatten_result`]
+    pub fn transpose_result(self) -> ImmediateValuePromise<Option<T>> {
+        self.map_settled(|result| result.and_then(|inner| inner.transpose().map_err(Into::into)))
+    }
+}
+
+/// Prints the state and whether a value is cached, without requiring `T: Debug`
+#[cfg(feature = "tokio")]
+impl<T: Send> Debug for ImmediateValuePromise<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ImmediateValuePromise")
+            .field("name", &self.name)
+            .field("state", &self.state.tag())
+            .field("has_value", &self.state.is_success())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<T: Send + 'static> Default for ImmediateValuePromise<T> {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<T, V> From<T> for ImmediateValuePromise<V>
 where
     T: Future<Output = V> + Send + 'static,
-    V: Send + 'static
+    V: Send + 'static,
 {
     fn from(value: T) -> Self {
-        ImmediateValuePromise::new(async move {
-            Ok(value.await)
-        })
+        ImmediateValuePromise::new(async move { Ok(value.await) })
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "tokio"))]
 mod test {
     use std::fs::File;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
     use std::time::Duration;
 
-    use crate::immediatevalue::{ImmediateValuePromise, ImmediateValueState};
-    use crate::DirectCacheAccess;
+    use crate::immediatevalue::{ImmediateValuePromise, ImmediateValueState, StateTag};
+    use crate::runtime::sleep;
+    use crate::{BoxedSendError, DirectCacheAccess};
 
     #[tokio::test]
     async fn default() {
         let mut oneshot_val = ImmediateValuePromise::new(async {
-            tokio::time::sleep(Duration::from_millis(50)).await;
+            sleep(Duration::from_millis(50)).await;
             Ok(34)
         });
         assert!(matches!(
             oneshot_val.poll_state(),
             ImmediateValueState::Updating
         ));
-        tokio::time::sleep(Duration::from_millis(100)).await;
+        sleep(Duration::from_millis(100)).await;
         let result = oneshot_val.poll_state();
         if let ImmediateValueState::Success(val) = result {
             assert_eq!(*val, 34);
@@ -273,6 +1214,7 @@ mod test {
     #[tokio::test]
     async fn error() {
         let mut oneshot_val = ImmediateValuePromise::new(async {
+            sleep(Duration::from_millis(10)).await;
             let some_result = File::open("DOES_NOT_EXIST");
             some_result?;
             Ok("bla".to_string())
@@ -281,7 +1223,7 @@ mod test {
             oneshot_val.poll_state(),
             ImmediateValueState::Updating
         ));
-        tokio::time::sleep(Duration::from_millis(50)).await;
+        sleep(Duration::from_millis(50)).await;
         let result = oneshot_val.poll_state();
         if let ImmediateValueState::Error(e) = result {
             let _ = format!("{}", **e);
@@ -296,7 +1238,7 @@ mod test {
         // get value does not trigger any polling
         let state = oneshot_val.get_state();
         assert!(matches!(state, ImmediateValueState::Updating));
-        tokio::time::sleep(Duration::from_millis(50)).await;
+        sleep(Duration::from_millis(50)).await;
         let state = oneshot_val.get_state();
         assert!(matches!(state, ImmediateValueState::Updating));
 
@@ -307,7 +1249,7 @@ mod test {
     #[tokio::test]
     async fn get_mut_take_value() {
         let mut oneshot_val = ImmediateValuePromise::new(async { Ok("bla".to_string()) });
-        tokio::time::sleep(Duration::from_millis(50)).await;
+        sleep(Duration::from_millis(50)).await;
         {
             // get value does not trigger any polling
             let result = oneshot_val.poll_state_mut();
@@ -336,11 +1278,328 @@ mod test {
         ));
     }
 
+    #[tokio::test]
+    async fn mapping_and_cloning() {
+        let mut oneshot_val = ImmediateValuePromise::new(async { Ok("bla".to_string()) });
+        assert!(oneshot_val.get_value_cloned().is_none());
+        assert!(oneshot_val.map_value(|v| v.len()).is_none());
+        sleep(Duration::from_millis(50)).await;
+        oneshot_val.poll_state();
+        assert_eq!(oneshot_val.get_value_cloned().unwrap(), "bla");
+        assert_eq!(oneshot_val.map_value(|v| v.len()), Some(3));
+        assert_eq!(oneshot_val.map_result(|r| r.is_ok()), Some(true));
+        // the cache is untouched by the helpers
+        assert_eq!(oneshot_val.take_value().unwrap(), "bla");
+    }
+
+    #[tokio::test]
+    async fn predicates() {
+        let mut oneshot_val = ImmediateValuePromise::new(async {
+            sleep(Duration::from_millis(20)).await;
+            Ok(34)
+        });
+        assert!(oneshot_val.poll_state().is_updating());
+        assert!(oneshot_val.is_pending());
+        assert!(!oneshot_val.has_value() && !oneshot_val.has_error());
+        oneshot_val.changed().await;
+        assert!(oneshot_val.poll_state().is_success());
+        assert!(oneshot_val.has_value() && !oneshot_val.is_pending());
+        oneshot_val.take_value();
+        assert!(oneshot_val.poll_state().is_empty());
+        assert!(!oneshot_val.has_value() && !oneshot_val.has_error() && !oneshot_val.is_pending());
+
+        let mut error_val = ImmediateValuePromise::<()>::new(async {
+            sleep(Duration::from_millis(10)).await;
+            File::open("DOES_NOT_EXIST")?;
+            Ok(())
+        });
+        error_val.changed().await;
+        assert!(error_val.poll_state().is_error());
+        assert!(error_val.has_error() && !error_val.has_value() && !error_val.is_pending());
+        let state = error_val.poll_state_mut();
+        assert!(state.has_error() && !state.is_pending());
+    }
+
+    #[tokio::test]
+    async fn take_error() {
+        let mut error_val = ImmediateValuePromise::<()>::new(async {
+            sleep(Duration::from_millis(10)).await;
+            File::open("DOES_NOT_EXIST")?;
+            Ok(())
+        });
+        sleep(Duration::from_millis(50)).await;
+        assert!(error_val.poll_state().is_error());
+        assert!(error_val.take_error().is_some());
+        assert!(error_val.poll_state().is_empty());
+        assert!(error_val.take_error().is_none());
+
+        let mut oneshot_val = ImmediateValuePromise::new(async { Ok(34) });
+        sleep(Duration::from_millis(50)).await;
+        assert!(oneshot_val.poll_state().is_success());
+        assert!(oneshot_val.take_error().is_none());
+        assert_eq!(*oneshot_val.get_value().unwrap(), 34);
+    }
+
+    #[tokio::test]
+    async fn get_result_mut() {
+        let mut oneshot_val = ImmediateValuePromise::new(async { Ok(vec![3, 1, 2]) });
+        assert!(oneshot_val.get_result_mut().is_none());
+        sleep(Duration::from_millis(50)).await;
+        oneshot_val.poll_state();
+        if let Some(Ok(values)) = oneshot_val.get_result_mut() {
+            values.sort();
+        } else {
+            unreachable!();
+        }
+        assert_eq!(oneshot_val.poll_state().get_value().unwrap(), &[1, 2, 3]);
+    }
+
+    #[test]
+    fn detached_state_cache_access() {
+        fn take_doubled(cache: &mut impl DirectCacheAccess<i32, BoxedSendError>) -> Option<i32> {
+            cache.take_value().map(|v| v * 2)
+        }
+        // no runtime required for bare states
+        let mut state = ImmediateValueState::Success(21);
+        assert_eq!(take_doubled(&mut state), Some(42));
+        assert!(state.is_empty());
+        assert_eq!(take_doubled(&mut state), None);
+
+        let mut state = ImmediateValueState::<i32>::Error(std::io::Error::other("bla").into());
+        assert!(state.take_result().unwrap().is_err());
+        assert!(state.is_empty());
+    }
+
+    #[tokio::test]
+    async fn debug_without_debug_value() {
+        struct Opaque;
+        let mut promise = ImmediateValuePromise::new_named("opaque", async { Ok(Opaque) });
+        assert_eq!(
+            format!("{promise:?}"),
+            r#"ImmediateValuePromise { name: Some("opaque"), state: Updating, has_value: false, .. }"#
+        );
+        sleep(Duration::from_millis(50)).await;
+        promise.poll_state();
+        assert_eq!(
+            format!("{promise:?}"),
+            r#"ImmediateValuePromise { name: Some("opaque"), state: Success, has_value: true, .. }"#
+        );
+    }
+
+    #[test]
+    fn display_state() {
+        assert_eq!(ImmediateValueState::<i32>::Updating.to_string(), "updating");
+        assert_eq!(ImmediateValueState::Success(vec![1]).to_string(), "success");
+        assert_eq!(ImmediateValueState::<i32>::Empty.to_string(), "empty");
+        let state = ImmediateValueState::<i32>::Error(BoxedSendError::msg("timeout"));
+        assert_eq!(state.to_string(), "error: timeout");
+    }
+
+    #[tokio::test]
+    async fn on_error_hook() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        let calls = Arc::new(AtomicUsize::new(0));
+        let calls_hook = calls.clone();
+        let mut error_val = ImmediateValuePromise::<()>::new(async {
+            sleep(Duration::from_millis(10)).await;
+            File::open("DOES_NOT_EXIST")?;
+            Ok(())
+        })
+        .with_on_error(move |_| {
+            calls_hook.fetch_add(1, Ordering::SeqCst);
+        });
+        error_val.poll_state();
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+        sleep(Duration::from_millis(50)).await;
+        assert!(error_val.poll_state().is_error());
+        assert!(error_val.poll_state().is_error());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn error_display() {
+        let mut error_val = ImmediateValuePromise::<()>::new(async {
+            Err(BoxedSendError::msg("ERR_CODE 5012: upstream shard timeout"))
+        })
+        .with_error_display(|_| "The server is busy, please try again".to_string());
+        assert!(error_val.display_error().is_none());
+        sleep(Duration::from_millis(50)).await;
+        error_val.poll_state();
+        assert_eq!(
+            error_val.display_error().unwrap(),
+            "The server is busy, please try again"
+        );
+        let Some(Err(error)) = error_val.get_result() else {
+            unreachable!();
+        };
+        assert_eq!(error.to_string(), "ERR_CODE 5012: upstream shard timeout");
+    }
+
+    #[tokio::test]
+    async fn named() {
+        let mut promise = ImmediateValuePromise::new_named("load_invoices", async { Ok(34) });
+        assert_eq!(promise.name(), Some("load_invoices"));
+        assert!(ImmediateValuePromise::new(async { Ok(34) })
+            .name()
+            .is_none());
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(promise.poll_state().get_value(), Some(&34));
+    }
+
+    #[tokio::test]
+    async fn state_watch() {
+        let promise = ImmediateValuePromise::new(async {
+            sleep(Duration::from_millis(10)).await;
+            Ok(34)
+        });
+        let mut watch = promise.state_watch();
+        assert_eq!(*watch.borrow(), StateTag::Updating);
+        watch
+            .wait_for(|tag| *tag != StateTag::Updating)
+            .await
+            .unwrap();
+        assert_eq!(*watch.borrow(), StateTag::Success);
+        assert_eq!(promise.get_state().tag(), StateTag::Updating);
+    }
+
+    #[tokio::test]
+    async fn set_value() {
+        let mut oneshot_val = ImmediateValuePromise::new(async {
+            sleep(Duration::from_millis(50)).await;
+            Ok(34)
+        });
+        // injecting while updating is visible immediately
+        assert!(oneshot_val.set_value(12).is_none());
+        assert_eq!(*oneshot_val.poll_state().get_value().unwrap(), 12);
+        // but the in-flight result overwrites it when it lands
+        sleep(Duration::from_millis(100)).await;
+        assert_eq!(*oneshot_val.poll_state().get_value().unwrap(), 34);
+        assert_eq!(oneshot_val.set_value(1), Some(34));
+        assert_eq!(*oneshot_val.poll_state().get_value().unwrap(), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn many_short_lived_promises() {
+        let mut promises: Vec<_> = (0..5000)
+            .map(|i| {
+                ImmediateValuePromise::new(async move {
+                    if i % 2 == 0 {
+                        Ok(i)
+                    } else {
+                        Err(BoxedSendError::msg("odd"))
+                    }
+                })
+            })
+            .collect();
+        // dropping promises with running tasks must not disturb the others
+        promises.truncate(4000);
+        sleep(Duration::from_millis(200)).await;
+        for (i, promise) in promises.iter_mut().enumerate() {
+            match promise.poll_state() {
+                ImmediateValueState::Success(value) => assert_eq!(*value, i),
+                ImmediateValueState::Error(_) => assert_eq!(i % 2, 1),
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn drop_aborts_unless_detached() {
+        let finished = Arc::new(AtomicU32::new(0));
+        let future = |finished: Arc<AtomicU32>| async move {
+            sleep(Duration::from_millis(30)).await;
+            finished.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        };
+        let dropped = ImmediateValuePromise::new(future(finished.clone()));
+        let detached = ImmediateValuePromise::new(future(finished.clone()));
+        let lazy = ImmediateValuePromise::builder()
+            .lazy()
+            .spawn(future(finished.clone()));
+        drop(dropped);
+        detached.detach();
+        lazy.detach();
+        sleep(Duration::from_millis(100)).await;
+        assert_eq!(finished.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn zip_fails_fast() {
+        let finished = Arc::new(AtomicU32::new(0));
+        let slow_finished = finished.clone();
+        let slow = ImmediateValuePromise::new(async move {
+            sleep(Duration::from_millis(80)).await;
+            slow_finished.fetch_add(1, Ordering::SeqCst);
+            Ok(34)
+        });
+        let failing = ImmediateValuePromise::<String>::new(async {
+            sleep(Duration::from_millis(20)).await;
+            Err(BoxedSendError::msg("forbidden"))
+        });
+        let ready = ImmediateValuePromise::new(async { Ok(1.5) });
+        let mut zipped = ImmediateValuePromise::zip3(slow, failing, ready);
+        assert_eq!(zipped.pending_parts(), [0, 1, 2]);
+        sleep(Duration::from_millis(10)).await;
+        assert!(zipped.poll_state().is_updating());
+        assert_eq!(zipped.pending_parts(), [0, 1]);
+        sleep(Duration::from_millis(30)).await;
+        assert!(zipped.poll_state().is_error());
+        assert_eq!(zipped.display_error().as_deref(), Some("forbidden"));
+        assert_eq!(*zipped.state_watch().borrow(), StateTag::Error);
+        sleep(Duration::from_millis(100)).await;
+        assert_eq!(finished.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn adopted_join_handles() {
+        let finished = Arc::new(AtomicU32::new(0));
+        let task_finished = finished.clone();
+        let mut adopted = ImmediateValuePromise::from_join_handle(tokio::spawn(async move {
+            sleep(Duration::from_millis(20)).await;
+            task_finished.fetch_add(1, Ordering::SeqCst);
+            Err::<(), _>(BoxedSendError::msg("not found"))
+        }));
+        assert!(adopted.poll_state().is_updating());
+        sleep(Duration::from_millis(50)).await;
+        assert!(adopted.poll_state().is_error());
+        assert_eq!(adopted.take_error().unwrap().to_string(), "not found");
+        assert_eq!(*adopted.state_watch().borrow(), StateTag::Error);
+
+        let panicking = tokio::spawn(async { panic!("boom") });
+        let mut panicked =
+            ImmediateValuePromise::<()>::from_join_handle::<BoxedSendError>(panicking);
+        sleep(Duration::from_millis(20)).await;
+        let error = panicked.poll_state().as_error().unwrap();
+        assert_eq!(error.kind(), crate::ErrorKind::Panicked);
+
+        let task_finished = finished.clone();
+        let dropped = ImmediateValuePromise::from_join_handle(tokio::spawn(async move {
+            sleep(Duration::from_millis(20)).await;
+            task_finished.fetch_add(1, Ordering::SeqCst);
+            Ok::<_, BoxedSendError>(())
+        }));
+        drop(dropped);
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(finished.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn panicking_future_fails() {
+        let mut promise = ImmediateValuePromise::<()>::new(async { panic!("boom") });
+        while promise.poll_state().is_updating() {
+            sleep(Duration::from_millis(5)).await;
+        }
+        let error = promise.poll_state().as_error().unwrap();
+        assert_eq!(error.kind(), crate::ErrorKind::Panicked);
+        assert_eq!(error.to_string(), "updater panicked: boom");
+        assert_eq!(*promise.state_watch().borrow(), StateTag::Updating);
+    }
+
     #[tokio::test]
     async fn option_laziness() {
         use crate::*;
         let mut option = Some(ImmediateValuePromise::new(async { Ok("bla".to_string()) }));
-        tokio::time::sleep(Duration::from_millis(50)).await;
+        sleep(Duration::from_millis(50)).await;
         option.as_mut().unwrap().poll_state();
         let _inner = option.get_value();
         let _inner_mut = option.get_value_mut();
@@ -351,4 +1610,140 @@ mod test {
         assert!(option.get_value_mut().is_none());
         assert!(option.take_value().is_none());
     }
+
+    #[tokio::test]
+    async fn flatten_result() {
+        let errors = Arc::new(AtomicU32::new(0));
+        let errors_hook = errors.clone();
+        let mut pending = ImmediateValuePromise::new(async {
+            sleep(Duration::from_millis(20)).await;
+            Ok(File::open("whatever.txt"))
+        })
+        .with_on_error(move |_| {
+            errors_hook.fetch_add(1, Ordering::SeqCst);
+        })
+        .flatten_result();
+        assert!(pending.poll_state().is_updating());
+        sleep(Duration::from_millis(50)).await;
+        assert!(pending.poll_state().is_error());
+        assert_eq!(errors.load(Ordering::SeqCst), 1);
+
+        let mut resolved = ImmediateValuePromise::new(async { Ok(Ok::<_, BoxedSendError>(34)) });
+        sleep(Duration::from_millis(50)).await;
+        resolved.poll_state();
+        let mut flattened = resolved.flatten_result();
+        assert_eq!(flattened.poll_state().get_value(), Some(&34));
+    }
+
+    #[tokio::test]
+    async fn transpose_result() {
+        let mut some_error = ImmediateValuePromise::new(async {
+            Ok(Some(Err::<i32, _>(BoxedSendError::msg("offline"))))
+        })
+        .transpose_result();
+        let mut none =
+            ImmediateValuePromise::new(async { Ok(None::<Result<i32, BoxedSendError>>) })
+                .transpose_result();
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(some_error.poll_state().to_string(), "error: offline");
+        assert_eq!(none.poll_state().get_value(), Some(&None));
+    }
+
+    #[tokio::test]
+    async fn get_or_spawn_with() {
+        let spawned = Arc::new(AtomicU32::new(0));
+        let factory = || {
+            spawned.fetch_add(1, Ordering::SeqCst);
+            async {
+                sleep(Duration::from_millis(10)).await;
+                Ok(34)
+            }
+        };
+        let mut promise = ImmediateValuePromise::new(async { Ok(1) });
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(promise.get_or_spawn_with(factory), Some(&1));
+        assert_eq!(promise.take_value(), Some(1));
+        assert_eq!(promise.get_or_spawn_with(factory), None);
+        // still updating, nothing is spawned
+        assert_eq!(promise.get_or_spawn_with(factory), None);
+        assert_eq!(spawned.load(Ordering::SeqCst), 1);
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(promise.get_or_spawn_with(factory), Some(&34));
+        assert_eq!(spawned.load(Ordering::SeqCst), 1);
+
+        let mut failed = ImmediateValuePromise::new(async { Err(BoxedSendError::msg("offline")) });
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(failed.get_or_spawn_with(factory), None);
+        assert!(failed.get_state().is_error());
+        assert_eq!(spawned.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn empty_then_set_future() {
+        let mut promise = ImmediateValuePromise::<i32>::default();
+        assert!(promise.poll_state().is_empty());
+        sleep(Duration::from_millis(20)).await;
+        assert!(promise.poll_state().is_empty());
+
+        let finished = Arc::new(AtomicU32::new(0));
+        let finished_task = finished.clone();
+        promise.set_future(async move {
+            sleep(Duration::from_millis(50)).await;
+            finished_task.fetch_add(1, Ordering::SeqCst);
+            Ok(1)
+        });
+        // replacing aborts the first future
+        promise.set_future(async { Ok(34) });
+        sleep(Duration::from_millis(100)).await;
+        assert_eq!(promise.poll_state().get_value(), Some(&34));
+        assert_eq!(finished.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn inline_steps_per_poll() {
+        let steps = Arc::new(AtomicU32::new(0));
+        let steps_future = steps.clone();
+        let mut promise = ImmediateValuePromise::new_inline(async move {
+            for _ in 0..2 {
+                steps_future.fetch_add(1, Ordering::SeqCst);
+                // pending once, without scheduling a wakeup
+                let mut yielded = false;
+                std::future::poll_fn(|_| {
+                    if std::mem::replace(&mut yielded, true) {
+                        std::task::Poll::Ready(())
+                    } else {
+                        std::task::Poll::Pending
+                    }
+                })
+                .await;
+            }
+            Ok(34)
+        });
+        assert_eq!(steps.load(Ordering::SeqCst), 0);
+        assert!(promise.poll_state().is_updating());
+        assert_eq!(steps.load(Ordering::SeqCst), 1);
+        assert!(promise.poll_state().is_updating());
+        assert_eq!(steps.load(Ordering::SeqCst), 2);
+        assert_eq!(promise.poll_state().get_value(), Some(&34));
+        assert_eq!(promise.take_value(), Some(34));
+        assert!(promise.poll_state().is_empty());
+    }
+
+    #[tokio::test]
+    async fn changed() {
+        let mut promise = ImmediateValuePromise::new(async {
+            sleep(Duration::from_millis(10)).await;
+            Ok(34)
+        });
+        tokio::time::timeout(Duration::from_secs(1), promise.changed())
+            .await
+            .unwrap();
+        assert_eq!(promise.poll_state().get_value(), Some(&34));
+        // no further changes
+        assert!(
+            tokio::time::timeout(Duration::from_millis(20), promise.changed())
+                .await
+                .is_err()
+        );
+    }
 }
@@ -0,0 +1,320 @@
+use std::future::Future;
+use std::marker::PhantomData;
+use std::time::Duration;
+
+#[cfg(feature = "tokio-util")]
+use tokio_util::sync::CancellationToken;
+
+use crate::immediatevalue::SettleHook;
+use crate::runtime::{sleep, timeout, TimedOut};
+#[cfg(feature = "tokio-util")]
+use crate::Cancelled;
+use crate::{
+    BoxedSendError, ErrorDisplay, ErrorHook, FutureResult, ImmediateValuePromise,
+    ImmediateValueState, RetryPolicy,
+};
+
+/// Mode of an [`ImmediateValuePromiseBuilder`] spawning a single future
+pub struct OneShot;
+
+/// Mode of an [`ImmediateValuePromiseBuilder`] with a [`RetryPolicy`], which needs a factory creating a new future
+/// for each attempt
+pub struct Retrying(RetryPolicy);
+
+/// # Builder for [`ImmediateValuePromise`]s with more options
+/// Created by [`ImmediateValuePromise::builder`] and finished with [`Self::spawn`] or [`Self::spawn_with`].
+/// Combinations which can't work are rejected at compile time: after [`Self::retries`], a one-shot future can't be
+/// retried, so only [`Self::spawn_with`] is available.
+/// ```rust, no_run
+/// use std::time::Duration;
+/// use lazy_async_promise::{ImmediateValuePromise, RetryPolicy};
+/// let promise = ImmediateValuePromise::builder()
+///     .name("fetch_config")
+///     .timeout(Duration::from_secs(5))
+///     .retries(RetryPolicy::new(3).with_backoff(Duration::from_millis(100), 2.0))
+///     .on_settle(|state| println!("config: {state}"))
+///     .lazy()
+///     .spawn_with(|| async { Ok(34) });
+/// ```
+pub struct ImmediateValuePromiseBuilder<T, Mode = OneShot> {
+    mode: Mode,
+    name: Option<String>,
+    timeout: Option<Duration>,
+    lazy: bool,
+    on_error: Option<ErrorHook>,
+    on_settle: Option<SettleHook<T>>,
+    error_display: Option<ErrorDisplay>,
+    #[cfg(feature = "tokio-util")]
+    token: Option<CancellationToken>,
+    value: PhantomData<fn() -> T>,
+}
+
+impl<T: Send + 'static> ImmediateValuePromiseBuilder<T> {
+    pub(crate) fn new() -> Self {
+        ImmediateValuePromiseBuilder {
+            mode: OneShot,
+            name: None,
+            timeout: None,
+            lazy: false,
+            on_error: None,
+            on_settle: None,
+            error_display: None,
+            #[cfg(feature = "tokio-util")]
+            token: None,
+            value: PhantomData,
+        }
+    }
+
+    /// Retry failing attempts according to the policy. While retrying, the promise stays
+    /// [`ImmediateValueState::Updating`] and only the final failure is reported as error.
+    pub fn retries(self, policy: RetryPolicy) -> ImmediateValuePromiseBuilder<T, Retrying> {
+        ImmediateValuePromiseBuilder {
+            mode: Retrying(policy),
+            name: self.name,
+            timeout: self.timeout,
+            lazy: self.lazy,
+            on_error: self.on_error,
+            on_settle: self.on_settle,
+            error_display: self.error_display,
+            #[cfg(feature = "tokio-util")]
+            token: self.token,
+            value: PhantomData,
+        }
+    }
+
+    /// Build the promise, spawning the future
+    #[cfg_attr(all(debug_assertions, feature = "log"), track_caller)]
+    pub fn spawn(
+        self,
+        updater: impl Future<Output = FutureResult<T>> + Send + 'static,
+    ) -> ImmediateValuePromise<T> {
+        let updater = with_timeout(self.timeout, updater);
+        self.build(updater)
+    }
+
+    /// Build the promise, spawning the future created by the factory
+    #[cfg_attr(all(debug_assertions, feature = "log"), track_caller)]
+    pub fn spawn_with<Fut: Future<Output = FutureResult<T>> + Send + 'static>(
+        self,
+        factory: impl FnOnce() -> Fut,
+    ) -> ImmediateValuePromise<T> {
+        self.spawn(factory())
+    }
+}
+
+impl<T: Send + 'static> ImmediateValuePromiseBuilder<T, Retrying> {
+    /// Build the promise, spawning a future created by the factory for each attempt
+    #[cfg_attr(all(debug_assertions, feature = "log"), track_caller)]
+    pub fn spawn_with<Fut: Future<Output = FutureResult<T>> + Send + 'static>(
+        self,
+        factory: impl Fn() -> Fut + Send + 'static,
+    ) -> ImmediateValuePromise<T> {
+        let policy = self.mode.0.clone();
+        let attempt_timeout = self.timeout;
+        self.build(async move {
+            let mut retries_done = 0;
+            loop {
+                match with_timeout(attempt_timeout, factory()).await {
+                    Err(error) if policy.should_retry(retries_done, &error) => {
+                        sleep(policy.delay_for(retries_done)).await;
+                        retries_done += 1;
+                    }
+                    result => return result,
+                }
+            }
+        })
+    }
+}
+
+impl<T: Send + 'static, Mode> ImmediateValuePromiseBuilder<T, Mode> {
+    /// Name the promise and its task, see [`ImmediateValuePromise::new_named`]
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Fail with a [`TimedOut`] error if the future takes longer. With retries, this limits each attempt.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Don't spawn the future before the promise is polled for the first time
+    pub fn lazy(mut self) -> Self {
+        self.lazy = true;
+        self
+    }
+
+    /// See [`ImmediateValuePromise::with_on_error`]
+    pub fn on_error(mut self, hook: impl FnMut(&BoxedSendError) + Send + 'static) -> Self {
+        self.on_error = Some(Box::new(hook));
+        self
+    }
+
+    /// See [`ImmediateValuePromise::with_on_settle`]
+    pub fn on_settle(
+        mut self,
+        hook: impl FnOnce(&ImmediateValueState<T>) + Send + 'static,
+    ) -> Self {
+        self.on_settle = Some(Box::new(hook));
+        self
+    }
+
+    /// See [`ImmediateValuePromise::with_error_display`]
+    pub fn error_display(
+        mut self,
+        mapper: impl Fn(&BoxedSendError) -> String + Send + Sync + 'static,
+    ) -> Self {
+        self.error_display = Some(Box::new(mapper));
+        self
+    }
+
+    /// Stop once the token is cancelled, see [`ImmediateValuePromise::new_with_token`]
+    #[cfg(feature = "tokio-util")]
+    pub fn cancellation(mut self, token: &CancellationToken) -> Self {
+        self.token = Some(token.child_token());
+        self
+    }
+
+    #[cfg_attr(all(debug_assertions, feature = "log"), track_caller)]
+    fn build(
+        self,
+        updater: impl Future<Output = FutureResult<T>> + Send + 'static,
+    ) -> ImmediateValuePromise<T> {
+        #[cfg(feature = "tokio-util")]
+        let updater = {
+            let task_token = self.token.clone();
+            async move {
+                match task_token {
+                    Some(token) => token
+                        .run_until_cancelled(updater)
+                        .await
+                        .unwrap_or_else(|| Err(Cancelled.into())),
+                    None => updater.await,
+                }
+            }
+        };
+        let mut promise = ImmediateValuePromise::spawn(updater, self.name, self.lazy);
+        promise.on_error = self.on_error;
+        promise.on_settle = self.on_settle;
+        promise.error_display = self.error_display;
+        #[cfg(feature = "tokio-util")]
+        {
+            promise.cancel_token = self.token;
+        }
+        promise
+    }
+}
+
+async fn with_timeout<T>(
+    duration: Option<Duration>,
+    updater: impl Future<Output = FutureResult<T>>,
+) -> FutureResult<T> {
+    match duration {
+        Some(duration) => timeout(duration, updater)
+            .await
+            .unwrap_or_else(|| Err(TimedOut.into())),
+        None => updater.await,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use crate::runtime::sleep;
+    use crate::{
+        BoxedSendError, DirectCacheAccess, ImmediateValuePromise, ImmediateValueState, RetryPolicy,
+    };
+
+    #[tokio::test]
+    async fn defaults_match_new() {
+        let mut promise = ImmediateValuePromise::builder().spawn(async { Ok(34) });
+        assert!(promise.name().is_none());
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(promise.poll_state().get_value(), Some(&34));
+    }
+
+    #[tokio::test]
+    async fn timeout() {
+        let mut promise = ImmediateValuePromise::builder()
+            .timeout(Duration::from_millis(10))
+            .spawn(async {
+                sleep(Duration::from_secs(10)).await;
+                Ok(34)
+            });
+        sleep(Duration::from_millis(50)).await;
+        let error = promise.poll_state().as_error().unwrap();
+        assert!(error.is_timed_out());
+    }
+
+    #[tokio::test]
+    async fn retries_with_factory() {
+        let attempts = Arc::new(AtomicU32::new(0));
+        let attempts_factory = attempts.clone();
+        let mut promise = ImmediateValuePromise::builder()
+            .name("flaky")
+            .retries(RetryPolicy::new(3))
+            .spawn_with(move || {
+                let attempt = attempts_factory.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    if attempt < 2 {
+                        Err(BoxedSendError::msg("flaky"))
+                    } else {
+                        Ok(attempt)
+                    }
+                }
+            });
+        assert_eq!(promise.name(), Some("flaky"));
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(promise.poll_state().get_value(), Some(&2));
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+
+        let mut failing = ImmediateValuePromise::<()>::builder()
+            .retries(RetryPolicy::new(1))
+            .spawn_with(|| async { Err(BoxedSendError::msg("fatal")) });
+        sleep(Duration::from_millis(50)).await;
+        assert!(failing.poll_state().is_error());
+    }
+
+    #[tokio::test]
+    async fn lazy_starts_on_poll() {
+        let started = Arc::new(AtomicU32::new(0));
+        let started_task = started.clone();
+        let mut promise = ImmediateValuePromise::builder().lazy().spawn(async move {
+            started_task.fetch_add(1, Ordering::SeqCst);
+            Ok(34)
+        });
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(started.load(Ordering::SeqCst), 0);
+        promise.poll_state();
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(promise.poll_state().get_value(), Some(&34));
+        assert_eq!(started.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn hooks() {
+        let settled = Arc::new(Mutex::new(Vec::new()));
+        let settled_hook = settled.clone();
+        let errors = Arc::new(AtomicU32::new(0));
+        let errors_hook = errors.clone();
+        let mut promise = ImmediateValuePromise::<()>::builder()
+            .on_error(move |_| {
+                errors_hook.fetch_add(1, Ordering::SeqCst);
+            })
+            .on_settle(move |state: &ImmediateValueState<()>| {
+                settled_hook.lock().unwrap().push(state.to_string())
+            })
+            .error_display(|_| "try again later".to_string())
+            .spawn(async { Err(BoxedSendError::msg("timeout")) });
+        sleep(Duration::from_millis(50)).await;
+        promise.poll_state();
+        promise.poll_state();
+        assert_eq!(*settled.lock().unwrap(), vec!["error: timeout"]);
+        assert_eq!(errors.load(Ordering::SeqCst), 1);
+        assert_eq!(promise.display_error().unwrap(), "try again later");
+    }
+}
@@ -1,15 +1,32 @@
-use crate::{BoxedSendError, DirectCacheAccess, Progress};
-use crate::{ImmediateValuePromise, ImmediateValueState};
+#[cfg(feature = "tokio")]
+use crate::control::{self, Control};
+#[cfg(feature = "tokio")]
+use crate::dropcount::DropCounter;
+#[cfg(feature = "indicatif")]
+use crate::indicatifprogress::AttachedBar;
+use crate::{
+    clock, BoxedSendError, DirectCacheAccess, ImmediateValueState, Progress, StateSnapshot,
+};
+#[cfg(feature = "tokio")]
+use crate::{drain_into, ErrorKind, ImmediateValuePromise, StateTag};
 use std::borrow::Cow;
-use std::time::Instant;
-use tokio::sync::mpsc::Receiver;
+use std::fmt::{self, Debug, Formatter};
+use std::time::{Duration, Instant};
+#[cfg(feature = "tokio")]
+use tokio::sync::mpsc::error::SendError;
+#[cfg(feature = "tokio")]
 use tokio::sync::mpsc::Sender;
+#[cfg(feature = "tokio")]
+use tokio::sync::mpsc::{Receiver, UnboundedReceiver};
+#[cfg(feature = "tokio")]
+use tokio::sync::watch;
 
 /// A status update struct containing the issue-date, progress and a message
 /// You can use any struct that can be transferred via tokio mpsc channels.
 #[derive(Debug)]
 pub struct Status<M> {
-    /// Time when this status was created
+    /// Time when this status was created. With the `tokio` feature, this follows tokio's clock,
+    /// so it respects `tokio::time::pause` in tests.
     pub time: Instant,
     /// Current progress
     pub progress: Progress,
@@ -23,7 +40,30 @@ impl<M> Status<M> {
         Self {
             progress,
             message,
-            time: Instant::now(),
+            time: clock::now(),
+        }
+    }
+
+    /// Time passed since the status was created, measured with the same clock as [`Self::time`]
+    pub fn age(&self) -> Duration {
+        clock::since(self.time)
+    }
+
+    /// Convert the message, keeping the time and progress, e.g. `Status<TaskEvent>` to `Status<String>` for a UI
+    pub fn map_message<N>(self, f: impl FnOnce(M) -> N) -> Status<N> {
+        Status {
+            time: self.time,
+            progress: self.progress,
+            message: f(self.message),
+        }
+    }
+
+    /// Like [`Self::map_message`], converting a borrowed status
+    pub fn map_message_ref<N>(&self, f: impl FnOnce(&M) -> N) -> Status<N> {
+        Status {
+            time: self.time,
+            progress: self.progress,
+            message: f(&self.message),
         }
     }
 }
@@ -32,11 +72,12 @@ impl<M> Status<M> {
 pub type StringStatus = Status<Cow<'static, str>>;
 
 impl StringStatus {
-    /// create a [`StringStatus`] from a `&'static str`
+    /// create a [`StringStatus`] from a `&'static str`. Reporting these doesn't allocate per message, only the
+    /// history grows. With [`ProgressTrackedImValProm::with_progress_coalescing`] repeats don't even grow the history.
     pub fn from_str(progress: Progress, static_message: &'static str) -> Self {
         StringStatus {
             message: Cow::Borrowed(static_message),
-            time: Instant::now(),
+            time: clock::now(),
             progress,
         }
     }
@@ -44,48 +85,148 @@ impl StringStatus {
     pub fn from_string(progress: Progress, message: String) -> Self {
         StringStatus {
             message: Cow::Owned(message),
-            time: Instant::now(),
+            time: clock::now(),
             progress,
         }
     }
 }
 
+/// How many messages [`crate::LazyVecPromise::poll_state_for`] and statuses [`ProgressTracked::poll_state_for`] apply
+/// between checks of the time budget
+pub const BUDGET_CHECK_ITEMS: usize = 32;
+
+/// # Promises which [`ProgressTracked`] can wrap
+/// Implemented for [`crate::ThreadPromise`] and, with the `tokio` feature, [`crate::ImmediateValuePromise`].
+pub trait TrackedPromise:
+    DirectCacheAccess<<Self as TrackedPromise>::Value, BoxedSendError> + Sized
+{
+    /// The type of the value
+    type Value;
+    /// Poll the state, taking the result if it's ready
+    fn poll_state(&mut self) -> &ImmediateValueState<Self::Value>;
+    /// Get the current state without polling
+    fn get_state(&self) -> &ImmediateValueState<Self::Value>;
+    /// The user-facing message if in error state
+    fn display_error(&self) -> Option<String>;
+    /// Set a hook which is called once with the error if the promise fails
+    fn with_on_error(self, hook: impl FnMut(&BoxedSendError) + Send + 'static) -> Self;
+    /// Set a mapper turning errors into user-facing messages
+    fn with_error_display(
+        self,
+        mapper: impl Fn(&BoxedSendError) -> String + Send + Sync + 'static,
+    ) -> Self;
+}
+
+/// The receiving end of the status channel, depending on the wrapped promise
+pub(crate) enum StatusReceiver<M> {
+    #[cfg(feature = "tokio")]
+    Async(Receiver<Status<M>>),
+    Thread(std::sync::mpsc::Receiver<Status<M>>),
+}
+
+impl<M> StatusReceiver<M> {
+    fn try_recv(&mut self) -> Option<Status<M>> {
+        match self {
+            #[cfg(feature = "tokio")]
+            StatusReceiver::Async(receiver) => receiver.try_recv().ok(),
+            StatusReceiver::Thread(receiver) => receiver.try_recv().ok(),
+        }
+    }
+
+    /// Moves all queued statuses into `buffer` at once, reserving their space up front where the channel tells it
+    fn drain_into(&mut self, buffer: &mut Vec<Status<M>>) {
+        match self {
+            #[cfg(feature = "tokio")]
+            StatusReceiver::Async(receiver) => {
+                buffer.reserve(receiver.len());
+                drain_into(receiver, buffer);
+            }
+            StatusReceiver::Thread(receiver) => buffer.extend(receiver.try_iter()),
+        }
+    }
+}
+
+/// # A progress and status enabling wrapper for any [`TrackedPromise`]
+/// Records the [`Status`] updates sent by the wrapped promise and keeps the latest [`Progress`].
+/// Use it as [`ProgressTrackedImValProm`] for futures or as [`crate::ProgressTrackedThreadPromise`] for threads.
+pub struct ProgressTracked<P, M> {
+    promise: P,
+    status: Vec<Status<M>>,
+    progress: Progress,
+    /// the time of the status which set `progress`, so a late, older status doesn't override it
+    progress_time: Option<Instant>,
+    receiver: StatusReceiver<M>,
+    backlog: bool,
+    /// the index of the oldest status received by the last poll
+    received_from: usize,
+    coalesce: Option<fn(&M, &M) -> bool>,
+    coalesced: usize,
+    #[cfg(feature = "tokio")]
+    dropped: DropCounter,
+    #[cfg(feature = "tokio")]
+    control: Option<Control>,
+    #[cfg(feature = "indicatif")]
+    pub(crate) bar: Option<AttachedBar<M>>,
+}
+
 /// # A progress and status enabling wrapper for [`ImmediateValuePromise`]
 /// This struct allows to use the [`Progress`] type and any kind of status message
 /// You can use this to set a computation progress and optionally attach any kind of status message.
 /// Assume your action runs  for an extended period of time and you want to inform the user about the state:
-///```rust, no_run
-///use std::borrow::Cow;
+/// The [`send_status!`](crate::send_status) macros take care of creating the [`StringStatus`] and sending it:
+///```rust
 ///use std::time::Duration;
-///use lazy_async_promise::{ImmediateValueState, ImmediateValuePromise, Progress, ProgressTrackedImValProm, StringStatus};
+///use lazy_async_promise::{ImmediateValueState, ImmediateValuePromise, Progress, ProgressTrackedImValProm};
+///use lazy_async_promise::api_macros::*;
+///# #[tokio::main]
+///# async fn main() {
 ///let mut oneshot_progress = ProgressTrackedImValProm::new( |s| { ImmediateValuePromise::new(
 ///  async move {
-///  //send some initial status
-///    s.send(StringStatus::new(
-///      Progress::from_percent(0.0),
-///      "Initializing".into(),
-///    )).await.unwrap();
+///    //send some initial status
+///    send_status!(s, 0.0, "Initializing");
 ///    // do some long running operation
-///    for i in 0..100 {
-///      tokio::time::sleep(Duration::from_millis(50)).await;
-///      s.send(StringStatus::new(
-///        Progress::from_percent(i as f64),
-///        Cow::Borrowed("In progress"))).await.unwrap();
+///    for i in 0..10 {
+///      send_status_fmt!(s, Progress::from_fraction(i + 1, 10), "Step {} of 10", i + 1);
 ///    }
 ///    Ok(34)
 ///  })}, 2000);
+///  //waiting and polling will yield the last step now :)
+///  tokio::time::sleep(Duration::from_millis(200)).await;
 ///  assert!(matches!(
 ///    oneshot_progress.poll_state(),
-///    ImmediateValueState::Updating));
-///   //waiting and polling will yield "In progress" now :)
+///    ImmediateValueState::Success(34)));
+///  assert_eq!(oneshot_progress.last_status().unwrap().message, "Step 10 of 10");
+///  assert_eq!(oneshot_progress.get_progress(), Progress::from_percent(100));
+///# }
 /// ```
 ///
-pub struct ProgressTrackedImValProm<T: Send, M> {
-    promise: ImmediateValuePromise<T>,
-    status: Vec<Status<M>>,
-    receiver: Receiver<Status<M>>,
+#[cfg(feature = "tokio")]
+pub type ProgressTrackedImValProm<T, M> = ProgressTracked<ImmediateValuePromise<T>, M>;
+
+#[cfg(feature = "tokio")]
+impl<T: Send + 'static> TrackedPromise for ImmediateValuePromise<T> {
+    type Value = T;
+    fn poll_state(&mut self) -> &ImmediateValueState<T> {
+        ImmediateValuePromise::poll_state(self)
+    }
+    fn get_state(&self) -> &ImmediateValueState<T> {
+        ImmediateValuePromise::get_state(self)
+    }
+    fn display_error(&self) -> Option<String> {
+        ImmediateValuePromise::display_error(self)
+    }
+    fn with_on_error(self, hook: impl FnMut(&BoxedSendError) + Send + 'static) -> Self {
+        ImmediateValuePromise::with_on_error(self, hook)
+    }
+    fn with_error_display(
+        self,
+        mapper: impl Fn(&BoxedSendError) -> String + Send + Sync + 'static,
+    ) -> Self {
+        ImmediateValuePromise::with_error_display(self, mapper)
+    }
 }
 
+#[cfg(feature = "tokio")]
 impl<T: Send + 'static, M> ProgressTrackedImValProm<T, M> {
     /// create a new Progress tracked immediate value promise.
     pub fn new(
@@ -93,67 +234,541 @@ impl<T: Send + 'static, M> ProgressTrackedImValProm<T, M> {
         buffer: usize,
     ) -> Self {
         let (sender, receiver) = tokio::sync::mpsc::channel(buffer);
-        ProgressTrackedImValProm {
-            receiver,
+        let dropped = DropCounter::default();
+        let promise = dropped.while_creating(|| creator(sender));
+        let mut tracked = ProgressTracked::wrap(promise, StatusReceiver::Async(receiver));
+        tracked.dropped = dropped;
+        tracked
+    }
+
+    /// Like [`Self::new`], the creator also gets the receiver of a control channel into the running future, e.g. to
+    /// pause it. See [`Self::send_control`].
+    pub fn new_with_control<C: Send + 'static>(
+        creator: impl FnOnce(Sender<Status<M>>, UnboundedReceiver<C>) -> ImmediateValuePromise<T>,
+        buffer: usize,
+    ) -> Self {
+        let (mut control, slot) = Control::new::<C>();
+        control.open();
+        let receiver = control::take_receiver(&slot);
+        let mut promise = Self::new(|sender| creator(sender, receiver), buffer);
+        promise.control = Some(control);
+        promise
+    }
+
+    /// Send `message` to the future of a promise from [`Self::new_with_control`]. Fails with the message once the
+    /// future finished or dropped its receiver, or if `C` is not the control type of the promise.
+    pub fn send_control<C: 'static>(&mut self, message: C) -> Result<(), SendError<C>> {
+        Control::send(&self.control, message)
+    }
+
+    /// Name the promise for debug overlays. The wrapped task is already spawned at this point,
+    /// to name it for tokio-console create it with [`ImmediateValuePromise::new_named`] instead.
+    pub fn with_task_name(mut self, name: impl Into<String>) -> Self {
+        self.promise.name = Some(name.into());
+        self
+    }
+
+    /// The name of the wrapped promise, see [`ImmediateValuePromise::name`]
+    pub fn name(&self) -> Option<&str> {
+        self.promise.name()
+    }
+
+    /// How many statuses were dropped by [`crate::try_send_status!`] because the buffer was full. Only sends from
+    /// the task of the promise created by the creator are counted.
+    pub fn dropped_messages(&self) -> u64 {
+        self.dropped.get()
+    }
+
+    /// A watch of the state the future ended in, see [`ImmediateValuePromise::state_watch`]
+    pub fn state_watch(&self) -> watch::Receiver<StateTag> {
+        self.promise.state_watch()
+    }
+}
+
+impl<P: TrackedPromise, M> ProgressTracked<P, M> {
+    /// Wrap `promise`, whose statuses arrive at `receiver`
+    pub(crate) fn wrap(promise: P, receiver: StatusReceiver<M>) -> Self {
+        ProgressTracked {
+            promise,
             status: Vec::new(),
-            promise: creator(sender),
+            progress: Progress::default(),
+            progress_time: None,
+            receiver,
+            backlog: false,
+            received_from: 0,
+            coalesce: None,
+            coalesced: 0,
+            #[cfg(feature = "tokio")]
+            dropped: DropCounter::default(),
+            #[cfg(feature = "tokio")]
+            control: None,
+            #[cfg(feature = "indicatif")]
+            bar: None,
         }
     }
 
+    /// Collapse consecutive statuses with equal messages received within one `poll_state` into the latest one,
+    /// so frequent progress reports don't flood the history. By default, every status is recorded.
+    pub fn with_progress_coalescing(self) -> Self
+    where
+        M: PartialEq,
+    {
+        self.with_progress_coalescing_by(M::eq)
+    }
+
+    /// Like [`Self::with_progress_coalescing`], with `is_progress_only(previous, next)` deciding whether the next
+    /// message only reports progress and may replace the previous status
+    pub fn with_progress_coalescing_by(mut self, is_progress_only: fn(&M, &M) -> bool) -> Self {
+        self.coalesce = Some(is_progress_only);
+        self
+    }
+
+    /// Number of statuses replaced by a later one due to progress coalescing
+    pub fn coalesced_count(&self) -> usize {
+        self.coalesced
+    }
+
+    /// Set a hook which is called once with the error if the promise fails, see [`TrackedPromise::with_on_error`]
+    pub fn with_on_error(mut self, hook: impl FnMut(&BoxedSendError) + Send + 'static) -> Self {
+        self.promise = self.promise.with_on_error(hook);
+        self
+    }
+
+    /// Set a mapper turning errors into user-facing messages, see [`TrackedPromise::with_error_display`]
+    pub fn with_error_display(
+        mut self,
+        mapper: impl Fn(&BoxedSendError) -> String + Send + Sync + 'static,
+    ) -> Self {
+        self.promise = self.promise.with_error_display(mapper);
+        self
+    }
+
+    /// The user-facing message if in error state, see [`TrackedPromise::display_error`]
+    pub fn display_error(&self) -> Option<String> {
+        self.promise.display_error()
+    }
+
     /// Slice of all recorded [`Status`] changes
     pub fn status_history(&self) -> &[Status<M>] {
         &self.status
     }
 
+    /// Drop all recorded [`Status`] changes, e.g. to bound the memory of long-running jobs.
+    /// [`Self::get_progress`] keeps reporting the latest progress.
+    pub fn clear_status_history(&mut self) {
+        self.status.clear();
+    }
+
+    /// Convert the recorded statuses with [`Status::map_message_ref`], keeping the history
+    pub fn map_history<N>(&self, mut f: impl FnMut(&M) -> N) -> Vec<Status<N>> {
+        let convert = |status: &Status<M>| status.map_message_ref(&mut f);
+        self.status.iter().map(convert).collect()
+    }
+
+    /// Take the recorded statuses converted with [`Status::map_message`], leaving the history empty like
+    /// [`Self::clear_status_history`]
+    pub fn take_history_mapped<N>(&mut self, mut f: impl FnMut(M) -> N) -> Vec<Status<N>> {
+        let convert = |status: Status<M>| status.map_message(&mut f);
+        self.status.drain(..).map(convert).collect()
+    }
+
     /// Get the last [`Status`] if there is any
     pub fn last_status(&self) -> Option<&Status<M>> {
         self.status.last()
     }
 
-    /// Is our future already finished?
+    /// Iterate the messages of the status history, oldest first. Together with [`Self::statuses_since`] and
+    /// [`Self::rfind_status`], this reads the history in place without allocating:
+    /// ```rust, no_run
+    /// # #[cfg(feature = "tokio")]
+    /// use lazy_async_promise::{ProgressTrackedImValProm, StringStatus};
+    /// # #[cfg(feature = "tokio")]
+    /// fn log_view(promise: &ProgressTrackedImValProm<i32, std::borrow::Cow<'static, str>>) -> String {
+    ///     let mut view = String::new();
+    ///     if let Some(warning) = promise.rfind_status(|status| status.message.starts_with("warning")) {
+    ///         view.push_str(&format!("last {}\n", warning.message));
+    ///     }
+    ///     for message in promise.messages() {
+    ///         view.push_str(message);
+    ///         view.push('\n');
+    ///     }
+    ///     view
+    /// }
+    /// ```
+    pub fn messages(&self) -> impl DoubleEndedIterator<Item = &M> + ExactSizeIterator {
+        self.status.iter().map(|status| &status.message)
+    }
+
+    /// Iterate the statuses created at or after `since`, oldest first. Found by binary search, see
+    /// [`Self::statuses_between`].
+    pub fn statuses_since(&self, since: Instant) -> impl DoubleEndedIterator<Item = &Status<M>> {
+        self.status[self.history_index(since)..].iter()
+    }
+
+    /// The statuses created at or after `from` and before `to`, e.g. those of the last 30 seconds. Found by binary
+    /// search, the history is kept in the order the statuses were created even if several senders deliver them out
+    /// of order.
+    pub fn statuses_between(&self, from: Instant, to: Instant) -> &[Status<M>] {
+        let start = self.history_index(from);
+        let end = self.history_index(to).max(start);
+        &self.status[start..end]
+    }
+
+    /// The first status matching the predicate
+    pub fn find_status(&self, predicate: impl FnMut(&&Status<M>) -> bool) -> Option<&Status<M>> {
+        self.status.iter().find(predicate)
+    }
+
+    /// The latest status matching the predicate
+    pub fn rfind_status(
+        &self,
+        mut predicate: impl FnMut(&Status<M>) -> bool,
+    ) -> Option<&Status<M>> {
+        self.status.iter().rev().find(|status| predicate(status))
+    }
+
+    /// How many statuses match the predicate, e.g. the number of warnings
+    pub fn count_matching(&self, mut predicate: impl FnMut(&Status<M>) -> bool) -> usize {
+        self.status
+            .iter()
+            .filter(|status| predicate(status))
+            .count()
+    }
+
+    /// The index of the first status created at or after `time`
+    fn history_index(&self, time: Instant) -> usize {
+        debug_assert!(
+            self.status.is_sorted_by_key(|status| status.time),
+            "received statuses are not kept in time order"
+        );
+        self.status.partition_point(|status| status.time < time)
+    }
+
+    /// Is the wrapped promise already finished, with a value or an error? Stays true after the result was taken.
     pub fn finished(&self) -> bool {
-        self.promise.get_value().is_some()
+        !self.promise.get_state().is_updating()
     }
 
     /// Poll the state and process the messages
-    pub fn poll_state(&mut self) -> &ImmediateValueState<T> {
-        while let Ok(msg) = self.receiver.try_recv() {
-            self.status.push(msg);
+    pub fn poll_state(&mut self) -> &ImmediateValueState<P::Value> {
+        self.poll_until(None)
+    }
+
+    /// Like [`Self::poll_state`], but stops applying received statuses once `budget` is used up, checked every
+    /// [`BUDGET_CHECK_ITEMS`] statuses, e.g. with an expensive coalescing function. The rest stays queued for
+    /// the next poll, until then the promise stays [`ImmediateValueState::Updating`]. See [`Self::has_backlog`].
+    pub fn poll_state_for(&mut self, budget: Duration) -> &ImmediateValueState<P::Value> {
+        self.poll_until(Some(clock::now() + budget))
+    }
+
+    /// Whether the last poll left received statuses to apply by the next one, see [`Self::poll_state_for`]
+    pub fn has_backlog(&self) -> bool {
+        self.backlog
+    }
+
+    fn poll_until(&mut self, deadline: Option<Instant>) -> &ImmediateValueState<P::Value> {
+        self.received_from = self.status.len();
+        let drained = match (self.coalesce, deadline) {
+            (None, None) => {
+                // the queued messages are counted up front, so the history grows at most once per poll
+                self.receiver.drain_into(&mut self.status);
+                let received = self.status[self.received_from..].iter();
+                let determinate = received.filter(|status| !status.progress.is_indeterminate());
+                if let Some(newest) = determinate.max_by_key(|status| status.time) {
+                    self.update_progress(newest.progress, newest.time);
+                }
+                self.restore_order();
+                true
+            }
+            (coalesce, deadline) => self.drain_until(coalesce, deadline),
+        };
+        self.backlog = !drained;
+        // the result is only applied after the statuses sent before it
+        let state = match drained {
+            true => self.promise.poll_state(),
+            false => self.promise.get_state(),
+        };
+        #[cfg(feature = "indicatif")]
+        if let Some(bar) = &mut self.bar {
+            bar.show(self.progress, self.status.last(), state);
         }
-        self.promise.poll_state()
+        state
+    }
+
+    /// Poll like `poll_state`, also returning the statuses received by this poll, oldest first. The history keeps
+    /// accumulating, so UIs can append the new lines without tracking the history length. If another sender
+    /// delivered a status created before ones already recorded, the returned slice starts at its place in the
+    /// history and includes the later statuses as well.
+    pub fn poll_state_with_status(&mut self) -> (&ImmediateValueState<P::Value>, &[Status<M>]) {
+        self.poll_state();
+        (self.promise.get_state(), &self.status[self.received_from..])
+    }
+
+    /// Sort the statuses received since `received_from` into the history. Statuses of one sender arrive in order,
+    /// so this only sorts the part after the oldest new status, which usually is sorted already.
+    fn restore_order(&mut self) {
+        let (old, new) = self.status.split_at(self.received_from);
+        let Some(oldest) = new.iter().map(|status| status.time).min() else {
+            return;
+        };
+        let from = old.partition_point(|status| status.time <= oldest);
+        self.status[from..].sort_by_key(|status| status.time);
+        self.received_from = from;
+    }
+
+    /// Apply the received statuses one by one, coalescing them if enabled. Returns false if `deadline` passed
+    /// before all were applied.
+    fn drain_until(
+        &mut self,
+        coalesce: Option<fn(&M, &M) -> bool>,
+        deadline: Option<Instant>,
+    ) -> bool {
+        let mut applied = 0;
+        // whether the last status of the history was received by this poll
+        let mut received_last = false;
+        loop {
+            let checkpoint = applied > 0 && applied % BUDGET_CHECK_ITEMS == 0;
+            if checkpoint && deadline.is_some_and(|deadline| clock::now() >= deadline) {
+                return false;
+            }
+            let Some(msg) = self.receiver.try_recv() else {
+                return true;
+            };
+            self.update_progress(msg.progress, msg.time);
+            if self.status.last().is_some_and(|last| last.time > msg.time) {
+                // created before statuses of another sender which arrived first
+                let index = self
+                    .status
+                    .partition_point(|status| status.time <= msg.time);
+                self.status.insert(index, msg);
+                self.received_from = self.received_from.min(index);
+                applied += 1;
+                continue;
+            }
+            // statuses of earlier polls are never coalesced
+            let previous = self.status.last_mut().filter(|_| received_last);
+            match coalesce.zip(previous) {
+                Some((coalesce, previous)) if coalesce(&previous.message, &msg.message) => {
+                    *previous = msg;
+                    self.coalesced += 1;
+                }
+                _ => self.status.push(msg),
+            }
+            received_last = true;
+            applied += 1;
+        }
+    }
+
+    /// Poll the state like `poll_state`, returning an owned [`StateSnapshot`] which doesn't borrow the promise
+    pub fn poll_snapshot(&mut self) -> StateSnapshot {
+        let state = self.poll_state().tag();
+        StateSnapshot {
+            state,
+            error: self.display_error(),
+            has_value: self.has_value(),
+        }
+    }
+
+    /// Get the current state without polling, see [`TrackedPromise::get_state`]
+    pub fn get_state(&self) -> &ImmediateValueState<P::Value> {
+        self.promise.get_state()
     }
 
     /// Get the current progress
     pub fn get_progress(&self) -> Progress {
-        self.status
-            .last()
-            .map(|p| p.progress)
-            .unwrap_or(Progress::default())
+        self.progress
+    }
+
+    /// The progress of the last status while updating, like [`crate::Promise::progress`] for the lazy promises.
+    /// Unlike [`Self::get_progress`], this is [`Progress::indeterminate`] if the last status didn't report a progress.
+    pub fn progress(&self) -> Option<Progress> {
+        match self.promise.get_state() {
+            ImmediateValueState::Updating => Some(
+                self.last_status()
+                    .map_or(self.progress, |status| status.progress),
+            ),
+            _ => None,
+        }
+    }
+
+    /// Keep the progress of the newest status, statuses without progress keep the previous one
+    fn update_progress(&mut self, progress: Progress, time: Instant) {
+        let older = self.progress_time.is_some_and(|latest| latest > time);
+        if !older && !progress.is_indeterminate() {
+            self.progress = progress;
+            self.progress_time = Some(time);
+        }
     }
 }
 
-impl<T: Send + 'static, M> DirectCacheAccess<T, BoxedSendError> for ProgressTrackedImValProm<T, M> {
-    fn get_value_mut(&mut self) -> Option<&mut T> {
+/// Prints the wrapped promise, the progress and the length of the status history, without requiring `T: Debug`
+impl<P: Debug, M> Debug for ProgressTracked<P, M> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProgressTracked")
+            .field("promise", &self.promise)
+            .field("progress", &self.progress)
+            .field("statuses", &self.status.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<P: TrackedPromise, M> DirectCacheAccess<P::Value, BoxedSendError> for ProgressTracked<P, M> {
+    fn get_value_mut(&mut self) -> Option<&mut P::Value> {
         self.promise.get_value_mut()
     }
-    fn get_value(&self) -> Option<&T> {
+    fn get_value(&self) -> Option<&P::Value> {
         self.promise.get_value()
     }
-    fn get_result(&self) -> Option<Result<&T, &BoxedSendError>> {
+    fn get_result(&self) -> Option<Result<&P::Value, &BoxedSendError>> {
         self.promise.get_result()
     }
-    fn take_value(&mut self) -> Option<T> {
+    fn get_result_mut(&mut self) -> Option<Result<&mut P::Value, &mut BoxedSendError>> {
+        self.promise.get_result_mut()
+    }
+    fn take_value(&mut self) -> Option<P::Value> {
         self.promise.take_value()
     }
-    fn take_result(&mut self) -> Option<Result<T, BoxedSendError>> {
+    fn take_result(&mut self) -> Option<Result<P::Value, BoxedSendError>> {
         self.promise.take_result()
     }
+    fn set_value(&mut self, value: P::Value) -> Option<P::Value> {
+        self.promise.set_value(value)
+    }
+    fn is_pending(&self) -> bool {
+        self.promise.is_pending()
+    }
+    fn take_error(&mut self) -> Option<BoxedSendError> {
+        self.promise.take_error()
+    }
+}
+
+/// # A status sender for synchronous code, e.g. inside `spawn_blocking`
+/// Wraps the [`Sender`] given to the creator of a [`ProgressTrackedImValProm`]. The statuses go through the same
+/// channel, so `poll_state` picks them up like statuses sent with `.await`. Sending waits while the buffer is full
+/// and needs no runtime handle, but it panics when called from async code - use the async [`Sender`] there.
+/// ```rust
+/// # #[cfg(not(feature = "async-std"))]
+/// # #[tokio::main]
+/// # async fn main() {
+/// use tokio::sync::mpsc::Sender;
+/// use lazy_async_promise::{BlockingStatusSender, ImmediateValuePromise, Progress, ProgressTrackedImValProm, StringStatus};
+/// let mut promise = ProgressTrackedImValProm::new(
+///     |sender: Sender<StringStatus>| {
+///         let sender = BlockingStatusSender::from(sender);
+///         ImmediateValuePromise::new(async move {
+///             let sum = tokio::task::spawn_blocking(move || {
+///                 let mut sum = 0u64;
+///                 for chunk in 0..4u32 {
+///                     sum += (u64::from(chunk) * 1000..u64::from(chunk + 1) * 1000).sum::<u64>();
+///                     sender.set_progress(Progress::from_fraction(chunk + 1, 4))?;
+///                 }
+///                 sender.send(Progress::from_percent(100), "summed up".into())?;
+///                 Ok::<_, lazy_async_promise::BoxedSendError>(sum)
+///             })
+///             .await??;
+///             Ok(sum)
+///         })
+///     },
+///     8,
+/// );
+/// tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+/// assert!(promise.poll_state().is_success());
+/// assert_eq!(promise.status_history().len(), 5);
+/// assert_eq!(promise.last_status().unwrap().message, "summed up");
+/// assert_eq!(promise.get_progress(), Progress::from_percent(100));
+/// # }
+/// # #[cfg(feature = "async-std")]
+/// # fn main() {}
+/// ```
+#[cfg(feature = "tokio")]
+pub struct BlockingStatusSender<M> {
+    sender: Sender<Status<M>>,
+}
+
+#[cfg(feature = "tokio")]
+impl<M> BlockingStatusSender<M> {
+    /// Send a status with the given progress and message, waiting while the buffer is full.
+    /// Fails if the promise is gone.
+    pub fn send(&self, progress: Progress, message: M) -> Result<(), BoxedSendError> {
+        self.send_status(Status::new(progress, message))
+    }
+
+    /// Send a prepared status, see [`Self::send`]
+    pub fn send_status(&self, status: Status<M>) -> Result<(), BoxedSendError> {
+        self.sender.blocking_send(status).map_err(|_| {
+            BoxedSendError::msg("the progress tracked promise is gone")
+                .with_kind(ErrorKind::ChannelClosed)
+        })
+    }
+
+    /// Send a status with the given progress and an empty message
+    pub fn set_progress(&self, progress: Progress) -> Result<(), BoxedSendError>
+    where
+        M: Default,
+    {
+        self.send(progress, M::default())
+    }
 }
-#[cfg(test)]
+
+#[cfg(feature = "tokio")]
+impl<M> From<Sender<Status<M>>> for BlockingStatusSender<M> {
+    fn from(sender: Sender<Status<M>>) -> Self {
+        BlockingStatusSender { sender }
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<M> Clone for BlockingStatusSender<M> {
+    fn clone(&self) -> Self {
+        BlockingStatusSender {
+            sender: self.sender.clone(),
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<M> Debug for BlockingStatusSender<M> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BlockingStatusSender")
+            .field("closed", &self.sender.is_closed())
+            .finish()
+    }
+}
+
+#[cfg(all(test, feature = "tokio"))]
 mod test {
     use super::*;
+    use crate::api_macros::*;
+    use crate::runtime::sleep;
     use crate::ImmediateValuePromise;
     use std::time::Duration;
+    #[tokio::test]
+    async fn control_channel() {
+        let mut promise = ProgressTrackedImValProm::new_with_control(
+            |s, mut pause: UnboundedReceiver<bool>| {
+                ImmediateValuePromise::new(async move {
+                    for i in 0..3 {
+                        while pause.try_recv() == Ok(true) {
+                            while pause.recv().await == Some(true) {}
+                        }
+                        send_status!(s, Progress::from_fraction(i + 1, 3), "step");
+                        sleep(Duration::from_millis(10)).await;
+                    }
+                    Ok(34)
+                })
+            },
+            6,
+        );
+        promise.send_control(true).unwrap();
+        sleep(Duration::from_millis(50)).await;
+        assert!(promise.poll_state().is_updating());
+        assert!(promise.get_progress().as_f64() < 1.0);
+        promise.send_control(false).unwrap();
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(promise.poll_state().get_value(), Some(&34));
+        assert!(promise.send_control(true).is_err());
+    }
+
     #[tokio::test]
     async fn basic_usage_cycle() {
         let mut oneshot_progress = ProgressTrackedImValProm::new(
@@ -165,18 +780,18 @@ mod test {
                     ))
                     .await
                     .unwrap();
-                    tokio::time::sleep(Duration::from_millis(25)).await;
+                    sleep(Duration::from_millis(25)).await;
                     s.send(StringStatus::new(
                         Progress::from_percent(50.0),
                         "processing".into(),
                     ))
                     .await
                     .unwrap();
-                    tokio::time::sleep(Duration::from_millis(25)).await;
+                    sleep(Duration::from_millis(25)).await;
 
                     s.send(StringStatus::from_string(
                         Progress::from_percent(100.0),
-                        format!("Done"),
+                        "Done".to_string(),
                     ))
                     .await
                     .unwrap();
@@ -190,9 +805,11 @@ mod test {
             ImmediateValueState::Updating
         ));
         assert!(!oneshot_progress.finished());
+        assert!(oneshot_progress.is_pending());
+        assert!(!oneshot_progress.has_value());
 
         assert_eq!(*oneshot_progress.get_progress(), 0.0);
-        tokio::time::sleep(Duration::from_millis(100)).await;
+        sleep(Duration::from_millis(100)).await;
         let _ = oneshot_progress.poll_state();
         assert_eq!(*oneshot_progress.get_progress(), 1.0);
         let result = oneshot_progress.poll_state();
@@ -204,6 +821,7 @@ mod test {
         }
         // check finished
         assert!(oneshot_progress.finished());
+        assert!(oneshot_progress.has_value() && !oneshot_progress.is_pending());
         let history = oneshot_progress.status_history();
         assert_eq!(history.len(), 3);
 
@@ -216,5 +834,422 @@ mod test {
         let val = oneshot_progress.take_value().unwrap();
         assert_eq!(val, 33);
         assert!(oneshot_progress.get_value().is_none());
+        assert!(!oneshot_progress.has_value() && !oneshot_progress.has_error());
+        assert!(!oneshot_progress.is_pending());
+    }
+
+    #[tokio::test]
+    async fn large_backlog_in_one_poll() {
+        const COUNT: usize = 100_000;
+        let mut promise = ProgressTrackedImValProm::new(
+            |s| {
+                ImmediateValuePromise::new(async move {
+                    for i in 0..COUNT {
+                        s.try_send(StringStatus::from_str(
+                            Progress::from_fraction(i as f64 + 1.0, COUNT as f64),
+                            "hashing",
+                        ))
+                        .unwrap();
+                    }
+                    Ok(34)
+                })
+            },
+            COUNT,
+        );
+        sleep(Duration::from_millis(200)).await;
+        assert!(promise.poll_state().is_success());
+        assert_eq!(promise.status_history().len(), COUNT);
+        assert_eq!(*promise.get_progress(), 1.0);
+    }
+
+    #[tokio::test]
+    async fn progress_of_last_status() {
+        let mut promise = ProgressTrackedImValProm::new(
+            |s| {
+                ImmediateValuePromise::new(async move {
+                    status!(s, Progress::from_percent(40), "loading");
+                    status!(s, "waiting for the server");
+                    sleep(Duration::from_millis(100)).await;
+                    Ok(34)
+                })
+            },
+            4,
+        );
+        sleep(Duration::from_millis(30)).await;
+        promise.poll_state();
+        assert!(promise.progress().unwrap().is_indeterminate());
+        assert_eq!(promise.get_progress(), Progress::from_percent(40));
+        sleep(Duration::from_millis(150)).await;
+        assert!(promise.poll_state().is_success());
+        assert_eq!(promise.progress(), None);
+    }
+
+    #[tokio::test]
+    async fn counts_dropped_statuses() {
+        let mut promise = ProgressTrackedImValProm::new(
+            |s| {
+                ImmediateValuePromise::new(async move {
+                    let sent = (0..10)
+                        .filter(|i| try_send_status!(s, Progress::from_fraction(*i, 10), "hashing"))
+                        .count();
+                    Ok(sent)
+                })
+            },
+            4,
+        );
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(promise.poll_state().get_value(), Some(&4));
+        assert_eq!(promise.status_history().len(), 4);
+        assert_eq!(promise.dropped_messages(), 6);
+    }
+
+    #[tokio::test]
+    async fn error_display() {
+        let mut promise = ProgressTrackedImValProm::<(), _>::new(
+            |s| {
+                ImmediateValuePromise::new(async move {
+                    send_status!(s, 50, "uploading");
+                    Err(BoxedSendError::msg("ERR_CODE 5012: upstream shard timeout"))
+                })
+            },
+            6,
+        )
+        .with_error_display(|_| "The server is busy, please try again".to_string());
+        assert!(promise.display_error().is_none());
+        sleep(Duration::from_millis(50)).await;
+        assert!(promise.poll_state().is_error());
+        assert_eq!(
+            promise.display_error().unwrap(),
+            "The server is busy, please try again"
+        );
+        let Some(Err(error)) = promise.get_result() else {
+            unreachable!();
+        };
+        assert_eq!(error.to_string(), "ERR_CODE 5012: upstream shard timeout");
+    }
+
+    #[tokio::test]
+    async fn progress_coalescing() {
+        let mut promise = ProgressTrackedImValProm::new(
+            |s| {
+                ImmediateValuePromise::new(async move {
+                    for percent in 0..10 {
+                        let message = if percent < 5 { "loading" } else { "parsing" };
+                        s.send(StringStatus::from_str(
+                            Progress::from_percent(percent),
+                            message,
+                        ))
+                        .await
+                        .unwrap();
+                    }
+                    Ok(34)
+                })
+            },
+            20,
+        )
+        .with_progress_coalescing();
+        sleep(Duration::from_millis(50)).await;
+        assert!(promise.poll_state().is_success());
+        let history = promise.status_history();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].message, "loading");
+        assert_eq!(*history[1].progress, 0.09);
+        assert_eq!(promise.coalesced_count(), 8);
+    }
+
+    #[tokio::test]
+    async fn progress_survives_history_clear() {
+        let mut promise = ProgressTrackedImValProm::new(
+            |s| {
+                ImmediateValuePromise::new(async move {
+                    s.send(StringStatus::from_str(
+                        Progress::from_percent(40),
+                        "loading",
+                    ))
+                    .await
+                    .unwrap();
+                    sleep(Duration::from_millis(50)).await;
+                    s.send(StringStatus::from_str(
+                        Progress::from_percent(80),
+                        "parsing",
+                    ))
+                    .await
+                    .unwrap();
+                    Ok(34)
+                })
+            },
+            20,
+        );
+        sleep(Duration::from_millis(20)).await;
+        promise.poll_state();
+        promise.clear_status_history();
+        assert!(promise.status_history().is_empty());
+        assert_eq!(*promise.get_progress(), 0.4);
+        promise.poll_state();
+        assert_eq!(*promise.get_progress(), 0.4);
+        sleep(Duration::from_millis(80)).await;
+        assert!(promise.poll_state().is_success());
+        assert_eq!(*promise.get_progress(), 0.8);
+        assert_eq!(promise.status_history().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn send_status_returns_on_closed_channel() {
+        let (sender, receiver) = tokio::sync::mpsc::channel::<StringStatus>(1);
+        drop(receiver);
+        let result: Result<i32, BoxedSendError> = async move {
+            send_status!(sender, Progress::from_percent(10), "connecting");
+            Ok(34)
+        }
+        .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn poll_state_with_status() {
+        let mut promise = ProgressTrackedImValProm::new(
+            |s| {
+                ImmediateValuePromise::new(async move {
+                    send_status!(s, 10, "connecting");
+                    send_status!(s, 20, "downloading");
+                    sleep(Duration::from_millis(50)).await;
+                    send_status!(s, 100, "done");
+                    Ok(34)
+                })
+            },
+            10,
+        );
+        sleep(Duration::from_millis(20)).await;
+        let (state, new) = promise.poll_state_with_status();
+        assert!(state.is_updating());
+        assert_eq!(new.len(), 2);
+        assert!(promise.poll_state_with_status().1.is_empty());
+        sleep(Duration::from_millis(80)).await;
+        let (state, new) = promise.poll_state_with_status();
+        assert!(state.is_success());
+        assert_eq!(new[0].message, "done");
+        assert_eq!(promise.status_history().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn history_iterators() {
+        let start = Instant::now();
+        let mut promise = ProgressTrackedImValProm::new(
+            |s| {
+                ImmediateValuePromise::new(async move {
+                    send_status!(s, 10, "warning: slow mirror");
+                    sleep(Duration::from_millis(10)).await;
+                    send_status!(s, 50, "downloading");
+                    send_status!(s, 100, "done");
+                    Ok(34)
+                })
+            },
+            10,
+        );
+        sleep(Duration::from_millis(50)).await;
+        promise.poll_state();
+        let messages: Vec<_> = promise.messages().collect();
+        assert_eq!(
+            messages,
+            vec!["warning: slow mirror", "downloading", "done"]
+        );
+        assert_eq!(promise.statuses_since(start).count(), 3);
+        let second = promise.status_history()[1].time;
+        assert_eq!(promise.statuses_since(second).count(), 2);
+        let warning = promise
+            .rfind_status(|status| status.message.starts_with("warning"))
+            .unwrap();
+        assert_eq!(warning.progress, Progress::from_percent(10));
+        assert!(promise
+            .rfind_status(|status| status.message == "error")
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn history_queries() {
+        let mut promise = ProgressTrackedImValProm::new(
+            |s| {
+                ImmediateValuePromise::new(async move {
+                    for step in [
+                        "warning: cold cache",
+                        "reading",
+                        "warning: retrying",
+                        "done",
+                    ] {
+                        send_status!(s, 0, step);
+                        sleep(Duration::from_millis(10)).await;
+                    }
+                    Ok(34)
+                })
+            },
+            10,
+        );
+        sleep(Duration::from_millis(80)).await;
+        promise.poll_state();
+        let history = promise.status_history();
+        let (second, last) = (history[1].time, history[3].time);
+        let between: Vec<_> = promise
+            .statuses_between(second, last)
+            .iter()
+            .map(|status| &status.message)
+            .collect();
+        assert_eq!(between, ["reading", "warning: retrying"]);
+        assert!(promise.statuses_between(last, second).is_empty());
+        let warning = |status: &StringStatus| status.message.starts_with("warning");
+        assert_eq!(promise.count_matching(warning), 2);
+        let first = promise.find_status(|status| warning(status)).unwrap();
+        assert_eq!(first.message, "warning: cold cache");
+    }
+    #[tokio::test(start_paused = true)]
+    async fn status_age_follows_paused_clock() {
+        let ttl = Duration::from_secs(60);
+        let status = StringStatus::from_str(Progress::from_percent(100), "done");
+        let is_stale = |status: &StringStatus| status.age() > ttl;
+        assert!(!is_stale(&status));
+        tokio::time::advance(Duration::from_secs(61)).await;
+        assert!(is_stale(&status));
+        // timestamps taken now are ahead of the earlier ones by the advanced time
+        let later = StringStatus::from_str(Progress::from_percent(100), "done");
+        assert!(later.time - status.time >= Duration::from_secs(61));
+    }
+    #[tokio::test]
+    async fn blocking_sender_from_thread() {
+        let mut promise = ProgressTrackedImValProm::new(
+            |s| {
+                let sender: BlockingStatusSender<Cow<'static, str>> = s.into();
+                ImmediateValuePromise::new(async move {
+                    let (tx, rx) = tokio::sync::oneshot::channel();
+                    std::thread::spawn(move || {
+                        sender.set_progress(Progress::from_percent(50)).unwrap();
+                        sender
+                            .send(Progress::from_percent(100), "done".into())
+                            .unwrap();
+                        tx.send(sender).unwrap();
+                    });
+                    let sender = rx.await?;
+                    sleep(Duration::from_millis(30)).await;
+                    Ok(sender)
+                })
+            },
+            4,
+        );
+        sleep(Duration::from_millis(10)).await;
+        let (_, statuses) = promise.poll_state_with_status();
+        assert_eq!(statuses.len(), 2);
+        assert_eq!(statuses[1].message, "done");
+        assert_eq!(promise.get_progress(), Progress::from_percent(100));
+
+        sleep(Duration::from_millis(40)).await;
+        assert!(promise.poll_state().is_success());
+        let sender = promise.take_value().unwrap();
+        drop(promise);
+        let closed = std::thread::spawn(move || sender.set_progress(Progress::from_percent(0)));
+        assert!(closed.join().unwrap().is_err());
+    }
+    #[tokio::test]
+    async fn map_history_keeps_time_and_progress() {
+        #[derive(Debug)]
+        enum TaskEvent {
+            Fetched(u32),
+            Stored,
+        }
+        let mut promise = ProgressTrackedImValProm::new(
+            |s| {
+                ImmediateValuePromise::new(async move {
+                    s.send(Status::new(
+                        Progress::from_percent(50),
+                        TaskEvent::Fetched(3),
+                    ))
+                    .await?;
+                    s.send(Status::new(Progress::from_percent(100), TaskEvent::Stored))
+                        .await?;
+                    Ok(())
+                })
+            },
+            4,
+        );
+        sleep(Duration::from_millis(20)).await;
+        promise.poll_state();
+        let times: Vec<_> = promise.status_history().iter().map(|s| s.time).collect();
+        let strings = promise.map_history(|event| format!("{event:?}"));
+        assert_eq!(strings[0].message, "Fetched(3)");
+        assert_eq!(strings[1].progress, Progress::from_percent(100));
+        assert_eq!(strings.iter().map(|s| s.time).collect::<Vec<_>>(), times);
+        assert_eq!(promise.status_history().len(), 2);
+
+        let taken = promise.take_history_mapped(|event| match event {
+            TaskEvent::Fetched(count) => count,
+            TaskEvent::Stored => 0,
+        });
+        assert_eq!(taken.iter().map(|s| s.message).collect::<Vec<_>>(), [3, 0]);
+        assert_eq!(taken[0].time, times[0]);
+        assert!(promise.status_history().is_empty());
+        assert_eq!(promise.get_progress(), Progress::from_percent(100));
+    }
+
+    #[tokio::test]
+    async fn poll_state_for_budget() {
+        let mut promise = ProgressTrackedImValProm::new(
+            |s| {
+                ImmediateValuePromise::new(async move {
+                    for i in 0..100 {
+                        send_status_fmt!(s, i, "step {}", i);
+                    }
+                    Ok(34)
+                })
+            },
+            200,
+        )
+        .with_progress_coalescing_by(|_, _| {
+            std::thread::sleep(Duration::from_millis(1));
+            false
+        });
+        sleep(Duration::from_millis(20)).await;
+        assert!(promise
+            .poll_state_for(Duration::from_millis(5))
+            .is_updating());
+        assert_eq!(promise.status_history().len(), BUDGET_CHECK_ITEMS);
+        assert!(promise.has_backlog());
+
+        assert!(promise.poll_state().is_success());
+        assert_eq!(promise.status_history().len(), 100);
+        assert!(!promise.has_backlog());
+    }
+
+    #[tokio::test]
+    async fn out_of_order_senders() {
+        for coalescing in [false, true] {
+            let mut promise = ProgressTrackedImValProm::new(
+                |s| {
+                    let other = s.clone();
+                    ImmediateValuePromise::new(async move {
+                        let early = StringStatus::from_str(Progress::from_percent(10), "early");
+                        sleep(Duration::from_millis(5)).await;
+                        let late = StringStatus::from_str(Progress::from_percent(20), "late");
+                        s.send(late).await.unwrap();
+                        sleep(Duration::from_millis(30)).await;
+                        other.send(early).await.unwrap();
+                        Ok(34)
+                    })
+                },
+                6,
+            );
+            if coalescing {
+                promise = promise.with_progress_coalescing();
+            }
+            sleep(Duration::from_millis(20)).await;
+            assert_eq!(promise.poll_state_with_status().1.len(), 1);
+            sleep(Duration::from_millis(50)).await;
+            let (state, received) = promise.poll_state_with_status();
+            assert!(state.is_success());
+            let messages: Vec<_> = received.iter().map(|status| &status.message).collect();
+            assert_eq!(messages, ["early", "late"]);
+
+            let history = promise.status_history();
+            let (early, late) = (history[0].time, history[1].time);
+            assert_eq!(promise.statuses_since(late).count(), 1);
+            assert_eq!(promise.statuses_between(early, late).len(), 1);
+            assert_eq!(promise.get_progress(), Progress::from_percent(20));
+        }
     }
 }
@@ -0,0 +1,147 @@
+use std::fmt::Display;
+use std::time::{Duration, Instant};
+
+use indicatif::ProgressBar;
+
+use crate::runtime::sleep;
+use crate::{
+    BoxedSendError, DirectCacheAccess, ErrorKind, FutureResult, ImmediateValueState, Progress,
+    ProgressTrackedImValProm, Status,
+};
+
+/// How often [`ProgressTrackedImValProm::drive_with_indicatif`] polls
+const DRIVE_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A bar attached to a [`ProgressTrackedImValProm`], updated by each `poll_state`
+pub(crate) struct AttachedBar<M> {
+    bar: ProgressBar,
+    describe: fn(&M) -> String,
+    /// the time of the status last shown as message
+    shown: Option<Instant>,
+}
+
+impl<M> AttachedBar<M> {
+    pub(crate) fn show<T>(
+        &mut self,
+        progress: Progress,
+        last: Option<&Status<M>>,
+        state: &ImmediateValueState<T>,
+    ) {
+        if self.bar.is_finished() {
+            return;
+        }
+        if let Some(length) = self.bar.length() {
+            self.bar
+                .set_position((progress.as_f64() * length as f64).round() as u64);
+        }
+        if let Some(last) = last.filter(|last| Some(last.time) != self.shown) {
+            self.shown = Some(last.time);
+            self.bar.set_message((self.describe)(&last.message));
+        }
+        match state {
+            ImmediateValueState::Success(_) => self.bar.finish(),
+            ImmediateValueState::Error(error) => self.bar.abandon_with_message(error.to_string()),
+            _ => {}
+        }
+    }
+}
+
+impl<T: Send + 'static, M: Display> ProgressTrackedImValProm<T, M> {
+    /// Drive `bar` from this promise, e.g. in a CLI tool: each `poll_state` sets the position from the progress
+    /// and the message from the last status. The bar is finished on success and abandoned with the error message
+    /// on error. A bar without length gets a length of 100.
+    pub fn attach_indicatif(&mut self, bar: ProgressBar) {
+        if bar.length().is_none() {
+            bar.set_length(100);
+        }
+        let mut attached = AttachedBar {
+            bar,
+            describe: M::to_string,
+            shown: None,
+        };
+        attached.show(self.get_progress(), self.last_status(), self.get_state());
+        self.bar = Some(attached);
+    }
+
+    /// Attach `bar` like [`Self::attach_indicatif`] and poll until the future finished, for contexts without a UI
+    /// loop. Returns the result, or an error if it was already taken.
+    /// ```rust, no_run
+    /// use indicatif::ProgressBar;
+    /// use lazy_async_promise::{ImmediateValuePromise, ProgressTrackedImValProm, api_macros::*};
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let export = ProgressTrackedImValProm::new(
+    ///     |s| {
+    ///         ImmediateValuePromise::new(async move {
+    ///             for chunk in 0..10 {
+    ///                 send_status_fmt!(s, (chunk + 1) * 10, "chunk {}", chunk + 1);
+    ///             }
+    ///             Ok(34)
+    ///         })
+    ///     },
+    ///     100,
+    /// );
+    /// let rows = export.drive_with_indicatif(ProgressBar::new(100)).await.unwrap();
+    /// # }
+    /// ```
+    pub async fn drive_with_indicatif(mut self, bar: ProgressBar) -> FutureResult<T> {
+        self.attach_indicatif(bar);
+        while self.poll_state().is_updating() {
+            sleep(DRIVE_INTERVAL).await;
+        }
+        self.take_result().unwrap_or_else(|| {
+            let error = BoxedSendError::msg("value was already taken");
+            Err(error.with_kind(ErrorKind::Runtime))
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use indicatif::ProgressBar;
+
+    use crate::api_macros::*;
+    use crate::runtime::sleep;
+    use crate::{BoxedSendError, ImmediateValuePromise, ProgressTrackedImValProm};
+
+    #[tokio::test]
+    async fn bar_follows_promise() {
+        let mut promise = ProgressTrackedImValProm::new(
+            |s| {
+                ImmediateValuePromise::new(async move {
+                    send_status!(s, 40, "reading");
+                    sleep(Duration::from_millis(40)).await;
+                    Ok(34)
+                })
+            },
+            6,
+        );
+        let bar = ProgressBar::hidden();
+        promise.attach_indicatif(bar.clone());
+        sleep(Duration::from_millis(20)).await;
+        promise.poll_state();
+        assert_eq!((bar.position(), bar.length()), (40, Some(100)));
+        assert_eq!(bar.message(), "reading");
+        assert!(!bar.is_finished());
+        sleep(Duration::from_millis(50)).await;
+        promise.poll_state();
+        assert!(bar.is_finished());
+
+        let failing = ProgressTrackedImValProm::<u32, _>::new(
+            |s| {
+                ImmediateValuePromise::new(async move {
+                    send_status!(s, 10, "connecting");
+                    Err(BoxedSendError::msg("unreachable"))
+                })
+            },
+            6,
+        );
+        let bar = ProgressBar::new(10);
+        let result = failing.drive_with_indicatif(bar.clone()).await;
+        assert_eq!(result.unwrap_err().to_string(), "unreachable");
+        assert_eq!(bar.message(), "unreachable");
+        assert!(bar.is_finished());
+    }
+}
@@ -0,0 +1,122 @@
+use std::future::Future;
+
+use tokio::sync::mpsc::Sender;
+
+use crate::{BoxedSendError, ImmediateValuePromise, ProgressTrackedImValProm, Status};
+
+/// # Turn a future into an [`ImmediateValuePromise`]
+/// Implemented for every `Send` future resolving to a `Result` whose error converts into [`BoxedSendError`],
+/// so the error type doesn't have to be mapped by hand:
+/// ```rust, no_run
+/// use lazy_async_promise::{ImmediateValuePromise, IntoPromise};
+/// let promises: Vec<ImmediateValuePromise<String>> = ["a.txt", "b.txt"]
+///     .into_iter()
+///     .map(|path| tokio::fs::read_to_string(path).into_promise())
+///     .collect();
+/// ```
+pub trait IntoPromise<T: Send, E>: Future<Output = Result<T, E>> + Sized {
+    /// Spawn the future as [`ImmediateValuePromise`], see [`ImmediateValuePromise::new`]
+    fn into_promise(self) -> ImmediateValuePromise<T>;
+}
+
+impl<T, E, F> IntoPromise<T, E> for F
+where
+    T: Send + 'static,
+    E: Into<BoxedSendError>,
+    F: Future<Output = Result<T, E>> + Send + 'static,
+{
+    fn into_promise(self) -> ImmediateValuePromise<T> {
+        ImmediateValuePromise::new(async move { self.await.map_err(Into::into) })
+    }
+}
+
+/// # Turn a closure creating a future into a [`ProgressTrackedImValProm`]
+/// The closure gets the status sender, like the creator passed to [`ProgressTrackedImValProm::new`]:
+/// ```rust, no_run
+/// use tokio::sync::mpsc::Sender;
+/// use lazy_async_promise::{IntoTrackedPromise, Progress, StringStatus};
+/// let promise = (|status: Sender<StringStatus>| async move {
+///     status.send(StringStatus::from_str(Progress::from_percent(50), "halfway")).await?;
+///     Ok::<_, tokio::sync::mpsc::error::SendError<_>>(34)
+/// })
+/// .into_tracked_promise(10);
+/// ```
+pub trait IntoTrackedPromise<T: Send, M, E> {
+    /// Spawn the future created by the closure, with a status channel of the given buffer size
+    fn into_tracked_promise(self, buffer: usize) -> ProgressTrackedImValProm<T, M>;
+}
+
+impl<T, M, E, Fut, F> IntoTrackedPromise<T, M, E> for F
+where
+    T: Send + 'static,
+    E: Into<BoxedSendError>,
+    Fut: Future<Output = Result<T, E>> + Send + 'static,
+    F: FnOnce(Sender<Status<M>>) -> Fut,
+{
+    fn into_tracked_promise(self, buffer: usize) -> ProgressTrackedImValProm<T, M> {
+        ProgressTrackedImValProm::new(|sender| self(sender).into_promise(), buffer)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use tokio::sync::mpsc::Sender;
+
+    use super::{IntoPromise, IntoTrackedPromise};
+    use crate::runtime::sleep;
+    use crate::{DirectCacheAccess, Progress, StringStatus};
+
+    #[derive(Debug)]
+    struct Offline;
+
+    impl std::fmt::Display for Offline {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "offline")
+        }
+    }
+
+    impl std::error::Error for Offline {}
+
+    #[tokio::test]
+    async fn into_promise() {
+        let mut promises: Vec<_> = (0..3)
+            .map(|i| {
+                async move {
+                    if i < 2 {
+                        Ok(i)
+                    } else {
+                        Err(Offline)
+                    }
+                }
+                .into_promise()
+            })
+            .collect();
+        sleep(Duration::from_millis(50)).await;
+        let states: Vec<_> = promises
+            .iter_mut()
+            .map(|promise| promise.poll_state().to_string())
+            .collect();
+        assert_eq!(states, vec!["success", "success", "error: offline"]);
+        assert_eq!(promises[1].get_value(), Some(&1));
+    }
+
+    #[tokio::test]
+    async fn into_tracked_promise() {
+        let mut promise = (|status: Sender<StringStatus>| async move {
+            status
+                .send(StringStatus::from_str(
+                    Progress::from_percent(50),
+                    "halfway",
+                ))
+                .await?;
+            Ok::<_, tokio::sync::mpsc::error::SendError<_>>(34)
+        })
+        .into_tracked_promise(10);
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(promise.poll_state().get_value(), Some(&34));
+        assert_eq!(promise.get_progress(), Progress::from_percent(50));
+        assert_eq!(promise.status_history().len(), 1);
+    }
+}
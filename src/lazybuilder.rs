@@ -0,0 +1,342 @@
+use std::fmt::Debug;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::time::Duration;
+
+use tokio::sync::mpsc::Sender;
+#[cfg(feature = "tokio-util")]
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    BoxedSendError, ErrorDisplay, ErrorHook, LazyValuePromise, LazyVecPromise, Message,
+    RetryPolicy, UpdateLimit,
+};
+
+/// Channel buffer size used by [`LazyPromiseBuilder`] unless set with [`LazyPromiseBuilder::buffer_size`]
+pub const DEFAULT_BUFFER_SIZE: usize = 100;
+
+/// # Builder for [`LazyVecPromise`]s and [`LazyValuePromise`]s
+/// Created by [`LazyVecPromise::builder`] or [`LazyValuePromise::builder`] and finished with [`Self::build`].
+/// Every option defaults to what the constructor does without the matching `with_*` call, so
+/// `LazyVecPromise::new(updater, n)` is the same as `LazyVecPromise::builder(updater).buffer_size(n).build()`.
+/// ```rust, no_run
+/// use tokio::sync::mpsc::Sender;
+/// use lazy_async_promise::{DataState, LazyVecPromise, Message, RetryPolicy, api_macros::*};
+/// let updater = |tx: Sender<Message<i32>>| async move {
+///     send_data!(34, tx);
+///     set_finished!(tx);
+/// };
+/// let promise = LazyVecPromise::builder(updater)
+///     .name("numbers")
+///     .buffer_size(10)
+///     .capacity(1)
+///     .retry(RetryPolicy::new(3))
+///     .build();
+/// ```
+pub struct LazyPromiseBuilder<P, U> {
+    updater: U,
+    buffer_size: usize,
+    capacity: usize,
+    sources: usize,
+    name: Option<String>,
+    on_error: Option<ErrorHook>,
+    retry: Option<RetryPolicy>,
+    error_display: Option<ErrorDisplay>,
+    min_updating_display: Duration,
+    min_update_interval: Option<(Duration, UpdateLimit)>,
+    transition_log: bool,
+    #[cfg(feature = "tokio-util")]
+    token: Option<CancellationToken>,
+    #[cfg(feature = "log")]
+    log_progress_step: Option<crate::Progress>,
+    promise: PhantomData<fn() -> P>,
+}
+
+impl<P, U> LazyPromiseBuilder<P, U> {
+    pub(crate) fn new(updater: U) -> Self {
+        LazyPromiseBuilder {
+            updater,
+            buffer_size: DEFAULT_BUFFER_SIZE,
+            capacity: 0,
+            sources: 1,
+            name: None,
+            on_error: None,
+            retry: None,
+            error_display: None,
+            min_updating_display: Duration::ZERO,
+            min_update_interval: None,
+            transition_log: false,
+            #[cfg(feature = "tokio-util")]
+            token: None,
+            #[cfg(feature = "log")]
+            log_progress_step: None,
+            promise: PhantomData,
+        }
+    }
+
+    /// Size of the channel between updater and promise, defaults to [`DEFAULT_BUFFER_SIZE`]
+    pub fn buffer_size(mut self, buffer_size: usize) -> Self {
+        self.buffer_size = buffer_size;
+        self
+    }
+
+    /// See [`LazyVecPromise::with_name`]
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// See [`LazyVecPromise::with_on_error`]
+    pub fn on_error(mut self, hook: impl FnMut(&BoxedSendError) + Send + 'static) -> Self {
+        self.on_error = Some(Box::new(hook));
+        self
+    }
+
+    /// See [`LazyVecPromise::with_retry`]
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = Some(policy);
+        self
+    }
+
+    /// See [`LazyVecPromise::with_error_display`]
+    pub fn error_display(
+        mut self,
+        mapper: impl Fn(&BoxedSendError) -> String + Send + Sync + 'static,
+    ) -> Self {
+        self.error_display = Some(Box::new(mapper));
+        self
+    }
+
+    /// See [`LazyVecPromise::with_min_updating_display`]
+    pub fn min_updating_display(mut self, duration: Duration) -> Self {
+        self.min_updating_display = duration;
+        self
+    }
+
+    /// See [`LazyVecPromise::with_min_update_interval`]
+    pub fn min_update_interval(mut self, interval: Duration, limit: UpdateLimit) -> Self {
+        self.min_update_interval = Some((interval, limit));
+        self
+    }
+
+    /// See [`LazyVecPromise::with_transition_log`]
+    pub fn transition_log(mut self) -> Self {
+        self.transition_log = true;
+        self
+    }
+
+    /// See [`LazyVecPromise::with_cancellation`]
+    #[cfg(feature = "tokio-util")]
+    pub fn cancellation(mut self, token: &CancellationToken) -> Self {
+        self.token = Some(token.child_token());
+        self
+    }
+
+    /// See [`LazyVecPromise::with_log_progress_step`]
+    #[cfg(feature = "log")]
+    pub fn log_progress_step(mut self, step: crate::Progress) -> Self {
+        self.log_progress_step = Some(step);
+        self
+    }
+}
+
+impl<T, U, Fut> LazyPromiseBuilder<LazyVecPromise<T>, U>
+where
+    T: Debug + Send + 'static,
+    U: Fn(Sender<Message<T>>) -> Fut + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    /// See [`LazyVecPromise::with_capacity`]
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// See [`LazyVecPromise::with_sources`]
+    pub fn sources(mut self, sources: usize) -> Self {
+        self.sources = sources;
+        self
+    }
+
+    /// Create the promise, the updater is started on the first poll as usual
+    pub fn build(self) -> LazyVecPromise<T> {
+        let mut promise = LazyVecPromise::new(self.updater, self.buffer_size)
+            .with_capacity(self.capacity)
+            .with_sources(self.sources);
+        promise.on_error = self.on_error;
+        promise.retry = self.retry;
+        promise.error_display = self.error_display;
+        promise.log.min_updating = self.min_updating_display;
+        if let Some((interval, limit)) = self.min_update_interval {
+            promise = promise.with_min_update_interval(interval, limit);
+        }
+        if self.transition_log {
+            promise = promise.with_transition_log();
+        }
+        if let Some(name) = self.name {
+            promise = promise.with_name(name);
+        }
+        #[cfg(feature = "tokio-util")]
+        {
+            promise.cancel_token = self.token;
+        }
+        #[cfg(feature = "log")]
+        if let Some(step) = self.log_progress_step {
+            promise = promise.with_log_progress_step(step);
+        }
+        promise
+    }
+}
+
+impl<T, U, Fut> LazyPromiseBuilder<LazyValuePromise<T>, U>
+where
+    T: Debug + Send + 'static,
+    U: Fn(Sender<Message<T>>) -> Fut + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    /// Create the promise, the updater is started on the first poll as usual
+    pub fn build(self) -> LazyValuePromise<T> {
+        let mut promise = LazyValuePromise::new(self.updater, self.buffer_size);
+        promise.on_error = self.on_error;
+        promise.retry = self.retry;
+        promise.error_display = self.error_display;
+        promise.log.min_updating = self.min_updating_display;
+        if let Some((interval, limit)) = self.min_update_interval {
+            promise = promise.with_min_update_interval(interval, limit);
+        }
+        if self.transition_log {
+            promise = promise.with_transition_log();
+        }
+        if let Some(name) = self.name {
+            promise = promise.with_name(name);
+        }
+        #[cfg(feature = "tokio-util")]
+        {
+            promise.cancel_token = self.token;
+        }
+        #[cfg(feature = "log")]
+        if let Some(step) = self.log_progress_step {
+            promise = promise.with_log_progress_step(step);
+        }
+        promise
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use tokio::sync::mpsc::Sender;
+
+    use crate::api_macros::*;
+    use crate::runtime::sleep;
+    use crate::{
+        BoxedSendError, DataState, DirectCacheAccess, LazyValuePromise, LazyVecPromise, Message,
+        Promise, RetryPolicy,
+    };
+
+    #[tokio::test]
+    async fn defaults_match_new() {
+        // all messages fit into the default buffer without the updater waiting for a poll
+        const ITEMS: i32 = super::DEFAULT_BUFFER_SIZE as i32 - 1;
+        let updater = |tx: Sender<Message<i32>>| async move {
+            for i in 0..ITEMS {
+                send_data!(i, tx);
+            }
+            set_finished!(tx);
+        };
+        let mut promise = LazyVecPromise::builder(updater).build();
+        assert!(promise.name().is_none());
+        assert!(promise.is_uninitialized());
+        promise.poll_state();
+        sleep(Duration::from_millis(50)).await;
+        assert!(promise.poll_state().is_success());
+        assert_eq!(promise.as_slice().len(), ITEMS as usize);
+    }
+
+    #[tokio::test]
+    async fn vec_options() {
+        let updater = |tx: Sender<Message<i32>>| async move {
+            send_data!(1, tx);
+            send_data!(2, tx);
+            set_finished!(tx);
+        };
+        let mut promise = LazyVecPromise::builder(updater)
+            .name("numbers")
+            .buffer_size(1)
+            .capacity(16)
+            .build();
+        assert_eq!(promise.name(), Some("numbers"));
+        for _ in 0..10 {
+            promise.poll_state();
+            sleep(Duration::from_millis(10)).await;
+        }
+        assert!(promise.poll_state().is_success());
+        assert_eq!(promise.as_slice(), &[1, 2]);
+        assert!(promise.get_value().unwrap().capacity() >= 16);
+    }
+
+    #[tokio::test]
+    async fn error_options() {
+        let attempts = Arc::new(AtomicU32::new(0));
+        let attempts_updater = attempts.clone();
+        let errors = Arc::new(AtomicU32::new(0));
+        let errors_hook = errors.clone();
+        let updater = move |tx: Sender<Message<i32>>| {
+            let attempts = attempts_updater.clone();
+            async move {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                set_error!(BoxedSendError::msg("offline"), tx);
+            }
+        };
+        let mut promise = LazyValuePromise::builder(updater)
+            .retry(RetryPolicy::new(1))
+            .on_error(move |_| {
+                errors_hook.fetch_add(1, Ordering::SeqCst);
+            })
+            .error_display(|_| "try again later".to_string())
+            .build();
+        promise.poll_state();
+        for _ in 0..10 {
+            sleep(Duration::from_millis(10)).await;
+            promise.poll_state();
+        }
+        assert!(promise.poll_state().is_error());
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+        assert_eq!(errors.load(Ordering::SeqCst), 1);
+        assert_eq!(promise.display_error().unwrap(), "try again later");
+    }
+
+    #[cfg(feature = "tokio-util")]
+    #[tokio::test]
+    async fn cancellation() {
+        let token = tokio_util::sync::CancellationToken::new();
+        let updater = |tx: Sender<Message<i32>>| async move {
+            sleep(Duration::from_secs(10)).await;
+            send_data!(1, tx);
+            set_finished!(tx);
+        };
+        let mut promise = LazyValuePromise::builder(updater)
+            .cancellation(&token)
+            .build();
+        promise.poll_state();
+        token.cancel();
+        sleep(Duration::from_millis(20)).await;
+        assert!(promise.poll_state().is_cancelled());
+    }
+
+    #[cfg(feature = "log")]
+    #[tokio::test]
+    async fn log_progress_step() {
+        let updater = |tx: Sender<Message<i32>>| async move {
+            send_data!(1, tx);
+            set_finished!(tx);
+        };
+        let promise = LazyVecPromise::builder(updater)
+            .log_progress_step(Progress::from_percent(10))
+            .build();
+        assert_eq!(promise.log.progress_step, 0.1);
+    }
+}
@@ -1,14 +1,43 @@
+#[cfg(feature = "tokio-util")]
+use crate::cancellation::{cancellable, is_cancelled};
+use crate::control::{self, Control};
+use crate::dropcount::DropCounter;
+use crate::lazybuilder::LazyPromiseBuilder;
+use crate::ratelimit::MinInterval;
+#[cfg(feature = "registry")]
+use crate::registry::{data_state_variant, Registration};
+use crate::runtime::Task;
+#[cfg(feature = "futures")]
+use crate::statechange::state_change_stream;
+use crate::statechange::{spawn_updater, Observers};
+use crate::transitionlog::{self, Transition, TransitionCause, TransitionLog};
+use crate::updatelog::{TransitionTimes, UpdateLog};
+#[cfg(feature = "futures")]
+use crate::StateChange;
 use crate::{
-    box_future_factory, BoxedFutureFactory, DataState, DirectCacheAccess, Message, Promise,
+    box_future_factory, display_error, drain_into, report_error, updater_task_error,
+    BoxedFutureFactory, BoxedSendError, DataState, DataStateSnapshot, DataStateTag,
+    DirectCacheAccess, ErrorDisplay, ErrorHook, ErrorKind, FutureResult, ImmediateValuePromise,
+    ImmediateValueState, Message, Promise, RetryPolicy, UpdateLimit, UpdateOutcome,
 };
-use std::{fmt::Debug, mem};
-use std::future::Future;
-use tokio::sync::mpsc::{channel, Receiver, Sender};
+use std::fmt::{self, Debug, Formatter};
+use std::future::{poll_fn, Future};
+use std::mem;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::Poll;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::error::SendError;
+use tokio::sync::mpsc::{channel, Receiver, Sender, UnboundedReceiver};
+use tokio::sync::{oneshot, watch};
+#[cfg(feature = "tokio-util")]
+use tokio_util::sync::CancellationToken;
 
 /// # A single lazy-async updated value
 /// Create one with the [`LazyValuePromise::new`] method and supply an updater.
 /// It's Updated only on first try to poll it making it scale nicely on more complex UIs.
 /// While still updating, the value can be read out already, this can make sense for iterative algorithms where an intermediate result can be used as a preview.
+/// Dropping the promise aborts a running updater.
 /// Examples:
 /// ```rust, no_run
 /// use std::time::Duration;
@@ -41,15 +70,58 @@ use tokio::sync::mpsc::{channel, Receiver, Sender};
 /// ```
 ///
 ///
-
 pub struct LazyValuePromise<T: Debug> {
     cache: Option<T>,
     updater: BoxedFutureFactory<T>,
     state: DataState,
     rx: Receiver<Message<T>>,
     tx: Sender<Message<T>>,
+    task: Option<Task>,
+    pub(crate) on_error: Option<ErrorHook>,
+    pub(crate) retry: Option<RetryPolicy>,
+    retries_done: u32,
+    pub(crate) error_display: Option<ErrorDisplay>,
+    observers: Observers,
+    #[cfg(feature = "tokio-util")]
+    pub(crate) cancel_token: Option<CancellationToken>,
+    pub(crate) name: Option<String>,
+    pub(crate) log: UpdateLog,
+    batch: Vec<Message<T>>,
+    seed: Option<Seed<T>>,
+    completions: Vec<Completion<T>>,
+    optimistic: Option<Optimistic<T>>,
+    generation: u64,
+    /// the generation the running or last update started from
+    update_generation: u64,
+    compare_and_set: bool,
+    /// shares the start generation with the updater of [`LazyValuePromise::new_with_generation`]
+    start_generation: Option<Arc<AtomicU64>>,
+    superseded: bool,
+    stale: bool,
+    fallback: Option<T>,
+    dropped: DropCounter,
+    min_interval: MinInterval,
+    failed_attempts: u32,
+    transitions: Option<Box<TransitionLog<DataState>>>,
+    /// the error of the last failed update, kept while the next one runs
+    last_error: Option<BoxedSendError>,
+    control: Option<Control>,
+    #[cfg(feature = "registry")]
+    registration: Option<Registration>,
 }
-impl<T: Debug> LazyValuePromise<T> {
+
+/// A running save of [`LazyValuePromise::optimistic_update`] and the value to restore if it fails
+struct Optimistic<T> {
+    previous: Option<T>,
+    save: ImmediateValuePromise<()>,
+}
+
+/// Polls the [`ImmediateValuePromise`] a [`LazyValuePromise`] was created from, see [`LazyValuePromise::from_immediate`]
+type Seed<T> = Box<dyn FnMut() -> Option<FutureResult<T>> + Send>;
+
+/// Hands the result of an update to a promise returned by [`LazyValuePromise::next_completion`]
+type Completion<T> = Box<dyn FnOnce(Result<&T, &BoxedSendError>) + Send>;
+impl<T: Debug + Send + 'static> LazyValuePromise<T> {
     /// Creates a new LazyValuePromise given an Updater and a tokio buffer size
     pub fn new<
         U: Fn(Sender<Message<T>>) -> Fut + 'static,
@@ -65,31 +137,584 @@ impl<T: Debug> LazyValuePromise<T> {
             state: DataState::Uninitialized,
             rx,
             tx,
+            task: None,
+            on_error: None,
+            retry: None,
+            retries_done: 0,
+            error_display: None,
+            observers: Observers::new(),
+            #[cfg(feature = "tokio-util")]
+            cancel_token: None,
+            name: None,
+            log: UpdateLog::new(),
+            batch: Vec::new(),
             updater: box_future_factory(future_factory),
+            seed: None,
+            completions: Vec::new(),
+            optimistic: None,
+            generation: 0,
+            update_generation: 0,
+            compare_and_set: false,
+            start_generation: None,
+            superseded: false,
+            stale: false,
+            fallback: None,
+            dropped: DropCounter::default(),
+            min_interval: MinInterval::default(),
+            failed_attempts: 0,
+            transitions: TransitionLog::by_default(&DataState::Uninitialized),
+            last_error: None,
+            control: None,
+            #[cfg(feature = "registry")]
+            registration: None,
+        }
+    }
+
+    /// Creates a LazyValuePromise showing `value` right away, e.g. restored from the last session. It starts
+    /// [`DataState::UpToDate`] and [`LazyValuePromise::is_stale`], so polling doesn't update on its own - call
+    /// [`Promise::update`] to refresh. Unlike other values, the initial value is kept while updating, until the
+    /// update sends a new one.
+    pub fn new_with_initial<
+        U: Fn(Sender<Message<T>>) -> Fut + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    >(
+        value: T,
+        future_factory: U,
+        buffer_size: usize,
+    ) -> Self {
+        let mut promise = Self::new(future_factory, buffer_size);
+        promise.cache = Some(value);
+        promise.state = DataState::UpToDate;
+        promise.state_changed(TransitionCause::ValueSet);
+        promise.stale = true;
+        promise
+    }
+
+    /// Creates a LazyValuePromise whose updater also gets the receiver of a control channel, e.g. to change a
+    /// parameter of a long running update without restarting it. See [`crate::LazyVecPromise::new_with_control`].
+    pub fn new_with_control<
+        C: Send + 'static,
+        U: Fn(Sender<Message<T>>, UnboundedReceiver<C>) -> Fut + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    >(
+        future_factory: U,
+        buffer_size: usize,
+    ) -> Self {
+        let (control, slot) = Control::new::<C>();
+        let future_factory = move |tx| future_factory(tx, control::take_receiver(&slot));
+        let mut promise = Self::new(future_factory, buffer_size);
+        promise.control = Some(control);
+        promise
+    }
+
+    /// Creates a LazyValuePromise whose updater also gets the generation the update starts from, see
+    /// [`Self::update_tracked`]. The updater can hand it on, e.g. as version of a conditional request, so the
+    /// backend rejects writes based on an outdated value - [`Self::with_compare_and_set`] only guards the promise.
+    pub fn new_with_generation<
+        U: Fn(Sender<Message<T>>, u64) -> Fut + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    >(
+        future_factory: U,
+        buffer_size: usize,
+    ) -> Self {
+        let start = Arc::new(AtomicU64::new(0));
+        let slot = start.clone();
+        let future_factory = move |tx| future_factory(tx, slot.load(Ordering::Relaxed));
+        let mut promise = Self::new(future_factory, buffer_size);
+        promise.start_generation = Some(start);
+        promise
+    }
+
+    /// Send `message` to the running update of a promise from [`Self::new_with_control`], see
+    /// [`crate::LazyVecPromise::send_control`]
+    pub fn send_control<C: 'static>(&mut self, message: C) -> Result<(), SendError<C>> {
+        Control::send(&self.control, message)
+    }
+
+    /// Whether the value is still the initial one of [`LazyValuePromise::new_with_initial`], until an update sent
+    /// a value or the value was replaced
+    pub fn is_stale(&self) -> bool {
+        self.stale
+    }
+
+    /// Show `value` via [`DirectCacheAccess::get_value`] while the promise is uninitialized or updating without a
+    /// value, e.g. a sensible default for settings. The fallback is never handed out mutably or taken, and
+    /// [`DirectCacheAccess::get_result`] still waits for the real value. See [`LazyValuePromise::is_fallback`].
+    /// ```rust
+    /// use tokio::sync::mpsc::Sender;
+    /// use lazy_async_promise::{DataState, DirectCacheAccess, LazyValuePromise, Message, api_macros::*};
+    /// let updater = |tx: Sender<Message<u32>>| async move {
+    ///     send_data!(120, tx);
+    ///     set_finished!(tx);
+    /// };
+    /// let mut font_size = LazyValuePromise::new(updater, 6).with_fallback(14);
+    /// assert_eq!(font_size.get_value(), Some(&14));
+    /// assert!(font_size.is_fallback());
+    /// assert_eq!(font_size.take_value(), None);
+    /// ```
+    pub fn with_fallback(mut self, value: T) -> Self {
+        self.fallback = Some(value);
+        self
+    }
+
+    /// Whether [`DirectCacheAccess::get_value`] returns the fallback of [`LazyValuePromise::with_fallback`] instead of
+    /// fetched data
+    pub fn is_fallback(&self) -> bool {
+        self.cache.is_none()
+            && self.fallback.is_some()
+            && matches!(
+                self.state,
+                DataState::Uninitialized | DataState::Updating(_)
+            )
+    }
+
+    /// Creates a LazyValuePromise whose first value is the result of a one-shot promise, while later updates run
+    /// the updater. The lazy promise takes over the one-shot promise and moves its value into the own cache once it
+    /// arrives, an error becomes the error state. If the value was taken from the one-shot promise already, the
+    /// updater runs on the first poll as usual.
+    pub fn from_immediate<
+        U: Fn(Sender<Message<T>>) -> Fut + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    >(
+        mut promise: ImmediateValuePromise<T>,
+        future_factory: U,
+        buffer_size: usize,
+    ) -> Self {
+        let mut lazy = Self::new(future_factory, buffer_size);
+        if !promise.poll_state().is_empty() {
+            lazy.state = DataState::Updating(0.0.into());
+            lazy.state_changed(TransitionCause::Update);
+            lazy.seed = Some(Box::new(move || match promise.poll_state() {
+                ImmediateValueState::Updating => None,
+                _ => promise.take_result(),
+            }));
         }
+        lazy
+    }
+
+    /// A one-shot promise resolving once the running update finishes, or the next one if the promise is not updating.
+    /// The lazy promise keeps the value in its cache, the one-shot promise gets a clone. Errors can't be cloned, so
+    /// they are passed on as message with [`BoxedSendError::full_message`]. The update is observed by polling this
+    /// promise, so the one-shot promise only resolves once it is polled.
+    pub fn next_completion(&mut self) -> ImmediateValuePromise<T>
+    where
+        T: Clone,
+    {
+        let (result_tx, result_rx) = oneshot::channel();
+        self.completions
+            .push(Box::new(move |result: Result<&T, &BoxedSendError>| {
+                let result = result.cloned().map_err(|error| {
+                    BoxedSendError::msg(error.full_message()).with_kind(error.kind())
+                });
+                // nobody is interested in the result if the one-shot promise is gone
+                let _ = result_tx.send(result);
+            }));
+        ImmediateValuePromise::new(async move {
+            result_rx.await.unwrap_or_else(|_| {
+                let error = BoxedSendError::msg("promise dropped before completing");
+                Err(error.with_kind(ErrorKind::ChannelClosed))
+            })
+        })
+    }
+
+    /// Show `new_value` right away while the future created by `save` persists it. If the save fails, the previous
+    /// value is restored and the state becomes [`DataState::Error`] with the save error. The new value is injected
+    /// like with [`DirectCacheAccess::set_value`], so an update in flight still overwrites it.
+    /// A second optimistic update before the first save finished replaces it: the result of the first save is
+    /// ignored and a failure of the second restores the value from before the first one. Dropping the promise
+    /// aborts a running save.
+    pub fn optimistic_update<
+        E: Into<BoxedSendError>,
+        Fut: Future<Output = Result<(), E>> + Send + 'static,
+    >(
+        &mut self,
+        new_value: T,
+        save: impl FnOnce(&T) -> Fut,
+    ) {
+        let save = save(&new_value);
+        let save = ImmediateValuePromise::new(async move { save.await.map_err(Into::into) });
+        let replaced = self.set_value(new_value);
+        let previous = match self.optimistic.take() {
+            Some(running) => {
+                // the first save still reaches the backend, only its result is ignored
+                running.save.detach();
+                running.previous
+            }
+            None => replaced,
+        };
+        self.optimistic = Some(Optimistic { previous, save });
+    }
+
+    /// Whether an optimistic value is shown while its save is still running, see
+    /// [`LazyValuePromise::optimistic_update`]
+    pub fn is_optimistic(&self) -> bool {
+        self.optimistic.is_some()
+    }
+
+    /// Counts the writes to the value: bumped by each finished update and each [`DirectCacheAccess::set_value`],
+    /// including optimistic updates
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Like [`Promise::update`], returns the generation the update starts from to correlate it with
+    /// [`LazyValuePromise::generation`]. If an update is running already, its start generation is returned.
+    pub fn update_tracked(&mut self) -> u64 {
+        self.update();
+        self.update_generation
+    }
+
+    /// See [`crate::LazyVecPromise::with_min_update_interval`]
+    pub fn with_min_update_interval(mut self, interval: Duration, limit: UpdateLimit) -> Self {
+        self.min_interval.set(interval, limit);
+        self
+    }
+
+    /// See [`crate::LazyVecPromise::request_update`]
+    pub fn request_update(&mut self) -> UpdateOutcome {
+        self.start_update(true)
+    }
+
+    /// See [`crate::LazyVecPromise::update_now`]
+    pub fn update_now(&mut self) {
+        self.start_update(false);
+    }
+
+    /// Whether the data of the last update was discarded since the value was written while it ran, see
+    /// [`LazyValuePromise::with_compare_and_set`]. Reset when the next update starts.
+    pub fn superseded(&self) -> bool {
+        self.superseded
+    }
+
+    /// How many messages the updaters dropped because the buffer was full, see [`crate::LazyVecPromise::dropped_messages`]
+    pub fn dropped_messages(&self) -> u64 {
+        self.dropped.get()
+    }
+
+    fn is_conflicting(&self) -> bool {
+        self.compare_and_set && self.generation != self.update_generation
+    }
+
+    /// Start a [`LazyPromiseBuilder`] for the updater, to set the buffer size and other options by name
+    pub fn builder<
+        U: Fn(Sender<Message<T>>) -> Fut + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    >(
+        future_factory: U,
+    ) -> LazyPromiseBuilder<Self, U> {
+        LazyPromiseBuilder::new(future_factory)
+    }
+
+    /// Set a hook which is called once with the error for every update that fails, during the `poll_state` observing it.
+    /// Also called for panicking updaters. Replaces the default hook set by [`crate::set_default_on_error`] for this promise.
+    pub fn with_on_error(mut self, hook: impl FnMut(&BoxedSendError) + Send + 'static) -> Self {
+        self.on_error = Some(Box::new(hook));
+        self
+    }
+
+    /// Retry failing updates according to the given [`RetryPolicy`]. While retrying, the state stays
+    /// [`DataState::Updating`] and the error hook is only called for the final failure.
+    pub fn with_retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = Some(policy);
+        self
+    }
+
+    /// Only apply the data of an update if the value wasn't written in the meantime, e.g. by
+    /// [`DirectCacheAccess::set_value`]. The data of such an update is discarded and [`LazyValuePromise::superseded`]
+    /// is set. Without this, the last arriving data wins. This only covers writes through the promise, for changes
+    /// made elsewhere hand the start generation to the backend via [`LazyValuePromise::new_with_generation`].
+    pub fn with_compare_and_set(mut self) -> Self {
+        self.compare_and_set = true;
+        self
+    }
+
+    /// Set a mapper turning errors into user-facing messages for `display_error`
+    pub fn with_error_display(
+        mut self,
+        mapper: impl Fn(&BoxedSendError) -> String + Send + Sync + 'static,
+    ) -> Self {
+        self.error_display = Some(Box::new(mapper));
+        self
+    }
+
+    /// The user-facing message if in error state. Without a mapper, this is the error's `Display` output.
+    /// The original error stays accessible via [`DataState::Error`].
+    pub fn display_error(&self) -> Option<String> {
+        match &self.state {
+            DataState::Error(error) => Some(display_error(&self.error_display, error)),
+            _ => None,
+        }
+    }
+
+    /// Stop updating once the token is cancelled: the updater is dropped and the promise switches to
+    /// [`DataState::Cancelled`], even if the updater task didn't get to run. Cancellations are neither retried nor
+    /// reported to error hooks. A child token is used, so [`Self::cancel`] only cancels this promise. A cancelled
+    /// promise stays cancelled, further updates end cancelled as well.
+    #[cfg(feature = "tokio-util")]
+    pub fn with_cancellation(mut self, token: &CancellationToken) -> Self {
+        self.cancel_token = Some(token.child_token());
+        self
+    }
+
+    /// Cancel this promise, see [`Self::with_cancellation`]. Does nothing if no token was set.
+    #[cfg(feature = "tokio-util")]
+    pub fn cancel(&self) {
+        if let Some(token) = &self.cancel_token {
+            token.cancel();
+        }
+    }
+
+    /// Name the promise, used to attribute the debug records emitted with the `log` feature
+    /// and for the updater tasks, see [`Self::with_task_name`]
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        let name = name.into();
+        #[cfg(feature = "registry")]
+        {
+            let state = data_state_variant(&self.state);
+            self.registration = Some(Registration::new(&name, "LazyValuePromise", state));
+        }
+        self.name = Some(name);
+        self
+    }
+
+    /// Name the spawned updater tasks so they can be told apart in tokio-console. Requires building with
+    /// `--cfg tokio_unstable`, otherwise the tasks stay anonymous. The same name as [`Self::with_name`].
+    pub fn with_task_name(self, name: impl Into<String>) -> Self {
+        self.with_name(name)
+    }
+
+    /// The name given by [`Self::with_name`] or [`Self::with_task_name`]
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Replace the updater, e.g. after the query behind it changed. A running update is not interrupted,
+    /// the new updater is used from the next [`Promise::update`] or retry on.
+    pub fn set_updater<
+        U: Fn(Sender<Message<T>>) -> Fut + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    >(
+        &mut self,
+        future_factory: U,
+    ) {
+        self.updater = box_future_factory(future_factory);
+        self.control = None;
+        self.start_generation = None;
+    }
+
+    /// Emit a debug record each time the progress passes another multiple of `step`, defaults to 25%.
+    /// A step of zero disables progress records.
+    #[cfg(feature = "log")]
+    pub fn with_log_progress_step(mut self, step: crate::Progress) -> Self {
+        self.log.progress_step = *step;
+        self
+    }
+
+    /// Report the state as [`DataState::Updating`] until an update ran for at least `duration`, so a fast refresh
+    /// doesn't flash a spinner for a single frame. Only the state returned by [`Promise::poll_state`] and
+    /// [`Promise::state`] is held, the data is available right away.
+    pub fn with_min_updating_display(mut self, duration: Duration) -> Self {
+        self.log.min_updating = duration;
+        self
+    }
+
+    /// How long the reported state has been [`DataState::UpToDate`] or [`DataState::Error`], e.g. to show "Saved"
+    /// once it was stable for a moment. `None` while updating, also while the state is held by
+    /// [`Self::with_min_updating_display`].
+    pub fn settled_for(&self) -> Option<Duration> {
+        self.log.settled_for(&self.state)
+    }
+
+    /// See [`crate::LazyVecPromise::retry`]
+    pub fn retry(&mut self) -> bool {
+        if !self.state.is_error() {
+            return false;
+        }
+        self.update_now();
+        true
+    }
+
+    /// See [`crate::LazyVecPromise::failed_attempts`]
+    pub fn failed_attempts(&self) -> u32 {
+        self.failed_attempts
+    }
+
+    /// See [`crate::LazyVecPromise::last_error`]
+    pub fn last_error(&self) -> Option<&BoxedSendError> {
+        match &self.state {
+            DataState::Error(error) => Some(error),
+            _ => self.last_error.as_ref(),
+        }
+    }
+
+    /// Number of retries done in the current update cycle
+    pub fn retries_done(&self) -> u32 {
+        self.retries_done
+    }
+
+    /// When the latest update started, received its first item and settled
+    pub fn transitions(&self) -> &TransitionTimes {
+        &self.log.times
+    }
+
+    /// When the last successful update finished, see [`TransitionTimes::last_updated`]
+    pub fn last_updated(&self) -> Option<Instant> {
+        self.log.times.last_updated
+    }
+
+    /// Wait until the updater sent a message or ended, without polling the promise. Nothing is applied yet, call
+    /// [`Promise::poll_state`] afterwards to observe the change. Resolves right away if there is an unobserved change.
+    /// Cancellation safe, so it can be used in `select!` - a received message is kept for the next poll.
+    pub async fn changed(&mut self) {
+        poll_fn(|cx| {
+            if let Poll::Ready(Some(message)) = self.rx.poll_recv(cx) {
+                self.batch.push(message);
+                return Poll::Ready(());
+            }
+            match &mut self.task {
+                Some(task) => task.poll_finished(cx),
+                None => Poll::Pending,
+            }
+        })
+        .await
+    }
+
+    /// A watch of the current state, without the payload. Updated by the updater task itself as messages are sent,
+    /// so observers don't depend on the owner polling the promise. Changes made by the promise, like detecting a
+    /// panicked updater, are published during [`Promise::poll_state`].
+    pub fn state_watch(&self) -> watch::Receiver<DataStateTag> {
+        self.observers.tags.subscribe()
+    }
+
+    /// A stream of the state changes of the next update, ending with [`StateChange::Finished`].
+    /// If an update is already running, the stream starts with the following one - so create the stream
+    /// before polling the promise for the first time or before calling [`Promise::update`].
+    /// With a [`RetryPolicy`], the stream ends with the first failed attempt.
+    /// Updates are observed as they are sent by the updater, without the promise being polled.
+    #[cfg(feature = "futures")]
+    pub fn state_stream(&self) -> impl futures_core::Stream<Item = StateChange> {
+        state_change_stream(self.observers.state_changes.subscribe(), true)
+    }
+
+    /// Poll the state like [`Promise::poll_state`], returning an owned [`DataStateSnapshot`] which doesn't borrow
+    /// the promise
+    pub fn poll_snapshot(&mut self) -> DataStateSnapshot {
+        let state = self.poll_state().tag();
+        DataStateSnapshot {
+            state,
+            error: self.display_error(),
+            has_value: self.has_value(),
+        }
+    }
+
+    fn start_updater(&mut self, delay: Duration) {
+        if !self.stale {
+            self.cache = None;
+        }
+        self.update_generation = self.generation;
+        if let Some(start) = &self.start_generation {
+            start.store(self.update_generation, Ordering::Relaxed);
+        }
+        self.superseded = false;
+        let previous = mem::replace(&mut self.state, DataState::Updating(0.0.into()));
+        if let DataState::Error(error) = previous {
+            self.last_error = Some(error);
+        }
+        self.state_changed(match self.retries_done {
+            0 => TransitionCause::Update,
+            _ => TransitionCause::Retry,
+        });
+        self.min_interval.starting(delay);
+        self.log
+            .update_triggered(self.name.as_deref(), self.retries_done);
+        if let Some(control) = &mut self.control {
+            control.open();
+        }
+        let updater = &self.updater;
+        #[cfg(feature = "tokio-util")]
+        let cancel_token = self.cancel_token.clone();
+        let dropped = self.dropped.clone();
+        let make_future = move |tx| {
+            let future = dropped.scope(updater(tx));
+            #[cfg(feature = "tokio-util")]
+            let future = cancellable(future, cancel_token);
+            future
+        };
+        self.task = Some(spawn_updater(
+            make_future,
+            delay,
+            self.tx.clone(),
+            &self.observers,
+            self.name.as_deref(),
+        ));
+    }
+
+    /// Poll and get the value once the promise is up to date. Starts an update if the promise is uninitialized,
+    /// e.g. after the value was taken. Returns `None` while updating without starting another update, and after
+    /// an error without retrying - call [`Promise::update`] for that.
+    pub fn get_or_update(&mut self) -> Option<&T> {
+        self.poll_state();
+        self.get_result().and_then(Result::ok)
     }
 
     #[cfg(test)]
+    /// test helper: is the state [`DataState::Uninitialized`]
     pub fn is_uninitialized(&self) -> bool {
         self.state == DataState::Uninitialized
     }
 }
 
-impl<T: Debug> DirectCacheAccess<T, String> for LazyValuePromise<T> {
+impl<T: Debug> LazyValuePromise<T> {
+    /// See [`crate::LazyVecPromise::with_transition_log`]
+    pub fn with_transition_log(mut self) -> Self {
+        self.transitions
+            .get_or_insert_with(|| TransitionLog::enabled(&self.state));
+        self
+    }
+
+    /// See [`crate::LazyVecPromise::transition_log`]
+    pub fn transition_log(&self) -> &[Transition] {
+        TransitionLog::entries(&self.transitions)
+    }
+
+    /// Publish a state change made by the promise itself
+    fn state_changed(&mut self, cause: TransitionCause) {
+        self.observers.sync_tag(&self.state);
+        transitionlog::record(&mut self.transitions, &self.state, cause);
+    }
+}
+
+/// Prints the state and whether a value is cached, but not the value itself
+impl<T: Debug> Debug for LazyValuePromise<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LazyValuePromise")
+            .field("name", &self.name)
+            .field("state", &self.state.tag())
+            .field("has_value", &self.cache.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: Debug> DirectCacheAccess<T, BoxedSendError> for LazyValuePromise<T> {
     /// get current value (may be incomplete) as mutable ref, be careful with this as
     /// further modification from the future may still push data.
     fn get_value_mut(&mut self) -> Option<&mut T> {
         self.cache.as_mut()
     }
 
-    /// get current value, may be incomplete depending on status
+    /// get current value, may be incomplete depending on status. The fallback while loading, if one is set.
     fn get_value(&self) -> Option<&T> {
-        self.cache.as_ref()
+        let loading = matches!(
+            self.state,
+            DataState::Uninitialized | DataState::Updating(_)
+        );
+        let fallback = self.fallback.as_ref().filter(|_| loading);
+        self.cache.as_ref().or(fallback)
     }
 
-    fn get_result(&self) -> Option<Result<&T, &String>> {
+    fn get_result(&self) -> Option<Result<&T, &BoxedSendError>> {
         if let DataState::UpToDate = self.state {
-            self.cache.as_ref().map(|cache| Ok(cache))
+            self.cache.as_ref().map(Ok)
         } else if let DataState::Error(error) = &self.state {
             Some(Err(error))
         } else {
@@ -97,61 +722,233 @@ impl<T: Debug> DirectCacheAccess<T, String> for LazyValuePromise<T> {
         }
     }
 
+    fn get_result_mut(&mut self) -> Option<Result<&mut T, &mut BoxedSendError>> {
+        if let DataState::UpToDate = self.state {
+            self.cache.as_mut().map(Ok)
+        } else if let DataState::Error(error) = &mut self.state {
+            Some(Err(error))
+        } else {
+            None
+        }
+    }
+
     /// takes the current value, if data was [`DataState::UpToDate`] it returns the value and sets the state to
     /// [`DataState::Uninitialized`]. Otherwise, returns None.
     fn take_value(&mut self) -> Option<T> {
         if self.state == DataState::UpToDate {
             self.state = DataState::Uninitialized;
+            self.state_changed(TransitionCause::Taken);
             self.cache.take()
         } else {
             None
         }
     }
 
-    fn take_result(&mut self) -> Option<Result<T, String>> {
+    fn take_result(&mut self) -> Option<Result<T, BoxedSendError>> {
         if self.state == DataState::UpToDate {
             self.state = DataState::Uninitialized;
-            self.cache.take().map(|cache|Ok(cache))
+            self.state_changed(TransitionCause::Taken);
+            self.cache.take().map(Ok)
         } else if let DataState::Error(_) = self.state {
-            let DataState::Error(err) = mem::replace(&mut self.state, DataState::Uninitialized) else {
+            let DataState::Error(err) = mem::replace(&mut self.state, DataState::Uninitialized)
+            else {
                 unreachable!();
             };
+            self.state_changed(TransitionCause::Taken);
             Some(Err(err))
         } else {
             None
         }
     }
+
+    fn is_pending(&self) -> bool {
+        self.state.is_updating()
+    }
+
+    /// Injects a value and sets the state to [`DataState::UpToDate`], returning the previous value.
+    /// If an update is in flight, the state is left untouched and data arriving from the updater will
+    /// overwrite the injected value, unless [`LazyValuePromise::with_compare_and_set`] is used.
+    fn set_value(&mut self, value: T) -> Option<T> {
+        self.generation += 1;
+        self.stale = false;
+        if !matches!(self.state, DataState::Updating(_)) {
+            self.state = DataState::UpToDate;
+            self.state_changed(TransitionCause::ValueSet);
+        }
+        self.cache.replace(value)
+    }
 }
 
-impl<T: Debug> Promise for LazyValuePromise<T> {
+impl<T: Debug + Send + 'static> Promise for LazyValuePromise<T> {
     fn poll_state(&mut self) -> &DataState {
         if self.state == DataState::Uninitialized {
             self.update();
         }
+        let was_updating = self.state.is_updating();
+        match self.seed.as_mut().map(|seed| seed()) {
+            Some(Some(result)) => self.apply_seed(result),
+            Some(None) => {}
+            None => self.poll_updater(),
+        }
+        self.poll_optimistic();
+        if was_updating && !self.state.is_updating() && !self.completions.is_empty() {
+            let missing = BoxedSendError::msg("update finished without a value")
+                .with_kind(ErrorKind::Runtime);
+            let cancelled =
+                BoxedSendError::msg("update was cancelled").with_kind(ErrorKind::Cancelled);
+            let result = match (&self.state, &self.cache) {
+                (DataState::Error(error), _) => Err(error),
+                (DataState::Cancelled, _) => Err(&cancelled),
+                (_, Some(value)) => Ok(value),
+                (_, None) => Err(&missing),
+            };
+            for completion in self.completions.drain(..) {
+                completion(result);
+            }
+        }
+        #[cfg(feature = "registry")]
+        if let Some(registration) = &self.registration {
+            registration.record_data_state(&self.state);
+        }
+        self.log.reported(&self.state)
+    }
+
+    fn state(&self) -> &DataState {
+        self.log.reported(&self.state)
+    }
+
+    fn update(&mut self) {
+        self.start_update(true);
+    }
+}
 
-        while let Ok(msg) = self.rx.try_recv() {
+impl<T: Debug + Send + 'static> LazyValuePromise<T> {
+    fn start_update(&mut self, limited: bool) -> UpdateOutcome {
+        if matches!(self.state, DataState::Updating(_)) {
+            return UpdateOutcome::AlreadyUpdating;
+        }
+        let outcome = match limited {
+            true => self.min_interval.check(),
+            false => UpdateOutcome::Started,
+        };
+        let delay = match outcome {
+            UpdateOutcome::Started => Duration::ZERO,
+            UpdateOutcome::Deferred(delay) => delay,
+            _ => return outcome,
+        };
+        self.retries_done = 0;
+        self.start_updater(delay);
+        outcome
+    }
+
+    fn apply_seed(&mut self, result: FutureResult<T>) {
+        self.seed = None;
+        match result {
+            Ok(value) => {
+                self.cache = Some(value);
+                self.generation += 1;
+                self.state = DataState::UpToDate;
+            }
+            Err(error) => {
+                report_error(&mut self.on_error, &error);
+                self.state = DataState::Error(error);
+            }
+        }
+        self.state_changed(TransitionCause::Updater);
+    }
+
+    fn poll_optimistic(&mut self) {
+        let Some(optimistic) = self.optimistic.as_mut() else {
+            return;
+        };
+        if matches!(optimistic.save.poll_state(), ImmediateValueState::Updating) {
+            return;
+        }
+        let Some(Optimistic { previous, mut save }) = self.optimistic.take() else {
+            return;
+        };
+        if let Some(Err(error)) = save.take_result() {
+            self.log.error(self.name.as_deref(), &error);
+            report_error(&mut self.on_error, &error);
+            self.cache = previous;
+            self.state = DataState::Error(error);
+            self.state_changed(TransitionCause::Updater);
+        }
+    }
+
+    fn poll_updater(&mut self) {
+        // checked before draining, so all messages of a finished task are applied
+        let task_result = self.task.as_mut().and_then(Task::try_join);
+        let was_error = self.state.is_error();
+        drain_into(&mut self.rx, &mut self.batch);
+        let conflicting = self.is_conflicting();
+        for msg in self.batch.drain(..).flat_map(Message::split) {
             match msg {
+                Message::NewData(_) if conflicting => self.superseded = true,
                 Message::NewData(data) => {
+                    self.stale = false;
                     self.cache = Some(data);
+                    self.log.item_received(self.name.as_deref());
                 }
                 Message::StateChange(new_state) => {
+                    match new_state {
+                        DataState::Updating(progress) => {
+                            self.log.progress(self.name.as_deref(), progress)
+                        }
+                        DataState::UpToDate => {
+                            self.failed_attempts = 0;
+                            self.last_error = None;
+                            if conflicting {
+                                self.superseded = true;
+                            } else {
+                                self.generation += 1;
+                            }
+                            self.log.finished(self.name.as_deref());
+                        }
+                        _ => {}
+                    }
                     self.state = new_state;
                 }
+                Message::NewDataWithProgress(..) => unreachable!("split before"),
             }
         }
 
-        &self.state
-    }
+        let mut cause = TransitionCause::Updater;
+        #[cfg(feature = "tokio-util")]
+        if self.state.is_updating() && is_cancelled(&self.cancel_token) {
+            cause = TransitionCause::Cancelled;
+            self.task = None;
+            self.state = DataState::Cancelled;
+        }
 
-    fn update(&mut self) {
-        if matches!(self.state, DataState::Updating(_)) {
-            return;
+        if let Some(result) = task_result {
+            self.task = None;
+            if self.state.is_updating() {
+                cause = TransitionCause::UpdaterEnded;
+                self.state = DataState::Error(updater_task_error(result));
+            }
         }
-        self.cache = None;
 
-        self.state = DataState::Updating(0.0.into());
-        let future = (self.updater)(self.tx.clone());
-        tokio::spawn(future);
+        self.state_changed(cause);
+        if let (false, DataState::Error(error)) = (was_error, &self.state) {
+            self.log.error(self.name.as_deref(), error);
+            #[cfg(feature = "tokio-util")]
+            if error.is_cancelled() {
+                return;
+            }
+            let retry_delay = self
+                .retry
+                .as_ref()
+                .filter(|retry| retry.should_retry(self.retries_done, error))
+                .map(|retry| retry.delay_for(self.retries_done));
+            if let Some(delay) = retry_delay {
+                self.retries_done += 1;
+                self.start_updater(delay);
+            } else {
+                self.failed_attempts += 1;
+                report_error(&mut self.on_error, error);
+            }
+        }
     }
 }
 
@@ -159,6 +956,7 @@ impl<T: Debug> Promise for LazyValuePromise<T> {
 mod test {
     use super::*;
     use crate::api_macros::*;
+    use crate::runtime::sleep;
     use std::time::Duration;
     use tokio::runtime::Builder;
     use tokio::sync::mpsc::Sender;
@@ -167,9 +965,9 @@ mod test {
     fn basic_usage_cycle() {
         let string_maker = |tx: Sender<Message<String>>| async move {
             for i in 0..2 {
-                tokio::time::sleep(Duration::from_millis(10)).await;
+                sleep(Duration::from_millis(10)).await;
                 send_data!(i.to_string(), tx);
-                tokio::time::sleep(Duration::from_millis(20)).await;
+                sleep(Duration::from_millis(20)).await;
             }
             set_finished!(tx);
         };
@@ -191,7 +989,7 @@ mod test {
                 );
                 assert!(delayed_value.get_value().is_none());
                 //after wait, value is there
-                tokio::time::sleep(Duration::from_millis(150)).await;
+                sleep(Duration::from_millis(150)).await;
                 assert_eq!(*delayed_value.poll_state(), DataState::UpToDate);
                 assert_eq!(delayed_value.get_value().unwrap(), "1");
                 //update resets
@@ -202,7 +1000,7 @@ mod test {
                 );
                 assert!(delayed_value.get_value().is_none());
                 //after wait, value is there again and identical
-                tokio::time::sleep(Duration::from_millis(150)).await;
+                sleep(Duration::from_millis(150)).await;
                 assert_eq!(*delayed_value.poll_state(), DataState::UpToDate);
                 assert!(delayed_value.poll_state().get_progress().is_none());
                 assert_eq!(delayed_value.get_value().unwrap(), "1");
@@ -212,6 +1010,7 @@ mod test {
     #[tokio::test]
     async fn error_propagation() {
         let error_maker = |tx: Sender<Message<String>>| async move {
+            sleep(Duration::from_millis(10)).await;
             let _ = unpack_result!(std::fs::read_to_string("FILE_NOT_EXISTING"), tx);
             unreachable!();
         };
@@ -219,28 +1018,414 @@ mod test {
         let mut delayed_vec = LazyValuePromise::new(error_maker, 1);
         assert_eq!(*delayed_vec.poll_state(), DataState::Updating(0.0.into()));
         assert!(delayed_vec.get_value().is_none());
-        tokio::time::sleep(Duration::from_millis(150)).await;
+        sleep(Duration::from_millis(150)).await;
         assert!(matches!(*delayed_vec.poll_state(), DataState::Error(_)));
+        assert!(delayed_vec.has_error() && !delayed_vec.has_value());
         assert!(delayed_vec.get_value().is_none());
     }
 
+    #[tokio::test]
+    async fn error_display() {
+        let error_maker = |tx: Sender<Message<String>>| async move {
+            sleep(Duration::from_millis(10)).await;
+            set_error!("ERR_CODE 5012: upstream shard timeout", tx);
+        };
+
+        let mut delayed_value = LazyValuePromise::new(error_maker, 1)
+            .with_error_display(|error| format!("Could not load: {}", error));
+        delayed_value.poll_state();
+        assert!(delayed_value.display_error().is_none());
+        sleep(Duration::from_millis(50)).await;
+        assert!(delayed_value.poll_state().is_error());
+        assert_eq!(
+            delayed_value.display_error().unwrap(),
+            "Could not load: ERR_CODE 5012: upstream shard timeout"
+        );
+        let Some(Err(error)) = delayed_value.get_result() else {
+            unreachable!();
+        };
+        assert_eq!(error.to_string(), "ERR_CODE 5012: upstream shard timeout");
+    }
+
+    #[tokio::test]
+    async fn get_or_update() {
+        use std::sync::atomic::{AtomicU32, Ordering};
+        use std::sync::Arc;
+        let runs = Arc::new(AtomicU32::new(0));
+        let runs_updater = runs.clone();
+        let updater = move |tx: Sender<Message<u32>>| {
+            let run = runs_updater.fetch_add(1, Ordering::SeqCst);
+            async move {
+                sleep(Duration::from_millis(10)).await;
+                if run == 2 {
+                    set_error!("offline", tx);
+                    return;
+                }
+                send_data!(run, tx);
+                set_finished!(tx);
+            }
+        };
+        let mut promise = LazyValuePromise::new(updater, 6);
+        assert_eq!(promise.get_or_update(), None);
+        assert_eq!(promise.get_or_update(), None);
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(promise.get_or_update(), Some(&0));
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+        // taking the value makes the promise uninitialized, so the next call updates
+        assert_eq!(promise.take_value(), Some(0));
+        assert_eq!(promise.get_or_update(), None);
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(promise.get_or_update(), Some(&1));
+
+        // errors are not retried implicitly
+        promise.update();
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(promise.get_or_update(), None);
+        assert_eq!(promise.get_or_update(), None);
+        assert!(promise.state().is_error());
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn from_immediate() {
+        let updater = |tx: Sender<Message<i32>>| async move {
+            send_data!(2, tx);
+            set_finished!(tx);
+        };
+        let seed = ImmediateValuePromise::new(async {
+            sleep(Duration::from_millis(10)).await;
+            Ok(1)
+        });
+        let mut promise = LazyValuePromise::from_immediate(seed, updater, 6);
+        assert!(promise.poll_state().is_updating());
+        assert!(promise.get_value().is_none());
+        sleep(Duration::from_millis(50)).await;
+        assert!(promise.poll_state().is_success());
+        assert_eq!(promise.get_value(), Some(&1));
+        promise.update();
+        sleep(Duration::from_millis(50)).await;
+        promise.poll_state();
+        assert_eq!(promise.get_value(), Some(&2));
+
+        let mut taken = ImmediateValuePromise::new(async { Ok(1) });
+        sleep(Duration::from_millis(50)).await;
+        taken.poll_state();
+        assert_eq!(taken.take_value(), Some(1));
+        let mut promise = LazyValuePromise::from_immediate(taken, updater, 6);
+        assert!(promise.is_uninitialized());
+        promise.poll_state();
+        sleep(Duration::from_millis(50)).await;
+        promise.poll_state();
+        assert_eq!(promise.get_value(), Some(&2));
+    }
+
+    #[tokio::test]
+    async fn next_completion() {
+        let updater = |tx: Sender<Message<String>>| async move {
+            sleep(Duration::from_millis(10)).await;
+            send_data!("value".to_string(), tx);
+            set_finished!(tx);
+        };
+        let mut promise = LazyValuePromise::new(updater, 6);
+        let mut completion = promise.next_completion();
+        promise.poll_state();
+        sleep(Duration::from_millis(50)).await;
+        assert!(completion.poll_state().is_updating());
+        promise.poll_state();
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(completion.poll_state().get_value().unwrap(), "value");
+        // the lazy promise keeps its value
+        assert_eq!(promise.get_value().unwrap(), "value");
+
+        // registered while up to date, so it waits for the next update
+        let mut next = promise.next_completion();
+        promise.poll_state();
+        sleep(Duration::from_millis(50)).await;
+        assert!(next.poll_state().is_updating());
+        promise.update();
+        sleep(Duration::from_millis(50)).await;
+        promise.poll_state();
+        sleep(Duration::from_millis(50)).await;
+        assert!(next.poll_state().is_success());
+    }
+
+    #[tokio::test]
+    async fn error_context() {
+        let error_maker = |tx: Sender<Message<String>>| async move {
+            let file = "FILE_NOT_EXISTING";
+            let _ = unpack_result!(std::fs::read_to_string(file), tx, "while reading {file}");
+            unreachable!();
+        };
+
+        let mut promise = LazyValuePromise::new(error_maker, 1);
+        promise.poll_state();
+        sleep(Duration::from_millis(50)).await;
+        let error = promise.poll_state().as_error().unwrap();
+        assert_eq!(error.to_string(), "while reading FILE_NOT_EXISTING");
+        assert!(error.source().unwrap().is::<std::io::Error>());
+    }
+
+    #[tokio::test]
+    async fn updater_panic_sets_error() {
+        let panicking_maker = |_tx: Sender<Message<i32>>| async move {
+            panic!("boom");
+        };
+        let unfinished_maker = |tx: Sender<Message<i32>>| async move {
+            send_data!(1, tx);
+        };
+
+        let mut panicking = LazyValuePromise::new(panicking_maker, 6);
+        let mut unfinished = LazyValuePromise::new(unfinished_maker, 6);
+        assert!(panicking.poll_state().is_updating());
+        assert!(unfinished.poll_state().is_updating());
+        sleep(Duration::from_millis(50)).await;
+        let DataState::Error(error) = panicking.poll_state() else {
+            unreachable!();
+        };
+        assert_eq!(error.to_string(), "updater panicked: boom");
+        assert!(unfinished.poll_state().is_error());
+        assert_eq!(*unfinished.get_value().unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn retry_policy() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let attempts_clone = attempts.clone();
+        let flaky_maker = move |tx: Sender<Message<i32>>| {
+            let attempts = attempts_clone.clone();
+            async move {
+                if attempts.fetch_add(1, Ordering::SeqCst) < 2 {
+                    set_error!("429", tx);
+                    return;
+                }
+                send_data!(42, tx);
+                set_finished!(tx);
+            }
+        };
+        let policy = RetryPolicy::new(2).with_backoff(Duration::from_millis(10), 1.0);
+        let mut delayed_value = LazyValuePromise::new(flaky_maker, 6).with_retry(policy.clone());
+        for _ in 0..20 {
+            assert!(!delayed_value.poll_state().is_error());
+            sleep(Duration::from_millis(5)).await;
+        }
+        assert_eq!(*delayed_value.poll_state(), DataState::UpToDate);
+        assert_eq!(*delayed_value.get_value().unwrap(), 42);
+        assert_eq!(delayed_value.retries_done(), 2);
+
+        // the final failure is reported once retries are exhausted
+        let failing_maker = |tx: Sender<Message<i32>>| async move {
+            set_error!("429", tx);
+        };
+        let mut delayed_value = LazyValuePromise::new(failing_maker, 6).with_retry(policy);
+        delayed_value.poll_state();
+        sleep(Duration::from_millis(100)).await;
+        for _ in 0..3 {
+            delayed_value.poll_state();
+            sleep(Duration::from_millis(50)).await;
+        }
+        assert!(delayed_value.poll_state().is_error());
+        assert_eq!(delayed_value.retries_done(), 2);
+    }
+
     #[tokio::test]
     async fn test_direct_cache_access() {
         let int_maker = |tx: Sender<Message<i32>>| async move {
+            sleep(Duration::from_millis(10)).await;
             send_data!(42, tx);
             set_finished!(tx);
         };
 
         let mut delayed_value = LazyValuePromise::new(int_maker, 6);
         let _ = delayed_value.poll_state();
-        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(delayed_value.is_pending() && !delayed_value.has_value());
+        sleep(Duration::from_millis(50)).await;
         let poll_result = delayed_value.poll_state();
         assert!(matches!(poll_result, DataState::UpToDate));
+        assert!(delayed_value.has_value() && !delayed_value.is_pending());
         let val = delayed_value.get_value();
         assert_eq!(*val.unwrap(), 42);
         let _val_mut = delayed_value.get_value_mut();
         let value_owned = delayed_value.take_value().unwrap();
         assert_eq!(value_owned, 42);
         assert!(delayed_value.is_uninitialized());
+        assert!(
+            !delayed_value.has_value() && !delayed_value.has_error() && !delayed_value.is_pending()
+        );
+    }
+
+    #[tokio::test]
+    async fn set_value() {
+        let int_maker = |tx: Sender<Message<i32>>| async move {
+            sleep(Duration::from_millis(20)).await;
+            send_data!(42, tx);
+            set_finished!(tx);
+        };
+
+        let mut delayed_value = LazyValuePromise::new(int_maker, 6);
+        // injecting into an uninitialized promise makes it up to date without updating
+        assert!(delayed_value.set_value(1).is_none());
+        assert_eq!(*delayed_value.poll_state(), DataState::UpToDate);
+        assert_eq!(delayed_value.set_value(2), Some(1));
+        // an in-flight update overwrites the injected value
+        delayed_value.update();
+        delayed_value.set_value(3);
+        assert!(matches!(delayed_value.poll_state(), DataState::Updating(_)));
+        assert_eq!(*delayed_value.get_value().unwrap(), 3);
+        sleep(Duration::from_millis(100)).await;
+        assert_eq!(*delayed_value.poll_state(), DataState::UpToDate);
+        assert_eq!(*delayed_value.get_value().unwrap(), 42);
+    }
+
+    #[tokio::test]
+    async fn optimistic_update() {
+        let int_maker = |tx: Sender<Message<i32>>| async move {
+            send_data!(1, tx);
+            set_finished!(tx);
+        };
+        let save = |fail: bool| async move {
+            sleep(Duration::from_millis(20)).await;
+            if fail {
+                Err(BoxedSendError::msg("offline"))
+            } else {
+                Ok(())
+            }
+        };
+
+        let mut delayed_value = LazyValuePromise::new(int_maker, 6);
+        delayed_value.poll_state();
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(*delayed_value.poll_state(), DataState::UpToDate);
+
+        delayed_value.optimistic_update(2, |_| save(false));
+        assert!(delayed_value.is_optimistic());
+        assert_eq!(delayed_value.get_value(), Some(&2));
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(*delayed_value.poll_state(), DataState::UpToDate);
+        assert!(!delayed_value.is_optimistic());
+        assert_eq!(delayed_value.get_value(), Some(&2));
+
+        // the second update replaces the first, the rollback goes back to the value before both
+        delayed_value.optimistic_update(3, |_| save(false));
+        delayed_value.optimistic_update(4, |_| save(true));
+        assert_eq!(delayed_value.get_value(), Some(&4));
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(
+            delayed_value.poll_state().to_string(),
+            "error: offline".to_string()
+        );
+        assert!(!delayed_value.is_optimistic());
+        assert_eq!(delayed_value.get_value(), Some(&2));
+    }
+
+    #[tokio::test]
+    async fn compare_and_set() {
+        let int_maker = |tx: Sender<Message<i32>>| async move {
+            sleep(Duration::from_millis(20)).await;
+            send_data!(42, tx);
+            set_finished!(tx);
+        };
+
+        let mut delayed_value = LazyValuePromise::new(int_maker, 6).with_compare_and_set();
+        assert_eq!(delayed_value.update_tracked(), 0);
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(*delayed_value.poll_state(), DataState::UpToDate);
+        assert_eq!(delayed_value.generation(), 1);
+        assert!(!delayed_value.superseded());
+
+        // the slower update doesn't overwrite the newer value
+        assert_eq!(delayed_value.update_tracked(), 1);
+        delayed_value.set_value(7);
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(*delayed_value.poll_state(), DataState::UpToDate);
+        assert!(delayed_value.superseded());
+        assert_eq!(delayed_value.get_value(), Some(&7));
+        assert_eq!(delayed_value.generation(), 2);
+    }
+
+    #[tokio::test]
+    async fn new_with_generation() {
+        let generation_echo = |tx: Sender<Message<u64>>, generation: u64| async move {
+            send_data!(generation, tx);
+            set_finished!(tx);
+        };
+
+        let mut delayed_value = LazyValuePromise::new_with_generation(generation_echo, 6);
+        delayed_value.poll_state();
+        sleep(Duration::from_millis(20)).await;
+        assert_eq!(*delayed_value.poll_state(), DataState::UpToDate);
+        assert_eq!(delayed_value.get_value(), Some(&0));
+
+        delayed_value.set_value(5);
+        assert_eq!(delayed_value.update_tracked(), 2);
+        sleep(Duration::from_millis(20)).await;
+        delayed_value.poll_state();
+        assert_eq!(delayed_value.get_value(), Some(&2));
+    }
+
+    #[tokio::test]
+    async fn new_with_initial() {
+        let int_maker = |tx: Sender<Message<i32>>| async move {
+            sleep(Duration::from_millis(20)).await;
+            send_data!(42, tx);
+            set_finished!(tx);
+        };
+
+        let mut delayed_value = LazyValuePromise::new_with_initial(1, int_maker, 6);
+        assert_eq!(*delayed_value.poll_state(), DataState::UpToDate);
+        assert!(delayed_value.is_stale());
+        delayed_value.update();
+        assert_eq!(delayed_value.get_value(), Some(&1));
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(*delayed_value.poll_state(), DataState::UpToDate);
+        assert!(!delayed_value.is_stale());
+        assert_eq!(delayed_value.get_value(), Some(&42));
+    }
+
+    #[tokio::test]
+    async fn fallback_while_loading() {
+        let int_maker = |tx: Sender<Message<i32>>| async move {
+            sleep(Duration::from_millis(20)).await;
+            send_data!(42, tx);
+            set_finished!(tx);
+        };
+
+        let mut delayed_value = LazyValuePromise::new(int_maker, 6).with_fallback(1);
+        assert!(delayed_value.poll_state().is_updating());
+        assert_eq!(delayed_value.get_value(), Some(&1));
+        assert!(delayed_value.is_fallback());
+        assert!(delayed_value.get_value_mut().is_none());
+        assert!(delayed_value.get_result().is_none());
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(*delayed_value.poll_state(), DataState::UpToDate);
+        assert!(!delayed_value.is_fallback());
+        assert_eq!(delayed_value.take_value(), Some(42));
+        // after taking the value, the fallback shows until the next value arrives
+        assert_eq!(delayed_value.get_value(), Some(&1));
+        assert!(delayed_value.is_fallback());
+    }
+
+    #[tokio::test]
+    async fn poll_snapshot() {
+        let int_maker = |tx: Sender<Message<i32>>| async move {
+            sleep(Duration::from_millis(10)).await;
+            send_data!(42, tx);
+            set_finished!(tx);
+        };
+
+        let mut delayed_value = LazyValuePromise::new(int_maker, 6);
+        let snapshot = delayed_value.poll_snapshot();
+        assert!(matches!(snapshot.state, DataStateTag::Updating(_)));
+        assert!(!snapshot.has_value && snapshot.error.is_none());
+        sleep(Duration::from_millis(50)).await;
+        let snapshot = delayed_value.poll_snapshot();
+        if snapshot.state == DataStateTag::UpToDate {
+            // the promise can be mutated while the snapshot is alive
+            delayed_value.update();
+        }
+        assert!(snapshot.has_value);
+        assert!(delayed_value.is_pending());
     }
 }
@@ -1,14 +1,52 @@
+use crate::aggregate::Aggregate;
+#[cfg(feature = "tokio-util")]
+use crate::cancellation::{cancellable, is_cancelled};
+use crate::clock;
+use crate::control::{self, Control};
+use crate::dropcount::DropCounter;
+use crate::lazybuilder::LazyPromiseBuilder;
+use crate::ratelimit::MinInterval;
+#[cfg(feature = "registry")]
+use crate::registry::{data_state_variant, Registration};
+use crate::runtime::Task;
+#[cfg(feature = "futures")]
+use crate::statechange::state_change_stream;
+use crate::statechange::{spawn_updater, Observers};
+use crate::transitionlog::{self, Transition, TransitionCause, TransitionLog};
+use crate::updatelog::{TransitionTimes, UpdateLog};
+#[cfg(feature = "futures")]
+use crate::StateChange;
 use crate::{
-    box_future_factory, BoxedFutureFactory, DataState, DirectCacheAccess, Message, Promise,
+    box_future_factory, display_error, drain_into, report_error, updater_task_error,
+    BoxedFutureFactory, BoxedSendError, DataState, DataStateSnapshot, DataStateTag,
+    DirectCacheAccess, ErrorDisplay, ErrorHook, Message, Promise, RetryPolicy, UpdateLimit,
+    UpdateOutcome, BUDGET_CHECK_ITEMS,
 };
-use std::fmt::Debug;
-use std::future::Future;
+use std::cmp::Ordering;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Formatter};
+use std::future::{poll_fn, Future};
+use std::hash::Hash;
 use std::mem;
-use tokio::sync::mpsc::{channel, Receiver, Sender};
+use std::ops::Range;
+use std::task::Poll;
+#[cfg(feature = "serde")]
+use std::time::SystemTime;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::error::SendError;
+use tokio::sync::mpsc::{channel, Receiver, Sender, UnboundedReceiver};
+use tokio::sync::watch;
+#[cfg(feature = "tokio-util")]
+use tokio_util::sync::CancellationToken;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 /// # A lazy, async and partially readable vector promise
 /// This promise is the right one for async acquiring of lists which should be partially readable on each frame.
 /// Imagine slowly streaming data and wanting to read them out as far as they are available each frame.
+/// Dropping the promise aborts a running updater.
 /// Examples:
 /// ```rust, no_run
 /// use std::time::Duration;
@@ -49,16 +87,295 @@ use tokio::sync::mpsc::{channel, Receiver, Sender};
 /// ```
 ///
 ///
-
 pub struct LazyVecPromise<T: Debug> {
     data: Vec<T>,
     state: DataState,
     rx: Receiver<Message<T>>,
     tx: Sender<Message<T>>,
+    task: Option<Task>,
+    pub(crate) on_error: Option<ErrorHook>,
+    pub(crate) retry: Option<RetryPolicy>,
+    retries_done: u32,
+    pub(crate) error_display: Option<ErrorDisplay>,
+    observers: Observers,
+    #[cfg(feature = "tokio-util")]
+    pub(crate) cancel_token: Option<CancellationToken>,
+    pub(crate) name: Option<String>,
+    pub(crate) log: UpdateLog,
     updater: BoxedFutureFactory<T>,
+    batch: Vec<Message<T>>,
+    reserve_hint: usize,
+    sort: Option<SortOrder<T>>,
+    filter: Option<Filter<T>>,
+    filtered_out: usize,
+    on_new_items: Option<NewItemsHook<T>>,
+    aggregate: Option<Aggregate<T>>,
+    arrived: ArrivalTimes,
+    insert_order: InsertOrder,
+    refresh: RefreshStrategy<T>,
+    /// the strategy given to [`LazyVecPromise::update_with`] for the current update
+    refresh_once: Option<RefreshStrategy<T>>,
+    staged: Vec<T>,
+    last_refresh: Option<MergeCounts>,
+    items: ItemCounts,
+    /// how many finished markers complete an update
+    sources: usize,
+    sources_finished: usize,
+    stale: bool,
+    /// whether [`DirectCacheAccess::set_value`] replaced the data of the running update
+    injected: bool,
+    dropped: DropCounter,
+    min_interval: MinInterval,
+    failed_attempts: u32,
+    transitions: Option<Box<TransitionLog<DataState>>>,
+    /// the error of the last failed update, kept while the next one runs
+    last_error: Option<BoxedSendError>,
+    control: Option<Control>,
+    #[cfg(feature = "registry")]
+    registration: Option<Registration>,
 }
 
-impl<T: Debug> LazyVecPromise<T> {
+#[derive(Default)]
+struct ItemCounts {
+    this_update: usize,
+    /// the count of the current update once it finished, moved to `last_update` when the next one starts
+    finished: Option<usize>,
+    last_update: Option<usize>,
+    total: u64,
+}
+
+type Merger<T> = Box<dyn Fn(&mut Vec<T>, Vec<T>) -> MergeCounts + Send>;
+
+/// How [`LazyVecPromise`] combines the items of an update with the data it already has
+pub enum RefreshStrategy<T> {
+    /// Clear the data when an update starts and stream the new items in, the default
+    Replace,
+    /// Keep the data and add the new items once the update finished
+    Append,
+    /// Keep the data and merge the new items by key once the update finished, see [`RefreshStrategy::merge_by_key`]
+    MergeByKey(Merger<T>),
+}
+
+impl<T> RefreshStrategy<T> {
+    /// Items with a known key are updated in place, so rows keep their position, items with a new key are added.
+    /// With `remove_missing`, items whose key is not part of the new result are removed.
+    pub fn merge_by_key<K: Hash + Eq>(
+        key: impl Fn(&T) -> K + Send + 'static,
+        remove_missing: bool,
+    ) -> Self {
+        RefreshStrategy::MergeByKey(Box::new(move |data: &mut Vec<T>, items: Vec<T>| {
+            let mut positions: HashMap<K, usize> = data
+                .iter()
+                .enumerate()
+                .map(|(position, item)| (key(item), position))
+                .collect();
+            let mut seen = vec![false; data.len()];
+            let mut counts = MergeCounts::default();
+            for item in items {
+                match positions.entry(key(&item)) {
+                    Entry::Occupied(entry) => {
+                        data[*entry.get()] = item;
+                        seen[*entry.get()] = true;
+                        counts.updated += 1;
+                    }
+                    Entry::Vacant(entry) => {
+                        entry.insert(data.len());
+                        data.push(item);
+                        seen.push(true);
+                        counts.added += 1;
+                    }
+                }
+            }
+            if remove_missing {
+                let mut seen = seen.into_iter();
+                let before = data.len();
+                data.retain(|_| seen.next().unwrap_or(true));
+                counts.removed = before - data.len();
+            }
+            counts
+        }))
+    }
+
+    fn is_staged(&self) -> bool {
+        !matches!(self, RefreshStrategy::Replace)
+    }
+}
+
+/// The saved cache of a [`LazyVecPromise`], see [`LazyVecPromise::serialize_cache`]. Serialized as plain struct,
+/// so restoring items saved by an older version of `T` is up to the serde attributes of `T`.
+#[cfg(feature = "serde")]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CacheSnapshot<D> {
+    /// The items, borrowed when saving
+    pub data: D,
+    /// How many items were saved
+    pub item_count: usize,
+    /// When the saved data was last updated successfully
+    pub last_updated: Option<SystemTime>,
+}
+
+/// What a refresh with [`RefreshStrategy::Append`] or [`RefreshStrategy::MergeByKey`] changed, e.g. to show
+/// "3 new items", see [`LazyVecPromise::last_refresh`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MergeCounts {
+    /// new items
+    pub added: usize,
+    /// items replaced in place
+    pub updated: usize,
+    /// items removed since they were missing in the new result
+    pub removed: usize,
+}
+
+/// Where [`LazyVecPromise`] places newly received items, see [`LazyVecPromise::with_insert_order`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InsertOrder {
+    /// Oldest item first, new items are appended
+    #[default]
+    Append,
+    /// Newest item first, e.g. for chats and logs
+    Prepend,
+}
+
+/// A change of the items of a [`LazyVecPromise`], see [`LazyVecPromise::with_on_new_items`]
+#[derive(Debug)]
+pub enum ItemsChange<'a, T> {
+    /// Items were applied by a poll, they are at `data[index]` for each of the ascending `indices`
+    Applied {
+        /// All items, after sorting
+        data: &'a [T],
+        /// The positions of the applied items
+        indices: &'a [usize],
+    },
+    /// All items were removed by an update, or replaced by [`RefreshStrategy::MergeByKey`]
+    Cleared,
+}
+
+impl<'a, T> ItemsChange<'a, T> {
+    /// The applied items in the order of the data, none for [`ItemsChange::Cleared`]
+    pub fn items(&self) -> impl Iterator<Item = &'a T> + '_ {
+        let (data, indices): (&[T], &[usize]) = match self {
+            ItemsChange::Applied { data, indices } => (data, indices),
+            ItemsChange::Cleared => (&[], &[]),
+        };
+        indices.iter().map(move |index| &data[*index])
+    }
+}
+
+type Comparator<T> = Box<dyn Fn(&T, &T) -> Ordering + Send>;
+type Filter<T> = Box<dyn Fn(&T) -> bool + Send>;
+type NewItemsHook<T> = Box<dyn FnMut(ItemsChange<'_, T>) + Send>;
+/// The arrival time of each item in the order of the data, see [`LazyVecPromise::with_arrival_times`]
+type ArrivalTimes = Option<Vec<Instant>>;
+
+/// Move the arrival times along with their items, `positions` are the previous positions in the new order
+fn reorder_times(times: &mut ArrivalTimes, positions: &[usize]) {
+    if let Some(times) = times
+        .as_mut()
+        .filter(|times| times.len() == positions.len())
+    {
+        *times = positions.iter().map(|position| times[*position]).collect();
+    }
+}
+
+/// The comparator set by [`LazyVecPromise::set_sort`] and the arrival index of each item, to restore the order
+struct SortOrder<T> {
+    cmp: Comparator<T>,
+    arrival: Vec<usize>,
+    /// the data was newest first before sorting, see [`InsertOrder::Prepend`]
+    newest_first: bool,
+}
+
+impl<T> SortOrder<T> {
+    /// If the data was replaced or modified behind our back, its current order counts as arrival order
+    fn sync(&mut self, data: &[T]) {
+        if self.arrival.len() != data.len() {
+            self.arrival = match self.newest_first {
+                true => (0..data.len()).rev().collect(),
+                false => (0..data.len()).collect(),
+            };
+        }
+    }
+
+    /// Sort by the comparator, equal items stay in arrival order
+    fn sort(&mut self, data: &mut Vec<T>, times: &mut ArrivalTimes) {
+        self.sync(data);
+        let arrivals = self.arrival.drain(..);
+        let mut items: Vec<_> = arrivals.zip(data.drain(..).enumerate()).collect();
+        items.sort_by(|(a_arrival, (_, a)), (b_arrival, (_, b))| {
+            (self.cmp)(a, b).then(a_arrival.cmp(b_arrival))
+        });
+        let mut positions = Vec::with_capacity(items.len());
+        for (arrival, (position, item)) in items {
+            self.arrival.push(arrival);
+            data.push(item);
+            positions.push(position);
+        }
+        reorder_times(times, &positions);
+    }
+
+    /// Insert behind all items which are not greater, so equal items stay in arrival order. Returns the position.
+    fn insert(&mut self, data: &mut Vec<T>, item: T) -> usize {
+        self.sync(data);
+        let arrival = self.next_arrival(data.len());
+        self.place(data, item, arrival)
+    }
+
+    /// The arrival index of the next item inserted into `len` items
+    fn next_arrival(&self, len: usize) -> usize {
+        if self.arrival.len() != len {
+            // see sync
+            return len;
+        }
+        // filtered items leave gaps, so the count of items is not necessarily a new index
+        self.arrival.iter().max().map_or(0, |last| last + 1)
+    }
+
+    /// Insert at the position given by the comparator and the arrival index, which is returned
+    fn place(&mut self, data: &mut Vec<T>, item: T, arrival: usize) -> usize {
+        let (mut low, mut high) = (0, data.len());
+        while low < high {
+            let mid = low + (high - low) / 2;
+            match (self.cmp)(&data[mid], &item).then(self.arrival[mid].cmp(&arrival)) {
+                Ordering::Greater => high = mid,
+                _ => low = mid + 1,
+            }
+        }
+        self.arrival.insert(low, arrival);
+        data.insert(low, item);
+        low
+    }
+
+    fn remove(&mut self, data: &mut Vec<T>, index: usize) -> (T, usize) {
+        self.sync(data);
+        (data.remove(index), self.arrival.remove(index))
+    }
+
+    /// Add the item, at its sorted position if there is an order. Returns the position.
+    fn push(order: Option<&mut Self>, data: &mut Vec<T>, item: T) -> usize {
+        match order {
+            Some(order) => order.insert(data, item),
+            None => {
+                data.push(item);
+                data.len() - 1
+            }
+        }
+    }
+
+    fn restore(mut self, data: &mut Vec<T>, times: &mut ArrivalTimes) {
+        self.sync(data);
+        let arrivals = self.arrival.into_iter();
+        let mut items: Vec<_> = arrivals.zip(data.drain(..).enumerate()).collect();
+        items.sort_by_key(|(arrival, _)| *arrival);
+        let mut positions = Vec::with_capacity(items.len());
+        for (_, (position, item)) in items {
+            data.push(item);
+            positions.push(position);
+        }
+        reorder_times(times, &positions);
+    }
+}
+
+impl<T: Debug + Send + 'static> LazyVecPromise<T> {
     /// creates a new LazyVecPromise given an updater functor and a tokio buffer size
     pub fn new<
         U: Fn(Sender<Message<T>>) -> Fut + 'static,
@@ -74,10 +391,173 @@ impl<T: Debug> LazyVecPromise<T> {
             state: DataState::Uninitialized,
             rx,
             tx,
+            task: None,
+            on_error: None,
+            retry: None,
+            retries_done: 0,
+            error_display: None,
+            observers: Observers::new(),
+            #[cfg(feature = "tokio-util")]
+            cancel_token: None,
+            name: None,
+            log: UpdateLog::new(),
             updater: box_future_factory(future_factory),
+            batch: Vec::new(),
+            reserve_hint: 0,
+            sort: None,
+            filter: None,
+            filtered_out: 0,
+            on_new_items: None,
+            aggregate: None,
+            arrived: None,
+            insert_order: InsertOrder::Append,
+            refresh: RefreshStrategy::Replace,
+            refresh_once: None,
+            staged: Vec::new(),
+            last_refresh: None,
+            items: ItemCounts::default(),
+            sources: 1,
+            sources_finished: 0,
+            stale: false,
+            injected: false,
+            dropped: DropCounter::default(),
+            min_interval: MinInterval::default(),
+            failed_attempts: 0,
+            transitions: TransitionLog::by_default(&DataState::Uninitialized),
+            last_error: None,
+            control: None,
+            #[cfg(feature = "registry")]
+            registration: None,
+        }
+    }
+
+    /// Creates a LazyVecPromise showing `initial` right away, e.g. restored from the last session. It starts
+    /// [`DataState::UpToDate`] and [`LazyVecPromise::is_stale`], so polling doesn't update on its own - call
+    /// [`Promise::update`] to refresh. With the default [`RefreshStrategy::Replace`], the update clears the initial
+    /// items, use [`RefreshStrategy::Append`] or [`RefreshStrategy::merge_by_key`] to keep them visible meanwhile.
+    pub fn new_with_initial<
+        U: Fn(Sender<Message<T>>) -> Fut + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    >(
+        future_factory: U,
+        buffer_size: usize,
+        initial: Vec<T>,
+    ) -> Self {
+        let mut promise = Self::new(future_factory, buffer_size);
+        promise.data = initial;
+        promise.state = DataState::UpToDate;
+        promise.state_changed(TransitionCause::ValueSet);
+        promise.stale = true;
+        promise
+    }
+
+    /// Creates a LazyVecPromise whose updater also gets the receiver of a control channel, e.g. to pause a big
+    /// export or change a parameter without restarting the update. Each update gets a new channel, see
+    /// [`Self::send_control`]. [`Self::set_updater`] replaces the updater with one without control channel.
+    /// ```rust
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use std::time::Duration;
+    /// use tokio::sync::mpsc::{Sender, UnboundedReceiver};
+    /// use lazy_async_promise::{DataState, DirectCacheAccess, LazyVecPromise, Message, Promise, api_macros::*};
+    /// let updater = |tx: Sender<Message<u32>>, mut step: UnboundedReceiver<u32>| async move {
+    ///     // wait for the first instruction, then follow the latest one
+    ///     let mut current = step.recv().await.unwrap_or(1);
+    ///     for i in 0..3 {
+    ///         current = step.try_recv().unwrap_or(current);
+    ///         send_data!(i * current, tx);
+    ///     }
+    ///     set_finished!(tx);
+    /// };
+    /// let mut promise = LazyVecPromise::new_with_control(updater, 6);
+    /// assert!(promise.send_control(10_u32).is_err(), "no update is running yet");
+    /// promise.poll_state();
+    /// // the type must match exactly, a plain `10` would be an `i32`
+    /// promise.send_control(10_u32).unwrap();
+    /// tokio::time::sleep(Duration::from_millis(50)).await;
+    /// assert_eq!(*promise.poll_state(), DataState::UpToDate);
+    /// assert_eq!(promise.as_slice(), &[0, 10, 20]);
+    /// assert!(promise.send_control(10_u32).is_err(), "the update finished");
+    /// # }
+    /// ```
+    pub fn new_with_control<
+        C: Send + 'static,
+        U: Fn(Sender<Message<T>>, UnboundedReceiver<C>) -> Fut + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    >(
+        future_factory: U,
+        buffer_size: usize,
+    ) -> Self {
+        let (control, slot) = Control::new::<C>();
+        let future_factory = move |tx| future_factory(tx, control::take_receiver(&slot));
+        let mut promise = Self::new(future_factory, buffer_size);
+        promise.control = Some(control);
+        promise
+    }
+
+    /// Send `message` to the running update of a promise from [`Self::new_with_control`]. Messages are not kept for
+    /// later updates: fails with the message if no update was started, the updater finished or dropped its receiver,
+    /// or `C` is not the control type of the promise.
+    pub fn send_control<C: 'static>(&mut self, message: C) -> Result<(), SendError<C>> {
+        Control::send(&self.control, message)
+    }
+
+    /// Whether the data is still the initial one of [`LazyVecPromise::new_with_initial`], until an update finished
+    /// or the data was replaced
+    pub fn is_stale(&self) -> bool {
+        self.stale
+    }
+
+    /// Capture the items to save them, e.g. when the app exits. Restore with [`LazyVecPromise::restore_cache`].
+    /// ```rust
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use tokio::sync::mpsc::Sender;
+    /// use lazy_async_promise::{CacheSnapshot, DataState, LazyVecPromise, Message, api_macros::*};
+    /// let updater = |tx: Sender<Message<i32>>| async move {
+    ///     send_data!(3, tx);
+    ///     set_finished!(tx);
+    /// };
+    /// let promise = LazyVecPromise::new_with_initial(updater, 10, vec![1, 2]);
+    /// let json = serde_json::to_string(&promise.serialize_cache()).unwrap();
+    ///
+    /// let mut restored = LazyVecPromise::new(updater, 10);
+    /// restored.restore_cache(serde_json::from_str::<CacheSnapshot<Vec<i32>>>(&json).unwrap());
+    /// assert_eq!(restored.as_slice(), &[1, 2]);
+    /// assert!(restored.is_stale());
+    /// # }
+    /// ```
+    #[cfg(feature = "serde")]
+    pub fn serialize_cache(&self) -> CacheSnapshot<&[T]>
+    where
+        T: Serialize,
+    {
+        CacheSnapshot {
+            data: &self.data,
+            item_count: self.data.len(),
+            last_updated: self.log.times.last_updated_at,
         }
     }
 
+    /// Replace the data with a saved cache, like [`DirectCacheAccess::set_value`]. The data counts as
+    /// [`LazyVecPromise::is_stale`] until it is refreshed, polling doesn't update on its own.
+    #[cfg(feature = "serde")]
+    pub fn restore_cache(&mut self, snapshot: CacheSnapshot<Vec<T>>) {
+        self.set_value(snapshot.data);
+        self.stale = true;
+        self.log.times.last_updated_at = snapshot.last_updated;
+    }
+
+    /// Start a [`LazyPromiseBuilder`] for the updater, to set the buffer size and other options by name
+    pub fn builder<
+        U: Fn(Sender<Message<T>>) -> Fut + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    >(
+        future_factory: U,
+    ) -> LazyPromiseBuilder<Self, U> {
+        LazyPromiseBuilder::new(future_factory)
+    }
+
     /// get current data as slice, may be incomplete depending on status
     pub fn as_slice(&self) -> &[T] {
         self.data.as_slice()
@@ -85,25 +565,1056 @@ impl<T: Debug> LazyVecPromise<T> {
 
     /// Get the current data as mutable slice
     pub fn as_slice_mut(&mut self) -> &mut [T] {
+        self.invalidate_aggregate();
         self.data.as_mut_slice()
     }
 
+    /// Keep only the items matching the predicate, e.g. after deleting rows in the UI. Like the other mutations,
+    /// this works during an update too: the items received so far are changed, later items are added as usual.
+    /// With [`RefreshStrategy::Append`] or [`RefreshStrategy::MergeByKey`], the running update may bring removed
+    /// items back when it finishes.
+    pub fn retain(&mut self, predicate: impl FnMut(&T) -> bool) {
+        let keep = self.data.iter().map(predicate).collect();
+        self.retain_marked(keep);
+    }
+
+    /// Remove the item at `index`, `None` if there is none
+    pub fn remove(&mut self, index: usize) -> Option<T> {
+        if index >= self.data.len() {
+            return None;
+        }
+        if let Some(times) = self.synced_times() {
+            times.remove(index);
+        }
+        let removed = match &mut self.sort {
+            Some(order) => order.remove(&mut self.data, index).0,
+            None => self.data.remove(index),
+        };
+        self.recompute_aggregate();
+        Some(removed)
+    }
+
+    /// Insert an item at `index`. With a sort order set, the item is inserted at its sorted position instead.
+    /// Panics if `index` is out of bounds, like [`Vec::insert`].
+    pub fn insert(&mut self, index: usize, item: T) {
+        assert!(
+            index <= self.data.len(),
+            "insert index {index} out of bounds"
+        );
+        let synced = self.synced_times().is_some();
+        let position = match &mut self.sort {
+            Some(order) => order.insert(&mut self.data, item),
+            None => {
+                self.data.insert(index, item);
+                index
+            }
+        };
+        if let (true, Some(times)) = (synced, &mut self.arrived) {
+            times.insert(position, clock::now());
+        }
+        self.recompute_aggregate();
+    }
+
+    /// Replace the item at `index` and return the previous one, `None` if there is none. With a sort order set,
+    /// the new item moves to its sorted position and takes over the arrival order and time of the replaced one.
+    pub fn replace(&mut self, index: usize, item: T) -> Option<T> {
+        if index >= self.data.len() {
+            return None;
+        }
+        self.synced_times();
+        let previous = match &mut self.sort {
+            Some(order) => {
+                let (previous, arrival) = order.remove(&mut self.data, index);
+                let position = order.place(&mut self.data, item, arrival);
+                if let Some(times) = self.arrived.as_mut() {
+                    let time = times.remove(index);
+                    times.insert(position, time);
+                }
+                previous
+            }
+            None => mem::replace(&mut self.data[index], item),
+        };
+        self.recompute_aggregate();
+        Some(previous)
+    }
+
+    /// Sort the data and keep it sorted: items arriving later are inserted at their position. Equal items keep their
+    /// arrival order. Replaces a previous comparator, the data is re-sorted then, also in the middle of an update.
+    pub fn set_sort(&mut self, cmp: impl Fn(&T, &T) -> Ordering + Send + 'static) {
+        let mut order = SortOrder {
+            cmp: Box::new(cmp),
+            arrival: self
+                .sort
+                .take()
+                .map(|order| order.arrival)
+                .unwrap_or_default(),
+            newest_first: self.insert_order == InsertOrder::Prepend,
+        };
+        self.synced_times();
+        order.sort(&mut self.data, &mut self.arrived);
+        self.sort = Some(order);
+    }
+
+    /// Restore the arrival order, or the reverse of it with [`InsertOrder::Prepend`], and place new items according
+    /// to the insert order again. Items modified via mutable access are kept in place.
+    pub fn clear_sort(&mut self) {
+        if let Some(order) = self.sort.take() {
+            self.synced_times();
+            order.restore(&mut self.data, &mut self.arrived);
+            if self.insert_order == InsertOrder::Prepend {
+                self.data.reverse();
+                if let Some(times) = &mut self.arrived {
+                    times.reverse();
+                }
+            }
+        }
+    }
+
+    /// Reserve room for `capacity` items when an update starts. The buffer is kept across updates and only cleared,
+    /// so this mostly saves the reallocations of the first update. If the data was taken, the next update allocates
+    /// room for the most items seen so far at once.
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.reserve_hint = capacity;
+        self
+    }
+
+    /// Place new items at the end or at the start of the data. Prepending moves the data once per poll, not per item.
+    /// Ignored while sorted with [`Self::set_sort`].
+    pub fn with_insert_order(mut self, order: InsertOrder) -> Self {
+        self.insert_order = order;
+        self
+    }
+
+    /// How updates combine their items with the current data, defaults to [`RefreshStrategy::Replace`]
+    pub fn with_refresh_strategy(mut self, strategy: RefreshStrategy<T>) -> Self {
+        self.refresh = strategy;
+        self
+    }
+
+    /// Like [`Promise::update`], using the strategy for this update only. Does nothing while updating.
+    pub fn update_with(&mut self, strategy: RefreshStrategy<T>) -> UpdateOutcome {
+        self.start_update(Some(strategy), true)
+    }
+
+    /// Limit how often updates start: an update requested sooner than `interval` after the last started one is
+    /// ignored or deferred, depending on `limit`. Applies to [`Promise::update`], [`Self::request_update`] and
+    /// [`Self::update_with`], while [`Self::update_now`] and retries bypass it.
+    pub fn with_min_update_interval(mut self, interval: Duration, limit: UpdateLimit) -> Self {
+        self.min_interval.set(interval, limit);
+        self
+    }
+
+    /// Like [`Promise::update`], telling whether the update started, was deferred or ignored, see
+    /// [`Self::with_min_update_interval`]
+    pub fn request_update(&mut self) -> UpdateOutcome {
+        self.start_update(None, true)
+    }
+
+    /// Like [`Promise::update`], bypassing the minimum update interval. Does nothing while updating.
+    pub fn update_now(&mut self) {
+        self.start_update(None, false);
+    }
+
+    /// The counts of the last update finished with [`RefreshStrategy::Append`] or [`RefreshStrategy::MergeByKey`]
+    pub fn last_refresh(&self) -> Option<MergeCounts> {
+        self.last_refresh
+    }
+
+    /// Only keep items matching the predicate, the others are dropped as they arrive and never enter the cache
+    pub fn with_filter(mut self, predicate: impl Fn(&T) -> bool + Send + 'static) -> Self {
+        self.set_filter(predicate);
+        self
+    }
+
+    /// Replace the filter and drop the cached items not matching it. Dropped items don't come back when the filter
+    /// is relaxed later, only with the next [`Promise::update`].
+    pub fn set_filter(&mut self, predicate: impl Fn(&T) -> bool + Send + 'static) {
+        let keep = self.data.iter().map(&predicate).collect();
+        let before = self.data.len();
+        self.retain_marked(keep);
+        self.filtered_out += before - self.data.len();
+        self.filter = Some(Box::new(predicate));
+    }
+
+    /// Keep all items arriving from now on, see [`Self::set_filter`]
+    pub fn clear_filter(&mut self) {
+        self.filter = None;
+    }
+
+    /// How many items of the current update were dropped by the filter
+    pub fn filtered_out(&self) -> usize {
+        self.filtered_out
+    }
+
+    /// How many items the current update delivered so far, or the last update once finished. Filtered items
+    /// are not counted.
+    pub fn items_this_update(&self) -> usize {
+        self.items.this_update
+    }
+
+    /// Let the updater feed the promise from `sources` concurrent sources, e.g. three backends queried in parallel
+    /// with clones of the sender. The promise is up to date once each of them sent [`crate::set_finished!`], and
+    /// fails as soon as one of them sends an error - the messages of the others are ignored then.
+    /// The sources have to run within the updater future, e.g. with `join!`, since the updater ending early
+    /// means it crashed.
+    /// ```rust
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use tokio::sync::mpsc::Sender;
+    /// use lazy_async_promise::{api_macros::*, DataState, LazyVecPromise, Message, Promise};
+    /// async fn query(backend: i32, tx: Sender<Message<i32>>) {
+    ///     send_data!(backend, tx);
+    ///     set_finished!(tx);
+    /// }
+    /// let updater = |tx: Sender<Message<i32>>| async move {
+    ///     tokio::join!(query(1, tx.clone()), query(2, tx.clone()), query(3, tx));
+    /// };
+    /// let mut promise = LazyVecPromise::new(updater, 10).with_sources(3);
+    /// promise.poll_state();
+    /// tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    /// assert_eq!(*promise.poll_state(), DataState::UpToDate);
+    /// assert_eq!(promise.as_slice().len(), 3);
+    /// # }
+    /// ```
+    pub fn with_sources(mut self, sources: usize) -> Self {
+        self.sources = sources.max(1);
+        self
+    }
+
+    /// How many sources of the current update didn't finish yet, e.g. to show "2 of 3 sources loaded".
+    /// See [`Self::with_sources`].
+    pub fn active_sources(&self) -> usize {
+        self.sources - self.sources_finished
+    }
+
+    /// How many items the previous successful update delivered, e.g. to show "loaded 128 items (was 120)"
+    pub fn items_last_update(&self) -> Option<usize> {
+        self.items.last_update
+    }
+
+    /// How many items all updates of this promise delivered
+    pub fn total_items_ever(&self) -> u64 {
+        self.items.total
+    }
+
+    /// How many messages [`crate::try_send_data!`] and [`crate::try_set_progress!`] dropped because the buffer was
+    /// full, over all updates. Only sends from the updater task itself are counted, not from tasks it spawns.
+    /// With the `log` feature, the first drop is logged as a warning.
+    pub fn dropped_messages(&self) -> u64 {
+        self.dropped.get()
+    }
+
+    /// Set a hook which is called once with the error for every update that fails, during the `poll_state` observing it.
+    /// Also called for panicking updaters. Replaces the default hook set by [`crate::set_default_on_error`] for this promise.
+    pub fn with_on_error(mut self, hook: impl FnMut(&BoxedSendError) + Send + 'static) -> Self {
+        self.on_error = Some(Box::new(hook));
+        self
+    }
+
+    /// Set a hook which is told about the items applied by each poll, e.g. to maintain an index over them without
+    /// rebuilding it. It is called from within `poll_state` once per poll which applied items, after sorting and
+    /// prepending, so the indices are final. With [`RefreshStrategy::Replace`], it is called with
+    /// [`ItemsChange::Cleared`] when an update clears the data, before any item of that update. A merge by key
+    /// reports [`ItemsChange::Cleared`] followed by all items. Changes through [`DirectCacheAccess`],
+    /// [`Self::set_sort`] and the like are not reported.
+    /// ```rust
+    /// use std::sync::{Arc, Mutex};
+    /// use tokio::sync::mpsc::Sender;
+    /// use lazy_async_promise::{DataState, ItemsChange, LazyVecPromise, Message, Promise, api_macros::*};
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let updater = |tx: Sender<Message<i32>>| async move {
+    ///     for i in [3, 1, 2] {
+    ///         send_data!(i, tx);
+    ///     }
+    ///     set_finished!(tx);
+    /// };
+    /// let index = Arc::new(Mutex::new(Vec::new()));
+    /// let hook_index = index.clone();
+    /// let mut promise = LazyVecPromise::new(updater, 6).with_on_new_items(move |change| match change {
+    ///     ItemsChange::Cleared => hook_index.lock().unwrap().clear(),
+    ///     applied => hook_index.lock().unwrap().extend(applied.items().copied()),
+    /// });
+    /// promise.poll_state();
+    /// tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    /// promise.poll_state();
+    /// assert_eq!(*index.lock().unwrap(), [3, 1, 2]);
+    /// # }
+    /// ```
+    pub fn with_on_new_items(
+        mut self,
+        hook: impl FnMut(ItemsChange<'_, T>) + Send + 'static,
+    ) -> Self {
+        self.on_new_items = Some(Box::new(hook));
+        self
+    }
+
+    /// Keep an aggregate over the items, e.g. counts and sums for a status bar, instead of folding all items every
+    /// frame. `fold` is applied to each item as it is added, the aggregate starts over from `init` when an update
+    /// clears the data. After removing or changing items, it is recomputed from all items, after mutable access
+    /// like [`Self::as_slice_mut`] by the next poll. Combine several aggregates in a tuple. Replaces a previous
+    /// aggregate.
+    /// ```rust
+    /// use tokio::sync::mpsc::Sender;
+    /// use lazy_async_promise::{DataState, LazyVecPromise, Message, Promise, api_macros::*};
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let updater = |tx: Sender<Message<u64>>| async move {
+    ///     for size in [300, 100, 200] {
+    ///         send_data!(size, tx);
+    ///     }
+    ///     set_finished!(tx);
+    /// };
+    /// let mut promise = LazyVecPromise::new(updater, 6).with_aggregate((0usize, 0u64), |(rows, bytes), size| {
+    ///     *rows += 1;
+    ///     *bytes += size;
+    /// });
+    /// promise.poll_state();
+    /// tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    /// promise.poll_state();
+    /// assert_eq!(promise.aggregate::<(usize, u64)>(), Some(&(3, 600)));
+    /// promise.retain(|size| *size < 300);
+    /// assert_eq!(promise.aggregate::<(usize, u64)>(), Some(&(2, 300)));
+    /// # }
+    /// ```
+    pub fn with_aggregate<A: Clone + Send + 'static>(
+        mut self,
+        init: A,
+        fold: impl FnMut(&mut A, &T) + Send + 'static,
+    ) -> Self {
+        let mut aggregate = Aggregate::new(init, fold);
+        aggregate.add(&self.data);
+        self.aggregate = Some(aggregate);
+        self
+    }
+
+    /// The aggregate set by [`Self::with_aggregate`], `None` without one or if it is not an `A`
+    pub fn aggregate<A: 'static>(&self) -> Option<&A> {
+        self.aggregate.as_ref()?.get()
+    }
+
+    /// Record when each item was applied by a poll, e.g. to highlight rows which just arrived. The times move with
+    /// their items when they are sorted, filtered, removed or replaced. A merge by [`RefreshStrategy::MergeByKey`]
+    /// applies all items at once, so they share its time. Items already cached count as arrived now.
+    /// Costs an [`Instant`] per item, only when enabled.
+    /// ```rust
+    /// # #[cfg(not(feature = "async-std"))]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use std::time::Duration;
+    /// use tokio::sync::mpsc::Sender;
+    /// use lazy_async_promise::{DataState, LazyVecPromise, Message, Promise, api_macros::*};
+    /// let updater = |tx: Sender<Message<&'static str>>| async move {
+    ///     send_data!("first", tx);
+    ///     tokio::time::sleep(Duration::from_millis(100)).await;
+    ///     send_data!("second", tx);
+    ///     set_finished!(tx);
+    /// };
+    /// let mut feed = LazyVecPromise::new(updater, 6).with_arrival_times();
+    /// feed.poll_state();
+    /// tokio::time::sleep(Duration::from_millis(50)).await;
+    /// feed.poll_state();
+    /// tokio::time::sleep(Duration::from_millis(150)).await;
+    /// feed.poll_state();
+    /// let fresh: Vec<_> = feed
+    ///     .iter_with_arrival()
+    ///     .filter(|(_, arrived)| arrived.elapsed() < Duration::from_millis(50))
+    ///     .map(|(item, _)| *item)
+    ///     .collect();
+    /// assert_eq!(fresh, ["second"]);
+    /// # }
+    /// # #[cfg(feature = "async-std")]
+    /// # fn main() {}
+    /// ```
+    pub fn with_arrival_times(mut self) -> Self {
+        self.arrived = Some(vec![clock::now(); self.data.len()]);
+        self
+    }
+
+    /// When the item at `index` arrived, `None` without [`Self::with_arrival_times`]. Items added via
+    /// [`DirectCacheAccess::get_value_mut`] only get a time with the next poll.
+    pub fn arrival_time(&self, index: usize) -> Option<Instant> {
+        let times = self.arrived.as_ref()?;
+        (times.len() == self.data.len())
+            .then(|| times.get(index).copied())
+            .flatten()
+    }
+
+    /// The items with their arrival times, see [`Self::with_arrival_times`]. Empty without arrival times.
+    pub fn iter_with_arrival(&self) -> impl Iterator<Item = (&T, Instant)> {
+        let times = self
+            .arrived
+            .as_deref()
+            .filter(|times| times.len() == self.data.len())
+            .unwrap_or_default();
+        self.data.iter().zip(times.iter().copied())
+    }
+
+    /// Retry failing updates according to the given [`RetryPolicy`]. While retrying, the state stays
+    /// [`DataState::Updating`] and the error hook is only called for the final failure.
+    pub fn with_retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = Some(policy);
+        self
+    }
+
+    /// Set a mapper turning errors into user-facing messages for `display_error`
+    pub fn with_error_display(
+        mut self,
+        mapper: impl Fn(&BoxedSendError) -> String + Send + Sync + 'static,
+    ) -> Self {
+        self.error_display = Some(Box::new(mapper));
+        self
+    }
+
+    /// The user-facing message if in error state. Without a mapper, this is the error's `Display` output.
+    /// The original error stays accessible via [`DataState::Error`].
+    pub fn display_error(&self) -> Option<String> {
+        match &self.state {
+            DataState::Error(error) => Some(display_error(&self.error_display, error)),
+            _ => None,
+        }
+    }
+
+    /// Stop updating once the token is cancelled: the updater is dropped and the promise switches to
+    /// [`DataState::Cancelled`], even if the updater task didn't get to run. Cancellations are neither retried nor
+    /// reported to error hooks. A child token is used, so [`Self::cancel`] only cancels this promise. A cancelled
+    /// promise stays cancelled, further updates end cancelled as well.
+    #[cfg(feature = "tokio-util")]
+    pub fn with_cancellation(mut self, token: &CancellationToken) -> Self {
+        self.cancel_token = Some(token.child_token());
+        self
+    }
+
+    /// Cancel this promise, see [`Self::with_cancellation`]. Does nothing if no token was set.
+    #[cfg(feature = "tokio-util")]
+    pub fn cancel(&self) {
+        if let Some(token) = &self.cancel_token {
+            token.cancel();
+        }
+    }
+
+    /// Name the promise, used to attribute the debug records emitted with the `log` feature
+    /// and for the updater tasks, see [`Self::with_task_name`]
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        let name = name.into();
+        #[cfg(feature = "registry")]
+        {
+            let state = data_state_variant(&self.state);
+            self.registration = Some(Registration::new(&name, "LazyVecPromise", state));
+        }
+        self.name = Some(name);
+        self
+    }
+
+    /// Name the spawned updater tasks so they can be told apart in tokio-console. Requires building with
+    /// `--cfg tokio_unstable`, otherwise the tasks stay anonymous. The same name as [`Self::with_name`].
+    pub fn with_task_name(self, name: impl Into<String>) -> Self {
+        self.with_name(name)
+    }
+
+    /// The name given by [`Self::with_name`] or [`Self::with_task_name`]
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Replace the updater, e.g. after the query behind it changed. A running update is not interrupted,
+    /// the new updater is used from the next [`Promise::update`] or retry on.
+    pub fn set_updater<
+        U: Fn(Sender<Message<T>>) -> Fut + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    >(
+        &mut self,
+        future_factory: U,
+    ) {
+        self.updater = box_future_factory(future_factory);
+        self.control = None;
+    }
+
+    /// Emit a debug record each time the progress passes another multiple of `step`, defaults to 25%.
+    /// A step of zero disables progress records.
+    #[cfg(feature = "log")]
+    pub fn with_log_progress_step(mut self, step: crate::Progress) -> Self {
+        self.log.progress_step = *step;
+        self
+    }
+
+    /// Report the state as [`DataState::Updating`] until an update ran for at least `duration`, so a fast refresh
+    /// doesn't flash a spinner for a single frame. Only the state returned by [`Promise::poll_state`] and
+    /// [`Promise::state`] is held, the data is available right away.
+    pub fn with_min_updating_display(mut self, duration: Duration) -> Self {
+        self.log.min_updating = duration;
+        self
+    }
+
+    /// How long the reported state has been [`DataState::UpToDate`] or [`DataState::Error`], e.g. to show "Saved"
+    /// once it was stable for a moment. `None` while updating, also while the state is held by
+    /// [`Self::with_min_updating_display`].
+    pub fn settled_for(&self) -> Option<Duration> {
+        self.log.settled_for(&self.state)
+    }
+
+    /// Start a new update if the promise is in the error state, e.g. from a "Retry" button. Returns whether an update
+    /// started. Bypasses [`Self::with_min_update_interval`] like [`Self::update_now`].
+    /// The error stays available via [`Self::last_error`] while the new update runs.
+    pub fn retry(&mut self) -> bool {
+        if !self.state.is_error() {
+            return false;
+        }
+        self.update_now();
+        true
+    }
+
+    /// How many updates failed in a row, reset by a successful update. Automatic retries of
+    /// [`Self::with_retry`] count as part of the update they retry.
+    pub fn failed_attempts(&self) -> u32 {
+        self.failed_attempts
+    }
+
+    /// The error of the current state, or of the last failed update while the next one runs, e.g. to show
+    /// "last attempt failed: ..." during a retry. Cleared by a successful update.
+    pub fn last_error(&self) -> Option<&BoxedSendError> {
+        match &self.state {
+            DataState::Error(error) => Some(error),
+            _ => self.last_error.as_ref(),
+        }
+    }
+
+    /// Number of retries done in the current update cycle
+    pub fn retries_done(&self) -> u32 {
+        self.retries_done
+    }
+
+    /// When the latest update started, received its first item and settled
+    pub fn transitions(&self) -> &TransitionTimes {
+        &self.log.times
+    }
+
+    /// When the last successful update finished, see [`TransitionTimes::last_updated`]
+    pub fn last_updated(&self) -> Option<Instant> {
+        self.log.times.last_updated
+    }
+
+    /// Like [`Promise::poll_state`], but stops applying received messages once `budget` is used up, checked every
+    /// [`BUDGET_CHECK_ITEMS`] messages. The rest is applied by the next poll before newer messages, so the frame time
+    /// stays predictable regardless of the cost per item. [`Self::has_backlog`] tells whether messages are left.
+    pub fn poll_state_for(&mut self, budget: Duration) -> &DataState {
+        self.poll_until(Some(clock::now() + budget))
+    }
+
+    /// Whether received messages are left to apply by the next poll, see [`Self::poll_state_for`]
+    pub fn has_backlog(&self) -> bool {
+        !self.batch.is_empty()
+    }
+
+    /// Wait until the updater sent a message or ended, without polling the promise. Nothing is applied yet, call
+    /// [`Promise::poll_state`] afterwards to observe the change. Resolves right away if there is an unobserved change.
+    /// Cancellation safe, so it can be used in `select!` - a received message is kept for the next poll.
+    pub async fn changed(&mut self) {
+        poll_fn(|cx| {
+            if self.has_backlog() {
+                return Poll::Ready(());
+            }
+            if let Poll::Ready(Some(message)) = self.rx.poll_recv(cx) {
+                self.batch.push(message);
+                return Poll::Ready(());
+            }
+            match &mut self.task {
+                Some(task) => task.poll_finished(cx),
+                None => Poll::Pending,
+            }
+        })
+        .await
+    }
+
+    /// A watch of the current state, without the payload. Updated by the updater task itself as messages are sent,
+    /// so observers don't depend on the owner polling the promise. Changes made by the promise, like detecting a
+    /// panicked updater, are published during [`Promise::poll_state`].
+    pub fn state_watch(&self) -> watch::Receiver<DataStateTag> {
+        self.observers.tags.subscribe()
+    }
+
+    /// A stream of the state changes of the next update, ending with [`StateChange::Finished`].
+    /// If an update is already running, the stream starts with the following one - so create the stream
+    /// before polling the promise for the first time or before calling [`Promise::update`].
+    /// With a [`RetryPolicy`], the stream ends with the first failed attempt.
+    /// Updates are observed as they are sent by the updater, without the promise being polled.
+    #[cfg(feature = "futures")]
+    pub fn state_stream(&self) -> impl futures_core::Stream<Item = StateChange> {
+        state_change_stream(self.observers.state_changes.subscribe(), true)
+    }
+
+    /// Poll the state like [`Promise::poll_state`], returning an owned [`DataStateSnapshot`] which doesn't borrow
+    /// the promise
+    pub fn poll_snapshot(&mut self) -> DataStateSnapshot {
+        let state = self.poll_state().tag();
+        DataStateSnapshot {
+            state,
+            error: self.display_error(),
+            has_value: self.has_value(),
+        }
+    }
+
+    /// Keep the items marked in `keep`, together with their arrival order
+    fn retain_marked(&mut self, keep: Vec<bool>) {
+        if let Some(order) = &mut self.sort {
+            order.sync(&self.data);
+            let mut keep = keep.iter();
+            order.arrival.retain(|_| *keep.next().unwrap_or(&true));
+        }
+        if let Some(times) = self.synced_times() {
+            let mut keep = keep.iter();
+            times.retain(|_| *keep.next().unwrap_or(&true));
+        }
+        let mut keep = keep.into_iter();
+        self.data.retain(|_| keep.next().unwrap_or(true));
+        self.recompute_aggregate();
+    }
+
+    fn refresh_strategy(&self) -> &RefreshStrategy<T> {
+        self.refresh_once.as_ref().unwrap_or(&self.refresh)
+    }
+
+    /// Adds the items of a finished update, returns from which position on the data holds new items
+    fn apply_staged(&mut self) -> usize {
+        let staged = mem::take(&mut self.staged);
+        let strategy = self.refresh_once.take();
+        let counts = match strategy.as_ref().unwrap_or(&self.refresh) {
+            RefreshStrategy::MergeByKey(merge) => {
+                let counts = merge(&mut self.data, staged);
+                // the merge applies the whole result, like it is reported to `on_new_items`
+                if let Some(times) = &mut self.arrived {
+                    *times = vec![clock::now(); self.data.len()];
+                }
+                if let Some(order) = &mut self.sort {
+                    order.arrival.clear();
+                    order.sort(&mut self.data, &mut self.arrived);
+                }
+                counts
+            }
+            _ => {
+                let added = staged.len();
+                for item in staged {
+                    let position = SortOrder::push(self.sort.as_mut(), &mut self.data, item);
+                    self.record_arrival(position);
+                }
+                MergeCounts {
+                    added,
+                    ..MergeCounts::default()
+                }
+            }
+        };
+        self.last_refresh = Some(counts);
+        self.data.len() - counts.added
+    }
+
+    fn start_update(
+        &mut self,
+        refresh_once: Option<RefreshStrategy<T>>,
+        limited: bool,
+    ) -> UpdateOutcome {
+        if matches!(self.state, DataState::Updating(_)) {
+            return UpdateOutcome::AlreadyUpdating;
+        }
+        let outcome = match limited {
+            true => self.min_interval.check(),
+            false => UpdateOutcome::Started,
+        };
+        let delay = match outcome {
+            UpdateOutcome::Started => Duration::ZERO,
+            UpdateOutcome::Deferred(delay) => delay,
+            _ => return outcome,
+        };
+        self.refresh_once = refresh_once;
+        self.retries_done = 0;
+        self.start_updater(delay);
+        outcome
+    }
+
+    fn clear_data(&mut self) {
+        self.data.clear();
+        if let Some(times) = &mut self.arrived {
+            times.clear();
+        }
+        if let Some(hook) = &mut self.on_new_items {
+            hook(ItemsChange::Cleared);
+        }
+        if let Some(aggregate) = &mut self.aggregate {
+            aggregate.reset();
+        }
+        if let Some(order) = &mut self.sort {
+            order.arrival.clear();
+        }
+    }
+
+    fn start_updater(&mut self, delay: Duration) {
+        self.injected = false;
+        if self.refresh_strategy().is_staged() {
+            self.staged.clear();
+        } else {
+            self.stale = false;
+            self.clear_data();
+            self.data.reserve(self.reserve_hint);
+        }
+        self.filtered_out = 0;
+        if let Some(finished) = self.items.finished.take() {
+            self.items.last_update = Some(finished);
+        }
+        self.items.this_update = 0;
+        self.sources_finished = 0;
+        let previous = mem::replace(&mut self.state, DataState::Updating(0.0.into()));
+        if let DataState::Error(error) = previous {
+            self.last_error = Some(error);
+        }
+        self.state_changed(match self.retries_done {
+            0 => TransitionCause::Update,
+            _ => TransitionCause::Retry,
+        });
+        self.min_interval.starting(delay);
+        self.log
+            .update_triggered(self.name.as_deref(), self.retries_done);
+        if let Some(control) = &mut self.control {
+            control.open();
+        }
+        let updater = &self.updater;
+        #[cfg(feature = "tokio-util")]
+        let cancel_token = self.cancel_token.clone();
+        let dropped = self.dropped.clone();
+        let make_future = move |tx| {
+            let future = dropped.scope(updater(tx));
+            #[cfg(feature = "tokio-util")]
+            let future = cancellable(future, cancel_token);
+            future
+        };
+        self.task = Some(spawn_updater(
+            make_future,
+            delay,
+            self.tx.clone(),
+            &self.observers,
+            self.name.as_deref(),
+        ));
+    }
+
+    /// Poll and get the value once the promise is up to date. Starts an update if the promise is uninitialized,
+    /// e.g. after the value was taken. Returns `None` while updating without starting another update, and after
+    /// an error without retrying - call [`Promise::update`] for that.
+    pub fn get_or_update(&mut self) -> Option<&Vec<T>> {
+        self.poll_state();
+        self.get_result().and_then(Result::ok)
+    }
+
     #[cfg(test)]
+    /// test helper: is the state [`DataState::Uninitialized`]
     pub fn is_uninitialized(&self) -> bool {
         self.state == DataState::Uninitialized
     }
+
+    fn apply_message(&mut self, message: Message<T>, staged: bool) {
+        if self.sources > 1 && self.state.is_error() {
+            // the other sources of a failed update are ignored
+            return;
+        }
+        match message {
+            Message::NewData(data) => {
+                if self.filter.as_ref().is_some_and(|keep| !keep(&data)) {
+                    self.filtered_out += 1;
+                    return;
+                }
+                self.items.this_update += 1;
+                self.items.total += 1;
+                if staged {
+                    self.staged.push(data);
+                } else {
+                    let position = SortOrder::push(self.sort.as_mut(), &mut self.data, data);
+                    self.record_arrival(position);
+                }
+                self.log.item_received(self.name.as_deref());
+            }
+            Message::StateChange(new_state) => {
+                match new_state {
+                    DataState::Updating(progress) => {
+                        self.log.progress(self.name.as_deref(), progress)
+                    }
+                    DataState::UpToDate if self.active_sources() > 1 => {
+                        self.sources_finished += 1;
+                        return;
+                    }
+                    DataState::UpToDate => {
+                        self.sources_finished = self.sources;
+                        self.failed_attempts = 0;
+                        self.last_error = None;
+                        self.stale = false;
+                        self.items.finished = Some(self.items.this_update);
+                        self.log.finished(self.name.as_deref());
+                    }
+                    _ => {}
+                }
+                self.state = new_state;
+            }
+            Message::NewDataWithProgress(..) => unreachable!("split before"),
+        }
+    }
+
+    fn poll_until(&mut self, deadline: Option<Instant>) -> &DataState {
+        self.poll_updater(deadline);
+        #[cfg(feature = "registry")]
+        if let Some(registration) = &self.registration {
+            registration.record_data_state(&self.state);
+        }
+        self.log.reported(&self.state)
+    }
+
+    /// Tell the hook about the items of `range`, or with a sort order about those arrived from `arrival_mark` on
+    fn report_new_items(
+        &mut self,
+        range: Range<usize>,
+        arrival_mark: Option<usize>,
+        cleared: bool,
+    ) {
+        if self.on_new_items.is_none() && self.aggregate.is_none() {
+            return;
+        }
+        // without arrival bookkeeping matching the data, the new items can't be told apart: report all of them
+        let out_of_sync = match &self.sort {
+            Some(order) => arrival_mark.is_some() && order.arrival.len() != self.data.len(),
+            None => false,
+        };
+        let cleared = cleared || out_of_sync;
+        if let (true, Some(hook)) = (cleared, &mut self.on_new_items) {
+            hook(ItemsChange::Cleared);
+        }
+        let indices: Vec<usize> = match (&self.sort, arrival_mark) {
+            _ if out_of_sync => (0..self.data.len()).collect(),
+            (Some(order), Some(mark)) => {
+                let arrivals = order.arrival.iter().enumerate();
+                let new = arrivals.filter(|(_, arrival)| **arrival >= mark);
+                new.map(|(index, _)| index).collect()
+            }
+            _ => range.collect(),
+        };
+        if let Some(aggregate) = &mut self.aggregate {
+            if cleared {
+                aggregate.reset();
+            }
+            aggregate.add(indices.iter().map(|index| &self.data[*index]));
+        }
+        if let (false, Some(hook)) = (indices.is_empty(), &mut self.on_new_items) {
+            hook(ItemsChange::Applied {
+                data: &self.data,
+                indices: &indices,
+            });
+        }
+    }
+
+    /// Apply the received messages, until `deadline` if given
+    fn poll_updater(&mut self, deadline: Option<Instant>) {
+        // checked before draining, so all messages of a finished task are applied
+        let finished = self.task.as_mut().is_some_and(Task::is_finished);
+        let was_error = self.state.is_error();
+        let was_updating = self.state.is_updating();
+        let staged = self.refresh_strategy().is_staged();
+        if let Some(aggregate) = &mut self.aggregate {
+            aggregate.refresh(&self.data);
+        }
+        self.synced_times();
+        drain_into(&mut self.rx, &mut self.batch);
+        let result_arrived = self.batch.iter().any(|message| {
+            let finished = matches!(message, Message::StateChange(DataState::UpToDate));
+            finished || !matches!(message, Message::StateChange(_))
+        });
+        if self.injected && !staged && result_arrived {
+            // the running update overwrites injected data, like with `LazyValuePromise`
+            self.injected = false;
+            self.clear_data();
+        }
+        self.data.reserve(self.batch.len());
+        let mut received_from = self.data.len();
+        if let Some(order) = &mut self.sort {
+            // e.g. `get_value_mut` changed the length, the arrival mark has to refer to the current data
+            order.sync(&self.data);
+        }
+        let arrival_mark = self
+            .sort
+            .as_ref()
+            .map(|order| order.next_arrival(received_from));
+        let mut merged = false;
+        let mut batch = mem::take(&mut self.batch);
+        let mut messages = batch.drain(..);
+        for (applied, message) in messages.by_ref().enumerate() {
+            for msg in message.split() {
+                self.apply_message(msg, staged);
+            }
+            let checked = (applied + 1) % BUDGET_CHECK_ITEMS == 0;
+            if checked && deadline.is_some_and(|deadline| clock::now() >= deadline) {
+                break;
+            }
+        }
+        // the rest is applied by the next poll, before newer messages
+        self.batch.extend(messages);
+        if self.batch.is_empty() {
+            self.batch = batch;
+        }
+        if staged && was_updating && self.state == DataState::UpToDate {
+            merged = matches!(self.refresh_strategy(), RefreshStrategy::MergeByKey(_));
+            received_from = self.apply_staged();
+        }
+        let mut new_items = received_from..self.data.len();
+        if self.insert_order == InsertOrder::Prepend && self.sort.is_none() {
+            // newest first: the items of this poll are moved to the front in reverse
+            let received = self.data.len() - received_from;
+            self.data[received_from..].reverse();
+            self.data.rotate_right(received);
+            if let Some(times) = &mut self.arrived {
+                times[received_from..].reverse();
+                times.rotate_right(received);
+            }
+            new_items = 0..received;
+        }
+        if merged {
+            new_items = 0..self.data.len();
+        }
+        self.report_new_items(new_items, arrival_mark.filter(|_| !merged), merged);
+        self.reserve_hint = self.reserve_hint.max(self.data.len());
+
+        let mut cause = TransitionCause::Updater;
+        #[cfg(feature = "tokio-util")]
+        if self.state.is_updating() && is_cancelled(&self.cancel_token) {
+            cause = TransitionCause::Cancelled;
+            self.task = None;
+            self.batch.clear();
+            self.state = DataState::Cancelled;
+        }
+
+        // a finished task is only joined once its messages are all applied
+        let task_result = match finished && self.batch.is_empty() {
+            true => self.task.as_mut().and_then(Task::try_join),
+            false => None,
+        };
+        if let Some(result) = task_result {
+            self.task = None;
+            if self.state.is_updating() {
+                cause = TransitionCause::UpdaterEnded;
+                self.state = DataState::Error(updater_task_error(result));
+            }
+        }
+
+        self.state_changed(cause);
+        if let (false, DataState::Error(error)) = (was_error, &self.state) {
+            self.log.error(self.name.as_deref(), error);
+            #[cfg(feature = "tokio-util")]
+            if error.is_cancelled() {
+                return;
+            }
+            let retry_delay = self
+                .retry
+                .as_ref()
+                .filter(|retry| retry.should_retry(self.retries_done, error))
+                .map(|retry| retry.delay_for(self.retries_done));
+            if let Some(delay) = retry_delay {
+                self.retries_done += 1;
+                self.start_updater(delay);
+            } else {
+                self.failed_attempts += 1;
+                report_error(&mut self.on_error, error);
+            }
+        }
+
+        if self.state == DataState::Uninitialized {
+            self.update();
+        }
+    }
 }
 
-impl<T: Debug> DirectCacheAccess<Vec<T>, String> for LazyVecPromise<T> {
+impl<T: Debug> LazyVecPromise<T> {
+    /// Keep a log of the last [`crate::TRANSITION_LOG_CAPACITY`] state transitions with their cause, e.g.
+    /// to attach to a bug report. Also enabled by [`crate::set_transition_log_default`].
+    pub fn with_transition_log(mut self) -> Self {
+        self.transitions
+            .get_or_insert_with(|| TransitionLog::enabled(&self.state));
+        self
+    }
+
+    /// The logged transitions, oldest first. Empty unless enabled by [`Self::with_transition_log`].
+    /// ```rust
+    /// use tokio::sync::mpsc::Sender;
+    /// use lazy_async_promise::{DataState, LazyVecPromise, Message, Promise, TransitionCause, api_macros::*};
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let updater = |tx: Sender<Message<i32>>| async move {
+    ///     set_error!(lazy_async_promise::BoxedSendError::msg("offline"), tx);
+    /// };
+    /// let mut promise = LazyVecPromise::new(updater, 6).with_transition_log();
+    /// promise.poll_state();
+    /// tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    /// promise.poll_state();
+    /// let log = promise.transition_log();
+    /// assert_eq!(log[0].cause, TransitionCause::Update);
+    /// assert!(log[1].to_string().ends_with("updater -> error: offline"));
+    /// # }
+    /// ```
+    pub fn transition_log(&self) -> &[Transition] {
+        TransitionLog::entries(&self.transitions)
+    }
+
+    /// Publish a state change made by the promise itself
+    fn state_changed(&mut self, cause: TransitionCause) {
+        self.observers.sync_tag(&self.state);
+        transitionlog::record(&mut self.transitions, &self.state, cause);
+    }
+
+    /// The arrival times, after adding or dropping times at the end if the data was changed behind our back
+    fn synced_times(&mut self) -> Option<&mut Vec<Instant>> {
+        let times = self.arrived.as_mut()?;
+        if times.len() != self.data.len() {
+            times.resize(self.data.len(), clock::now());
+        }
+        Some(times)
+    }
+
+    /// The item at `position` just arrived
+    fn record_arrival(&mut self, position: usize) {
+        if let Some(times) = &mut self.arrived {
+            times.insert(position.min(times.len()), clock::now());
+        }
+    }
+
+    fn recompute_aggregate(&mut self) {
+        if let Some(aggregate) = &mut self.aggregate {
+            aggregate.recompute(&self.data);
+        }
+    }
+
+    /// The data may be changed in place, recompute the aggregate with the next poll
+    fn invalidate_aggregate(&mut self) {
+        if let Some(aggregate) = &mut self.aggregate {
+            aggregate.invalidate();
+        }
+    }
+}
+
+/// Prints the state and the number of cached items, but not the items themselves
+impl<T: Debug> Debug for LazyVecPromise<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LazyVecPromise")
+            .field("name", &self.name)
+            .field("state", &self.state.tag())
+            .field("items", &self.data.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: Debug> DirectCacheAccess<Vec<T>, BoxedSendError> for LazyVecPromise<T> {
+    /// The data, may be incomplete while updating. `None` if uninitialized, e.g. after the data was taken.
     fn get_value_mut(&mut self) -> Option<&mut Vec<T>> {
-        Some(&mut self.data)
+        self.invalidate_aggregate();
+        (self.state != DataState::Uninitialized).then_some(&mut self.data)
     }
 
+    /// The data, may be incomplete while updating. `None` if uninitialized, e.g. after the data was taken.
     fn get_value(&self) -> Option<&Vec<T>> {
-        Some(&self.data)
+        (self.state != DataState::Uninitialized).then_some(&self.data)
     }
 
-    fn get_result(&self) -> Option<Result<&Vec<T>, &String>> {
+    fn get_result(&self) -> Option<Result<&Vec<T>, &BoxedSendError>> {
         if let DataState::UpToDate = self.state {
             Some(Ok(&self.data))
         } else if let DataState::Error(error) = &self.state {
@@ -113,62 +1624,95 @@ impl<T: Debug> DirectCacheAccess<Vec<T>, String> for LazyVecPromise<T> {
         }
     }
 
+    fn get_result_mut(&mut self) -> Option<Result<&mut Vec<T>, &mut BoxedSendError>> {
+        self.invalidate_aggregate();
+        if let DataState::UpToDate = self.state {
+            Some(Ok(&mut self.data))
+        } else if let DataState::Error(error) = &mut self.state {
+            Some(Err(error))
+        } else {
+            None
+        }
+    }
+
     /// Take the current data. If state was  [`DataState::UpToDate`] it will return the value.
     /// If the state was anything else, it will return None. If data is taken successfully, will leave
     /// the object in state [`DataState::Uninitialized`]
     fn take_value(&mut self) -> Option<Vec<T>> {
         if self.state == DataState::UpToDate {
             self.state = DataState::Uninitialized;
-            Some(mem::take(&mut self.data))
+            self.state_changed(TransitionCause::Taken);
+            let data = mem::take(&mut self.data);
+            self.recompute_aggregate();
+            self.synced_times();
+            Some(data)
         } else {
             None
         }
     }
 
-    fn take_result(&mut self) -> Option<Result<Vec<T>, String>> {
+    fn take_result(&mut self) -> Option<Result<Vec<T>, BoxedSendError>> {
         if self.state == DataState::UpToDate {
             self.state = DataState::Uninitialized;
-            Some(Ok(mem::take(&mut self.data)))
+            self.state_changed(TransitionCause::Taken);
+            let data = mem::take(&mut self.data);
+            self.recompute_aggregate();
+            self.synced_times();
+            Some(Ok(data))
         } else if let DataState::Error(_) = self.state {
-            let DataState::Error(err) = mem::replace(&mut self.state, DataState::Uninitialized) else {
+            let DataState::Error(err) = mem::replace(&mut self.state, DataState::Uninitialized)
+            else {
                 unreachable!();
             };
+            self.state_changed(TransitionCause::Taken);
             Some(Err(err))
         } else {
             None
         }
     }
-}
 
-impl<T: Debug> Promise for LazyVecPromise<T> {
-    fn poll_state(&mut self) -> &DataState {
-        while let Ok(msg) = self.rx.try_recv() {
-            match msg {
-                Message::NewData(data) => {
-                    self.data.push(data);
-                }
-                Message::StateChange(new_state) => {
-                    self.state = new_state;
-                }
-            }
-        }
+    fn is_pending(&self) -> bool {
+        self.state.is_updating()
+    }
 
-        if self.state == DataState::Uninitialized {
-            self.update();
+    /// Replaces the data and sets the state to [`DataState::UpToDate`], returning the previous data.
+    /// If an update is in flight, the state is left untouched and the injected data is shown until the first items
+    /// or the end of the update arrive, which replace it. With a staged [`RefreshStrategy`], the update is applied
+    /// to the injected data instead, like to any previous data.
+    fn set_value(&mut self, value: Vec<T>) -> Option<Vec<T>> {
+        // like `get_value`, an uninitialized promise has no value
+        let had_value = self.state != DataState::Uninitialized;
+        if matches!(self.state, DataState::Updating(_)) {
+            self.injected = true;
+        } else {
+            self.state = DataState::UpToDate;
+            self.state_changed(TransitionCause::ValueSet);
+        }
+        self.stale = false;
+        let previous = mem::replace(&mut self.data, value);
+        if let Some(times) = &mut self.arrived {
+            *times = vec![clock::now(); self.data.len()];
         }
+        if let Some(order) = &mut self.sort {
+            order.arrival.clear();
+            order.sort(&mut self.data, &mut self.arrived);
+        }
+        self.recompute_aggregate();
+        had_value.then_some(previous)
+    }
+}
+
+impl<T: Debug + Send + 'static> Promise for LazyVecPromise<T> {
+    fn poll_state(&mut self) -> &DataState {
+        self.poll_until(None)
+    }
 
-        &self.state
+    fn state(&self) -> &DataState {
+        self.log.reported(&self.state)
     }
 
     fn update(&mut self) {
-        if matches!(self.state, DataState::Updating(_)) {
-            return;
-        }
-
-        self.state = DataState::Updating(0.0.into());
-        self.data.clear();
-        let future = (self.updater)(self.tx.clone());
-        tokio::spawn(future);
+        self.start_update(None, true);
     }
 }
 
@@ -176,6 +1720,7 @@ impl<T: Debug> Promise for LazyVecPromise<T> {
 mod test {
     use super::*;
     use crate::api_macros::*;
+    use crate::runtime::sleep;
     use std::time::Duration;
     use tokio::runtime::Builder;
     use tokio::sync::mpsc::Sender;
@@ -185,10 +1730,10 @@ mod test {
         let string_maker = |tx: Sender<Message<String>>| async move {
             const COUNT: i32 = 5;
             for i in 0..COUNT {
-                tokio::time::sleep(Duration::from_millis(10)).await;
+                sleep(Duration::from_millis(10)).await;
                 send_data!(i.to_string(), tx);
                 set_progress!(Progress::from_fraction(i, COUNT), tx);
-                tokio::time::sleep(Duration::from_millis(20)).await;
+                sleep(Duration::from_millis(20)).await;
             }
             set_finished!(tx);
         };
@@ -208,7 +1753,7 @@ mod test {
                 assert!(delayed_vec.as_slice_mut().is_empty());
 
                 // We have some numbers ready in between
-                tokio::time::sleep(Duration::from_millis(80)).await;
+                sleep(Duration::from_millis(80)).await;
                 let progress = delayed_vec.poll_state().get_progress().unwrap();
                 assert!(progress.as_f32() > 0.0);
                 assert!(progress.as_f32() < 1.0);
@@ -216,7 +1761,7 @@ mod test {
                 assert!(!delayed_vec.as_slice_mut().is_empty());
 
                 // after wait we have a result
-                tokio::time::sleep(Duration::from_millis(200)).await;
+                sleep(Duration::from_millis(200)).await;
                 assert_eq!(*delayed_vec.poll_state(), DataState::UpToDate);
                 assert_eq!(delayed_vec.as_slice().len(), 5);
                 assert_eq!(delayed_vec.as_slice_mut().len(), 5);
@@ -228,7 +1773,7 @@ mod test {
                 assert!(delayed_vec.as_slice_mut().is_empty());
 
                 // finally after waiting it's full again
-                tokio::time::sleep(Duration::from_millis(400)).await;
+                sleep(Duration::from_millis(400)).await;
                 assert_eq!(*delayed_vec.poll_state(), DataState::UpToDate);
                 assert_eq!(delayed_vec.as_slice().len(), 5);
                 assert_eq!(delayed_vec.as_slice_mut().len(), 5);
@@ -245,11 +1790,176 @@ mod test {
         let mut delayed_vec = LazyVecPromise::new(error_maker, 1);
         assert_eq!(*delayed_vec.poll_state(), DataState::Updating(0.0.into()));
         assert!(delayed_vec.as_slice().is_empty());
-        tokio::time::sleep(Duration::from_millis(200)).await;
+        sleep(Duration::from_millis(200)).await;
         assert!(matches!(*delayed_vec.poll_state(), DataState::Error(_)));
+        assert!(delayed_vec.has_error() && !delayed_vec.has_value());
+        // errors can be amended in place
+        if let Some(Err(error)) = delayed_vec.get_result_mut() {
+            *error = BoxedSendError::msg(format!("while reading: {}", error));
+        }
+        assert!(
+            matches!(delayed_vec.poll_state(), DataState::Error(e) if e.to_string().starts_with("while reading: "))
+        );
         assert!(delayed_vec.as_slice().is_empty());
     }
 
+    #[tokio::test]
+    async fn error_display() {
+        let error_maker = |tx: Sender<Message<String>>| async move {
+            sleep(Duration::from_millis(10)).await;
+            set_error!("ERR_CODE 5012: upstream shard timeout", tx);
+        };
+
+        let mut delayed_vec = LazyVecPromise::new(error_maker, 1)
+            .with_error_display(|_| "The server is busy, please try again".to_string());
+        delayed_vec.poll_state();
+        assert!(delayed_vec.display_error().is_none());
+        sleep(Duration::from_millis(50)).await;
+        assert!(delayed_vec.poll_state().is_error());
+        assert_eq!(
+            delayed_vec.display_error().unwrap(),
+            "The server is busy, please try again"
+        );
+        let Some(Err(error)) = delayed_vec.get_result() else {
+            unreachable!();
+        };
+        assert_eq!(error.to_string(), "ERR_CODE 5012: upstream shard timeout");
+    }
+
+    #[tokio::test]
+    async fn updater_panic_sets_error() {
+        let panicking_maker = |tx: Sender<Message<i32>>| async move {
+            send_data!(1, tx);
+            panic!("boom");
+        };
+
+        let hook_calls = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let hook_calls_clone = hook_calls.clone();
+        let mut delayed_vec = LazyVecPromise::new(panicking_maker, 6).with_on_error(move |_| {
+            hook_calls_clone.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        });
+        assert!(delayed_vec.poll_state().is_updating());
+        sleep(Duration::from_millis(50)).await;
+        let DataState::Error(error) = delayed_vec.poll_state() else {
+            unreachable!();
+        };
+        assert!(error.to_string().contains("boom"));
+        assert_eq!(delayed_vec.as_slice(), &[1]);
+        // the hook fires once per transition into the error state
+        assert!(delayed_vec.poll_state().is_error());
+        assert_eq!(hook_calls.load(std::sync::atomic::Ordering::SeqCst), 1);
+        delayed_vec.update();
+        sleep(Duration::from_millis(50)).await;
+        assert!(delayed_vec.poll_state().is_error());
+        assert_eq!(hook_calls.load(std::sync::atomic::Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn retry_clears_partial_data() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+        let failed = Arc::new(AtomicBool::new(false));
+        let flaky_maker = move |tx: Sender<Message<i32>>| {
+            let failed = failed.clone();
+            async move {
+                send_data!(1, tx);
+                if !failed.swap(true, Ordering::SeqCst) {
+                    set_error!("flaky", tx);
+                    return;
+                }
+                send_data!(2, tx);
+                set_finished!(tx);
+            }
+        };
+        let mut delayed_vec = LazyVecPromise::new(flaky_maker, 6).with_retry(RetryPolicy::new(1));
+        for _ in 0..10 {
+            assert!(!delayed_vec.poll_state().is_error());
+            sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(*delayed_vec.poll_state(), DataState::UpToDate);
+        assert_eq!(delayed_vec.as_slice(), &[1, 2]);
+        assert_eq!(delayed_vec.retries_done(), 1);
+    }
+
+    #[tokio::test]
+    async fn buffer_reused_across_updates() {
+        let int_maker = |tx: Sender<Message<i32>>| async move {
+            for i in 0..1000 {
+                send_data!(i, tx);
+            }
+            set_finished!(tx);
+        };
+        let mut delayed_vec = LazyVecPromise::new(int_maker, 2000).with_capacity(500);
+        delayed_vec.poll_state();
+        assert!(delayed_vec.get_value().unwrap().capacity() >= 500);
+        sleep(Duration::from_millis(50)).await;
+        assert!(delayed_vec.poll_state().is_success());
+        let pointer = delayed_vec.as_slice().as_ptr();
+        let capacity = delayed_vec.get_value().unwrap().capacity();
+        for _ in 0..3 {
+            delayed_vec.update();
+            sleep(Duration::from_millis(50)).await;
+            assert!(delayed_vec.poll_state().is_success());
+            assert_eq!(delayed_vec.as_slice().len(), 1000);
+            assert_eq!(delayed_vec.as_slice().as_ptr(), pointer);
+            assert_eq!(delayed_vec.get_value().unwrap().capacity(), capacity);
+        }
+        // after taking the data, the next update reserves room for all items at once
+        assert_eq!(delayed_vec.take_value().unwrap().len(), 1000);
+        delayed_vec.poll_state();
+        assert!(delayed_vec.get_value().unwrap().capacity() >= 1000);
+    }
+
+    #[tokio::test]
+    async fn non_blocking_send() {
+        let dropping_maker = |tx: Sender<Message<i32>>| async move {
+            let mut sent = 0;
+            for i in 0..10 {
+                if try_send_data!(i, tx, FullBuffer::Drop) {
+                    sent += 1;
+                }
+            }
+            assert_eq!(sent, 3);
+            assert!(!try_set_progress!(
+                Progress::from_percent(50),
+                tx,
+                FullBuffer::Drop
+            ));
+            // the buffer is still full, so this returns from the updater without a final state
+            try_set_progress!(Progress::from_percent(60), tx, FullBuffer::Return);
+            unreachable!();
+        };
+        let mut delayed_vec = LazyVecPromise::new(dropping_maker, 3);
+        delayed_vec.poll_state();
+        sleep(Duration::from_millis(50)).await;
+        assert!(
+            matches!(delayed_vec.poll_state(), DataState::Error(e) if e.to_string() == crate::UNFINISHED_UPDATER_MESSAGE)
+        );
+        assert_eq!(delayed_vec.as_slice(), &[0, 1, 2]);
+        assert_eq!(delayed_vec.dropped_messages(), 9);
+    }
+
+    #[tokio::test]
+    async fn debug_prints_item_count() {
+        let int_maker = |tx: Sender<Message<i32>>| async move {
+            send_data!(1, tx);
+            send_data!(2, tx);
+            set_finished!(tx);
+        };
+        let mut delayed_vec = LazyVecPromise::new(int_maker, 6).with_name("numbers");
+        assert_eq!(
+            format!("{delayed_vec:?}"),
+            r#"LazyVecPromise { name: Some("numbers"), state: Uninitialized, items: 0, .. }"#
+        );
+        delayed_vec.poll_state();
+        sleep(Duration::from_millis(50)).await;
+        delayed_vec.poll_state();
+        assert_eq!(
+            format!("{delayed_vec:?}"),
+            r#"LazyVecPromise { name: Some("numbers"), state: UpToDate, items: 2, .. }"#
+        );
+    }
+
     #[tokio::test]
     async fn test_direct_cache_access() {
         let int_maker = |tx: Sender<Message<i32>>| async move {
@@ -259,9 +1969,11 @@ mod test {
 
         let mut delayed_vec = LazyVecPromise::new(int_maker, 6);
         let _ = delayed_vec.poll_state();
-        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(delayed_vec.is_pending() && !delayed_vec.has_value());
+        sleep(Duration::from_millis(50)).await;
         let poll_result = delayed_vec.poll_state();
         assert!(matches!(poll_result, DataState::UpToDate));
+        assert!(delayed_vec.has_value() && !delayed_vec.is_pending());
         let val = delayed_vec.get_value();
         assert_eq!(val.unwrap().len(), 1);
         assert_eq!(*val.unwrap().first().unwrap(), 42);
@@ -269,5 +1981,830 @@ mod test {
         let value_owned = delayed_vec.take_value().unwrap();
         assert_eq!(*value_owned.first().unwrap(), 42);
         assert!(delayed_vec.is_uninitialized());
+        assert!(!delayed_vec.has_value() && !delayed_vec.has_error() && !delayed_vec.is_pending());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn state_watch() {
+        let updater = |tx: Sender<Message<i32>>| async move {
+            sleep(Duration::from_millis(10)).await;
+            send_data!(1, tx);
+            set_progress!(Progress::from_percent(50), tx);
+            sleep(Duration::from_millis(10)).await;
+            set_finished!(tx);
+        };
+        let mut promise = LazyVecPromise::new(updater, 6);
+        let mut watch = promise.state_watch();
+        assert_eq!(*watch.borrow(), DataStateTag::Uninitialized);
+        promise.poll_state();
+        assert!(matches!(*watch.borrow(), DataStateTag::Updating(_)));
+        // resolves without the owner polling
+        watch
+            .wait_for(|tag| *tag == DataStateTag::UpToDate)
+            .await
+            .unwrap();
+        assert!(promise.take_value().is_none());
+        promise.poll_state();
+        assert_eq!(promise.take_value(), Some(vec![1]));
+        assert_eq!(*watch.borrow(), DataStateTag::Uninitialized);
+    }
+
+    #[tokio::test]
+    async fn set_value() {
+        let int_maker = |tx: Sender<Message<i32>>| async move {
+            send_data!(42, tx);
+            set_finished!(tx);
+        };
+
+        let mut delayed_vec = LazyVecPromise::new(int_maker, 6);
+        assert_eq!(delayed_vec.set_value(vec![1, 2]), None);
+        // no update is triggered since we are up to date
+        assert_eq!(*delayed_vec.poll_state(), DataState::UpToDate);
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(delayed_vec.poll_state(), &DataState::UpToDate);
+        assert_eq!(delayed_vec.as_slice(), &[1, 2]);
+        assert_eq!(delayed_vec.set_value(vec![3]).unwrap(), vec![1, 2]);
+        assert_eq!(delayed_vec.as_slice(), &[3]);
+
+        // the items of a running update replace the injected data instead of being appended
+        delayed_vec.update();
+        assert_eq!(delayed_vec.set_value(vec![1, 2]), Some(vec![]));
+        assert!(delayed_vec.state().is_updating());
+        assert_eq!(delayed_vec.as_slice(), &[1, 2]);
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(delayed_vec.poll_state(), &DataState::UpToDate);
+        assert_eq!(delayed_vec.as_slice(), &[42]);
+    }
+
+    #[tokio::test]
+    async fn new_with_initial() {
+        let int_maker = |tx: Sender<Message<i32>>| async move {
+            send_data!(3, tx);
+            set_finished!(tx);
+        };
+        let mut promise = LazyVecPromise::new_with_initial(int_maker, 6, vec![1, 2])
+            .with_refresh_strategy(RefreshStrategy::Append);
+        assert_eq!(*promise.poll_state(), DataState::UpToDate);
+        assert!(promise.is_stale());
+        sleep(Duration::from_millis(20)).await;
+        // no update on its own
+        assert_eq!(promise.poll_state(), &DataState::UpToDate);
+        assert_eq!(promise.as_slice(), &[1, 2]);
+
+        promise.update();
+        assert_eq!(promise.as_slice(), &[1, 2]);
+        sleep(Duration::from_millis(50)).await;
+        promise.poll_state();
+        assert!(!promise.is_stale());
+        assert_eq!(promise.as_slice(), &[1, 2, 3]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[tokio::test]
+    async fn cache_round_trip() {
+        let string_maker = |tx: Sender<Message<String>>| async move {
+            send_data!("a".to_string(), tx);
+            send_data!("b".to_string(), tx);
+            set_finished!(tx);
+        };
+        let mut promise = LazyVecPromise::new(string_maker, 6);
+        promise.poll_state();
+        sleep(Duration::from_millis(50)).await;
+        promise.poll_state();
+        let json = serde_json::to_string(&promise.serialize_cache()).unwrap();
+
+        let snapshot: CacheSnapshot<Vec<String>> = serde_json::from_str(&json).unwrap();
+        assert_eq!(snapshot.item_count, 2);
+        assert_eq!(snapshot.last_updated, promise.transitions().last_updated_at);
+        let mut restored = LazyVecPromise::new(string_maker, 6);
+        restored.restore_cache(snapshot);
+        assert_eq!(*restored.poll_state(), DataState::UpToDate);
+        assert!(restored.is_stale());
+        assert_eq!(restored.as_slice(), promise.as_slice());
+        assert_eq!(
+            restored.serialize_cache().last_updated,
+            promise.transitions().last_updated_at
+        );
+    }
+
+    #[tokio::test]
+    async fn set_updater() {
+        let mut promise = LazyVecPromise::new(
+            |tx: Sender<Message<i32>>| async move {
+                send_data!(1, tx);
+                set_finished!(tx);
+            },
+            6,
+        );
+        promise.set_updater(|tx: Sender<Message<i32>>| async move {
+            send_data!(2, tx);
+            set_finished!(tx);
+        });
+        promise.poll_state();
+        sleep(Duration::from_millis(50)).await;
+        promise.poll_state();
+        assert_eq!(promise.as_slice(), &[2]);
+        promise.set_updater(|tx: Sender<Message<i32>>| async move {
+            send_data!(3, tx);
+            set_finished!(tx);
+        });
+        // only used by the next update
+        assert_eq!(promise.as_slice(), &[2]);
+        promise.update();
+        sleep(Duration::from_millis(50)).await;
+        promise.poll_state();
+        assert_eq!(promise.as_slice(), &[3]);
+    }
+
+    #[tokio::test]
+    async fn sorting_is_kept_for_new_items() {
+        let updater = |tx: Sender<Message<i32>>| async move {
+            for value in [3, 1, 2] {
+                send_data!(value, tx);
+            }
+            sleep(Duration::from_millis(50)).await;
+            for value in [0, 5, 2] {
+                send_data!(value, tx);
+            }
+            set_finished!(tx);
+        };
+        let mut promise = LazyVecPromise::new(updater, 6);
+        promise.poll_state();
+        sleep(Duration::from_millis(20)).await;
+        promise.poll_state();
+        assert_eq!(promise.as_slice(), &[3, 1, 2]);
+        promise.set_sort(|a, b| a.cmp(b));
+        assert_eq!(promise.as_slice(), &[1, 2, 3]);
+        sleep(Duration::from_millis(100)).await;
+        assert!(promise.poll_state().is_success());
+        assert_eq!(promise.as_slice(), &[0, 1, 2, 2, 3, 5]);
+        // switching the comparator keeps all items
+        promise.set_sort(|a, b| b.cmp(a));
+        assert_eq!(promise.as_slice(), &[5, 3, 2, 2, 1, 0]);
+        promise.clear_sort();
+        assert_eq!(promise.as_slice(), &[3, 1, 2, 0, 5, 2]);
+    }
+
+    #[tokio::test]
+    async fn local_mutations() {
+        let updater = |tx: Sender<Message<i32>>| async move {
+            for value in [3, 1, 4] {
+                send_data!(value, tx);
+            }
+            sleep(Duration::from_millis(50)).await;
+            send_data!(2, tx);
+            set_finished!(tx);
+        };
+        let mut promise = LazyVecPromise::new(updater, 6);
+        promise.poll_state();
+        sleep(Duration::from_millis(20)).await;
+        promise.poll_state();
+        // mutating during the update, the remaining items are added as usual
+        assert_eq!(promise.remove(1), Some(1));
+        assert_eq!(promise.remove(5), None);
+        promise.insert(0, 9);
+        assert_eq!(promise.replace(2, 5), Some(4));
+        sleep(Duration::from_millis(100)).await;
+        assert!(promise.poll_state().is_success());
+        assert_eq!(promise.as_slice(), &[9, 3, 5, 2]);
+
+        promise.set_sort(|a, b| a.cmp(b));
+        assert_eq!(promise.as_slice(), &[2, 3, 5, 9]);
+        promise.insert(0, 4);
+        promise.retain(|value| *value != 3);
+        assert_eq!(promise.replace(0, 7), Some(2));
+        assert_eq!(promise.as_slice(), &[4, 5, 7, 9]);
+        // the replacement took over the arrival of 2, inserted items count as arrived last
+        promise.clear_sort();
+        assert_eq!(promise.as_slice(), &[9, 5, 7, 4]);
+    }
+
+    #[tokio::test]
+    async fn filter_on_receive() {
+        let updater = |tx: Sender<Message<i32>>| async move {
+            for value in 0..10 {
+                send_data!(value, tx);
+            }
+            set_finished!(tx);
+        };
+        let mut promise = LazyVecPromise::new(updater, 20).with_filter(|value| value % 2 == 0);
+        promise.poll_state();
+        sleep(Duration::from_millis(50)).await;
+        assert!(promise.poll_state().is_success());
+        assert_eq!(promise.as_slice(), &[0, 2, 4, 6, 8]);
+        assert_eq!(promise.filtered_out(), 5);
+
+        promise.set_sort(|a, b| b.cmp(a));
+        promise.set_filter(|value| *value > 4);
+        assert_eq!(promise.as_slice(), &[8, 6]);
+        assert_eq!(promise.filtered_out(), 8);
+        // relaxing the filter doesn't bring items back
+        promise.clear_filter();
+        promise.clear_sort();
+        assert_eq!(promise.as_slice(), &[6, 8]);
+
+        promise.update();
+        sleep(Duration::from_millis(50)).await;
+        promise.poll_state();
+        assert_eq!(promise.as_slice().len(), 10);
+        assert_eq!(promise.filtered_out(), 0);
+    }
+
+    #[tokio::test]
+    async fn prepend_newest_first() {
+        let updater = |tx: Sender<Message<i32>>| async move {
+            send_data!(1, tx);
+            send_data!(2, tx);
+            sleep(Duration::from_millis(50)).await;
+            send_data!(3, tx);
+            send_data!(4, tx);
+            set_finished!(tx);
+        };
+        let mut promise = LazyVecPromise::new(updater, 6).with_insert_order(InsertOrder::Prepend);
+        promise.poll_state();
+        sleep(Duration::from_millis(20)).await;
+        promise.poll_state();
+        assert_eq!(promise.as_slice(), &[2, 1]);
+        sleep(Duration::from_millis(100)).await;
+        assert!(promise.poll_state().is_success());
+        assert_eq!(promise.as_slice(), &[4, 3, 2, 1]);
+        promise.set_sort(|a, b| a.cmp(b));
+        promise.clear_sort();
+        assert_eq!(promise.as_slice(), &[4, 3, 2, 1]);
+    }
+
+    #[tokio::test]
+    async fn drop_aborts_updater() {
+        let sent = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = sent.clone();
+        let updater = move |tx: Sender<Message<i32>>| {
+            let sent = counter.clone();
+            async move {
+                for i in 0..10 {
+                    send_data!(i, tx);
+                    sent.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    sleep(Duration::from_millis(10)).await;
+                }
+                set_finished!(tx);
+            }
+        };
+        let mut promise = LazyVecPromise::new(updater, 20);
+        promise.poll_state();
+        sleep(Duration::from_millis(25)).await;
+        drop(promise);
+        let at_drop = sent.load(std::sync::atomic::Ordering::SeqCst);
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(sent.load(std::sync::atomic::Ordering::SeqCst), at_drop);
+        assert!(at_drop < 10);
+    }
+
+    #[tokio::test]
+    async fn refresh_strategies() {
+        let runs = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let updater = move |tx: Sender<Message<(i32, &'static str)>>| {
+            let run = runs.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            async move {
+                let items = if run == 0 {
+                    vec![(1, "a"), (2, "b"), (3, "c")]
+                } else {
+                    // still running at the first poll, also on multi-threaded runtimes
+                    sleep(Duration::from_millis(10)).await;
+                    vec![(2, "B"), (4, "d")]
+                };
+                for item in items {
+                    send_data!(item, tx);
+                }
+                set_finished!(tx);
+            }
+        };
+        let mut promise = LazyVecPromise::new(updater, 6);
+        promise.poll_state();
+        sleep(Duration::from_millis(50)).await;
+        assert!(promise.poll_state().is_success());
+        assert_eq!(promise.last_refresh(), None);
+
+        promise.update_with(RefreshStrategy::merge_by_key(
+            |item: &(i32, &str)| item.0,
+            true,
+        ));
+        // the old data stays visible until the update finished
+        promise.poll_state();
+        assert_eq!(promise.as_slice().len(), 3);
+        sleep(Duration::from_millis(50)).await;
+        assert!(promise.poll_state().is_success());
+        assert_eq!(promise.as_slice(), &[(2, "B"), (4, "d")]);
+        assert_eq!(
+            promise.last_refresh(),
+            Some(MergeCounts {
+                added: 1,
+                updated: 1,
+                removed: 2
+            })
+        );
+
+        promise.update_with(RefreshStrategy::Append);
+        sleep(Duration::from_millis(50)).await;
+        promise.poll_state();
+        assert_eq!(promise.as_slice().len(), 4);
+        assert_eq!(promise.last_refresh().unwrap().added, 2);
+
+        // the default strategy applies again
+        promise.update();
+        sleep(Duration::from_millis(50)).await;
+        promise.poll_state();
+        assert_eq!(promise.as_slice(), &[(2, "B"), (4, "d")]);
+    }
+
+    #[tokio::test]
+    async fn item_counts() {
+        let runs = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(3));
+        let updater = move |tx: Sender<Message<usize>>| {
+            let count = runs.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            async move {
+                for value in 0..count {
+                    send_data!(value, tx);
+                }
+                set_finished!(tx);
+            }
+        };
+        let mut promise = LazyVecPromise::new(updater, 10);
+        promise.poll_state();
+        sleep(Duration::from_millis(50)).await;
+        assert!(promise.poll_state().is_success());
+        assert_eq!(promise.items_this_update(), 3);
+        assert_eq!(promise.items_last_update(), None);
+
+        promise.update();
+        assert_eq!(promise.items_this_update(), 0);
+        assert_eq!(promise.items_last_update(), Some(3));
+        sleep(Duration::from_millis(50)).await;
+        promise.poll_state();
+        promise.as_slice();
+        assert_eq!(promise.items_this_update(), 4);
+        assert_eq!(promise.items_last_update(), Some(3));
+        assert_eq!(promise.total_items_ever(), 7);
+    }
+
+    #[tokio::test]
+    async fn transition_times() {
+        let updater = |tx: Sender<Message<i32>>| async move {
+            sleep(Duration::from_millis(20)).await;
+            send_data!(1, tx);
+            set_finished!(tx);
+        };
+        let mut promise = LazyVecPromise::new(updater, 6);
+        assert_eq!(promise.transitions(), &TransitionTimes::default());
+        promise.poll_state();
+        sleep(Duration::from_millis(50)).await;
+        promise.poll_state();
+        let times = *promise.transitions();
+        let started = times.update_started.unwrap();
+        assert!(times.first_item.unwrap() >= started);
+        assert!(times.duration().unwrap() >= Duration::from_millis(20));
+        assert!(times.settled_at.is_some());
+        assert_eq!(promise.last_updated(), times.settled);
+
+        // a new cycle forgets the times of the last one, except for the last successful update
+        promise.update();
+        assert!(promise.transitions().update_started.unwrap() >= started);
+        assert_eq!(promise.transitions().settled, None);
+        assert_eq!(promise.last_updated(), times.settled);
+    }
+
+    #[tokio::test]
+    async fn min_updating_display() {
+        let updater = |tx: Sender<Message<i32>>| async move {
+            send_data!(1, tx);
+            set_finished!(tx);
+        };
+        let mut promise = LazyVecPromise::builder(updater)
+            .min_updating_display(Duration::from_millis(60))
+            .build();
+        promise.poll_state();
+        sleep(Duration::from_millis(20)).await;
+        assert!(promise.poll_state().is_updating());
+        assert_eq!(promise.as_slice(), [1]);
+        assert_eq!(promise.settled_for(), None);
+        sleep(Duration::from_millis(60)).await;
+        assert_eq!(*promise.poll_state(), DataState::UpToDate);
+        assert!(promise.settled_for().unwrap() < Duration::from_millis(40));
+
+        // a restart reports the new update right away
+        promise.update();
+        assert!(promise.state().is_updating());
+        assert_eq!(promise.settled_for(), None);
+    }
+
+    #[tokio::test]
+    async fn min_update_interval() {
+        let updater = |tx: Sender<Message<i32>>| async move {
+            send_data!(1, tx);
+            set_finished!(tx);
+        };
+        let mut ignoring = LazyVecPromise::new(updater, 6)
+            .with_min_update_interval(Duration::from_millis(100), UpdateLimit::Ignore);
+        assert_eq!(ignoring.request_update(), UpdateOutcome::Started);
+        assert_eq!(ignoring.request_update(), UpdateOutcome::AlreadyUpdating);
+        sleep(Duration::from_millis(20)).await;
+        assert_eq!(*ignoring.poll_state(), DataState::UpToDate);
+        assert_eq!(ignoring.request_update(), UpdateOutcome::Ignored);
+        assert_eq!(*ignoring.poll_state(), DataState::UpToDate);
+        ignoring.update_now();
+        assert!(ignoring.state().is_updating());
+
+        let mut deferring = LazyVecPromise::new(updater, 6)
+            .with_min_update_interval(Duration::from_millis(100), UpdateLimit::Defer);
+        deferring.poll_state();
+        sleep(Duration::from_millis(20)).await;
+        deferring.poll_state();
+        let UpdateOutcome::Deferred(delay) = deferring.request_update() else {
+            panic!("update not deferred");
+        };
+        assert!(delay <= Duration::from_millis(80));
+        sleep(Duration::from_millis(40)).await;
+        assert!(deferring.poll_state().is_updating());
+        assert!(deferring.as_slice().is_empty());
+        sleep(Duration::from_millis(80)).await;
+        assert_eq!(*deferring.poll_state(), DataState::UpToDate);
+        assert_eq!(deferring.as_slice(), [1]);
+    }
+
+    #[tokio::test]
+    async fn poll_state_for_budget() {
+        let updater = |tx: Sender<Message<i32>>| async move {
+            for i in 0..100 {
+                send_data!(i, tx);
+            }
+            set_finished!(tx);
+        };
+        let mut promise = LazyVecPromise::new(updater, 200).with_filter(|_| {
+            std::thread::sleep(Duration::from_millis(1));
+            true
+        });
+        promise.poll_state();
+        sleep(Duration::from_millis(20)).await;
+        assert!(promise
+            .poll_state_for(Duration::from_millis(5))
+            .is_updating());
+        assert_eq!(promise.as_slice().len(), BUDGET_CHECK_ITEMS);
+        assert!(promise.has_backlog());
+        promise.changed().await;
+
+        assert_eq!(*promise.poll_state(), DataState::UpToDate);
+        assert_eq!(promise.as_slice(), (0..100).collect::<Vec<_>>());
+        assert!(!promise.has_backlog());
+    }
+
+    #[tokio::test]
+    async fn concurrent_sources() {
+        async fn source(delay: u64, fail: bool, tx: Sender<Message<u64>>) {
+            sleep(Duration::from_millis(delay)).await;
+            if fail {
+                set_error!(BoxedSendError::msg("backend down"), tx);
+                return;
+            }
+            send_data!(delay, tx);
+            set_finished!(tx);
+        }
+        let updater = |tx: Sender<Message<u64>>| async move {
+            tokio::join!(
+                source(10, false, tx.clone()),
+                source(40, false, tx.clone()),
+                source(80, false, tx)
+            );
+        };
+        let mut promise = LazyVecPromise::builder(updater).sources(3).build();
+        promise.poll_state();
+        assert_eq!(promise.active_sources(), 3);
+        sleep(Duration::from_millis(60)).await;
+        assert!(promise.poll_state().is_updating());
+        assert_eq!(promise.active_sources(), 1);
+        assert_eq!(promise.as_slice(), [10, 40]);
+        sleep(Duration::from_millis(60)).await;
+        assert_eq!(*promise.poll_state(), DataState::UpToDate);
+        assert_eq!(promise.active_sources(), 0);
+
+        let failing = |tx: Sender<Message<u64>>| async move {
+            tokio::join!(source(10, true, tx.clone()), source(30, false, tx));
+        };
+        let mut promise = LazyVecPromise::new(failing, 6).with_sources(2);
+        promise.poll_state();
+        sleep(Duration::from_millis(60)).await;
+        assert!(promise.poll_state().is_error());
+        assert!(promise.as_slice().is_empty());
+    }
+
+    #[tokio::test]
+    async fn retry_tracks_attempts() {
+        let calls = std::sync::Arc::new(std::sync::atomic::AtomicU32::new(0));
+        let counter = calls.clone();
+        let updater = move |tx: Sender<Message<i32>>| {
+            let call = counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            async move {
+                if call < 2 {
+                    set_error!(BoxedSendError::msg(format!("attempt {call} failed")), tx);
+                    return;
+                }
+                send_data!(1, tx);
+                set_finished!(tx);
+            }
+        };
+        let mut promise = LazyVecPromise::new(updater, 6);
+        assert!(!promise.retry());
+        promise.poll_state();
+        sleep(Duration::from_millis(20)).await;
+        assert!(promise.poll_state().is_error());
+        assert_eq!(promise.failed_attempts(), 1);
+
+        assert!(promise.retry());
+        assert!(promise.state().is_updating());
+        let last_error = promise.last_error().map(ToString::to_string);
+        assert_eq!(last_error.as_deref(), Some("attempt 0 failed"));
+        sleep(Duration::from_millis(20)).await;
+        promise.poll_state();
+        assert_eq!(promise.failed_attempts(), 2);
+        assert!(promise.retry());
+        sleep(Duration::from_millis(20)).await;
+        assert_eq!(*promise.poll_state(), DataState::UpToDate);
+        assert_eq!(promise.failed_attempts(), 0);
+        assert!(promise.last_error().is_none());
+    }
+
+    #[tokio::test]
+    async fn transition_log() {
+        let calls = std::sync::Arc::new(std::sync::atomic::AtomicU32::new(0));
+        let counter = calls.clone();
+        let updater = move |tx: Sender<Message<i32>>| {
+            let call = counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            async move {
+                if call == 0 {
+                    set_error!(BoxedSendError::msg("offline"), tx);
+                    return;
+                }
+                for i in 0..3 {
+                    send_data!(i, tx);
+                }
+                set_finished!(tx);
+            }
+        };
+        let mut unlogged = LazyVecPromise::new(updater.clone(), 6);
+        unlogged.poll_state();
+        assert!(unlogged.transition_log().is_empty());
+
+        calls.store(0, std::sync::atomic::Ordering::SeqCst);
+        let mut promise = LazyVecPromise::new(updater, 6).with_transition_log();
+        promise.poll_state();
+        sleep(Duration::from_millis(20)).await;
+        promise.poll_state();
+        assert!(promise.retry());
+        sleep(Duration::from_millis(20)).await;
+        promise.poll_state();
+        promise.take_value();
+        let log: Vec<_> = promise
+            .transition_log()
+            .iter()
+            .map(|transition| (transition.cause, transition.state.as_str()))
+            .collect();
+        assert_eq!(
+            log,
+            [
+                (TransitionCause::Update, "updating (0%)"),
+                (TransitionCause::Updater, "error: offline"),
+                (TransitionCause::Update, "updating (0%)"),
+                (TransitionCause::Updater, "up to date"),
+                (TransitionCause::Taken, "uninitialized"),
+            ]
+        );
+        assert!(promise.transition_log()[0].to_string().starts_with('+'));
+    }
+
+    #[tokio::test]
+    async fn on_new_items_once_per_poll() {
+        let updater = |tx: Sender<Message<i32>>| async move {
+            for batch in [[5, 1], [3, 0]] {
+                for i in batch {
+                    send_data!(i, tx);
+                }
+                sleep(Duration::from_millis(20)).await;
+            }
+            set_finished!(tx);
+        };
+        let changes = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let hook_changes = changes.clone();
+        let mut promise = LazyVecPromise::new(updater, 6).with_on_new_items(move |change| {
+            let record = match &change {
+                ItemsChange::Cleared => None,
+                ItemsChange::Applied { indices, .. } => Some((
+                    indices.to_vec(),
+                    change.items().copied().collect::<Vec<_>>(),
+                )),
+            };
+            hook_changes.lock().unwrap().push(record);
+        });
+        promise.set_sort(|a, b| a.cmp(b));
+        promise.poll_state();
+        sleep(Duration::from_millis(10)).await;
+        promise.poll_state();
+        promise.poll_state();
+        sleep(Duration::from_millis(20)).await;
+        promise.poll_state();
+        sleep(Duration::from_millis(20)).await;
+        promise.poll_state();
+        assert_eq!(promise.as_slice(), [0, 1, 3, 5]);
+        assert_eq!(
+            *changes.lock().unwrap(),
+            [
+                None,
+                Some((vec![0, 1], vec![1, 5])),
+                Some((vec![0, 2], vec![0, 3])),
+            ]
+        );
+
+        changes.lock().unwrap().clear();
+        promise.update();
+        assert_eq!(*changes.lock().unwrap(), [None]);
+    }
+
+    #[tokio::test]
+    async fn on_new_items_after_length_change() {
+        let updater = |tx: Sender<Message<i32>>| async move {
+            for batch in [[5, 1], [3, 0]] {
+                for i in batch {
+                    send_data!(i, tx);
+                }
+                sleep(Duration::from_millis(20)).await;
+            }
+            set_finished!(tx);
+        };
+        let changes = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let hook_changes = changes.clone();
+        let mut promise = LazyVecPromise::new(updater, 6).with_on_new_items(move |change| {
+            let items = change.items().copied().collect::<Vec<_>>();
+            hook_changes.lock().unwrap().push(items);
+        });
+        promise.set_sort(|a, b| a.cmp(b));
+        promise.poll_state();
+        sleep(Duration::from_millis(10)).await;
+        promise.poll_state();
+        assert_eq!(promise.as_slice(), [1, 5]);
+        promise.get_value_mut().unwrap().pop();
+        sleep(Duration::from_millis(20)).await;
+        promise.poll_state();
+        sleep(Duration::from_millis(20)).await;
+        promise.poll_state();
+        assert_eq!(promise.as_slice(), [0, 1, 3]);
+        assert_eq!(*changes.lock().unwrap(), [vec![], vec![1, 5], vec![0, 3]]);
+    }
+
+    #[tokio::test]
+    async fn running_aggregate() {
+        let updater = |tx: Sender<Message<i32>>| async move {
+            for i in 1..=4 {
+                send_data!(i, tx);
+                sleep(Duration::from_millis(10)).await;
+            }
+            set_finished!(tx);
+        };
+        let mut promise =
+            LazyVecPromise::new(updater, 6).with_aggregate((0, i32::MIN), |(sum, max), item| {
+                *sum += item;
+                *max = (*max).max(*item);
+            });
+        assert_eq!(promise.aggregate::<(i32, i32)>(), Some(&(0, i32::MIN)));
+        assert_eq!(promise.aggregate::<i32>(), None);
+        promise.poll_state();
+        sleep(Duration::from_millis(15)).await;
+        promise.poll_state();
+        assert_eq!(promise.aggregate::<(i32, i32)>(), Some(&(3, 2)));
+        sleep(Duration::from_millis(40)).await;
+        promise.poll_state();
+        assert_eq!(promise.aggregate::<(i32, i32)>(), Some(&(10, 4)));
+
+        promise.remove(3);
+        assert_eq!(promise.aggregate::<(i32, i32)>(), Some(&(6, 3)));
+        promise.as_slice_mut()[0] = 10;
+        promise.poll_state();
+        assert_eq!(promise.aggregate::<(i32, i32)>(), Some(&(15, 10)));
+
+        promise.update();
+        assert_eq!(promise.aggregate::<(i32, i32)>(), Some(&(0, i32::MIN)));
+    }
+
+    #[tokio::test]
+    async fn arrival_times_follow_items() {
+        let updater = |tx: Sender<Message<i32>>| async move {
+            for i in [3, 1, 2] {
+                send_data!(i, tx);
+                sleep(Duration::from_millis(20)).await;
+            }
+            set_finished!(tx);
+        };
+        let mut promise = LazyVecPromise::new(updater, 6).with_arrival_times();
+        promise.poll_state();
+        for _ in 0..6 {
+            sleep(Duration::from_millis(10)).await;
+            promise.poll_state();
+        }
+        sleep(Duration::from_millis(30)).await;
+        assert!(promise.poll_state().is_success());
+        let arrived: Vec<_> = (0..3).map(|i| promise.arrival_time(i).unwrap()).collect();
+        assert!(arrived[0] < arrived[1] && arrived[1] < arrived[2]);
+
+        promise.set_sort(i32::cmp);
+        assert_eq!(promise.as_slice(), &[1, 2, 3]);
+        let sorted: Vec<_> = promise.iter_with_arrival().map(|(_, time)| time).collect();
+        assert_eq!(sorted, [arrived[1], arrived[2], arrived[0]]);
+        promise.remove(0);
+        promise.replace(0, 4);
+        // the replacement moved behind the 3 and kept the time of the 2
+        assert_eq!(promise.as_slice(), &[3, 4]);
+        assert_eq!(promise.arrival_time(1), Some(arrived[2]));
+        promise.clear_sort();
+        assert_eq!(promise.arrival_time(0), Some(arrived[0]));
+        promise.retain(|item| *item == 4);
+        assert_eq!(promise.arrival_time(0), Some(arrived[2]));
+
+        promise.take_value();
+        assert_eq!(promise.iter_with_arrival().count(), 0);
+        assert!(LazyVecPromise::new(updater, 6).arrival_time(0).is_none());
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Export {
+        Pause,
+        Resume,
+    }
+
+    #[tokio::test]
+    async fn control_channel_per_update() {
+        let updater = |tx: Sender<Message<i32>>, mut control: UnboundedReceiver<Export>| async move {
+            for i in 0..4 {
+                if let Ok(Export::Pause) = control.try_recv() {
+                    while control.recv().await != Some(Export::Resume) {}
+                }
+                send_data!(i, tx);
+                sleep(Duration::from_millis(10)).await;
+            }
+            set_finished!(tx);
+        };
+        let mut promise = LazyVecPromise::new_with_control(updater, 6);
+        assert!(promise.send_control(Export::Pause).is_err());
+        promise.poll_state();
+        promise.send_control(Export::Pause).unwrap();
+        sleep(Duration::from_millis(50)).await;
+        assert!(promise.poll_state().is_updating());
+        assert!(promise.as_slice().len() <= 1);
+        // not the control type of the promise
+        assert_eq!(promise.send_control(1).unwrap_err().0, 1);
+
+        promise.send_control(Export::Resume).unwrap();
+        sleep(Duration::from_millis(80)).await;
+        assert_eq!(*promise.poll_state(), DataState::UpToDate);
+        assert_eq!(promise.as_slice(), &[0, 1, 2, 3]);
+        assert!(promise.send_control(Export::Pause).is_err());
+
+        // the next update gets a new channel
+        promise.update();
+        promise.send_control(Export::Pause).unwrap();
+        sleep(Duration::from_millis(50)).await;
+        assert!(promise.poll_state().is_updating());
+    }
+
+    #[tokio::test]
+    async fn changed_without_polling() {
+        let updater = |tx: Sender<Message<i32>>| async move {
+            for i in 0..3 {
+                sleep(Duration::from_millis(10)).await;
+                send_data!(i, tx);
+            }
+            set_finished!(tx);
+        };
+        let mut promise = LazyVecPromise::new(updater, 6);
+        promise.poll_state();
+        let mut wakeups = 0;
+        while !promise.poll_state().is_success() {
+            // cancelled half of the time, received messages must not get lost
+            tokio::select! {
+                _ = promise.changed() => wakeups += 1,
+                _ = sleep(Duration::from_millis(5)) => {}
+            }
+        }
+        assert_eq!(promise.as_slice(), &[0, 1, 2]);
+        // polling may apply a message before `changed` sees it, so not every message wakes up
+        assert!(wakeups >= 1);
+    }
+
+    #[tokio::test]
+    async fn changed_on_panic() {
+        let updater = |_: Sender<Message<i32>>| async move {
+            sleep(Duration::from_millis(10)).await;
+            panic!("boom");
+        };
+        let mut promise = LazyVecPromise::new(updater, 6);
+        promise.poll_state();
+        tokio::time::timeout(Duration::from_secs(1), promise.changed())
+            .await
+            .unwrap();
+        assert!(promise.poll_state().is_error());
     }
 }
@@ -0,0 +1,387 @@
+use crate::runtime::{spawn, Task};
+use crate::{
+    drain_into, report_error, updater_task_error, BoxedSendError, DataState, ErrorHook, Message,
+    Progress, Promise,
+};
+use std::collections::BTreeMap;
+use std::fmt::{self, Debug, Formatter};
+use std::future::Future;
+use std::ops::Range;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::mpsc::{channel, Receiver, Sender};
+
+type BoxedRangeFactory<T> =
+    Box<dyn Fn(WindowRequest, Sender<Message<T>>) -> Pin<Box<dyn Future<Output = ()> + Send>>>;
+
+const UNKNOWN_LEN: usize = usize::MAX;
+
+/// The range a [`LazyWindowPromise`] updater should fetch. The updater sends the items of the range in order with
+/// [`send_data!`](crate::send_data), starting at `range.start`, and finishes with
+/// [`set_finished!`](crate::set_finished) like any lazy updater.
+pub struct WindowRequest {
+    /// The indices to fetch
+    pub range: Range<usize>,
+    total_len: Arc<AtomicUsize>,
+}
+
+impl WindowRequest {
+    /// Report the total number of items once known, later requests are clipped to it
+    pub fn set_total_len(&self, len: usize) {
+        self.total_len.store(len, Ordering::Relaxed);
+    }
+}
+
+struct Fetch<T: Debug> {
+    start: usize,
+    /// index of the next item to arrive
+    next: usize,
+    end: usize,
+    rx: Receiver<Message<T>>,
+    task: Task,
+}
+
+/// # A lazy promise fetching only requested ranges, e.g. the visible rows of a virtual table
+/// Nothing is fetched until [`Self::request_range`] is called. Each request spawns the updater for the indices
+/// that are neither loaded nor pending yet, so overlapping requests are merged. The items are cached sparsely,
+/// read them with [`Self::get`]. Fetches which scrolled out of view can be stopped with
+/// [`Self::cancel_pending_outside`]. The state is [`DataState::Updating`] while any range is pending, with the
+/// progress of all pending ranges, and [`DataState::UpToDate`] once all requested ranges arrived.
+/// ```rust, no_run
+/// use tokio::sync::mpsc::Sender;
+/// use lazy_async_promise::{DataState, LazyWindowPromise, Message, Promise, WindowRequest, api_macros::*};
+/// let updater = |request: WindowRequest, tx: Sender<Message<String>>| async move {
+///     request.set_total_len(200_000);
+///     for row in request.range {
+///         send_data!(format!("row {row}"), tx);
+///     }
+///     set_finished!(tx);
+/// };
+/// let mut rows = LazyWindowPromise::new(updater, 100);
+/// // each frame:
+/// let visible = 1000..1040;
+/// rows.cancel_pending_outside(visible.clone());
+/// rows.request_range(visible.clone());
+/// rows.poll_state();
+/// for row in visible {
+///     let text = rows.get(row).map(String::as_str).unwrap_or("loading...");
+/// }
+/// ```
+pub struct LazyWindowPromise<T: Debug> {
+    items: BTreeMap<usize, T>,
+    fetches: Vec<Fetch<T>>,
+    state: DataState,
+    total_len: Arc<AtomicUsize>,
+    buffer_size: usize,
+    updater: BoxedRangeFactory<T>,
+    on_error: Option<ErrorHook>,
+    batch: Vec<Message<T>>,
+}
+
+impl<T: Debug + Send + 'static> LazyWindowPromise<T> {
+    /// Creates the promise from an updater getting the requested range, and the channel buffer size per fetch
+    pub fn new<
+        U: Fn(WindowRequest, Sender<Message<T>>) -> Fut + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    >(
+        updater: U,
+        buffer_size: usize,
+    ) -> Self {
+        Self {
+            items: BTreeMap::new(),
+            fetches: Vec::new(),
+            state: DataState::Uninitialized,
+            total_len: Arc::new(AtomicUsize::new(UNKNOWN_LEN)),
+            buffer_size,
+            updater: Box::new(move |request, tx| Box::pin(updater(request, tx))),
+            on_error: None,
+            batch: Vec::new(),
+        }
+    }
+
+    /// Set a hook which is called once with the error for every fetch that fails
+    pub fn with_on_error(mut self, hook: impl FnMut(&BoxedSendError) + Send + 'static) -> Self {
+        self.on_error = Some(Box::new(hook));
+        self
+    }
+
+    /// Fetch the indices of the range which are neither loaded nor pending. The range is clipped to the total length
+    /// if the updater reported it. After an error, requesting a range starts fetching again.
+    pub fn request_range(&mut self, range: Range<usize>) {
+        let end = self.total_len().map_or(range.end, |len| range.end.min(len));
+        let mut gap_start = None;
+        for index in range.start..end {
+            let missing = !self.items.contains_key(&index) && !self.is_pending(index);
+            match (missing, gap_start) {
+                (true, None) => gap_start = Some(index),
+                (false, Some(start)) => {
+                    self.spawn_fetch(start..index);
+                    gap_start = None;
+                }
+                _ => {}
+            }
+        }
+        if let Some(start) = gap_start {
+            self.spawn_fetch(start..end);
+        }
+    }
+
+    /// Abort the pending fetches which don't overlap `keep`, e.g. rows scrolled out of view.
+    /// Items they already delivered stay loaded, the rest is fetched again when requested.
+    pub fn cancel_pending_outside(&mut self, keep: Range<usize>) {
+        let (kept, superseded) = self
+            .fetches
+            .drain(..)
+            .partition(|fetch| fetch.next < keep.end && keep.start < fetch.end);
+        self.fetches = kept;
+        for fetch in superseded {
+            fetch.task.abort();
+        }
+        self.update_state();
+    }
+
+    /// The item at `index` if it was loaded
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.items.get(&index)
+    }
+
+    /// Is the item at `index` being fetched?
+    pub fn is_pending(&self, index: usize) -> bool {
+        self.fetches
+            .iter()
+            .any(|fetch| (fetch.next..fetch.end).contains(&index))
+    }
+
+    /// The loaded indices as sorted, non-overlapping ranges
+    pub fn loaded_ranges(&self) -> Vec<Range<usize>> {
+        let mut ranges: Vec<Range<usize>> = Vec::new();
+        for &index in self.items.keys() {
+            match ranges.last_mut() {
+                Some(last) if last.end == index => last.end += 1,
+                _ => ranges.push(index..index + 1),
+            }
+        }
+        ranges
+    }
+
+    /// The indices still to arrive from running fetches, sorted by start
+    pub fn pending_ranges(&self) -> Vec<Range<usize>> {
+        let mut ranges: Vec<_> = self
+            .fetches
+            .iter()
+            .map(|fetch| fetch.next..fetch.end)
+            .filter(|range| !range.is_empty())
+            .collect();
+        ranges.sort_by_key(|range| range.start);
+        ranges
+    }
+
+    /// The total number of items, once reported by the updater via [`WindowRequest::set_total_len`]
+    pub fn total_len(&self) -> Option<usize> {
+        let len = self.total_len.load(Ordering::Relaxed);
+        (len != UNKNOWN_LEN).then_some(len)
+    }
+
+    fn spawn_fetch(&mut self, range: Range<usize>) {
+        let (tx, rx) = channel(self.buffer_size);
+        let request = WindowRequest {
+            range: range.clone(),
+            total_len: self.total_len.clone(),
+        };
+        let task = spawn((self.updater)(request, tx));
+        self.fetches.push(Fetch {
+            start: range.start,
+            next: range.start,
+            end: range.end,
+            rx,
+            task,
+        });
+        // a new request recovers from a previous error
+        if self.state.is_error() {
+            self.state = DataState::Updating(Progress::default());
+        }
+        self.update_state();
+    }
+
+    fn update_state(&mut self) {
+        if self.state.is_error() {
+            return;
+        }
+        let (done, requested) = self
+            .fetches
+            .iter()
+            .fold((0, 0), |(done, requested), fetch| {
+                (
+                    done + fetch.next - fetch.start,
+                    requested + fetch.end - fetch.start,
+                )
+            });
+        self.state = if requested > 0 {
+            DataState::Updating(Progress::from_fraction(done as f64, requested as f64))
+        } else if self.items.is_empty() && self.state == DataState::Uninitialized {
+            DataState::Uninitialized
+        } else {
+            DataState::UpToDate
+        };
+    }
+}
+
+impl<T: Debug> Debug for LazyWindowPromise<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LazyWindowPromise")
+            .field("state", &self.state.tag())
+            .field("loaded", &self.items.len())
+            .field("pending_fetches", &self.fetches.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: Debug + Send + 'static> Promise for LazyWindowPromise<T> {
+    /// Applies the arrived items. The first failing fetch sets [`DataState::Error`], the other fetches continue.
+    fn poll_state(&mut self) -> &DataState {
+        let items = &mut self.items;
+        let batch = &mut self.batch;
+        let mut error = None;
+        self.fetches.retain_mut(|fetch| {
+            // checked before draining, so all messages of a finished task are applied
+            let task_result = fetch.task.try_join();
+            drain_into(&mut fetch.rx, batch);
+            let mut finished = false;
+            for msg in batch.drain(..) {
+                match msg {
+                    Message::NewData(item) | Message::NewDataWithProgress(item, _)
+                        if fetch.next < fetch.end =>
+                    {
+                        items.insert(fetch.next, item);
+                        fetch.next += 1;
+                    }
+                    Message::NewData(_) | Message::NewDataWithProgress(..) => {}
+                    Message::StateChange(DataState::UpToDate) => finished = true,
+                    Message::StateChange(DataState::Error(fetch_error)) => {
+                        error.get_or_insert(fetch_error);
+                        finished = true;
+                    }
+                    Message::StateChange(_) => {}
+                }
+            }
+            if let (false, Some(result)) = (finished, task_result) {
+                error.get_or_insert(updater_task_error(result));
+                finished = true;
+            }
+            !finished
+        });
+        if let (false, Some(error)) = (self.state.is_error(), error) {
+            report_error(&mut self.on_error, &error);
+            self.state = DataState::Error(error);
+        }
+        self.update_state();
+        &self.state
+    }
+
+    fn state(&self) -> &DataState {
+        &self.state
+    }
+
+    /// Drops the cache and aborts all fetches, the next [`Self::request_range`] fetches again
+    fn update(&mut self) {
+        for fetch in self.fetches.drain(..) {
+            fetch.task.abort();
+        }
+        self.items.clear();
+        self.total_len.store(UNKNOWN_LEN, Ordering::Relaxed);
+        self.state = DataState::Uninitialized;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use tokio::sync::mpsc::Sender;
+
+    use super::{LazyWindowPromise, WindowRequest};
+    use crate::api_macros::*;
+    use crate::runtime::sleep;
+    use crate::{BoxedSendError, DataState, Message, Promise};
+
+    #[tokio::test]
+    async fn merges_overlapping_requests() {
+        let fetched = Arc::new(Mutex::new(Vec::new()));
+        let fetched_updater = fetched.clone();
+        let updater = move |request: WindowRequest, tx: Sender<Message<usize>>| {
+            fetched_updater.lock().unwrap().push(request.range.clone());
+            async move {
+                request.set_total_len(100);
+                for index in request.range {
+                    send_data!(index * 10, tx);
+                }
+                set_finished!(tx);
+            }
+        };
+        let mut promise = LazyWindowPromise::new(updater, 100);
+        assert_eq!(promise.poll_state(), &DataState::Uninitialized);
+        promise.request_range(0..10);
+        promise.request_range(5..15);
+        assert!(promise.state().is_updating());
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(promise.poll_state(), &DataState::UpToDate);
+        assert_eq!(promise.get(12), Some(&120));
+        assert_eq!(promise.get(15), None);
+        assert_eq!(promise.loaded_ranges(), vec![0..15]);
+        assert_eq!(promise.total_len(), Some(100));
+
+        // clipped to the reported length, loaded items are not fetched again
+        promise.request_range(90..200);
+        promise.request_range(3..8);
+        sleep(Duration::from_millis(50)).await;
+        promise.poll_state();
+        assert_eq!(promise.loaded_ranges(), vec![0..15, 90..100]);
+        assert_eq!(*fetched.lock().unwrap(), vec![0..10, 10..15, 90..100]);
+    }
+
+    #[tokio::test]
+    async fn cancels_superseded_fetches() {
+        let updater = |request: WindowRequest, tx: Sender<Message<usize>>| async move {
+            for index in request.range {
+                if index >= 1000 {
+                    sleep(Duration::from_secs(10)).await;
+                }
+                send_data!(index, tx);
+            }
+            set_finished!(tx);
+        };
+        let mut promise = LazyWindowPromise::new(updater, 100);
+        promise.request_range(0..10);
+        promise.request_range(1000..1010);
+        sleep(Duration::from_millis(50)).await;
+        assert!(promise.poll_state().is_updating());
+        assert_eq!(promise.pending_ranges(), vec![1000..1010]);
+        promise.cancel_pending_outside(0..10);
+        assert!(promise.pending_ranges().is_empty());
+        assert_eq!(promise.poll_state(), &DataState::UpToDate);
+        assert!(!promise.is_pending(1000));
+    }
+
+    #[tokio::test]
+    async fn failed_fetch_sets_error() {
+        let updater = |request: WindowRequest, tx: Sender<Message<usize>>| async move {
+            if request.range.start > 0 {
+                set_error!(BoxedSendError::msg("offline"), tx);
+                return;
+            }
+            send_data!(0, tx);
+            set_finished!(tx);
+        };
+        let mut promise = LazyWindowPromise::new(updater, 100).with_on_error(|_| {});
+        promise.request_range(5..6);
+        sleep(Duration::from_millis(50)).await;
+        assert!(promise.poll_state().is_error());
+        // requesting again recovers
+        promise.request_range(0..1);
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(promise.poll_state(), &DataState::UpToDate);
+        assert_eq!(promise.get(0), Some(&0));
+    }
+}
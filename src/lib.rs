@@ -6,6 +6,7 @@
 //! - [`ProgressTrackedImValProm`]: A progress/status emitting enhanced wrapper for [`ImmediateValuePromise`]
 //! - [`LazyVecPromise`]: A lazily evaluated, partially readable and async-enabled vector-backed promise
 //! - [`LazyValuePromise`]: A lazily evaluated and async-enabled single value promise
+//! - [`ThreadPromise`]: A single value promise computed on a plain thread, usable without any async runtime
 //!
 //! See these items for their respective documentation.
 //! ## What to use
@@ -14,6 +15,27 @@
 //! - If you need status update support for that, use [`ProgressTrackedImValProm`]
 //! - You want several items of the same kind / streamed? Use: [`LazyVecPromise`]
 //! - You want one item when ready and need lazy evaluation or have intermediate results? Use: [`LazyValuePromise`]
+//! - No async runtime at all? Use [`ThreadPromise`] and [`ProgressTrackedThreadPromise`]
+//! - Both progress tracked promises are a [`ProgressTracked`] wrapper, which works for any [`TrackedPromise`]
+//! ## Features
+//! - `tokio` (default): all the async promises. Without it, only the runtime-free parts like [`ThreadPromise`] remain
+//! - `anyhow`: conversions between [`BoxedSendError`] and `anyhow::Error` as well as `ImmediateValuePromise::new_anyhow`
+//! - `egui`: rendering helpers for egui, like `Progress::to_progress_bar`, `ProgressTrackedImValProm::show_progress`
+//!   and `PromiseUiExt::promise`
+//! - `async-std`: spawn futures on async-std instead of tokio. The updaters still use the runtime agnostic
+//!   `tokio::sync` channels, so the API stays the same
+//! - `rayon`: `ImmediateValuePromise::new_rayon` and `ProgressTrackedImValProm::new_rayon` for data-parallel jobs
+//!   running on the rayon global pool
+//! - `indicatif`: `ProgressTrackedImValProm::attach_indicatif` and `drive_with_indicatif` for progress bars in CLI tools
+//! - `tokio-util`: cancellation of promises with a `tokio_util::sync::CancellationToken`, see
+//!   `ImmediateValuePromise::new_with_token` and `LazyVecPromise::with_cancellation`
+//! - `futures`: `state_stream` methods yielding the [`StateChange`]s of a promise as `futures_core::Stream`, and
+//!   `SubscriptionPromise` following a never-ending stream like server-sent events, reconnecting with backoff
+//! - `log`: debug records of lazy promise updates - triggers, first item, progress milestones, errors and completion.
+//!   Name promises with `with_name` to tell their records apart. Debug builds also warn about [`ImmediateValuePromise`]s
+//!   dropped without ever being polled or polled repeatedly after their value was taken, naming where they were created
+//! - `bytes`: `BytesPromise` collecting a stream of byte chunks, e.g. a download, with progress and throughput
+//! - `poll-promise`: conversions between `poll_promise::Promise` and [`ImmediateValuePromise`] for incremental migrations
 #![deny(missing_docs)]
 #![deny(unused_qualifications)]
 #![deny(deprecated)]
@@ -23,41 +45,213 @@
 
 extern crate core;
 
+use std::any::Any;
+use std::backtrace::Backtrace;
 use std::error::Error;
-use std::fmt::Debug;
+use std::fmt::{self, Debug, Display, Formatter};
+#[cfg(feature = "tokio")]
 use std::future::Future;
+use std::iter;
 use std::ops::Deref;
-use std::pin::Pin;
+#[cfg(feature = "tokio")]
+use std::pin::{pin, Pin};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+#[cfg(feature = "tokio")]
+use std::task::{Context, Waker};
 
-use tokio::sync::mpsc::Sender;
+#[cfg(feature = "tokio")]
+use tokio::sync::mpsc::error::TrySendError;
+#[cfg(feature = "tokio")]
+use tokio::sync::mpsc::{Receiver, Sender};
 
+#[cfg(feature = "tokio")]
+use crate::dropcount::record_dropped;
+#[cfg(feature = "tokio")]
+use crate::runtime::TaskFailure;
+
+#[cfg(feature = "tokio")]
 #[doc(inline)]
 pub use immediatevalue::ImmediateValuePromise;
 pub use immediatevalue::ImmediateValueState;
+#[cfg(feature = "tokio")]
+pub use immediatevalue::SettleHook;
+pub use immediatevalue::StateTag;
+#[cfg(feature = "tokio")]
+pub use immediatevaluebuilder::{ImmediateValuePromiseBuilder, OneShot, Retrying};
+#[cfg(feature = "tokio")]
+pub use immediatevalueprogress::BlockingStatusSender;
+#[cfg(feature = "tokio")]
 #[doc(inline)]
 pub use immediatevalueprogress::ProgressTrackedImValProm;
 pub use immediatevalueprogress::Status;
 pub use immediatevalueprogress::StringStatus;
+pub use immediatevalueprogress::{ProgressTracked, TrackedPromise, BUDGET_CHECK_ITEMS};
 
+#[cfg(feature = "bytes")]
+pub use bytespromise::BytesPromise;
+#[cfg(feature = "tokio-util")]
+pub use cancellation::Cancelled;
+#[cfg(feature = "egui")]
+pub use eguiwidgets::{EguiPromise, PromiseStore, PromiseUiExt};
+#[cfg(feature = "tokio")]
+pub use intopromise::{IntoPromise, IntoTrackedPromise};
+#[cfg(feature = "tokio")]
+pub use lazybuilder::{LazyPromiseBuilder, DEFAULT_BUFFER_SIZE};
+#[cfg(feature = "tokio")]
 #[doc(inline)]
 pub use lazyvalue::LazyValuePromise;
+#[cfg(feature = "serde")]
+pub use lazyvec::CacheSnapshot;
+#[cfg(feature = "tokio")]
+#[doc(inline)]
+pub use lazyvec::{InsertOrder, ItemsChange, LazyVecPromise, MergeCounts, RefreshStrategy};
+#[cfg(feature = "tokio")]
+pub use lazywindow::{LazyWindowPromise, WindowRequest};
+pub use lockedaccess::LockedCacheAccess;
+#[cfg(feature = "test-util")]
+pub use mockpromise::MockPromise;
+pub use promiseext::PromiseExt;
+#[cfg(feature = "tokio")]
+pub use ratelimit::{UpdateLimit, UpdateOutcome};
+#[cfg(feature = "rayon")]
+#[doc(inline)]
+pub use rayonpromise::ParallelProgress;
+pub use retry::RetryPolicy;
+#[cfg(feature = "tokio")]
+pub use runtime::TimedOut;
+pub use snapshot::{DataStateSnapshot, PollResult, SnapshotPoll, StateSnapshot, UnifiedPoll};
+#[cfg(feature = "tokio")]
 #[doc(inline)]
-pub use lazyvec::LazyVecPromise;
+pub use statechange::StateChange;
+#[cfg(feature = "futures")]
+pub use subscriptionpromise::{SubscriptionPromise, SubscriptionState};
+#[cfg(feature = "tokio")]
+pub use supersedingpromise::SupersedingPromise;
+pub use syncpromise::SyncPromise;
+#[cfg(feature = "test-util")]
+pub use testutil::TestablePromise;
+#[cfg(all(feature = "test-util", feature = "tokio"))]
+pub use testutil::{wait_for_error, wait_for_success, wait_until_up_to_date};
+#[doc(inline)]
+pub use threadpromise::{ProgressTrackedThreadPromise, StatusSender, ThreadPromise};
+#[cfg(feature = "tokio")]
+pub use transitionlog::{
+    set_transition_log_default, Transition, TransitionCause, TRANSITION_LOG_CAPACITY,
+};
+#[cfg(feature = "tokio")]
+pub use updatelog::TransitionTimes;
 
+#[cfg(feature = "tokio")]
+mod aggregate;
+#[cfg(feature = "anyhow")]
+mod anyhowinterop;
+#[cfg(feature = "bytes")]
+mod bytespromise;
+#[cfg(feature = "tokio-util")]
+mod cancellation;
+mod clock;
+#[cfg(feature = "tokio")]
+mod control;
+#[cfg(all(debug_assertions, feature = "log"))]
+mod diagnostics;
+#[cfg(feature = "tokio")]
+mod dropcount;
+#[cfg(feature = "egui")]
+mod eguiwidgets;
 mod immediatevalue;
+#[cfg(feature = "tokio")]
+mod immediatevaluebuilder;
 mod immediatevalueprogress;
+#[cfg(feature = "indicatif")]
+mod indicatifprogress;
+#[cfg(feature = "tokio")]
+mod intopromise;
+#[cfg(feature = "tokio")]
+mod lazybuilder;
+#[cfg(feature = "tokio")]
 mod lazyvalue;
+#[cfg(feature = "tokio")]
 mod lazyvec;
+#[cfg(feature = "tokio")]
+mod lazywindow;
+mod lockedaccess;
+#[cfg(feature = "test-util")]
+mod mockpromise;
+#[cfg(feature = "poll-promise")]
+mod pollpromise;
+mod promiseext;
+#[cfg(feature = "tokio")]
+mod ratelimit;
+#[cfg(feature = "rayon")]
+mod rayonpromise;
+#[cfg(feature = "registry")]
+pub mod registry;
+mod retry;
+#[cfg(feature = "tokio")]
+mod runtime;
+mod snapshot;
+#[cfg(feature = "tokio")]
+mod statechange;
+#[cfg(feature = "futures")]
+mod subscriptionpromise;
+#[cfg(feature = "tokio")]
+mod supersedingpromise;
+mod syncpromise;
+#[cfg(feature = "test-util")]
+mod testutil;
+mod threadpromise;
+#[cfg(feature = "tokio")]
+mod transitionlog;
+#[cfg(feature = "tokio")]
+mod updatelog;
 
 /// Strong type to keep the boxed error. You can just deref it to get the inside box.
-pub struct BoxedSendError(pub Box<dyn Error + Send>);
+/// A [`Backtrace`] is captured on conversion if enabled via `RUST_BACKTRACE`, see [`Backtrace::capture`].
+/// This type does not implement [`Error`] itself, since that would collide with the blanket `From` implementation
+/// which allows using the ?-operator in futures. Use [`BoxedSendError::as_error`] if you need a `&dyn Error`.
+pub struct BoxedSendError(pub Box<dyn Error + Send>, Backtrace, ErrorKind);
+
+/// Where an error came from, see [`BoxedSendError::kind`], e.g. to decide between offering a retry and asking
+/// for a bug report
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// Returned or set by the future or updater itself, the default
+    User,
+    /// The promise timed out, see [`crate::TimedOut`]
+    Timeout,
+    /// The promise was cancelled, see [`crate::Cancelled`]
+    Cancelled,
+    /// The future, updater or job panicked
+    Panicked,
+    /// A channel was closed before the result or status arrived, e.g. because the other side was dropped
+    ChannelClosed,
+    /// The runtime ended the task, or the updater ended without a final state
+    Runtime,
+}
+
+impl ErrorKind {
+    /// The kind of errors converted from `error`
+    fn of(error: &(dyn Error + Send + 'static)) -> ErrorKind {
+        #[cfg(feature = "tokio-util")]
+        if error.is::<Cancelled>() {
+            return ErrorKind::Cancelled;
+        }
+        #[cfg(feature = "tokio")]
+        if error.is::<TimedOut>() {
+            return ErrorKind::Timeout;
+        }
+        let _ = error;
+        ErrorKind::User
+    }
+}
 
 /// Type alias for futures with BoxedSendError
 pub type FutureResult<T> = Result<T, BoxedSendError>;
 
 impl<E: Error + Send + 'static> From<E> for BoxedSendError {
     fn from(e: E) -> Self {
-        BoxedSendError(Box::new(e))
+        BoxedSendError::new(Box::new(e))
     }
 }
 
@@ -69,6 +263,159 @@ impl Deref for BoxedSendError {
     }
 }
 
+impl Debug for BoxedSendError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&self.0, f)
+    }
+}
+
+impl Display for BoxedSendError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+/// Conversion into the payload of [`DataState::Error`], accepted by [`set_error!`].
+/// Implemented for plain messages and [`BoxedSendError`], so any error can be passed using `BoxedSendError::from(error)`.
+pub trait IntoBoxedSendError {
+    /// perform the conversion
+    fn into_boxed_send_error(self) -> BoxedSendError;
+}
+
+impl IntoBoxedSendError for BoxedSendError {
+    fn into_boxed_send_error(self) -> BoxedSendError {
+        self
+    }
+}
+
+impl IntoBoxedSendError for String {
+    fn into_boxed_send_error(self) -> BoxedSendError {
+        BoxedSendError::msg(self)
+    }
+}
+
+impl IntoBoxedSendError for &str {
+    fn into_boxed_send_error(self) -> BoxedSendError {
+        BoxedSendError::msg(self)
+    }
+}
+
+#[derive(Debug)]
+struct MessageError(String);
+
+impl Display for MessageError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Error for MessageError {}
+
+#[derive(Debug)]
+struct ContextError {
+    context: String,
+    source: Box<dyn Error + Send>,
+}
+
+impl Display for ContextError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.context)
+    }
+}
+
+impl Error for ContextError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(self.source.as_ref())
+    }
+}
+
+impl BoxedSendError {
+    /// Wrap an already boxed error, capturing a backtrace if enabled. [`crate::Cancelled`] and
+    /// [`crate::TimedOut`] get their [`ErrorKind`], all other errors are [`ErrorKind::User`].
+    pub fn new(error: Box<dyn Error + Send>) -> Self {
+        let kind = ErrorKind::of(error.as_ref());
+        BoxedSendError(error, Backtrace::capture(), kind)
+    }
+
+    /// Create an error without any source from a plain message
+    /// ```rust
+    /// use lazy_async_promise::BoxedSendError;
+    /// let error = BoxedSendError::msg("request failed");
+    /// assert_eq!(error.full_message(), "request failed");
+    /// assert!(error.source().is_none());
+    /// ```
+    pub fn msg(message: impl Into<String>) -> Self {
+        Self::new(Box::new(MessageError(message.into())))
+    }
+
+    /// Wrap the error with a message telling where it happened. The original error becomes the source,
+    /// the backtrace is kept.
+    /// ```rust
+    /// use lazy_async_promise::BoxedSendError;
+    /// let error = BoxedSendError::msg("connection refused").context("while fetching user list");
+    /// assert_eq!(error.to_string(), "while fetching user list");
+    /// assert_eq!(error.full_message(), "while fetching user list: connection refused");
+    /// ```
+    pub fn context(self, context: impl Into<String>) -> Self {
+        let context = ContextError {
+            context: context.into(),
+            source: self.0,
+        };
+        BoxedSendError(Box::new(context), self.1, self.2)
+    }
+
+    /// Where the error came from. Errors returned by futures and updaters are [`ErrorKind::User`], unless set
+    /// otherwise with [`Self::with_kind`].
+    /// ```rust
+    /// use lazy_async_promise::{BoxedSendError, ErrorKind};
+    /// let error = BoxedSendError::msg("connection reset").with_kind(ErrorKind::ChannelClosed);
+    /// assert_eq!(error.context("while streaming").kind(), ErrorKind::ChannelClosed);
+    /// assert_eq!(BoxedSendError::msg("not found").kind(), ErrorKind::User);
+    /// ```
+    pub fn kind(&self) -> ErrorKind {
+        self.2
+    }
+
+    /// Set the [`ErrorKind`], the message is kept
+    pub fn with_kind(mut self, kind: ErrorKind) -> Self {
+        self.2 = kind;
+        self
+    }
+
+    /// The inner error as trait object
+    pub fn as_error(&self) -> &(dyn Error + 'static) {
+        self.0.as_ref()
+    }
+
+    /// The source of the inner error, if any
+    pub fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.0.source()
+    }
+
+    /// Iterate the inner error and all of its sources, starting with the inner error
+    pub fn chain(&self) -> impl Iterator<Item = &(dyn Error + 'static)> {
+        iter::successors(Some(self.as_error()), |&error| error.source())
+    }
+
+    /// All messages of [`BoxedSendError::chain`] joined by `": "`
+    pub fn full_message(&self) -> String {
+        self.chain()
+            .map(|error| error.to_string())
+            .collect::<Vec<_>>()
+            .join(": ")
+    }
+
+    /// The backtrace captured when this error was created
+    pub fn backtrace(&self) -> &Backtrace {
+        &self.1
+    }
+
+    /// Unwrap the boxed error
+    pub fn into_inner(self) -> Box<dyn Error + Send> {
+        self.0
+    }
+}
+
 /// Trait for directly accessing the cache underneath any promise
 pub trait DirectCacheAccess<T, E> {
     /// returns mutable reference to the cache if applicable
@@ -77,14 +424,81 @@ pub trait DirectCacheAccess<T, E> {
     fn get_value(&self) -> Option<&T>;
     /// returns a reference to the cache or to error if applicable
     fn get_result(&self) -> Option<Result<&T, &E>>;
+    /// returns a mutable reference to the cache or to error if applicable.
+    /// The returned reference borrows the promise mutably, so it has to be dropped before polling again:
+    /// ```rust, no_run
+    /// use std::time::Duration;
+    /// use lazy_async_promise::{DirectCacheAccess, ThreadPromise};
+    /// let mut promise = ThreadPromise::new(|| Ok(vec![3, 1, 2]));
+    /// std::thread::sleep(Duration::from_millis(50));
+    /// promise.poll_state();
+    /// if let Some(Ok(values)) = promise.get_result_mut() {
+    ///     values.sort();
+    /// }
+    /// // `values` is out of scope, so polling is possible again
+    /// promise.poll_state();
+    /// assert_eq!(promise.get_value().unwrap(), &[1, 2, 3]);
+    /// ```
+    fn get_result_mut(&mut self) -> Option<Result<&mut T, &mut E>>;
     /// takes the value and leaves the promise in a valid state indicating its emptiness
     fn take_value(&mut self) -> Option<T>;
     /// takes the value or error and leaves the promise in a valid state indicating its emptiness
     fn take_result(&mut self) -> Option<Result<T, E>>;
+    /// injects a value into the cache without running any future, returns the previous value if there was one
+    fn set_value(&mut self, value: T) -> Option<T>;
+
+    /// is a final value available?
+    fn has_value(&self) -> bool {
+        matches!(self.get_result(), Some(Ok(_)))
+    }
+    /// did the computation fail and is the error still available?
+    fn has_error(&self) -> bool {
+        matches!(self.get_result(), Some(Err(_)))
+    }
+    /// is the computation still running? Implementors which can be emptied should override this,
+    /// since the default can't distinguish a running computation from a taken value.
+    fn is_pending(&self) -> bool {
+        self.get_result().is_none()
+    }
+
+    /// takes the error if the computation failed and leaves the promise empty, see `take_result`.
+    /// A success value is left untouched. Lazy promises report [`DataState::Uninitialized`] afterwards,
+    /// so the next [`Promise::poll_state`] will trigger a new update. Immediate promises will stay empty.
+    fn take_error(&mut self) -> Option<E> {
+        if self.has_error() {
+            self.take_result().and_then(Result::err)
+        } else {
+            None
+        }
+    }
+
+    /// returns a clone of the cached value if applicable
+    fn get_value_cloned(&self) -> Option<T>
+    where
+        T: Clone,
+    {
+        self.get_value().cloned()
+    }
+    /// maps a reference to the cached value if applicable
+    fn map_value<U>(&self, f: impl FnOnce(&T) -> U) -> Option<U>
+    where
+        Self: Sized,
+    {
+        self.get_value().map(f)
+    }
+    /// maps a reference to the cached value or error if applicable
+    fn map_result<U>(&self, f: impl FnOnce(Result<&T, &E>) -> U) -> Option<U>
+    where
+        Self: Sized,
+    {
+        self.get_result().map(f)
+    }
 }
 
 /// Blanket implementation for any `Option<DirectCacheAccess<T>>` allows for better handling of option-laziness
-impl<T: Send + 'static, E: Send + 'static, A: DirectCacheAccess<T, E>> DirectCacheAccess<T, E> for Option<A> {
+impl<T: Send + 'static, E: Send + 'static, A: DirectCacheAccess<T, E>> DirectCacheAccess<T, E>
+    for Option<A>
+{
     fn get_value_mut(&mut self) -> Option<&mut T> {
         self.as_mut().and_then(|inner| inner.get_value_mut())
     }
@@ -94,12 +508,24 @@ impl<T: Send + 'static, E: Send + 'static, A: DirectCacheAccess<T, E>> DirectCac
     fn get_result(&self) -> Option<Result<&T, &E>> {
         self.as_ref().and_then(|inner| inner.get_result())
     }
+    fn get_result_mut(&mut self) -> Option<Result<&mut T, &mut E>> {
+        self.as_mut().and_then(|inner| inner.get_result_mut())
+    }
     fn take_value(&mut self) -> Option<T> {
         self.as_mut().and_then(|inner| inner.take_value())
     }
     fn take_result(&mut self) -> Option<Result<T, E>> {
         self.as_mut().and_then(|inner| inner.take_result())
     }
+    /// Does nothing on `None`, the value is dropped
+    fn set_value(&mut self, value: T) -> Option<T> {
+        self.as_mut().and_then(|inner| inner.set_value(value))
+    }
+    fn is_pending(&self) -> bool {
+        self.as_ref()
+            .map(|inner| inner.is_pending())
+            .unwrap_or(false)
+    }
 }
 
 /// a f64 type which is constrained to the range of 0.0 and 1.0
@@ -112,14 +538,64 @@ impl<T: Into<f64>> From<T> for Progress {
     }
 }
 
+/// Reports the progress of [`ImmediateValuePromise::new_with_progress`] without a channel or a message. Setting
+/// doesn't await, so it works from tight loops and blocking code. Clones report to the same promise.
+#[derive(Clone, Debug, Default)]
+pub struct ProgressSender(Arc<AtomicU64>);
+
+impl ProgressSender {
+    /// Set the progress, given as [`Progress`] or as fraction between 0 and 1
+    pub fn set(&self, progress: impl Into<Progress>) {
+        self.0
+            .store(progress.into().as_f64().to_bits(), Ordering::Relaxed);
+    }
+
+    /// The latest progress set
+    pub fn get(&self) -> Progress {
+        Progress(f64::from_bits(self.0.load(Ordering::Relaxed)))
+    }
+}
+
+/// A progress given either as [`Progress`] or as percentage number, accepted by [`send_status!`]
+pub trait IntoProgress {
+    /// perform the conversion, numbers are read as percent
+    fn into_progress(self) -> Progress;
+}
+
+impl IntoProgress for Progress {
+    fn into_progress(self) -> Progress {
+        self
+    }
+}
+
+impl<T: Into<f64>> IntoProgress for T {
+    fn into_progress(self) -> Progress {
+        Progress::from_percent(self)
+    }
+}
+
 /// Use this to get all macros
 pub mod api_macros {
+    #[cfg(feature = "test-util")]
+    pub use crate::assert_state;
     pub use crate::send_data;
+    pub use crate::send_data_with_progress;
+    pub use crate::send_status;
+    pub use crate::send_status_fmt;
     pub use crate::set_error;
     pub use crate::set_finished;
     pub use crate::set_progress;
+    pub use crate::status;
+    pub use crate::try_send_data;
+    pub use crate::try_send_status;
+    pub use crate::try_set_progress;
     pub use crate::unpack_result;
+    pub use crate::FullBuffer;
     pub use crate::Progress;
+    #[cfg(feature = "tokio")]
+    pub use crate::TrySendMessage;
+    #[cfg(feature = "tokio")]
+    pub use crate::TrySendStatus;
 }
 
 impl Progress {
@@ -146,6 +622,17 @@ impl Progress {
         (numerator.into() / denominator.into()).into()
     }
 
+    /// A progress for statuses which only report a message, e.g. sent by [`status!`] without percentage. The
+    /// progress tracked promises keep their previous progress for those.
+    pub const fn indeterminate() -> Progress {
+        Progress(f64::NAN)
+    }
+
+    /// Is this [`Progress::indeterminate`]?
+    pub fn is_indeterminate(&self) -> bool {
+        self.0.is_nan()
+    }
+
     /// return progress as f32
     pub fn as_f32(&self) -> f32 {
         self.0 as f32
@@ -157,6 +644,19 @@ impl Progress {
     }
 }
 
+/// Formats as percentage, without decimals unless a precision is given
+/// ```rust
+/// use lazy_async_promise::Progress;
+/// let progress = Progress::from_fraction(1, 3);
+/// assert_eq!(progress.to_string(), "33%");
+/// assert_eq!(format!("{progress:.1}"), "33.3%");
+/// ```
+impl Display for Progress {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.*}%", f.precision().unwrap_or(0), self.0 * 100.0)
+    }
+}
+
 impl Default for Progress {
     fn default() -> Self {
         Progress(0.0)
@@ -170,7 +670,7 @@ impl Deref for Progress {
     }
 }
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Debug)]
 /// Represents a processing state.
 pub enum DataState {
     /// You can only receive this after taking ownership of the data
@@ -180,48 +680,243 @@ pub enum DataState {
     /// Data is not (completely) ready, depending on your implementation, you may be able to get partial results
     /// Embedded progress in [0,1)
     Updating(Progress),
-    /// Some error occurred
-    Error(String),
+    /// Some error occurred, the original error object is kept
+    Error(BoxedSendError),
+    /// The update was cancelled, e.g. via `with_cancellation` on the lazy promises. Kept until the next update.
+    Cancelled,
+}
+
+/// Errors are considered equal if their messages are equal
+impl PartialEq for DataState {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (DataState::Uninitialized, DataState::Uninitialized) => true,
+            (DataState::UpToDate, DataState::UpToDate) => true,
+            (DataState::Cancelled, DataState::Cancelled) => true,
+            (DataState::Updating(progress), DataState::Updating(other_progress)) => {
+                progress == other_progress
+            }
+            (DataState::Error(error), DataState::Error(other_error)) => {
+                error.to_string() == other_error.to_string()
+            }
+            _ => false,
+        }
+    }
 }
 
 impl DataState {
+    /// Is the state [`DataState::UpToDate`]?
+    pub fn is_success(&self) -> bool {
+        matches!(self, DataState::UpToDate)
+    }
+
+    /// Is the state [`DataState::Error`]?
+    pub fn is_error(&self) -> bool {
+        matches!(self, DataState::Error(_))
+    }
+
+    /// Is the state [`DataState::Updating`]?
+    pub fn is_updating(&self) -> bool {
+        matches!(self, DataState::Updating(_))
+    }
+
+    /// Is the state [`DataState::Cancelled`]?
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self, DataState::Cancelled)
+    }
+
+    /// Is the state [`DataState::Uninitialized`]?
+    pub fn is_empty(&self) -> bool {
+        matches!(self, DataState::Uninitialized)
+    }
+
+    /// The error if the state is [`DataState::Error`]
+    /// ```rust
+    /// use lazy_async_promise::{BoxedSendError, DataState};
+    /// let state = DataState::Error(BoxedSendError::msg("timeout"));
+    /// assert_eq!(state.as_error().unwrap().to_string(), "timeout");
+    /// assert!(DataState::UpToDate.as_error().is_none());
+    /// ```
+    pub fn as_error(&self) -> Option<&BoxedSendError> {
+        match self {
+            DataState::Error(error) => Some(error),
+            _ => None,
+        }
+    }
+
+    /// Converts into the error if the state is [`DataState::Error`], like [`Result::err`]
+    /// ```rust
+    /// use lazy_async_promise::{BoxedSendError, DataState};
+    /// let state = DataState::Error(BoxedSendError::msg("timeout"));
+    /// assert_eq!(state.err().unwrap().to_string(), "timeout");
+    /// ```
+    pub fn err(self) -> Option<BoxedSendError> {
+        match self {
+            DataState::Error(error) => Some(error),
+            _ => None,
+        }
+    }
+
     /// Yields the progress if state is `DataState::Updating` otherwise yields none, even if finished.
+    /// ```rust
+    /// use lazy_async_promise::{DataState, Progress};
+    /// let state = DataState::Updating(Progress::from_percent(50));
+    /// assert_eq!(state.get_progress().unwrap().as_f64(), 0.5);
+    /// assert!(DataState::UpToDate.get_progress().is_none());
+    /// ```
     pub fn get_progress(&self) -> Option<Progress> {
         match &self {
             DataState::Updating(progress) => Some(*progress),
             _ => None,
         }
     }
+
+    /// The state without the error payload
+    pub fn tag(&self) -> DataStateTag {
+        match self {
+            DataState::Uninitialized => DataStateTag::Uninitialized,
+            DataState::UpToDate => DataStateTag::UpToDate,
+            DataState::Updating(progress) => DataStateTag::Updating(*progress),
+            DataState::Error(_) => DataStateTag::Error,
+            DataState::Cancelled => DataStateTag::Cancelled,
+        }
+    }
+}
+
+/// Human-readable state, including the progress while updating and the error message
+/// ```rust
+/// use lazy_async_promise::{BoxedSendError, DataState, Progress};
+/// assert_eq!(DataState::Updating(Progress::from_percent(42)).to_string(), "updating (42%)");
+/// assert_eq!(DataState::Error(BoxedSendError::msg("timeout")).to_string(), "error: timeout");
+/// ```
+impl Display for DataState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            DataState::Uninitialized => f.write_str("uninitialized"),
+            DataState::UpToDate => f.write_str("up to date"),
+            DataState::Updating(progress) => write!(f, "updating ({progress})"),
+            DataState::Error(error) => write!(f, "error: {error}"),
+            DataState::Cancelled => f.write_str("cancelled"),
+        }
+    }
+}
+
+/// A copyable [`DataState`] without the error payload, see `state_watch` on the lazy promises
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DataStateTag {
+    /// See [`DataState::Uninitialized`]
+    Uninitialized,
+    /// See [`DataState::UpToDate`]
+    UpToDate,
+    /// See [`DataState::Updating`]
+    Updating(Progress),
+    /// See [`DataState::Error`]
+    Error,
+    /// See [`DataState::Cancelled`]
+    Cancelled,
 }
 
 #[derive(Debug)]
-/// The message-type to send from the updater to the main thread. There's only two variants,
-/// `NewData` which allows to send new data or `StateChange` which allows to signal readiness or error.
+/// The message-type to send from the updater to the main thread. `NewData` allows to send new data,
+/// `StateChange` allows to signal progress, readiness or error and `NewDataWithProgress` combines both.
 pub enum Message<T: Debug> {
     /// Adding or setting new data to the promise, depending on the implementation
     NewData(T),
     /// Modify the state of the promise, including setting an error
     StateChange(DataState),
+    /// New data and the progress after it, applied by the same poll, see [`send_data_with_progress!`]
+    NewDataWithProgress(T, Progress),
+}
+
+impl<T: Debug> Message<T> {
+    /// Splits combined messages into `NewData` and `StateChange`
+    #[cfg(feature = "tokio")]
+    pub(crate) fn split(self) -> impl Iterator<Item = Message<T>> {
+        let (first, second) = match self {
+            Message::NewDataWithProgress(data, progress) => (
+                Message::NewData(data),
+                Some(Message::StateChange(DataState::Updating(progress))),
+            ),
+            message => (message, None),
+        };
+        iter::once(first).chain(second)
+    }
 }
 
 /// Maybe this should rather be called "LazyUpdating"?
 /// Implementors can react to polling by queueing an update if needed.
 /// Update should force an update.
+///
+/// The trait is object safe, generic helpers live in [`PromiseExt`]. So promises of different types can be kept
+/// side by side and driven uniformly:
+/// ```rust
+/// # #[cfg(feature = "tokio")]
+/// # #[tokio::main]
+/// # async fn main() {
+/// use std::time::Duration;
+/// use tokio::sync::mpsc::Sender;
+/// use lazy_async_promise::{DataState, LazyValuePromise, LazyVecPromise, Message, Promise, api_macros::*};
+///
+/// let numbers = |tx: Sender<Message<i32>>| async move {
+///     send_data!(1, tx);
+///     set_finished!(tx);
+/// };
+/// let title = |tx: Sender<Message<String>>| async move {
+///     send_data!("dashboard".to_string(), tx);
+///     set_finished!(tx);
+/// };
+/// let mut promises: Vec<Box<dyn Promise>> = vec![
+///     Box::new(LazyVecPromise::new(numbers, 10)),
+///     Box::new(LazyValuePromise::new(title, 10)),
+/// ];
+/// for promise in promises.iter_mut() {
+///     promise.poll_state();
+/// }
+/// tokio::time::sleep(Duration::from_millis(50)).await;
+/// for promise in promises.iter_mut() {
+///     assert!(promise.poll_state().is_success());
+/// }
+/// # }
+/// # #[cfg(not(feature = "tokio"))]
+/// # fn main() {}
+/// ```
 pub trait Promise {
     /// Polls the promise, triggers update if state is [`DataState::Uninitialized`]
     fn poll_state(&mut self) -> &DataState;
+    /// The current state without polling
+    fn state(&self) -> &DataState;
     /// Clears the data cache and immediately triggers an update
     fn update(&mut self);
+    /// The progress while updating, e.g. for a progress bar shown for any kind of promise. Derived from
+    /// [`Self::state`] by default, which includes [`Progress::indeterminate`] if the updater doesn't know the progress.
+    fn progress(&self) -> Option<Progress> {
+        self.state().get_progress()
+    }
 }
 
 #[macro_export]
 /// Error checking in async updater functions is tedious - this helps out by resolving results and sending errors on error. Result will be unwrapped if no error occurs.
+/// The error is kept as [`BoxedSendError`] in [`DataState::Error`].
+/// Optionally takes a context message, also as format arguments, which the error is wrapped with
+/// using [`BoxedSendError::context`]: `unpack_result!(fetch(id).await, tx, "while fetching user {id}")`.
 macro_rules! unpack_result {
     ( $result: expr, $sender: expr ) => {
         match $result {
             Ok(val) => val,
             Err(e) => {
-                set_error!(format!("{}", e), $sender);
+                set_error!($crate::BoxedSendError::from(e), $sender);
+                return;
+            }
+        }
+    };
+    ( $result: expr, $sender: expr, $($context: tt)+ ) => {
+        match $result {
+            Ok(val) => val,
+            Err(e) => {
+                set_error!(
+                    $crate::BoxedSendError::from(e).context(::std::format!($($context)+)),
+                    $sender
+                );
                 return;
             }
         }
@@ -240,11 +935,13 @@ macro_rules! set_progress {
 }
 
 #[macro_export]
-/// Setting the given progress using a given sender.
+/// Setting the given error using a given sender. Accepts anything implementing [`IntoBoxedSendError`].
 macro_rules! set_error {
     ($error: expr, $sender: expr) => {
         $sender
-            .send(Message::StateChange(DataState::Error($error)))
+            .send(Message::StateChange(DataState::Error(
+                $crate::IntoBoxedSendError::into_boxed_send_error($error),
+            )))
             .await
             .unwrap();
     };
@@ -258,6 +955,50 @@ macro_rules! send_data {
     };
 }
 
+#[macro_export]
+/// Send new data and the progress after it in one message, so the promise never shows the item without the matching
+/// progress. The progress is a [`Progress`], a percentage number or a `(current, total)` pair.
+/// ```rust
+/// # #[cfg(all(feature = "tokio", not(feature = "async-std")))]
+/// # #[tokio::main]
+/// # async fn main() {
+/// use tokio::sync::mpsc::Sender;
+/// use lazy_async_promise::{DataState, LazyVecPromise, Message, Progress, Promise, api_macros::*};
+/// let updater = |tx: Sender<Message<i32>>| async move {
+///     for i in 1..=4 {
+///         send_data_with_progress!(i, (i, 5), tx);
+///     }
+///     tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+///     set_finished!(tx);
+/// };
+/// let mut promise = LazyVecPromise::new(updater, 10);
+/// promise.poll_state();
+/// tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+/// assert_eq!(promise.poll_state(), &DataState::Updating(Progress::from_fraction(4, 5)));
+/// assert_eq!(promise.as_slice(), &[1, 2, 3, 4]);
+/// # }
+/// # #[cfg(any(not(feature = "tokio"), feature = "async-std"))]
+/// # fn main() {}
+/// ```
+macro_rules! send_data_with_progress {
+    ($data: expr, ($current: expr, $total: expr), $sender: expr) => {
+        $crate::send_data_with_progress!(
+            $data,
+            $crate::Progress::from_fraction($current, $total),
+            $sender
+        )
+    };
+    ($data: expr, $progress: expr, $sender: expr) => {
+        $sender
+            .send(Message::NewDataWithProgress(
+                $data,
+                $crate::IntoProgress::into_progress($progress),
+            ))
+            .await
+            .unwrap();
+    };
+}
+
 #[macro_export]
 /// Set state to `DataState::UpToDate`
 macro_rules! set_finished {
@@ -269,9 +1010,173 @@ macro_rules! set_finished {
     };
 }
 
+#[macro_export]
+/// Send a [`StringStatus`] with the given progress and message on the status sender of a
+/// [`ProgressTrackedImValProm`]. The progress is a [`Progress`] or a percentage number, see [`IntoProgress`].
+/// If the promise is gone, the enclosing future returns with the send error instead of panicking.
+macro_rules! send_status {
+    ($sender: expr, $progress: expr, $message: expr) => {
+        if let Err(error) = $sender
+            .send($crate::StringStatus::new(
+                $crate::IntoProgress::into_progress($progress),
+                ::std::borrow::Cow::from($message),
+            ))
+            .await
+        {
+            return Err(
+                $crate::BoxedSendError::from(error).with_kind($crate::ErrorKind::ChannelClosed)
+            );
+        }
+    };
+}
+
+#[macro_export]
+/// Like [`send_status!`], with the message given as format arguments
+macro_rules! send_status_fmt {
+    ($sender: expr, $progress: expr, $($arg: tt)+) => {
+        $crate::send_status!($sender, $progress, ::std::format!($($arg)+))
+    };
+}
+
+#[macro_export]
+/// Send a formatted [`StringStatus`] on the status sender of a [`ProgressTrackedImValProm`] in one line, like
+/// [`send_status_fmt!`]. Without the progress, the status is sent with [`Progress::indeterminate`], so the promise
+/// keeps its previous progress. The first format argument can't be a literal, put it into the format string instead.
+/// If the promise is gone, the enclosing future returns with the send error.
+/// ```rust
+/// # #[cfg(feature = "tokio")]
+/// # #[tokio::main]
+/// # async fn main() {
+/// use lazy_async_promise::{ImmediateValuePromise, Progress, ProgressTrackedImValProm, api_macros::*};
+/// let mut promise = ProgressTrackedImValProm::new(
+///     |s| {
+///         ImmediateValuePromise::new(async move {
+///             let total = 8;
+///             for done in 1..=total {
+///                 status!(s, Progress::from_fraction(done, total), "processed {} of {} files", done, total);
+///             }
+///             status!(s, "cleaning up {total} files");
+///             Ok(total)
+///         })
+///     },
+///     20,
+/// );
+/// tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+/// assert!(promise.poll_state().is_success());
+/// assert_eq!(promise.last_status().unwrap().message, "cleaning up 8 files");
+/// assert_eq!(promise.get_progress(), Progress::from_percent(100));
+/// # }
+/// # #[cfg(not(feature = "tokio"))]
+/// # fn main() {}
+/// ```
+macro_rules! status {
+    ($sender: expr, $progress: expr, $format: literal $(, $arg: expr)* $(,)?) => {
+        $crate::send_status!($sender, $progress, ::std::format!($format $(, $arg)*))
+    };
+    ($sender: expr, $format: literal $(, $arg: expr)* $(,)?) => {
+        $crate::send_status!(
+            $sender,
+            $crate::Progress::indeterminate(),
+            ::std::format!($format $(, $arg)*)
+        )
+    };
+}
+
+/// What the non-blocking [`try_send_data!`] and [`try_set_progress!`] do if a message can't be sent right away,
+/// because the buffer of the promise is full or the promise is gone. Overwriting queued messages is not supported
+/// by the tokio channels, so the new message is always the one that's lost.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FullBuffer {
+    /// Drop the message and carry on, the macro evaluates to `false` so the caller can branch on it
+    Drop,
+    /// Drop the message and return from the enclosing function, like [`unpack_result!`] does on errors
+    Return,
+}
+
+/// Non-blocking counterparts of sending on the updater channel, used by [`try_send_data!`] and [`try_set_progress!`].
+/// For producers which can't `.await`, like synchronous callbacks, or must never block on a full buffer.
+/// Failed sends from the updater task are counted, see [`LazyVecPromise::dropped_messages`].
+#[cfg(feature = "tokio")]
+pub trait TrySendMessage<T: Debug> {
+    /// Send new data if there's room in the buffer
+    fn try_send_data(&self, data: T) -> Result<(), TrySendError<Message<T>>>;
+    /// Set the progress if there's room in the buffer
+    fn try_set_progress(&self, progress: Progress) -> Result<(), TrySendError<Message<T>>>;
+}
+
+#[cfg(feature = "tokio")]
+impl<T: Debug> TrySendMessage<T> for Sender<Message<T>> {
+    fn try_send_data(&self, data: T) -> Result<(), TrySendError<Message<T>>> {
+        self.try_send(Message::NewData(data))
+            .inspect_err(|_| record_dropped())
+    }
+    fn try_set_progress(&self, progress: Progress) -> Result<(), TrySendError<Message<T>>> {
+        self.try_send(Message::StateChange(DataState::Updating(progress)))
+            .inspect_err(|_| record_dropped())
+    }
+}
+
+/// Non-blocking sending of statuses to a [`ProgressTrackedImValProm`], used by [`try_send_status!`].
+/// Failed sends are counted, see [`ProgressTrackedImValProm::dropped_messages`].
+#[cfg(feature = "tokio")]
+pub trait TrySendStatus<M> {
+    /// Send the status if there's room in the buffer
+    fn try_send_status(&self, status: Status<M>) -> Result<(), TrySendError<Status<M>>>;
+}
+
+#[cfg(feature = "tokio")]
+impl<M> TrySendStatus<M> for Sender<Status<M>> {
+    fn try_send_status(&self, status: Status<M>) -> Result<(), TrySendError<Status<M>>> {
+        self.try_send(status).inspect_err(|_| record_dropped())
+    }
+}
+
+#[macro_export]
+/// Send new data via the sender without waiting. Evaluates to whether the data was sent,
+/// what happens otherwise is decided by the given [`FullBuffer`] policy.
+macro_rules! try_send_data {
+    ($data: expr, $sender: expr, $policy: expr) => {
+        match $crate::TrySendMessage::try_send_data(&$sender, $data) {
+            Ok(()) => true,
+            Err(_) if $policy == $crate::FullBuffer::Return => return,
+            Err(_) => false,
+        }
+    };
+}
+
+#[macro_export]
+/// Send a status like [`send_status!`] without waiting. Evaluates to whether the status was sent,
+/// statuses which don't fit into the buffer are dropped.
+macro_rules! try_send_status {
+    ($sender: expr, $progress: expr, $message: expr) => {
+        $crate::TrySendStatus::try_send_status(
+            &$sender,
+            $crate::StringStatus::new(
+                $crate::IntoProgress::into_progress($progress),
+                ::std::borrow::Cow::from($message),
+            ),
+        )
+        .is_ok()
+    };
+}
+
+#[macro_export]
+/// Setting the given progress using a given sender without waiting, see [`try_send_data!`]
+macro_rules! try_set_progress {
+    ($progress: expr, $sender: expr, $policy: expr) => {
+        match $crate::TrySendMessage::try_set_progress(&$sender, $progress) {
+            Ok(()) => true,
+            Err(_) if $policy == $crate::FullBuffer::Return => return,
+            Err(_) => false,
+        }
+    };
+}
+
+#[cfg(feature = "tokio")]
 type BoxedFutureFactory<T> =
     Box<dyn Fn(Sender<Message<T>>) -> Pin<Box<dyn Future<Output = ()> + Send + 'static>>>;
 
+#[cfg(feature = "tokio")]
 fn box_future_factory<
     T: Debug,
     U: Fn(Sender<Message<T>>) -> Fut + 'static,
@@ -282,6 +1187,97 @@ fn box_future_factory<
     Box::new(move |tx: Sender<Message<T>>| Box::pin(future_factory(tx)))
 }
 
+/// Moves all queued messages into `buffer` at once. `recv_many` is polled a single time without waiting, taking a
+/// backlog of 100k statuses about twice as fast as single `try_recv` calls. The rest is picked up one by one, in case
+/// the cooperative budget of the current task kept `recv_many` from taking everything.
+#[cfg(feature = "tokio")]
+fn drain_into<T>(receiver: &mut Receiver<T>, buffer: &mut Vec<T>) {
+    let queued = receiver.len();
+    if queued == 0 {
+        return;
+    }
+    let mut context = Context::from_waker(Waker::noop());
+    let _ = pin!(receiver.recv_many(buffer, queued)).poll(&mut context);
+    buffer.extend(iter::from_fn(|| receiver.try_recv().ok()));
+}
+
+/// Callback invoked when a promise transitions into an error state
+pub type ErrorHook = Box<dyn FnMut(&BoxedSendError) + Send>;
+
+type DefaultErrorHook = Box<dyn Fn(&BoxedSendError) + Send + Sync>;
+
+static DEFAULT_ERROR_HOOK: RwLock<Option<DefaultErrorHook>> = RwLock::new(None);
+
+/// Set a process-wide error hook, called for every promise transitioning into an error state that has no hook of
+/// its own (see `with_on_error` on the promise types). Like the per-promise hooks, it's called during the `poll_state`
+/// that observes the error.
+/// ```rust
+/// lazy_async_promise::set_default_on_error(|error| eprintln!("promise failed: {}", error.full_message()));
+/// ```
+pub fn set_default_on_error(hook: impl Fn(&BoxedSendError) + Send + Sync + 'static) {
+    if let Ok(mut default_hook) = DEFAULT_ERROR_HOOK.write() {
+        *default_hook = Some(Box::new(hook));
+    }
+}
+
+/// Remove the process-wide error hook again
+pub fn clear_default_on_error() {
+    if let Ok(mut default_hook) = DEFAULT_ERROR_HOOK.write() {
+        *default_hook = None;
+    }
+}
+
+fn report_error(hook: &mut Option<ErrorHook>, error: &BoxedSendError) {
+    #[cfg(feature = "tokio-util")]
+    if error.is_cancelled() {
+        return;
+    }
+    if let Some(hook) = hook {
+        hook(error);
+    } else if let Ok(default_hook) = DEFAULT_ERROR_HOOK.read() {
+        if let Some(default_hook) = default_hook.as_ref() {
+            default_hook(error);
+        }
+    }
+}
+
+/// Maps errors to user-facing messages, see `with_error_display` on the promise types
+pub type ErrorDisplay = Box<dyn Fn(&BoxedSendError) -> String + Send + Sync>;
+
+fn display_error(mapper: &Option<ErrorDisplay>, error: &BoxedSendError) -> String {
+    mapper
+        .as_ref()
+        .map(|mapper| mapper(error))
+        .unwrap_or_else(|| error.to_string())
+}
+
+/// Builds the error for an updater task that finished without reporting a final state
+#[cfg(feature = "tokio")]
+fn updater_task_error(result: Result<(), TaskFailure>) -> BoxedSendError {
+    match result {
+        Err(TaskFailure::Panic(panic)) => panic_error(panic.as_ref()),
+        Err(TaskFailure::Other(error)) => error.with_kind(ErrorKind::Runtime),
+        Ok(()) => BoxedSendError::msg(UNFINISHED_UPDATER_MESSAGE).with_kind(ErrorKind::Runtime),
+    }
+}
+
+#[cfg(feature = "tokio")]
+const UNFINISHED_UPDATER_MESSAGE: &str = "updater finished without setting a final state";
+
+fn panic_message(panic: &(dyn Any + Send)) -> String {
+    let message = panic
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_default();
+    format!("updater panicked: {message}")
+}
+
+/// The error of a caught panic
+fn panic_error(panic: &(dyn Any + Send)) -> BoxedSendError {
+    BoxedSendError::msg(panic_message(panic)).with_kind(ErrorKind::Panicked)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -304,8 +1300,238 @@ mod test {
         assert_eq!(progress.as_f64(), 1.0);
     }
 
+    #[derive(Debug)]
+    struct Outer(std::io::Error);
+
+    impl Display for Outer {
+        fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            f.write_str("loading config failed")
+        }
+    }
+
+    impl Error for Outer {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            Some(&self.0)
+        }
+    }
+
+    #[test]
+    fn boxed_error_keeps_chain() {
+        let error: BoxedSendError = Outer(std::io::Error::other("disk on fire")).into();
+        assert_eq!(error.source().unwrap().to_string(), "disk on fire");
+        assert_eq!(error.chain().count(), 2);
+        assert_eq!(error.full_message(), "loading config failed: disk on fire");
+        assert_eq!(error.as_error().to_string(), "loading config failed");
+        let _ = error.backtrace();
+
+        let message = BoxedSendError::msg(String::from("plain"));
+        assert_eq!(message.chain().count(), 1);
+        assert_eq!(message.full_message(), "plain");
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn error_kinds() {
+        use crate::runtime::sleep;
+        use std::time::Duration;
+        use tokio::sync::mpsc::Sender;
+
+        let user = ImmediateValuePromise::<()>::new(async { Err(BoxedSendError::msg("bad")) });
+        let panicked = ImmediateValuePromise::<()>::new(async { panic!("boom") });
+        let timed_out = ImmediateValuePromise::builder()
+            .timeout(Duration::from_millis(10))
+            .spawn(async {
+                sleep(Duration::from_secs(10)).await;
+                Ok(())
+            });
+        let closed = ImmediateValuePromise::new(async {
+            let (sender, receiver) = tokio::sync::mpsc::channel::<StringStatus>(1);
+            drop(receiver);
+            send_status!(sender, 0, "started");
+            Ok(())
+        });
+        let mut unfinished = LazyValuePromise::new(
+            |tx: Sender<Message<i32>>| async move {
+                send_data!(34, tx);
+            },
+            6,
+        );
+        let mut promises = [user, panicked, timed_out, closed];
+        unfinished.poll_state();
+        sleep(Duration::from_millis(50)).await;
+        // panics take longer with `RUST_BACKTRACE` set
+        while promises
+            .iter_mut()
+            .any(|promise| promise.poll_state().is_updating())
+        {
+            sleep(Duration::from_millis(5)).await;
+        }
+
+        let kinds: Vec<_> = promises
+            .iter_mut()
+            .map(|promise| promise.poll_state().as_error().unwrap().kind())
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                ErrorKind::User,
+                ErrorKind::Panicked,
+                ErrorKind::Timeout,
+                ErrorKind::ChannelClosed
+            ]
+        );
+        let DataState::Error(ended) = unfinished.poll_state() else {
+            panic!("the updater ended without a final state");
+        };
+        assert_eq!(ended.kind(), ErrorKind::Runtime);
+        // the kind survives added context, the message is unchanged
+        let ended = BoxedSendError::msg(ended.full_message()).with_kind(ended.kind());
+        assert_eq!(ended.context("loading").kind(), ErrorKind::Runtime);
+
+        #[cfg(feature = "tokio-util")]
+        {
+            let token = tokio_util::sync::CancellationToken::new();
+            let mut cancelled = ImmediateValuePromise::<()>::new_with_token(
+                async {
+                    sleep(Duration::from_secs(10)).await;
+                    Ok(())
+                },
+                &token,
+            );
+            token.cancel();
+            sleep(Duration::from_millis(50)).await;
+            let error = cancelled.poll_state().as_error().unwrap();
+            assert_eq!(error.kind(), ErrorKind::Cancelled);
+        }
+    }
+
+    #[test]
+    fn data_state_equality() {
+        assert_eq!(
+            DataState::Error(BoxedSendError::msg("bla")),
+            DataState::Error("bla".into_boxed_send_error())
+        );
+        assert_ne!(
+            DataState::Error(BoxedSendError::msg("bla")),
+            DataState::Error(BoxedSendError::msg("blub"))
+        );
+        assert_ne!(DataState::UpToDate, DataState::Uninitialized);
+    }
+
+    #[test]
+    fn data_state_predicates() {
+        assert!(DataState::UpToDate.is_success());
+        assert!(DataState::Error(BoxedSendError::msg("bla")).is_error());
+        assert!(DataState::Updating(0.5.into()).is_updating());
+        assert!(DataState::Uninitialized.is_empty());
+        assert!(!DataState::Uninitialized.is_updating());
+        assert!(!DataState::UpToDate.is_error());
+    }
+
+    #[test]
+    fn display_formats() {
+        assert_eq!(Progress::from_percent(0).to_string(), "0%");
+        assert_eq!(Progress::from_fraction(2, 3).to_string(), "67%");
+        assert_eq!(format!("{:.2}", Progress::from_fraction(1, 8)), "12.50%");
+        assert_eq!(Progress::from_percent(100).to_string(), "100%");
+        assert_eq!(DataState::Uninitialized.to_string(), "uninitialized");
+        assert_eq!(DataState::UpToDate.to_string(), "up to date");
+        assert_eq!(
+            DataState::Updating(Progress::from_percent(5)).to_string(),
+            "updating (5%)"
+        );
+        let error = Outer(std::io::Error::other("disk on fire"));
+        assert_eq!(
+            DataState::Error(error.into()).to_string(),
+            "error: loading config failed"
+        );
+        assert_eq!(BoxedSendError::msg("plain").to_string(), "plain");
+    }
+
     #[test]
     fn default_progress_is_start() {
         assert_eq!(Progress::default().as_f64(), 0.0);
     }
+
+    /// The contract shared by all [`DirectCacheAccess`] implementors once the value was taken
+    fn assert_take_empties<T: PartialEq + Debug, P: DirectCacheAccess<T, BoxedSendError>>(
+        promise: &mut P,
+        expected: T,
+    ) {
+        assert!(promise.has_value());
+        assert_eq!(promise.take_value(), Some(expected));
+        assert!(promise.get_value().is_none());
+        assert!(promise.get_value_mut().is_none());
+        assert!(promise.get_result().is_none());
+        assert!(promise.get_result_mut().is_none());
+        assert!(!promise.has_value() && !promise.has_error() && !promise.is_pending());
+        assert!(promise.take_value().is_none());
+        assert!(promise.take_result().is_none());
+        assert!(promise.take_error().is_none());
+    }
+
+    #[test]
+    fn take_empties_sync_implementors() {
+        let mut state = ImmediateValueState::Success(34);
+        assert_take_empties(&mut state, 34);
+        assert!(state.is_empty());
+
+        let mut thread = ThreadPromise::new(|| Ok(34));
+        let mut tracked =
+            ProgressTrackedThreadPromise::<_, ()>::new(|_| ThreadPromise::new(|| Ok(34)));
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        thread.poll_state();
+        tracked.poll_state();
+        assert_take_empties(&mut thread, 34);
+        assert!(thread.poll_state().is_empty());
+        assert_take_empties(&mut tracked, 34);
+        assert!(tracked.poll_state().is_empty());
+        assert!(tracked.finished());
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn take_empties_async_implementors() {
+        use crate::runtime::sleep;
+        use std::time::Duration;
+        use tokio::sync::mpsc::Sender;
+
+        let mut immediate = ImmediateValuePromise::new(async { Ok(34) });
+        let mut tracked = ProgressTrackedImValProm::<_, ()>::new(
+            |_| ImmediateValuePromise::new(async { Ok(34) }),
+            1,
+        );
+        let mut value = LazyValuePromise::new(
+            |tx: Sender<Message<i32>>| async move {
+                send_data!(34, tx);
+                set_finished!(tx);
+            },
+            6,
+        );
+        let mut vec = LazyVecPromise::new(
+            |tx: Sender<Message<i32>>| async move {
+                send_data!(34, tx);
+                set_finished!(tx);
+            },
+            6,
+        );
+        value.poll_state();
+        vec.poll_state();
+        sleep(Duration::from_millis(50)).await;
+        immediate.poll_state();
+        tracked.poll_state();
+        value.poll_state();
+        vec.poll_state();
+
+        assert_take_empties(&mut immediate, 34);
+        assert!(immediate.poll_state().is_empty());
+        assert_take_empties(&mut tracked, 34);
+        assert!(tracked.poll_state().is_empty());
+        assert!(tracked.finished());
+        // lazy promises have no separate empty state, the next poll starts an update
+        assert_take_empties(&mut value, 34);
+        assert!(value.state().is_empty());
+        assert_take_empties(&mut vec, vec![34]);
+        assert!(vec.state().is_empty());
+    }
 }
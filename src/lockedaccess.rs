@@ -0,0 +1,111 @@
+use crate::DirectCacheAccess;
+
+/// # Cache access for promises shared behind a lock
+/// Implemented for [`std::sync::Mutex`], [`std::sync::RwLock`], `tokio::sync::Mutex` and `tokio::sync::RwLock`
+/// wrapping any [`DirectCacheAccess`] implementor - and by auto-deref for `Arc`s of those.
+/// The lock is only held for the duration of the closure call.
+/// If the lock can't be acquired, all methods return [`None`]:
+/// - for the std locks this happens when the lock is poisoned, otherwise they block until the lock is free
+/// - for the tokio locks this happens whenever the lock is currently held, since they are locked using `try_lock`.
+///   Blocking on an async lock from the UI thread could deadlock with a task holding the lock across an `.await`.
+///
+/// ```rust
+/// use std::sync::{Arc, Mutex};
+/// use lazy_async_promise::{ImmediateValueState, LockedCacheAccess};
+/// let shared = Arc::new(Mutex::new(ImmediateValueState::Success(34)));
+/// assert_eq!(shared.with_value(|v| *v), Some(34));
+/// shared.with_value_mut(|v| *v += 1);
+/// assert_eq!(shared.take_value(), Some(35));
+/// assert_eq!(shared.with_value(|v| *v), None);
+/// ```
+pub trait LockedCacheAccess<T, E> {
+    /// locks and calls `f` with a reference to the cache if applicable
+    fn with_value<R>(&self, f: impl FnOnce(&T) -> R) -> Option<R>;
+    /// locks and calls `f` with a mutable reference to the cache if applicable
+    fn with_value_mut<R>(&self, f: impl FnOnce(&mut T) -> R) -> Option<R>;
+    /// locks and calls `f` with a reference to the cache or to the error if applicable
+    fn with_result<R>(&self, f: impl FnOnce(Result<&T, &E>) -> R) -> Option<R>;
+    /// locks and takes the value, see [`DirectCacheAccess::take_value`]
+    fn take_value(&self) -> Option<T>;
+    /// locks and takes the value or error, see [`DirectCacheAccess::take_result`]
+    fn take_result(&self) -> Option<Result<T, E>>;
+}
+
+macro_rules! impl_locked_cache_access {
+    ($lock: ty, $read: ident, $write: ident) => {
+        impl<T, E, P: DirectCacheAccess<T, E>> LockedCacheAccess<T, E> for $lock {
+            fn with_value<R>(&self, f: impl FnOnce(&T) -> R) -> Option<R> {
+                self.$read().ok()?.get_value().map(f)
+            }
+            fn with_value_mut<R>(&self, f: impl FnOnce(&mut T) -> R) -> Option<R> {
+                self.$write().ok()?.get_value_mut().map(f)
+            }
+            fn with_result<R>(&self, f: impl FnOnce(Result<&T, &E>) -> R) -> Option<R> {
+                self.$read().ok()?.get_result().map(f)
+            }
+            fn take_value(&self) -> Option<T> {
+                self.$write().ok()?.take_value()
+            }
+            fn take_result(&self) -> Option<Result<T, E>> {
+                self.$write().ok()?.take_result()
+            }
+        }
+    };
+}
+
+impl_locked_cache_access!(std::sync::Mutex<P>, lock, lock);
+impl_locked_cache_access!(std::sync::RwLock<P>, read, write);
+#[cfg(feature = "tokio")]
+impl_locked_cache_access!(tokio::sync::Mutex<P>, try_lock, try_lock);
+#[cfg(feature = "tokio")]
+impl_locked_cache_access!(tokio::sync::RwLock<P>, try_read, try_write);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ImmediateValueState;
+    use std::sync::{Arc, Mutex, RwLock};
+
+    #[test]
+    fn std_locks() {
+        let mutex = Arc::new(Mutex::new(ImmediateValueState::Success(1)));
+        assert_eq!(mutex.with_value(|v| *v), Some(1));
+        assert_eq!(mutex.with_result(|r| r.is_ok()), Some(true));
+        mutex.with_value_mut(|v| *v = 2);
+        assert_eq!(mutex.take_value(), Some(2));
+        assert!(mutex.take_result().is_none());
+
+        let rw_lock = RwLock::new(ImmediateValueState::Success(1));
+        assert_eq!(rw_lock.with_value(|v| *v), Some(1));
+        assert_eq!(rw_lock.take_result().unwrap().ok(), Some(1));
+    }
+
+    #[test]
+    fn poisoned_lock_yields_none() {
+        let mutex = Arc::new(Mutex::new(ImmediateValueState::Success(1)));
+        let clone = mutex.clone();
+        let _ = std::thread::spawn(move || {
+            let _guard = clone.lock().unwrap();
+            panic!("poisoning");
+        })
+        .join();
+        assert!(mutex.with_value(|v| *v).is_none());
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn tokio_locks_dont_block() {
+        let mutex = tokio::sync::Mutex::new(ImmediateValueState::Success(1));
+        assert_eq!(mutex.with_value(|v| *v), Some(1));
+        let guard = mutex.try_lock().unwrap();
+        assert!(mutex.with_value(|v| *v).is_none());
+        drop(guard);
+
+        let rw_lock = tokio::sync::RwLock::new(ImmediateValueState::Success(1));
+        let read_guard = rw_lock.try_read().unwrap();
+        assert_eq!(rw_lock.with_value(|v| *v), Some(1));
+        assert!(rw_lock.take_value().is_none());
+        drop(read_guard);
+        assert_eq!(rw_lock.take_value(), Some(1));
+    }
+}
@@ -0,0 +1,255 @@
+use std::borrow::Cow;
+use std::fmt::{self, Debug, Formatter};
+use std::mem;
+
+use crate::{
+    BoxedSendError, DataState, DirectCacheAccess, IntoBoxedSendError, IntoProgress, Progress,
+    Promise, Status,
+};
+
+/// # A scripted promise for tests
+/// Implements [`Promise`] and [`DirectCacheAccess`] without a runtime, channels or timing, so widget tests can walk
+/// through exact state sequences. The controls below queue changes like a future sending messages, they are applied
+/// in order by the next [`Promise::poll_state`]. Statuses can be emitted like from a progress tracked promise.
+/// ```rust
+/// use lazy_async_promise::{DataState, DirectCacheAccess, MockPromise, Progress, Promise};
+/// let mut promise = MockPromise::<i32>::new();
+/// assert_eq!(promise.poll_state(), &DataState::Uninitialized);
+/// promise.set_updating(50);
+/// assert_eq!(promise.poll_state(), &DataState::Updating(Progress::from_percent(50)));
+/// promise.resolve(34);
+/// assert_eq!(promise.get_value(), None);
+/// promise.poll_state();
+/// assert_eq!(promise.get_value(), Some(&34));
+/// promise.fail("offline");
+/// assert!(promise.poll_state().is_error());
+/// ```
+pub struct MockPromise<T, M = Cow<'static, str>> {
+    state: DataState,
+    value: Option<T>,
+    status: Vec<Status<M>>,
+    scripted: Vec<Step<T, M>>,
+    polls: usize,
+    updates: usize,
+}
+
+impl<T, M> MockPromise<T, M> {
+    /// A mock in [`DataState::Uninitialized`] without a value
+    pub fn new() -> Self {
+        Self {
+            state: DataState::Uninitialized,
+            value: None,
+            status: Vec::new(),
+            scripted: Vec::new(),
+            polls: 0,
+            updates: 0,
+        }
+    }
+
+    /// Switch to [`DataState::Updating`] with the given progress, numbers are read as percent. The value is kept,
+    /// like the cache of a lazy promise while updating.
+    pub fn set_updating(&mut self, progress: impl IntoProgress) {
+        self.scripted.push(Step::Updating(progress.into_progress()));
+    }
+
+    /// Switch to [`DataState::UpToDate`] with the given value
+    pub fn resolve(&mut self, value: T) {
+        self.scripted.push(Step::Resolve(value));
+    }
+
+    /// Switch to [`DataState::Error`], the value is dropped
+    pub fn fail(&mut self, error: impl IntoBoxedSendError) {
+        self.scripted
+            .push(Step::Fail(error.into_boxed_send_error()));
+    }
+
+    /// Record a status as if sent by a tracked future and switch to [`DataState::Updating`] with its progress
+    pub fn emit_status(&mut self, status: Status<M>) {
+        self.scripted.push(Step::Status(status));
+    }
+
+    /// All statuses emitted so far, oldest first
+    pub fn status_history(&self) -> &[Status<M>] {
+        &self.status
+    }
+
+    /// The latest emitted status
+    pub fn last_status(&self) -> Option<&Status<M>> {
+        self.status.last()
+    }
+
+    /// The progress of the latest emitted status, or [`Progress::default`] if there was none
+    pub fn get_progress(&self) -> Progress {
+        self.last_status()
+            .map(|status| status.progress)
+            .unwrap_or_default()
+    }
+
+    /// How often [`Promise::poll_state`] was called
+    pub fn poll_count(&self) -> usize {
+        self.polls
+    }
+
+    /// How often [`Promise::update`] was called, e.g. by a retry button
+    pub fn update_count(&self) -> usize {
+        self.updates
+    }
+}
+
+enum Step<T, M> {
+    Updating(Progress),
+    Resolve(T),
+    Fail(BoxedSendError),
+    Status(Status<M>),
+}
+
+impl<T, M> Default for MockPromise<T, M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, M> Debug for MockPromise<T, M> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MockPromise")
+            .field("state", &self.state.tag())
+            .field("has_value", &self.value.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T, M> Promise for MockPromise<T, M> {
+    /// Applies the queued changes
+    fn poll_state(&mut self) -> &DataState {
+        self.polls += 1;
+        for step in self.scripted.drain(..) {
+            match step {
+                Step::Updating(progress) => self.state = DataState::Updating(progress),
+                Step::Resolve(value) => {
+                    self.value = Some(value);
+                    self.state = DataState::UpToDate;
+                }
+                Step::Fail(error) => {
+                    self.value = None;
+                    self.state = DataState::Error(error);
+                }
+                Step::Status(status) => {
+                    self.state = DataState::Updating(status.progress);
+                    self.status.push(status);
+                }
+            }
+        }
+        &self.state
+    }
+
+    fn state(&self) -> &DataState {
+        &self.state
+    }
+
+    /// Switches to [`DataState::Updating`] without progress, like the lazy promises
+    fn update(&mut self) {
+        self.updates += 1;
+        self.state = DataState::Updating(Progress::default());
+    }
+}
+
+impl<T, M> DirectCacheAccess<T, BoxedSendError> for MockPromise<T, M> {
+    fn get_value_mut(&mut self) -> Option<&mut T> {
+        self.value.as_mut()
+    }
+
+    fn get_value(&self) -> Option<&T> {
+        self.value.as_ref()
+    }
+
+    fn get_result(&self) -> Option<Result<&T, &BoxedSendError>> {
+        match &self.state {
+            DataState::UpToDate => self.value.as_ref().map(Ok),
+            DataState::Error(error) => Some(Err(error)),
+            _ => None,
+        }
+    }
+
+    fn get_result_mut(&mut self) -> Option<Result<&mut T, &mut BoxedSendError>> {
+        match &mut self.state {
+            DataState::UpToDate => self.value.as_mut().map(Ok),
+            DataState::Error(error) => Some(Err(error)),
+            _ => None,
+        }
+    }
+
+    /// Same as for [`crate::LazyValuePromise`]: only an up-to-date value is taken, the mock is uninitialized after
+    fn take_value(&mut self) -> Option<T> {
+        if self.state != DataState::UpToDate {
+            return None;
+        }
+        self.state = DataState::Uninitialized;
+        self.value.take()
+    }
+
+    fn take_result(&mut self) -> Option<Result<T, BoxedSendError>> {
+        match mem::replace(&mut self.state, DataState::Uninitialized) {
+            DataState::UpToDate => self.value.take().map(Ok),
+            DataState::Error(error) => Some(Err(error)),
+            state => {
+                self.state = state;
+                None
+            }
+        }
+    }
+
+    fn is_pending(&self) -> bool {
+        self.state.is_updating()
+    }
+
+    /// Sets the value and [`DataState::UpToDate`] right away, returning the previous value
+    fn set_value(&mut self, value: T) -> Option<T> {
+        self.state = DataState::UpToDate;
+        self.value.replace(value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::MockPromise;
+    use crate::{
+        DataState, DataStateTag, DirectCacheAccess, Progress, Promise, PromiseExt, StringStatus,
+    };
+
+    #[test]
+    fn scripted_sequence() {
+        let mut promise = MockPromise::<Vec<i32>>::new();
+        let mut seen = Vec::new();
+        promise.update();
+        assert!(!promise.on_transition(|_, _| unreachable!()));
+        promise.emit_status(StringStatus::from_str(
+            Progress::from_percent(40),
+            "fetching",
+        ));
+        assert!(!promise.on_transition(|_, _| unreachable!()));
+        assert_eq!(promise.get_progress(), Progress::from_percent(40));
+        assert_eq!(promise.progress(), Some(Progress::from_percent(40)));
+        assert_eq!(promise.last_status().unwrap().message, "fetching");
+        promise.resolve(vec![1, 2]);
+        assert!(promise.on_transition(|previous, current| seen.push((previous, current.tag()))));
+        assert_eq!(
+            seen,
+            vec![(
+                DataStateTag::Updating(Progress::from_percent(40)),
+                DataStateTag::UpToDate
+            )]
+        );
+        assert_eq!(promise.get_value().map(Vec::len), Some(2));
+        assert_eq!(promise.poll_count(), 3);
+        assert_eq!(promise.update_count(), 1);
+
+        promise.fail("offline");
+        promise.poll_state();
+        assert_eq!(promise.get_value(), None);
+        assert_eq!(
+            promise.take_result().unwrap().unwrap_err().to_string(),
+            "offline"
+        );
+        assert_eq!(promise.poll_state(), &DataState::Uninitialized);
+    }
+}
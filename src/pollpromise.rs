@@ -0,0 +1,93 @@
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use tokio::sync::oneshot;
+
+use crate::{
+    panic_error, runtime, BoxedSendError, DirectCacheAccess, ImmediateValuePromise,
+    ImmediateValueState, StateTag,
+};
+
+impl<T: Send + 'static> ImmediateValuePromise<T> {
+    /// Adapts a [`poll_promise::Promise`]. This can't be a `From` impl, since it would collide with the one for futures.
+    /// poll-promise has no way to notify waiters, so a thread is spawned which blocks until it's ready and hands the
+    /// result over. It has neither cancellation nor progress, so `cancel` does nothing on the converted promise.
+    /// If the sender of the poll promise is dropped without a value, the promise switches to
+    /// [`ImmediateValueState::Error`].
+    pub fn from_poll_promise<E: Into<BoxedSendError> + Send + 'static>(
+        promise: poll_promise::Promise<Result<T, E>>,
+    ) -> Self {
+        let (tx, rx) = oneshot::channel();
+        std::thread::spawn(move || {
+            let result = catch_unwind(AssertUnwindSafe(|| promise.block_and_take()))
+                .map(|result| result.map_err(Into::into))
+                .unwrap_or_else(|panic| Err(panic_error(panic.as_ref())));
+            let _ = tx.send(result);
+        });
+        ImmediateValuePromise::new(async move { rx.await? })
+    }
+
+    /// Convert into a [`poll_promise::Promise`], which becomes ready once the future finished. Needs to be called
+    /// within the runtime, since a task waits for the result. Lost on the way are cancellation, since the promise
+    /// can't be reached anymore, as well as [`ImmediateValuePromise::state_watch`] and the name. The error hook is
+    /// called from that task. A value already taken out, or a panicking future, end in an error.
+    pub fn into_poll_promise(mut self) -> poll_promise::Promise<Result<T, BoxedSendError>> {
+        let (sender, promise) = poll_promise::Promise::new();
+        let mut tags = self.tags.clone();
+        let task = runtime::spawn(async move {
+            // the watch is closed without a final tag if the future panics
+            let _ = tags.wait_for(|tag| *tag != StateTag::Updating).await;
+            let result = match self.poll_state_mut() {
+                ImmediateValueState::Updating => Err(BoxedSendError::msg("future panicked")),
+                ImmediateValueState::Empty => Err(BoxedSendError::msg("value was already taken")),
+                state => state.take_result().expect("promise is finished"),
+            };
+            sender.send(result);
+        });
+        task.detach();
+        promise
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use crate::runtime::sleep;
+    use crate::{BoxedSendError, DirectCacheAccess, ImmediateValuePromise, ImmediateValueState};
+
+    #[tokio::test]
+    async fn from_poll_promise() {
+        let poll = poll_promise::Promise::spawn_thread("job", || Ok::<_, BoxedSendError>(34));
+        let mut promise = ImmediateValuePromise::from_poll_promise(poll);
+        sleep(Duration::from_millis(50)).await;
+        assert!(matches!(
+            promise.poll_state(),
+            ImmediateValueState::Success(34)
+        ));
+
+        let (sender, poll) = poll_promise::Promise::<Result<i32, BoxedSendError>>::new();
+        drop(sender);
+        let mut promise = ImmediateValuePromise::from_poll_promise(poll);
+        sleep(Duration::from_millis(50)).await;
+        assert!(promise.poll_state().is_error());
+    }
+
+    #[tokio::test]
+    async fn into_poll_promise() {
+        let promise = ImmediateValuePromise::new(async {
+            sleep(Duration::from_millis(10)).await;
+            Ok(34)
+        });
+        let poll = promise.into_poll_promise();
+        assert!(poll.ready().is_none());
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(*poll.ready().unwrap().as_ref().unwrap(), 34);
+
+        let mut taken = ImmediateValuePromise::new(async { Ok(1) });
+        sleep(Duration::from_millis(20)).await;
+        assert_eq!(taken.poll_state_mut().take_value(), Some(1));
+        let poll = taken.into_poll_promise();
+        sleep(Duration::from_millis(20)).await;
+        assert!(poll.ready().unwrap().is_err());
+    }
+}
@@ -0,0 +1,109 @@
+use std::mem;
+
+use crate::{BoxedSendError, DataState, DataStateTag, DirectCacheAccess, Promise};
+
+/// # Combinators for any [`Promise`] with a cache
+/// Blanket-implemented for everything implementing [`Promise`] and [`DirectCacheAccess`], so generic helpers and
+/// widgets can be written against the traits instead of the concrete promise types.
+/// ```rust, no_run
+/// use lazy_async_promise::{DataState, Promise, PromiseExt};
+/// fn item_count<P: PromiseExt<Vec<i32>>>(promise: &mut P) -> usize {
+///     promise.poll_and(|state, data| match state {
+///         DataState::Error(_) => 0,
+///         _ => data.map(Vec::len).unwrap_or_default(),
+///     })
+/// }
+/// ```
+pub trait PromiseExt<T>: Promise + DirectCacheAccess<T, BoxedSendError> {
+    /// Poll the promise and pass the new state together with the cached value to `f`
+    fn poll_and<R>(&mut self, f: impl FnOnce(&DataState, Option<&T>) -> R) -> R {
+        self.poll_state();
+        f(self.state(), self.get_value())
+    }
+
+    /// Trigger an update if the predicate holds for the current state and value, e.g. to refresh stale data.
+    /// Returns whether an update was started.
+    fn update_if(&mut self, predicate: impl FnOnce(&DataState, Option<&T>) -> bool) -> bool {
+        let stale = predicate(self.state(), self.get_value());
+        if stale {
+            self.update();
+        }
+        stale
+    }
+
+    /// Poll the promise and call `f` with the previous state tag and the new state if the poll changed the kind of
+    /// state. Progress changes while updating don't count as transition. Returns whether `f` was called.
+    fn on_transition(&mut self, f: impl FnOnce(DataStateTag, &DataState)) -> bool {
+        let previous = self.state().tag();
+        let current = self.poll_state();
+        let changed = mem::discriminant(&previous) != mem::discriminant(&current.tag());
+        if changed {
+            f(previous, current);
+        }
+        changed
+    }
+}
+
+impl<T, P: Promise + DirectCacheAccess<T, BoxedSendError>> PromiseExt<T> for P {}
+
+#[cfg(all(test, feature = "tokio"))]
+mod test {
+    use std::time::Duration;
+
+    use tokio::sync::mpsc::Sender;
+
+    use super::PromiseExt;
+    use crate::api_macros::*;
+    use crate::runtime::sleep;
+    use crate::{DataState, DataStateTag, LazyValuePromise, LazyVecPromise, Message, Promise};
+
+    fn item_count<P: PromiseExt<Vec<i32>>>(promise: &mut P) -> Option<usize> {
+        promise.poll_and(|state, data| {
+            state
+                .is_success()
+                .then(|| data.map(Vec::len).unwrap_or_default())
+        })
+    }
+
+    #[tokio::test]
+    async fn generic_combinators() {
+        let updater = |tx: Sender<Message<i32>>| async move {
+            send_data!(1, tx);
+            send_data!(2, tx);
+            set_finished!(tx);
+        };
+        let mut promise = LazyVecPromise::new(updater, 6);
+        assert_eq!(item_count(&mut promise), None);
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(item_count(&mut promise), Some(2));
+
+        assert!(!promise.update_if(|state, _| !state.is_success()));
+        assert!(promise.update_if(|_, data| data.is_some_and(|data| data.len() < 3)));
+        assert!(promise.state().is_updating());
+    }
+
+    #[tokio::test]
+    async fn transitions() {
+        let updater = |tx: Sender<Message<i32>>| async move {
+            sleep(Duration::from_millis(10)).await;
+            send_data!(34, tx);
+            set_finished!(tx);
+        };
+        let mut promise = LazyValuePromise::new(updater, 6);
+        let mut seen = Vec::new();
+        assert!(promise.on_transition(|previous, current| seen.push((previous, current.tag()))));
+        sleep(Duration::from_millis(50)).await;
+        assert!(promise.on_transition(|previous, current| seen.push((previous, current.tag()))));
+        assert!(!promise.on_transition(|_, _| unreachable!()));
+        assert_eq!(
+            seen,
+            vec![
+                (
+                    DataStateTag::Uninitialized,
+                    DataStateTag::Updating(0.0.into())
+                ),
+                (DataStateTag::Updating(0.0.into()), DataStateTag::UpToDate),
+            ]
+        );
+    }
+}
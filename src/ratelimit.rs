@@ -0,0 +1,57 @@
+use std::time::{Duration, Instant};
+
+use crate::clock;
+
+/// What a lazy promise does with an update requested sooner than its minimum interval after the last started
+/// update, see [`crate::LazyVecPromise::with_min_update_interval`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UpdateLimit {
+    /// Drop the request, the promise keeps its state
+    Ignore,
+    /// Start the update once the interval elapsed, the promise is updating meanwhile
+    Defer,
+}
+
+/// What happened to a requested update, see [`crate::LazyVecPromise::request_update`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UpdateOutcome {
+    /// The update started right away
+    Started,
+    /// The update starts after the given delay, see [`UpdateLimit::Defer`]
+    Deferred(Duration),
+    /// The request came too soon and was dropped, see [`UpdateLimit::Ignore`]
+    Ignored,
+    /// An update is running already, nothing was started
+    AlreadyUpdating,
+}
+
+/// Tracks the started updates of a lazy promise to enforce its minimum update interval
+#[derive(Default)]
+pub(crate) struct MinInterval {
+    limit: Option<(Duration, UpdateLimit)>,
+    last_start: Option<Instant>,
+}
+
+impl MinInterval {
+    pub(crate) fn set(&mut self, interval: Duration, limit: UpdateLimit) {
+        self.limit = Some((interval, limit));
+    }
+
+    /// What would happen to an update requested now
+    pub(crate) fn check(&self) -> UpdateOutcome {
+        let (Some((interval, limit)), Some(last_start)) = (self.limit, self.last_start) else {
+            return UpdateOutcome::Started;
+        };
+        let wait = (last_start + interval).saturating_duration_since(clock::now());
+        match limit {
+            _ if wait.is_zero() => UpdateOutcome::Started,
+            UpdateLimit::Ignore => UpdateOutcome::Ignored,
+            UpdateLimit::Defer => UpdateOutcome::Deferred(wait),
+        }
+    }
+
+    /// Record an update starting after `delay`
+    pub(crate) fn starting(&mut self, delay: Duration) {
+        self.last_start = Some(clock::now() + delay);
+    }
+}
@@ -0,0 +1,215 @@
+use std::borrow::Cow;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use tokio::sync::mpsc::Sender;
+use tokio::sync::oneshot;
+
+use crate::{
+    panic_error, BoxedSendError, ImmediateValuePromise, Progress, ProgressTrackedImValProm,
+    StringStatus,
+};
+
+/// Runs the job on the rayon global pool, panics are turned into errors
+fn spawn_rayon<T: Send + 'static>(
+    job: impl FnOnce() -> Result<T, BoxedSendError> + Send + 'static,
+) -> ImmediateValuePromise<T> {
+    let (tx, rx) = oneshot::channel();
+    rayon::spawn(move || {
+        let result = catch_unwind(AssertUnwindSafe(job))
+            .unwrap_or_else(|panic| Err(panic_error(panic.as_ref())));
+        let _ = tx.send(result);
+    });
+    ImmediateValuePromise::new(async move { rx.await? })
+}
+
+impl<T: Send + 'static> ImmediateValuePromise<T> {
+    /// Creator for blocking, data-parallel work: runs `job` on the rayon global thread pool, so `par_iter` and friends
+    /// can be used inside. The result is delivered like the one of a future given to [`ImmediateValuePromise::new`].
+    /// ```rust, no_run
+    /// use lazy_async_promise::ImmediateValuePromise;
+    /// use rayon::prelude::*;
+    /// let data: Vec<u64> = (0..1_000_000).collect();
+    /// let promise = ImmediateValuePromise::new_rayon(move || Ok(data.par_iter().map(|v| v * 2).sum::<u64>()));
+    /// ```
+    pub fn new_rayon(job: impl FnOnce() -> Result<T, BoxedSendError> + Send + 'static) -> Self {
+        spawn_rayon(job)
+    }
+}
+
+/// # Thread-safe progress counter for rayon jobs
+/// Handed to the job of [`ProgressTrackedImValProm::new_rayon`], cheap to clone and to share between `par_iter` closures.
+/// A [`StringStatus`] is sent whenever the progress advances by at least one percent. If the status buffer is full,
+/// the status is skipped instead of blocking the worker thread. Once the job succeeded, a final 100% status is sent
+/// if it was skipped, waiting for space in the buffer.
+#[derive(Clone)]
+pub struct ParallelProgress {
+    done: Arc<AtomicU64>,
+    /// the last percentage sent, locked while sending so the statuses go out in order
+    reported_percent: Arc<Mutex<u64>>,
+    total: u64,
+    sender: Sender<StringStatus>,
+}
+
+impl ParallelProgress {
+    /// Count one finished item
+    pub fn tick(&self) {
+        self.add(1);
+    }
+
+    /// Count `items` finished items
+    pub fn add(&self, items: u64) {
+        let done = self.done.fetch_add(items, Ordering::Relaxed) + items;
+        let percent = self.percent_of(done);
+        // only lock if this call passed a percent
+        if percent == self.percent_of(done - items) {
+            return;
+        }
+        let mut reported = self
+            .reported_percent
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if *reported < percent && self.sender.try_send(Self::status(percent)).is_ok() {
+            *reported = percent;
+        }
+    }
+
+    /// Send the 100% status if it was skipped, waiting for space in the buffer
+    fn finish(&self) {
+        let mut reported = self
+            .reported_percent
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if *reported < 100 && self.sender.blocking_send(Self::status(100)).is_ok() {
+            *reported = 100;
+        }
+    }
+
+    fn percent_of(&self, done: u64) -> u64 {
+        (done.saturating_mul(100) / self.total.max(1)).min(100)
+    }
+
+    fn status(percent: u64) -> StringStatus {
+        StringStatus::from_str(Progress::from_percent(percent as f64), "")
+    }
+
+    /// The current progress
+    pub fn progress(&self) -> Progress {
+        Progress::from_fraction(
+            self.done.load(Ordering::Relaxed) as f64,
+            self.total.max(1) as f64,
+        )
+    }
+}
+
+impl<T: Send + 'static> ProgressTrackedImValProm<T, Cow<'static, str>> {
+    /// Creator for data-parallel work with progress tracking, see [`ImmediateValuePromise::new_rayon`].
+    /// The job counts its finished items out of `total` on the given [`ParallelProgress`].
+    /// ```rust, no_run
+    /// use lazy_async_promise::ProgressTrackedImValProm;
+    /// use rayon::prelude::*;
+    /// let rows: Vec<u64> = (0..10_000).collect();
+    /// let promise = ProgressTrackedImValProm::new_rayon(rows.len() as u64, move |progress| {
+    ///     Ok(rows.par_iter().map(|row| { progress.tick(); row * 2 }).collect::<Vec<_>>())
+    /// }, 100);
+    /// ```
+    pub fn new_rayon(
+        total: u64,
+        job: impl FnOnce(&ParallelProgress) -> Result<T, BoxedSendError> + Send + 'static,
+        buffer: usize,
+    ) -> Self {
+        ProgressTrackedImValProm::new(
+            |sender| {
+                let progress = ParallelProgress {
+                    done: Arc::new(AtomicU64::new(0)),
+                    reported_percent: Arc::new(Mutex::new(0)),
+                    total,
+                    sender,
+                };
+                spawn_rayon(move || {
+                    let result = job(&progress);
+                    if result.is_ok() {
+                        progress.finish();
+                    }
+                    result
+                })
+            },
+            buffer,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use rayon::prelude::*;
+
+    use crate::runtime::sleep;
+    use crate::{ImmediateValuePromise, ImmediateValueState, ProgressTrackedImValProm};
+
+    #[tokio::test]
+    async fn rayon_job() {
+        let mut promise =
+            ImmediateValuePromise::new_rayon(|| Ok((1..=100u64).into_par_iter().sum()));
+        sleep(Duration::from_millis(50)).await;
+        assert!(matches!(
+            promise.poll_state(),
+            ImmediateValueState::Success(5050)
+        ));
+
+        let mut panicking = ImmediateValuePromise::<u64>::new_rayon(|| panic!("boom"));
+        sleep(Duration::from_millis(50)).await;
+        let ImmediateValueState::Error(error) = panicking.poll_state() else {
+            unreachable!();
+        };
+        assert_eq!(error.to_string(), "updater panicked: boom");
+    }
+
+    #[tokio::test]
+    async fn rayon_progress() {
+        let rows: Vec<u64> = (0..1000).collect();
+        let mut promise = ProgressTrackedImValProm::new_rayon(
+            rows.len() as u64,
+            move |progress| {
+                Ok(rows
+                    .par_iter()
+                    .map(|row| {
+                        progress.tick();
+                        row * 2
+                    })
+                    .collect::<Vec<_>>())
+            },
+            200,
+        );
+        sleep(Duration::from_millis(100)).await;
+        let ImmediateValueState::Success(doubled) = promise.poll_state() else {
+            unreachable!();
+        };
+        assert_eq!(doubled.len(), 1000);
+        assert_eq!(*promise.get_progress(), 1.0);
+        assert!(promise.status_history().len() <= 100);
+    }
+
+    #[tokio::test]
+    async fn rayon_progress_with_small_buffer() {
+        let mut promise = ProgressTrackedImValProm::new_rayon(
+            1000,
+            |progress| {
+                (0..1000u64).into_par_iter().for_each(|_| progress.tick());
+                Ok(())
+            },
+            8,
+        );
+        // the final status waits for the first poll to make room
+        while promise.poll_state().is_updating() {
+            sleep(Duration::from_millis(5)).await;
+        }
+        assert!(promise.poll_state().is_success());
+        assert_eq!(*promise.get_progress(), 1.0);
+        let history = promise.status_history();
+        assert!(history.len() <= 9);
+        assert!(history.is_sorted_by(|a, b| a.progress.as_f64() < b.progress.as_f64()));
+    }
+}
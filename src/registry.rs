@@ -0,0 +1,187 @@
+//! # Registry of live promises for debug overlays
+//! With the `registry` feature, every promise given a name registers itself here and deregisters on drop.
+//! [`snapshot`] lists them, e.g. to show a debug window explaining a spinner that doesn't go away:
+//! ```rust
+//! # #[tokio::main]
+//! # async fn main() {
+//! use lazy_async_promise::{registry, ImmediateValuePromise};
+//! let mut promise = ImmediateValuePromise::new_named("fetch_config", async { Ok(34) });
+//! promise.poll_state();
+//! let info = registry::snapshot()
+//!     .into_iter()
+//!     .find(|info| info.name == "fetch_config")
+//!     .unwrap();
+//! assert_eq!(info.type_name, "ImmediateValuePromise");
+//! assert_eq!(info.polls, 1);
+//! # }
+//! ```
+use std::sync::{Arc, Mutex, PoisonError, Weak};
+use std::time::{Duration, Instant};
+
+use crate::{clock, DataState, ImmediateValueState, Progress};
+
+static REGISTRY: Mutex<Vec<Weak<Entry>>> = Mutex::new(Vec::new());
+
+/// What the registry knows about a live promise, see [`snapshot`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PromiseInfo {
+    /// The name given to the promise
+    pub name: String,
+    /// The kind of promise, e.g. `"LazyVecPromise"`
+    pub type_name: &'static str,
+    /// The state observed by the last poll, e.g. `"Updating"`
+    pub state: &'static str,
+    /// The progress observed by the last poll, if the promise reports one
+    pub progress: Option<Progress>,
+    /// Time since the promise registered
+    pub age: Duration,
+    /// How often the promise was polled
+    pub polls: u64,
+}
+
+/// The live promises in the order they registered. The entries are copied while holding the registry lock, no user
+/// code runs meanwhile.
+pub fn snapshot() -> Vec<PromiseInfo> {
+    let registry = REGISTRY.lock().unwrap_or_else(PoisonError::into_inner);
+    registry
+        .iter()
+        .filter_map(Weak::upgrade)
+        .map(|entry| entry.info())
+        .collect()
+}
+
+struct Entry {
+    name: String,
+    type_name: &'static str,
+    created: Instant,
+    observed: Mutex<Observed>,
+}
+
+struct Observed {
+    state: &'static str,
+    progress: Option<Progress>,
+    polls: u64,
+}
+
+impl Entry {
+    fn info(&self) -> PromiseInfo {
+        let observed = self.observed.lock().unwrap_or_else(PoisonError::into_inner);
+        PromiseInfo {
+            name: self.name.clone(),
+            type_name: self.type_name,
+            state: observed.state,
+            progress: observed.progress,
+            age: clock::since(self.created),
+            polls: observed.polls,
+        }
+    }
+}
+
+/// The name of the state variant, as listed in [`PromiseInfo::state`]
+pub(crate) fn data_state_variant(state: &DataState) -> &'static str {
+    match state {
+        DataState::Uninitialized => "Uninitialized",
+        DataState::UpToDate => "UpToDate",
+        DataState::Updating(_) => "Updating",
+        DataState::Error(_) => "Error",
+        DataState::Cancelled => "Cancelled",
+    }
+}
+
+/// Keeps a promise listed in the registry until dropped
+pub(crate) struct Registration(Arc<Entry>);
+
+impl Registration {
+    pub(crate) fn new(name: &str, type_name: &'static str, state: &'static str) -> Self {
+        let entry = Arc::new(Entry {
+            name: name.to_string(),
+            type_name,
+            created: clock::now(),
+            observed: Mutex::new(Observed {
+                state,
+                progress: None,
+                polls: 0,
+            }),
+        });
+        let mut registry = REGISTRY.lock().unwrap_or_else(PoisonError::into_inner);
+        registry.push(Arc::downgrade(&entry));
+        Registration(entry)
+    }
+
+    fn record(&self, state: &'static str, progress: Option<Progress>) {
+        let mut observed = self
+            .0
+            .observed
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        observed.state = state;
+        observed.progress = progress;
+        observed.polls += 1;
+    }
+
+    pub(crate) fn record_data_state(&self, state: &DataState) {
+        self.record(data_state_variant(state), state.get_progress());
+    }
+
+    pub(crate) fn record_immediate_state<T>(
+        &self,
+        state: &ImmediateValueState<T>,
+        progress: Option<Progress>,
+    ) {
+        let name = match state {
+            ImmediateValueState::Updating => "Updating",
+            ImmediateValueState::Success(_) => "Success",
+            ImmediateValueState::Error(_) => "Error",
+            ImmediateValueState::Empty => "Empty",
+        };
+        self.record(name, progress);
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        let mut registry = REGISTRY.lock().unwrap_or_else(PoisonError::into_inner);
+        registry.retain(|entry| entry.strong_count() > 0 && entry.as_ptr() != Arc::as_ptr(&self.0));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use tokio::sync::mpsc::Sender;
+
+    use super::snapshot;
+    use crate::api_macros::*;
+    use crate::runtime::sleep;
+    use crate::{DataState, LazyVecPromise, Message, Promise};
+
+    #[tokio::test]
+    async fn lists_named_promises_until_dropped() {
+        let updater = |tx: Sender<Message<i32>>| async move {
+            set_progress!(Progress::from_percent(50), tx);
+            sleep(Duration::from_secs(10)).await;
+            set_finished!(tx);
+        };
+        let mut promise = LazyVecPromise::new(updater, 6).with_name("registry_test");
+        let mut unnamed = LazyVecPromise::new(updater, 6);
+        let find = || {
+            snapshot()
+                .into_iter()
+                .find(|info| info.name == "registry_test")
+        };
+        assert_eq!(find().unwrap().state, "Uninitialized");
+        promise.poll_state();
+        unnamed.poll_state();
+        sleep(Duration::from_millis(20)).await;
+        promise.poll_state();
+        let info = find().unwrap();
+        assert_eq!(info.type_name, "LazyVecPromise");
+        assert_eq!(info.state, "Updating");
+        assert_eq!(info.progress, Some(Progress::from_percent(50)));
+        assert_eq!(info.polls, 2);
+
+        drop(promise);
+        assert!(find().is_none());
+    }
+}
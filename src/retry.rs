@@ -0,0 +1,95 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::BoxedSendError;
+
+type RetryPredicate = Arc<dyn Fn(&BoxedSendError) -> bool + Send + Sync>;
+
+/// # Automatic retries for failing lazy promise updaters
+/// Used with [`crate::LazyVecPromise::with_retry`] and [`crate::LazyValuePromise::with_retry`].
+/// If an update fails with an error the predicate accepts, the updater is started again after the backoff delay
+/// while the promise stays in [`crate::DataState::Updating`]. Only the final failure is reported as error.
+/// Each retry starts from scratch like [`crate::Promise::update`], so partial data of the failed attempt is cleared.
+/// ```rust
+/// use std::time::Duration;
+/// use lazy_async_promise::RetryPolicy;
+/// let policy = RetryPolicy::new(3)
+///     .with_backoff(Duration::from_millis(100), 2.0)
+///     .with_predicate(|error| error.to_string().contains("429"));
+/// assert_eq!(policy.delay_for(2), Duration::from_millis(400));
+/// ```
+#[derive(Clone)]
+pub struct RetryPolicy {
+    max_retries: u32,
+    initial_delay: Duration,
+    factor: f64,
+    predicate: Option<RetryPredicate>,
+}
+
+impl RetryPolicy {
+    /// Retry up to `max_retries` times without delay for any error
+    pub fn new(max_retries: u32) -> Self {
+        RetryPolicy {
+            max_retries,
+            initial_delay: Duration::ZERO,
+            factor: 1.0,
+            predicate: None,
+        }
+    }
+
+    /// Wait `initial_delay` before the first retry, each further retry waits `factor` times longer
+    pub fn with_backoff(mut self, initial_delay: Duration, factor: f64) -> Self {
+        self.initial_delay = initial_delay;
+        self.factor = factor.max(0.0);
+        self
+    }
+
+    /// Only retry errors the predicate returns true for
+    pub fn with_predicate(
+        mut self,
+        predicate: impl Fn(&BoxedSendError) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.predicate = Some(Arc::new(predicate));
+        self
+    }
+
+    /// The maximum number of retries
+    pub fn max_retries(&self) -> u32 {
+        self.max_retries
+    }
+
+    /// The delay before the retry with the given zero-based index
+    pub fn delay_for(&self, retry: u32) -> Duration {
+        self.initial_delay
+            .mul_f64(self.factor.powi(retry.min(i32::MAX as u32) as i32))
+    }
+
+    /// Should we retry after `retries_done` retries failed with `error`?
+    pub fn should_retry(&self, retries_done: u32, error: &BoxedSendError) -> bool {
+        retries_done < self.max_retries
+            && self
+                .predicate
+                .as_ref()
+                .map(|predicate| predicate(error))
+                .unwrap_or(true)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn backoff_and_predicate() {
+        let policy = RetryPolicy::new(2)
+            .with_backoff(Duration::from_millis(10), 3.0)
+            .with_predicate(|error| error.to_string() == "retry me");
+        assert_eq!(policy.delay_for(0), Duration::from_millis(10));
+        assert_eq!(policy.delay_for(1), Duration::from_millis(30));
+        assert!(policy.should_retry(0, &BoxedSendError::msg("retry me")));
+        assert!(policy.should_retry(1, &BoxedSendError::msg("retry me")));
+        assert!(!policy.should_retry(2, &BoxedSendError::msg("retry me")));
+        assert!(!policy.should_retry(0, &BoxedSendError::msg("fatal")));
+        assert_eq!(RetryPolicy::new(1).delay_for(5), Duration::ZERO);
+    }
+}
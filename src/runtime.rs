@@ -0,0 +1,237 @@
+//! Spawning and timers of the selected async runtime. The channels and locks of `tokio::sync` are runtime agnostic,
+//! so only these need to be switched.
+use std::any::Any;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::future::{poll_fn, Future};
+use std::pin::{pin, Pin};
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+
+use crate::BoxedSendError;
+
+/// How a spawned task ended if it didn't return normally
+pub(crate) enum TaskFailure {
+    /// the task panicked with the given payload
+    Panic(Box<dyn Any + Send>),
+    /// the task was cancelled by the runtime
+    #[cfg_attr(feature = "async-std", allow(dead_code))]
+    Other(BoxedSendError),
+}
+
+#[cfg(not(feature = "async-std"))]
+type JoinHandle = tokio::task::JoinHandle<()>;
+#[cfg(feature = "async-std")]
+type JoinHandle = async_std::task::JoinHandle<Result<(), Box<dyn Any + Send>>>;
+
+/// Handle to a task spawned by [`spawn`]. The task is aborted when the handle is dropped, unless it was detached.
+pub(crate) struct Task {
+    /// only `None` while detaching or joining
+    handle: Option<JoinHandle>,
+    /// kept once [`Task::poll_finished`] saw the task end, until it is joined
+    outcome: Option<Result<(), TaskFailure>>,
+}
+
+impl Task {
+    fn new(handle: JoinHandle) -> Self {
+        Task {
+            handle: Some(handle),
+            outcome: None,
+        }
+    }
+
+    fn handle(&mut self) -> &mut JoinHandle {
+        self.handle
+            .as_mut()
+            .expect("task handle is kept until the task is dropped")
+    }
+
+    /// Ready once the task ended, the outcome is kept for [`Task::try_join`]
+    pub(crate) fn poll_finished(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        if self.outcome.is_none() {
+            let result = std::task::ready!(Pin::new(self.handle()).poll(cx));
+            self.outcome = Some(into_outcome(result));
+        }
+        Poll::Ready(())
+    }
+
+    /// The outcome of the task if it is finished, `None` if it's still running.
+    /// Must not be called again after it returned `Some`.
+    pub(crate) fn try_join(&mut self) -> Option<Result<(), TaskFailure>> {
+        let mut context = Context::from_waker(Waker::noop());
+        let _ = self.poll_finished(&mut context);
+        self.outcome.take()
+    }
+
+    /// Whether the task ended, its outcome is kept for [`Task::try_join`]
+    pub(crate) fn is_finished(&mut self) -> bool {
+        let mut context = Context::from_waker(Waker::noop());
+        self.poll_finished(&mut context).is_ready()
+    }
+
+    /// Stops the task at its next await point, its outcome is dropped. The same as dropping the handle.
+    pub(crate) fn abort(self) {
+        drop(self);
+    }
+
+    /// Lets the task run to completion without anyone waiting for it
+    pub(crate) fn detach(mut self) {
+        self.handle = None;
+    }
+
+    /// Waits for the task to end
+    pub(crate) async fn join(mut self) -> Result<(), TaskFailure> {
+        match self.outcome.take() {
+            Some(outcome) => outcome,
+            None => into_outcome(self.handle().await),
+        }
+    }
+}
+
+impl Drop for Task {
+    fn drop(&mut self) {
+        // a finished task has nothing left to abort
+        if self.outcome.is_some() {
+            return;
+        }
+        let Some(handle) = self.handle.take() else {
+            return;
+        };
+        #[cfg(not(feature = "async-std"))]
+        handle.abort();
+        #[cfg(feature = "async-std")]
+        async_std::task::spawn(handle.cancel());
+    }
+}
+
+/// A tokio task spawned elsewhere and adopted by a promise, see `ImmediateValuePromise::from_join_handle`.
+/// Aborted when dropped unless detached, like a [`Task`].
+pub(crate) struct AdoptedTask(Option<tokio::task::AbortHandle>);
+
+impl AdoptedTask {
+    pub(crate) fn new(handle: tokio::task::AbortHandle) -> Self {
+        AdoptedTask(Some(handle))
+    }
+
+    /// Lets the task run to completion without anyone waiting for it
+    pub(crate) fn detach(mut self) {
+        self.0 = None;
+    }
+}
+
+impl Drop for AdoptedTask {
+    fn drop(&mut self) {
+        if let Some(handle) = self.0.take() {
+            handle.abort();
+        }
+    }
+}
+
+#[cfg(not(feature = "async-std"))]
+fn into_outcome(result: Result<(), tokio::task::JoinError>) -> Result<(), TaskFailure> {
+    result.map_err(|error| {
+        if error.is_panic() {
+            TaskFailure::Panic(error.into_panic())
+        } else {
+            TaskFailure::Other(error.into())
+        }
+    })
+}
+
+#[cfg(feature = "async-std")]
+fn into_outcome(result: Result<(), Box<dyn Any + Send>>) -> Result<(), TaskFailure> {
+    result.map_err(TaskFailure::Panic)
+}
+
+/// Spawns the future on the runtime
+#[cfg(not(feature = "async-std"))]
+pub(crate) fn spawn(future: impl Future<Output = ()> + Send + 'static) -> Task {
+    Task::new(tokio::spawn(future))
+}
+
+/// Spawns the future on the runtime
+#[cfg(feature = "async-std")]
+pub(crate) fn spawn(future: impl Future<Output = ()> + Send + 'static) -> Task {
+    Task::new(async_std::task::spawn(CatchUnwind(Box::pin(future))))
+}
+
+/// Spawns the future on the runtime, naming the task for tokio-console. Names are only supported by tokio
+/// if built with `--cfg tokio_unstable`, otherwise this is a plain [`spawn`].
+pub(crate) fn spawn_named(
+    name: Option<&str>,
+    future: impl Future<Output = ()> + Send + 'static,
+) -> Task {
+    #[cfg(all(tokio_unstable, not(feature = "async-std")))]
+    if let Some(name) = name {
+        // only fails outside of a runtime, where plain spawning panics as well
+        let handle = tokio::task::Builder::new()
+            .name(name)
+            .spawn(future)
+            .expect("failed to spawn named task");
+        return Task::new(handle);
+    }
+    #[cfg(not(all(tokio_unstable, not(feature = "async-std"))))]
+    let _ = name;
+    spawn(future)
+}
+
+/// Waits for the given duration
+#[cfg(not(feature = "async-std"))]
+pub(crate) async fn sleep(duration: Duration) {
+    tokio::time::sleep(duration).await
+}
+
+/// Waits for the given duration
+#[cfg(feature = "async-std")]
+pub(crate) async fn sleep(duration: Duration) {
+    async_std::task::sleep(duration).await
+}
+
+/// The error a promise switches to if its future takes longer than the configured timeout,
+/// see `ImmediateValuePromiseBuilder::timeout`. Check for it with [`BoxedSendError::is_timed_out`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TimedOut;
+
+impl Display for TimedOut {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "promise timed out")
+    }
+}
+
+impl Error for TimedOut {}
+
+impl BoxedSendError {
+    /// Is this the error of a promise that timed out?
+    pub fn is_timed_out(&self) -> bool {
+        self.0.is::<TimedOut>()
+    }
+}
+
+/// Runs the future for at most the given duration, `None` if it didn't finish in time
+pub(crate) async fn timeout<F: Future>(duration: Duration, future: F) -> Option<F::Output> {
+    let mut future = pin!(future);
+    let mut deadline = pin!(sleep(duration));
+    poll_fn(|cx| match future.as_mut().poll(cx) {
+        Poll::Ready(output) => Poll::Ready(Some(output)),
+        Poll::Pending => deadline.as_mut().poll(cx).map(|()| None),
+    })
+    .await
+}
+
+/// Catches panics of the task and hands them to the join handle, like tokio does
+#[cfg(feature = "async-std")]
+struct CatchUnwind<F>(Pin<Box<F>>);
+
+#[cfg(feature = "async-std")]
+impl<F: Future<Output = ()>> Future for CatchUnwind<F> {
+    type Output = Result<(), Box<dyn Any + Send>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let future = self.0.as_mut();
+        match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| future.poll(cx))) {
+            Ok(Poll::Ready(())) => Poll::Ready(Ok(())),
+            Ok(Poll::Pending) => Poll::Pending,
+            Err(panic) => Poll::Ready(Err(panic)),
+        }
+    }
+}
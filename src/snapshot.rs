@@ -0,0 +1,334 @@
+#[cfg(feature = "bytes")]
+use crate::BytesPromise;
+#[cfg(feature = "test-util")]
+use crate::MockPromise;
+#[cfg(any(feature = "tokio", feature = "test-util"))]
+use crate::Promise;
+use crate::{
+    BoxedSendError, DataState, DataStateTag, ImmediateValueState, Progress, ProgressTracked,
+    StateTag, ThreadPromise, TrackedPromise,
+};
+#[cfg(feature = "tokio")]
+use crate::{ImmediateValuePromise, LazyValuePromise, LazyVecPromise, LazyWindowPromise};
+#[cfg(feature = "futures")]
+use crate::{SubscriptionPromise, SubscriptionState};
+#[cfg(feature = "tokio")]
+use std::fmt::Debug;
+
+/// An owned, lightweight description of a promise state, returned by the `poll_snapshot` methods of the promises.
+/// Unlike the state returned by `poll_state`, it doesn't borrow the promise, so other `&mut self` methods can be
+/// called while matching on it. The value stays in the promise and is accessible via [`crate::DirectCacheAccess`].
+/// ```rust, no_run
+/// use std::time::Duration;
+/// use lazy_async_promise::{DirectCacheAccess, StateTag, ThreadPromise};
+/// let mut promise = ThreadPromise::new(|| Ok(34));
+/// std::thread::sleep(Duration::from_millis(50));
+/// let snapshot = promise.poll_snapshot();
+/// if snapshot.state == StateTag::Success {
+///     // the promise is not borrowed anymore
+///     *promise.get_value_mut().unwrap() += 1;
+/// }
+/// assert_eq!(promise.get_value(), Some(&35));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct StateSnapshot<S = StateTag> {
+    /// The state without payload, a [`StateTag`] for immediate promises and a [`DataStateTag`] for lazy ones
+    pub state: S,
+    /// The user-facing error message in error state, see `display_error` on the promises
+    pub error: Option<String>,
+    /// Is a final value available, see [`crate::DirectCacheAccess::has_value`]
+    pub has_value: bool,
+}
+
+/// The [`StateSnapshot`] of the lazy promises
+pub type DataStateSnapshot = StateSnapshot<DataStateTag>;
+
+/// The state of any promise of this crate in one owned type, returned by [`UnifiedPoll::poll_unified`].
+/// Lets one function render all kinds of promises, the type specific states stay available via `poll_state`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PollResult {
+    /// Nothing was computed yet and no update is running, e.g. a [`crate::ImmediateValuePromise::empty`] promise
+    NotStarted,
+    /// Still computing, with the progress if the promise reports one, see [`Promise::progress`]
+    Pending {
+        /// The progress of the computation, [`Progress::indeterminate`] if the promise doesn't know it right now
+        progress: Option<Progress>,
+    },
+    /// The value is available via [`crate::DirectCacheAccess`]
+    Ready,
+    /// The computation failed
+    Failed {
+        /// The user-facing error message, see `display_error` on the promises
+        message: String,
+    },
+    /// The computation was cancelled
+    Cancelled,
+    /// The value was taken out of an immediate promise
+    Taken,
+}
+
+/// Polling any promise of this crate into a [`PollResult`]
+/// ```rust
+/// use lazy_async_promise::{PollResult, ThreadPromise, UnifiedPoll};
+/// fn label(promise: &mut impl UnifiedPoll) -> String {
+///     match promise.poll_unified() {
+///         PollResult::Pending { progress: Some(progress) } if !progress.is_indeterminate() => {
+///             format!("loading {:.0}%", progress.as_f64() * 100.0)
+///         }
+///         PollResult::Pending { .. } | PollResult::NotStarted => "loading".to_string(),
+///         PollResult::Ready => "done".to_string(),
+///         PollResult::Failed { message } => format!("failed: {message}"),
+///         PollResult::Cancelled | PollResult::Taken => String::new(),
+///     }
+/// }
+/// let mut promise = ThreadPromise::new(|| Ok(34));
+/// std::thread::sleep(std::time::Duration::from_millis(50));
+/// assert_eq!(label(&mut promise), "done");
+/// ```
+pub trait UnifiedPoll {
+    /// Poll the promise and describe its state
+    fn poll_unified(&mut self) -> PollResult;
+}
+
+/// Promises which describe their state as [`StateSnapshot`], implemented by all promises with a `poll_snapshot`
+/// method. Used by [`crate::SyncPromise::poll_state`].
+pub trait SnapshotPoll {
+    /// The state tag of the snapshot, [`StateTag`] or [`DataStateTag`]
+    type Tag;
+    /// Poll the promise and take a snapshot of its state
+    fn poll_snapshot(&mut self) -> StateSnapshot<Self::Tag>;
+}
+
+#[cfg(feature = "tokio")]
+impl<T: Send + 'static> SnapshotPoll for ImmediateValuePromise<T> {
+    type Tag = StateTag;
+    fn poll_snapshot(&mut self) -> StateSnapshot {
+        ImmediateValuePromise::poll_snapshot(self)
+    }
+}
+
+impl<T: Send + 'static> SnapshotPoll for ThreadPromise<T> {
+    type Tag = StateTag;
+    fn poll_snapshot(&mut self) -> StateSnapshot {
+        ThreadPromise::poll_snapshot(self)
+    }
+}
+
+impl<P: TrackedPromise, M> SnapshotPoll for ProgressTracked<P, M> {
+    type Tag = StateTag;
+    fn poll_snapshot(&mut self) -> StateSnapshot {
+        ProgressTracked::poll_snapshot(self)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<T: Debug + Send + 'static> SnapshotPoll for LazyVecPromise<T> {
+    type Tag = DataStateTag;
+    fn poll_snapshot(&mut self) -> DataStateSnapshot {
+        LazyVecPromise::poll_snapshot(self)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<T: Debug + Send + 'static> SnapshotPoll for LazyValuePromise<T> {
+    type Tag = DataStateTag;
+    fn poll_snapshot(&mut self) -> DataStateSnapshot {
+        LazyValuePromise::poll_snapshot(self)
+    }
+}
+
+#[cfg_attr(not(any(feature = "tokio", feature = "test-util")), allow(dead_code))]
+fn from_data_state(
+    state: &DataState,
+    message: impl FnOnce(&BoxedSendError) -> String,
+) -> PollResult {
+    match state {
+        DataState::Uninitialized => PollResult::NotStarted,
+        DataState::Updating(progress) => PollResult::Pending {
+            progress: Some(*progress),
+        },
+        DataState::UpToDate => PollResult::Ready,
+        DataState::Error(error) => PollResult::Failed {
+            message: message(error),
+        },
+        DataState::Cancelled => PollResult::Cancelled,
+    }
+}
+
+fn from_immediate_state<T>(
+    state: &ImmediateValueState<T>,
+    progress: Option<Progress>,
+    message: Option<String>,
+) -> PollResult {
+    match state {
+        ImmediateValueState::Updating => PollResult::Pending { progress },
+        ImmediateValueState::Success(_) => PollResult::Ready,
+        #[cfg(feature = "tokio-util")]
+        ImmediateValueState::Error(error) if error.is_cancelled() => PollResult::Cancelled,
+        ImmediateValueState::Error(error) => PollResult::Failed {
+            message: message.unwrap_or_else(|| error.to_string()),
+        },
+        ImmediateValueState::Empty => PollResult::Taken,
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<T: Send + 'static> UnifiedPoll for ImmediateValuePromise<T> {
+    fn poll_unified(&mut self) -> PollResult {
+        self.poll_state();
+        // the state watch never shows empty, unless no future was ever set
+        if self.get_state().is_empty() && *self.tags.borrow() == StateTag::Empty {
+            return PollResult::NotStarted;
+        }
+        from_immediate_state(self.get_state(), self.get_progress(), self.display_error())
+    }
+}
+
+impl<T: Send + 'static> UnifiedPoll for ThreadPromise<T> {
+    fn poll_unified(&mut self) -> PollResult {
+        self.poll_state();
+        from_immediate_state(self.get_state(), None, self.display_error())
+    }
+}
+
+impl<P: TrackedPromise, M> UnifiedPoll for ProgressTracked<P, M> {
+    fn poll_unified(&mut self) -> PollResult {
+        self.poll_state();
+        from_immediate_state(self.get_state(), self.progress(), self.display_error())
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<T: Debug + Send + 'static> UnifiedPoll for LazyVecPromise<T> {
+    fn poll_unified(&mut self) -> PollResult {
+        self.poll_state();
+        from_data_state(self.state(), |_| self.display_error().unwrap_or_default())
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<T: Debug + Send + 'static> UnifiedPoll for LazyValuePromise<T> {
+    fn poll_unified(&mut self) -> PollResult {
+        self.poll_state();
+        from_data_state(self.state(), |_| self.display_error().unwrap_or_default())
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<T: Debug + Send + 'static> UnifiedPoll for LazyWindowPromise<T> {
+    fn poll_unified(&mut self) -> PollResult {
+        from_data_state(self.poll_state(), ToString::to_string)
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl UnifiedPoll for BytesPromise {
+    fn poll_unified(&mut self) -> PollResult {
+        self.poll_state();
+        from_immediate_state(
+            self.get_state(),
+            Some(self.get_progress()),
+            self.display_error(),
+        )
+    }
+}
+
+/// The latest value is [`PollResult::Ready`] only while live, a stale one is still shown as pending
+#[cfg(feature = "futures")]
+impl<T: Send + 'static> UnifiedPoll for SubscriptionPromise<T> {
+    fn poll_unified(&mut self) -> PollResult {
+        match self.poll_state() {
+            SubscriptionState::Connecting | SubscriptionState::Reconnecting(_) => {
+                PollResult::Pending { progress: None }
+            }
+            SubscriptionState::Live => PollResult::Ready,
+            SubscriptionState::Disconnected => PollResult::Failed {
+                message: "subscription disconnected".to_string(),
+            },
+            SubscriptionState::Error(error) => PollResult::Failed {
+                message: error.to_string(),
+            },
+        }
+    }
+}
+
+#[cfg(feature = "test-util")]
+impl<T, M> UnifiedPoll for MockPromise<T, M> {
+    fn poll_unified(&mut self) -> PollResult {
+        from_data_state(self.poll_state(), ToString::to_string)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::threadpromise::ThreadPromise;
+    use crate::{BoxedSendError, DirectCacheAccess};
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    #[test]
+    fn snapshot_releases_borrow() {
+        let mut promise = ThreadPromise::new(|| Ok(34));
+        sleep(Duration::from_millis(50));
+        let snapshot = promise.poll_snapshot();
+        assert_eq!(
+            snapshot,
+            StateSnapshot {
+                state: StateTag::Success,
+                error: None,
+                has_value: true
+            }
+        );
+        assert_eq!(promise.take_value(), Some(34));
+        assert!(!promise.poll_snapshot().has_value);
+
+        let mut failing = ThreadPromise::<()>::new(|| Err(BoxedSendError::msg("disk on fire")))
+            .with_error_display(|error| format!("Sorry: {error}"));
+        sleep(Duration::from_millis(50));
+        let snapshot = failing.poll_snapshot();
+        assert_eq!(snapshot.state, StateTag::Error);
+        assert_eq!(snapshot.error.as_deref(), Some("Sorry: disk on fire"));
+        assert!(!snapshot.has_value);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn unified_poll() {
+        use crate::api_macros::*;
+        use crate::runtime::sleep;
+        use crate::{DataState, ImmediateValuePromise, LazyVecPromise, Message};
+        use tokio::sync::mpsc::Sender;
+
+        let mut immediate = ImmediateValuePromise::<i32>::empty();
+        assert_eq!(immediate.poll_unified(), PollResult::NotStarted);
+        immediate.set_future(async { Ok(34) });
+        sleep(Duration::from_millis(20)).await;
+        assert_eq!(immediate.poll_unified(), PollResult::Ready);
+        immediate.take_value();
+        assert_eq!(immediate.poll_unified(), PollResult::Taken);
+
+        let updater = |tx: Sender<Message<i32>>| async move {
+            set_progress!(Progress::from_percent(50), tx);
+            sleep(Duration::from_millis(50)).await;
+            set_error!("offline", tx);
+        };
+        let mut lazy =
+            LazyVecPromise::new(updater, 6).with_error_display(|error| format!("Sorry: {error}"));
+        lazy.poll_unified();
+        sleep(Duration::from_millis(20)).await;
+        assert_eq!(
+            lazy.poll_unified(),
+            PollResult::Pending {
+                progress: Some(Progress::from_percent(50))
+            }
+        );
+        sleep(Duration::from_millis(80)).await;
+        assert_eq!(
+            lazy.poll_unified(),
+            PollResult::Failed {
+                message: "Sorry: offline".to_string()
+            }
+        );
+    }
+}
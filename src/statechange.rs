@@ -0,0 +1,240 @@
+use std::fmt::Debug;
+use std::future::Future;
+use std::panic::resume_unwind;
+use std::time::Duration;
+
+use tokio::sync::mpsc::{channel, Sender};
+use tokio::sync::watch;
+
+use crate::runtime::{sleep, spawn_named, Task, TaskFailure};
+use crate::{
+    panic_message, DataState, DataStateTag, Message, Progress, UNFINISHED_UPDATER_MESSAGE,
+};
+
+/// A transition in the life cycle of a promise, as yielded by the `state_stream` methods
+#[derive(Clone, Debug, PartialEq)]
+pub enum StateChange {
+    /// The computation started
+    Started,
+    /// New progress was reported, only available for lazy promises
+    Progress(Progress),
+    /// The computation finished, the error is given as message - the error object itself stays in the promise
+    Finished(Result<(), String>),
+}
+
+impl StateChange {
+    fn from_data_state(state: &DataState) -> Option<Self> {
+        match state {
+            DataState::Updating(progress) => Some(StateChange::Progress(*progress)),
+            DataState::UpToDate => Some(StateChange::Finished(Ok(()))),
+            DataState::Error(error) => Some(StateChange::Finished(Err(error.to_string()))),
+            DataState::Cancelled => Some(StateChange::Finished(Err(state.to_string()))),
+            DataState::Uninitialized => None,
+        }
+    }
+}
+
+pub(crate) type StateChangeSender = watch::Sender<Option<StateChange>>;
+pub(crate) type StateChangeReceiver = watch::Receiver<Option<StateChange>>;
+
+/// The watch channels a lazy promise publishes its state on
+pub(crate) struct Observers {
+    pub(crate) state_changes: StateChangeSender,
+    pub(crate) tags: watch::Sender<DataStateTag>,
+}
+
+impl Observers {
+    pub(crate) fn new() -> Self {
+        Observers {
+            state_changes: watch::channel(None).0,
+            tags: watch::channel(DataStateTag::Uninitialized).0,
+        }
+    }
+
+    /// Publish state changes made by the promise itself
+    pub(crate) fn sync_tag(&self, state: &DataState) {
+        let tag = state.tag();
+        self.tags.send_if_modified(|current| {
+            let modified = *current != tag;
+            *current = tag;
+            modified
+        });
+    }
+
+    fn is_observed(&self) -> bool {
+        self.state_changes.receiver_count() > 0 || self.tags.receiver_count() > 0
+    }
+}
+
+/// Spawns the updater future of a lazy promise. If anyone observes the state changes, messages are inspected on
+/// their way to the promise, so observers are notified without the promise being polled. All tasks get the given name.
+pub(crate) fn spawn_updater<
+    T: Debug + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+>(
+    updater: impl FnOnce(Sender<Message<T>>) -> Fut,
+    delay: Duration,
+    tx: Sender<Message<T>>,
+    observers: &Observers,
+    name: Option<&str>,
+) -> Task {
+    let delayed = |future| async move {
+        if !delay.is_zero() {
+            sleep(delay).await;
+        }
+        future.await
+    };
+    if !observers.is_observed() {
+        return spawn_named(name, delayed(updater(tx)));
+    }
+
+    observers
+        .state_changes
+        .send_replace(Some(StateChange::Started));
+    let state_changes = observers.state_changes.clone();
+    let tags = observers.tags.clone();
+    let (observed_tx, mut observed_rx) = channel(tx.max_capacity());
+    let future = delayed(updater(observed_tx));
+    let updater = spawn_named(name, future);
+    spawn_named(name, async move {
+        let mut finished = false;
+        while let Some(msg) = observed_rx.recv().await {
+            let state = match &msg {
+                Message::StateChange(state) => {
+                    Some((StateChange::from_data_state(state), state.tag()))
+                }
+                Message::NewDataWithProgress(_, progress) => {
+                    let state = DataState::Updating(*progress);
+                    Some((StateChange::from_data_state(&state), state.tag()))
+                }
+                Message::NewData(_) => None,
+            };
+            // observers are notified after the promise can see the message
+            if tx.send(msg).await.is_err() {
+                break;
+            }
+            if let Some((change, tag)) = state {
+                tags.send_replace(tag);
+                if let Some(change) = change {
+                    finished = matches!(change, StateChange::Finished(_));
+                    state_changes.send_replace(Some(change));
+                }
+            }
+        }
+        let report = |message| {
+            if !finished {
+                tags.send_replace(DataStateTag::Error);
+                state_changes.send_replace(Some(StateChange::Finished(Err(message))));
+            }
+        };
+        match updater.join().await {
+            // keep the panic visible for the promise
+            Err(TaskFailure::Panic(panic)) => {
+                report(panic_message(panic.as_ref()));
+                resume_unwind(panic);
+            }
+            Err(TaskFailure::Other(error)) => report(error.to_string()),
+            Ok(()) => report(UNFINISHED_UPDATER_MESSAGE.to_string()),
+        }
+    })
+}
+
+#[cfg(feature = "futures")]
+pub(crate) fn state_change_stream(
+    receiver: StateChangeReceiver,
+    skip_current: bool,
+) -> impl futures_core::Stream<Item = StateChange> {
+    futures_util::stream::unfold(
+        (receiver, skip_current, false),
+        |(mut receiver, mut wait, finished)| async move {
+            if finished {
+                return None;
+            }
+            loop {
+                if wait {
+                    receiver.changed().await.ok()?;
+                }
+                wait = true;
+                let current = receiver.borrow_and_update().clone();
+                if let Some(change) = current {
+                    let finished = matches!(change, StateChange::Finished(_));
+                    return Some((change, (receiver, true, finished)));
+                }
+            }
+        },
+    )
+}
+
+#[cfg(all(test, feature = "futures"))]
+mod test {
+    use super::*;
+    use crate::api_macros::*;
+    use crate::runtime::sleep;
+    use crate::{BoxedSendError, ImmediateValuePromise, LazyVecPromise, Promise};
+    use futures_util::StreamExt;
+
+    #[tokio::test]
+    async fn immediate_stream() {
+        let promise = ImmediateValuePromise::new(async {
+            sleep(Duration::from_millis(20)).await;
+            Ok(1)
+        });
+        let changes: Vec<_> = promise.state_stream().collect().await;
+        assert_eq!(
+            changes,
+            vec![StateChange::Started, StateChange::Finished(Ok(()))]
+        );
+
+        let promise =
+            ImmediateValuePromise::<i32>::new(async { Err(BoxedSendError::msg("failed")) });
+        let changes: Vec<_> = promise.state_stream().collect().await;
+        assert_eq!(
+            changes.last(),
+            Some(&StateChange::Finished(Err("failed".to_string())))
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn lazy_stream_without_polling() {
+        let updater = |tx: Sender<Message<i32>>| async move {
+            sleep(Duration::from_millis(30)).await;
+            send_data!(1, tx);
+            set_progress!(Progress::from_percent(50), tx);
+            sleep(Duration::from_millis(30)).await;
+            set_finished!(tx);
+        };
+        let mut promise = LazyVecPromise::new(updater, 10);
+        let stream = promise.state_stream();
+        promise.poll_state();
+        let changes: Vec<_> = stream.collect().await;
+        assert_eq!(
+            changes,
+            vec![
+                StateChange::Started,
+                StateChange::Progress(Progress::from_percent(50)),
+                StateChange::Finished(Ok(()))
+            ]
+        );
+        assert_eq!(promise.poll_state(), &DataState::UpToDate);
+        assert_eq!(promise.as_slice(), &[1]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn lazy_stream_panicking_updater() {
+        let updater = |_: Sender<Message<i32>>| async move {
+            panic!("boom");
+        };
+        let mut promise = LazyVecPromise::new(updater, 10);
+        let stream = promise.state_stream();
+        promise.poll_state();
+        let changes: Vec<_> = stream.collect().await;
+        assert_eq!(
+            changes.last(),
+            Some(&StateChange::Finished(Err(
+                "updater panicked: boom".to_string()
+            )))
+        );
+        sleep(Duration::from_millis(10)).await;
+        assert!(promise.poll_state().is_error());
+    }
+}
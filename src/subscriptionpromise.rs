@@ -0,0 +1,319 @@
+use std::fmt::{self, Debug, Formatter};
+use std::future::Future;
+use std::mem;
+use std::pin::pin;
+use std::time::{Duration, Instant};
+
+use futures_core::Stream;
+use futures_util::StreamExt;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+use crate::runtime::{self, sleep, Task};
+use crate::{clock, BoxedSendError, DirectCacheAccess, ErrorKind, FutureResult, RetryPolicy};
+
+/// The state of a [`SubscriptionPromise`], the latest value stays available in all of them
+#[derive(Debug)]
+pub enum SubscriptionState {
+    /// Waiting for the first value
+    Connecting,
+    /// Values are arriving
+    Live,
+    /// The stream ended or failed, waiting for the backoff delay or the next value. The number is the retry,
+    /// starting at 1 and reset by each value received.
+    Reconnecting(u32),
+    /// The stream ended and no retry is left
+    Disconnected,
+    /// The stream or connecting failed and no retry is left
+    Error(BoxedSendError),
+}
+
+impl SubscriptionState {
+    /// Is the subscription running, i.e. neither disconnected nor failed?
+    pub fn is_running(&self) -> bool {
+        !matches!(
+            self,
+            SubscriptionState::Disconnected | SubscriptionState::Error(_)
+        )
+    }
+}
+
+/// Errors are considered equal if their messages are equal
+impl PartialEq for SubscriptionState {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (SubscriptionState::Connecting, SubscriptionState::Connecting) => true,
+            (SubscriptionState::Live, SubscriptionState::Live) => true,
+            (SubscriptionState::Reconnecting(retry), SubscriptionState::Reconnecting(other)) => {
+                retry == other
+            }
+            (SubscriptionState::Disconnected, SubscriptionState::Disconnected) => true,
+            (SubscriptionState::Error(error), SubscriptionState::Error(other)) => {
+                error.to_string() == other.to_string()
+            }
+            _ => false,
+        }
+    }
+}
+
+enum Event<T> {
+    Value(T, Instant),
+    State(SubscriptionState),
+}
+
+/// # A promise following a stream that never completes, e.g. server-sent events
+/// Keeps the latest value received, with the time it arrived. When the stream ends or fails, the promise is
+/// [`SubscriptionState::Disconnected`] or [`SubscriptionState::Error`] - unless it was created with
+/// [`Self::reconnecting`], which connects again with the backoff of a [`RetryPolicy`].
+/// Dropping the promise aborts the subscription.
+/// ```rust
+/// # #[tokio::main]
+/// # async fn main() {
+/// use lazy_async_promise::{BoxedSendError, DirectCacheAccess, SubscriptionPromise, SubscriptionState};
+/// let updates = futures_util::stream::iter([Ok::<_, BoxedSendError>("idle"), Ok("busy")]);
+/// let mut status = SubscriptionPromise::new(updates);
+/// tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+/// assert_eq!(*status.poll_state(), SubscriptionState::Disconnected);
+/// assert_eq!(status.get_value(), Some(&"busy"));
+/// assert!(status.value_age().unwrap() < std::time::Duration::from_secs(1));
+/// # }
+/// ```
+pub struct SubscriptionPromise<T: Send> {
+    events: UnboundedReceiver<Event<T>>,
+    value: Option<T>,
+    received_at: Option<Instant>,
+    state: SubscriptionState,
+    _task: Task,
+}
+
+impl<T: Send + 'static> SubscriptionPromise<T> {
+    /// Follow `stream` until it ends or fails, without reconnecting
+    pub fn new<S, E>(stream: S) -> Self
+    where
+        S: Stream<Item = Result<T, E>> + Send + 'static,
+        E: Into<BoxedSendError> + 'static,
+    {
+        let mut stream = Some(stream);
+        let connect = move || {
+            let stream = stream.take();
+            async move { stream.ok_or_else(|| BoxedSendError::msg("stream already used")) }
+        };
+        Self::reconnecting(connect, RetryPolicy::new(0))
+    }
+
+    /// Follow the streams made by `connect`. Whenever connecting fails or the stream ends or fails, `connect` is
+    /// called again after the backoff delay while `policy` allows it. Each value received resets the retries.
+    /// ```rust, no_run
+    /// use std::time::Duration;
+    /// use lazy_async_promise::{BoxedSendError, RetryPolicy, SubscriptionPromise};
+    /// # async fn open_event_stream() -> Result<futures_util::stream::Empty<Result<String, BoxedSendError>>, BoxedSendError> {
+    /// #     Ok(futures_util::stream::empty())
+    /// # }
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let policy = RetryPolicy::new(10).with_backoff(Duration::from_millis(500), 2.0);
+    /// let mut prices = SubscriptionPromise::reconnecting(|| async { open_event_stream().await }, policy);
+    /// # }
+    /// ```
+    pub fn reconnecting<F, Fut, S, E>(connect: F, policy: RetryPolicy) -> Self
+    where
+        F: FnMut() -> Fut + Send + 'static,
+        Fut: Future<Output = FutureResult<S>> + Send + 'static,
+        S: Stream<Item = Result<T, E>> + Send + 'static,
+        E: Into<BoxedSendError> + 'static,
+    {
+        let (sender, events) = unbounded_channel();
+        let task = runtime::spawn(subscribe(connect, policy, sender));
+        SubscriptionPromise {
+            events,
+            value: None,
+            received_at: None,
+            state: SubscriptionState::Connecting,
+            _task: task,
+        }
+    }
+
+    /// Apply the values and state changes received since the last poll
+    pub fn poll_state(&mut self) -> &SubscriptionState {
+        while let Ok(event) = self.events.try_recv() {
+            match event {
+                Event::Value(value, received_at) => {
+                    self.value = Some(value);
+                    self.received_at = Some(received_at);
+                    self.state = SubscriptionState::Live;
+                }
+                Event::State(state) => self.state = state,
+            }
+        }
+        &self.state
+    }
+}
+
+impl<T: Send> SubscriptionPromise<T> {
+    /// Get the current state without polling
+    pub fn get_state(&self) -> &SubscriptionState {
+        &self.state
+    }
+
+    /// When the latest value arrived, as of the last poll
+    pub fn received_at(&self) -> Option<Instant> {
+        self.received_at
+    }
+
+    /// How long ago the latest value arrived, e.g. to grey out stale values while reconnecting
+    pub fn value_age(&self) -> Option<Duration> {
+        self.received_at.map(clock::since)
+    }
+}
+
+async fn subscribe<T, F, Fut, S, E>(
+    mut connect: F,
+    policy: RetryPolicy,
+    events: UnboundedSender<Event<T>>,
+) where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = FutureResult<S>>,
+    S: Stream<Item = Result<T, E>>,
+    E: Into<BoxedSendError>,
+{
+    let mut retries = 0;
+    loop {
+        let (error, ended) = match connect().await {
+            Ok(stream) => {
+                let mut stream = pin!(stream);
+                loop {
+                    match stream.next().await {
+                        Some(Ok(value)) => {
+                            retries = 0;
+                            if events.send(Event::Value(value, clock::now())).is_err() {
+                                return;
+                            }
+                        }
+                        Some(Err(error)) => break (error.into(), false),
+                        None => {
+                            let error = BoxedSendError::msg("subscription stream ended");
+                            break (error.with_kind(ErrorKind::ChannelClosed), true);
+                        }
+                    }
+                }
+            }
+            Err(error) => (error, false),
+        };
+        if !policy.should_retry(retries, &error) {
+            let state = match ended {
+                true => SubscriptionState::Disconnected,
+                false => SubscriptionState::Error(error),
+            };
+            let _ = events.send(Event::State(state));
+            return;
+        }
+        let state = SubscriptionState::Reconnecting(retries + 1);
+        if events.send(Event::State(state)).is_err() {
+            return;
+        }
+        sleep(policy.delay_for(retries)).await;
+        retries += 1;
+    }
+}
+
+impl<T: Send> Debug for SubscriptionPromise<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SubscriptionPromise")
+            .field("state", &self.state)
+            .field("received_at", &self.received_at)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: Send> DirectCacheAccess<T, BoxedSendError> for SubscriptionPromise<T> {
+    fn get_value_mut(&mut self) -> Option<&mut T> {
+        self.value.as_mut()
+    }
+    fn get_value(&self) -> Option<&T> {
+        self.value.as_ref()
+    }
+    fn get_result(&self) -> Option<Result<&T, &BoxedSendError>> {
+        match &self.state {
+            SubscriptionState::Error(error) => Some(Err(error)),
+            _ => self.value.as_ref().map(Ok),
+        }
+    }
+    fn get_result_mut(&mut self) -> Option<Result<&mut T, &mut BoxedSendError>> {
+        match &mut self.state {
+            SubscriptionState::Error(error) => Some(Err(error)),
+            _ => self.value.as_mut().map(Ok),
+        }
+    }
+    /// Take the latest value, the next one received replaces it
+    fn take_value(&mut self) -> Option<T> {
+        self.value.take()
+    }
+    /// Take the error, the promise is [`SubscriptionState::Disconnected`] afterwards
+    fn take_result(&mut self) -> Option<Result<T, BoxedSendError>> {
+        if !matches!(self.state, SubscriptionState::Error(_)) {
+            return self.take_value().map(Ok);
+        }
+        match mem::replace(&mut self.state, SubscriptionState::Disconnected) {
+            SubscriptionState::Error(error) => Some(Err(error)),
+            _ => unreachable!("checked above"),
+        }
+    }
+    /// Replace the latest value until the next one is received
+    fn set_value(&mut self, value: T) -> Option<T> {
+        self.value.replace(value)
+    }
+    fn is_pending(&self) -> bool {
+        self.state.is_running()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use futures_util::{stream, StreamExt};
+
+    use super::{SubscriptionPromise, SubscriptionState};
+    use crate::runtime::sleep;
+    use crate::{BoxedSendError, DirectCacheAccess, RetryPolicy};
+
+    #[tokio::test]
+    async fn reconnects_with_backoff() {
+        let connections = Arc::new(AtomicU32::new(0));
+        let counter = connections.clone();
+        let connect = move || {
+            let connection = counter.fetch_add(1, Ordering::SeqCst);
+            async move {
+                if connection == 1 {
+                    return Err(BoxedSendError::msg("refused"));
+                }
+                let values = [Ok(connection * 10), Err(BoxedSendError::msg("reset"))];
+                Ok(stream::iter(values).then(|value| async move {
+                    sleep(Duration::from_millis(20)).await;
+                    value
+                }))
+            }
+        };
+        let policy = RetryPolicy::new(1).with_backoff(Duration::from_millis(40), 1.0);
+        let mut promise = SubscriptionPromise::reconnecting(connect, policy);
+        assert_eq!(*promise.poll_state(), SubscriptionState::Connecting);
+        sleep(Duration::from_millis(30)).await;
+        assert_eq!(*promise.poll_state(), SubscriptionState::Live);
+        assert_eq!(promise.get_value(), Some(&0));
+
+        // reset, backoff, refused - the only retry is used up
+        sleep(Duration::from_millis(30)).await;
+        assert_eq!(*promise.poll_state(), SubscriptionState::Reconnecting(1));
+        assert_eq!(promise.get_value(), Some(&0));
+        assert!(promise.value_age().unwrap() >= Duration::from_millis(20));
+        sleep(Duration::from_millis(60)).await;
+        assert_eq!(
+            *promise.poll_state(),
+            SubscriptionState::Error(BoxedSendError::msg("refused"))
+        );
+        assert!(!promise.is_pending());
+        assert_eq!(connections.load(Ordering::SeqCst), 2);
+        assert_eq!(promise.get_value(), Some(&0));
+    }
+}
@@ -0,0 +1,219 @@
+use std::fmt::{self, Debug, Formatter};
+use std::future::Future;
+use std::mem;
+
+use crate::{
+    BoxedSendError, DataState, DirectCacheAccess, FutureResult, ImmediateValuePromise, Progress,
+};
+
+type BoxedRequest<Q, T> = Box<dyn Fn(Q) -> ImmediateValuePromise<T>>;
+
+/// # A promise where only the newest query counts, e.g. for search-as-you-type
+/// Each [`Self::query`] spawns a request and aborts the one still running, so a stale response can never
+/// overwrite a newer one, no matter in which order they would finish. The value of the last finished query stays
+/// available while the next one runs, [`Self::current_query`] tells which query it belongs to.
+/// ```rust
+/// # #[tokio::main]
+/// # async fn main() {
+/// use lazy_async_promise::{DataState, DirectCacheAccess, SupersedingPromise};
+/// let mut search = SupersedingPromise::new(|term: String| async move {
+///     Ok(["apple", "apricot", "banana"]
+///         .into_iter()
+///         .filter(|fruit| fruit.starts_with(&term))
+///         .collect::<Vec<_>>())
+/// });
+/// search.query("a".to_string());
+/// search.query("ap".to_string());
+/// tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+/// assert_eq!(*search.poll_state(), DataState::UpToDate);
+/// assert_eq!(search.current_query().map(String::as_str), Some("ap"));
+/// assert_eq!(search.get_value().unwrap(), &["apple", "apricot"]);
+/// # }
+/// ```
+pub struct SupersedingPromise<Q, T: Send> {
+    request: BoxedRequest<Q, T>,
+    pending: Option<(Q, ImmediateValuePromise<T>)>,
+    current: Option<Q>,
+    value: Option<T>,
+    state: DataState,
+}
+
+impl<Q: Clone, T: Send + 'static> SupersedingPromise<Q, T> {
+    /// Create the promise from a function making the request future for a query. Nothing runs before the first
+    /// [`Self::query`].
+    pub fn new<Fut: Future<Output = FutureResult<T>> + Send + 'static>(
+        request: impl Fn(Q) -> Fut + 'static,
+    ) -> Self {
+        SupersedingPromise {
+            request: Box::new(move |query| ImmediateValuePromise::new(request(query))),
+            pending: None,
+            current: None,
+            value: None,
+            state: DataState::Uninitialized,
+        }
+    }
+
+    /// Start the request for `query`, aborting the previous one if it is still running. Its result is never applied.
+    pub fn query(&mut self, query: Q) {
+        let request = (self.request)(query.clone());
+        if let Some((_, mut superseded)) = self.pending.replace((query, request)) {
+            // aborting it is the point, no need to warn about it
+            superseded.disarm_diagnostics();
+        }
+        self.state = DataState::Updating(Progress::indeterminate());
+    }
+
+    /// Poll the newest request. [`DataState::Updating`] while it runs, then [`DataState::UpToDate`] or
+    /// [`DataState::Error`] for its query.
+    pub fn poll_state(&mut self) -> &DataState {
+        let finished = self
+            .pending
+            .as_mut()
+            .is_some_and(|(_, request)| !request.poll_state().is_updating());
+        if finished {
+            let (query, mut request) = self.pending.take().expect("checked above");
+            self.state = match request.take_result() {
+                Some(Ok(value)) => {
+                    self.value = Some(value);
+                    DataState::UpToDate
+                }
+                Some(Err(error)) => {
+                    // the value belongs to the previous query
+                    self.value = None;
+                    DataState::Error(error)
+                }
+                None => DataState::Uninitialized,
+            };
+            self.current = Some(query);
+        }
+        &self.state
+    }
+
+    /// Get the current state without polling
+    pub fn state(&self) -> &DataState {
+        &self.state
+    }
+}
+
+impl<Q, T: Send> SupersedingPromise<Q, T> {
+    /// The query of the last finished request, the value or error belong to it
+    pub fn current_query(&self) -> Option<&Q> {
+        self.current.as_ref()
+    }
+
+    /// The query of the running request, if any
+    pub fn pending_query(&self) -> Option<&Q> {
+        self.pending.as_ref().map(|(query, _)| query)
+    }
+}
+
+impl<Q: Debug, T: Send> Debug for SupersedingPromise<Q, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SupersedingPromise")
+            .field("state", &self.state.tag())
+            .field("current_query", &self.current)
+            .field("pending_query", &self.pending_query())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<Q, T: Send> DirectCacheAccess<T, BoxedSendError> for SupersedingPromise<Q, T> {
+    fn get_value_mut(&mut self) -> Option<&mut T> {
+        self.value.as_mut()
+    }
+    fn get_value(&self) -> Option<&T> {
+        self.value.as_ref()
+    }
+    fn get_result(&self) -> Option<Result<&T, &BoxedSendError>> {
+        match &self.state {
+            DataState::Error(error) => Some(Err(error)),
+            _ => self.value.as_ref().map(Ok),
+        }
+    }
+    fn get_result_mut(&mut self) -> Option<Result<&mut T, &mut BoxedSendError>> {
+        match &mut self.state {
+            DataState::Error(error) => Some(Err(error)),
+            _ => self.value.as_mut().map(Ok),
+        }
+    }
+    /// Take the value of the current query. Unless a request is running, the promise is
+    /// [`DataState::Uninitialized`] afterwards and has no current query.
+    fn take_value(&mut self) -> Option<T> {
+        let value = self.value.take();
+        if value.is_some() && self.pending.is_none() {
+            self.state = DataState::Uninitialized;
+            self.current = None;
+        }
+        value
+    }
+    fn take_result(&mut self) -> Option<Result<T, BoxedSendError>> {
+        if !self.state.is_error() {
+            return self.take_value().map(Ok);
+        }
+        self.current = None;
+        match mem::replace(&mut self.state, DataState::Uninitialized) {
+            DataState::Error(error) => Some(Err(error)),
+            _ => unreachable!("checked above"),
+        }
+    }
+    /// Replace the value, keeping the current query. A running request still overwrites it.
+    fn set_value(&mut self, value: T) -> Option<T> {
+        if self.pending.is_none() {
+            self.state = DataState::UpToDate;
+        }
+        self.value.replace(value)
+    }
+    fn is_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use super::SupersedingPromise;
+    use crate::runtime::sleep;
+    use crate::{DataState, DirectCacheAccess};
+
+    #[tokio::test]
+    async fn older_request_finishing_last_never_wins() {
+        let older_finished = Arc::new(AtomicBool::new(false));
+        let flag = older_finished.clone();
+        let mut promise = SupersedingPromise::new(move |(term, delay): (&'static str, u64)| {
+            let flag = flag.clone();
+            async move {
+                sleep(Duration::from_millis(delay)).await;
+                if term == "a" {
+                    flag.store(true, Ordering::SeqCst);
+                }
+                Ok(term.len())
+            }
+        });
+        promise.query(("a", 60));
+        sleep(Duration::from_millis(5)).await;
+        promise.query(("ab", 10));
+        assert_eq!(promise.pending_query(), Some(&("ab", 10)));
+        sleep(Duration::from_millis(30)).await;
+        assert_eq!(*promise.poll_state(), DataState::UpToDate);
+        assert_eq!(promise.current_query(), Some(&("ab", 10)));
+        assert_eq!(promise.get_value(), Some(&2));
+
+        // the older request was aborted, its response never arrives
+        sleep(Duration::from_millis(60)).await;
+        assert_eq!(*promise.poll_state(), DataState::UpToDate);
+        assert_eq!(promise.get_value(), Some(&2));
+        assert!(!older_finished.load(Ordering::SeqCst));
+
+        // the value stays while the next query runs
+        promise.query(("abc", 10));
+        assert!(promise.poll_state().is_updating());
+        assert_eq!(promise.get_value(), Some(&2));
+        sleep(Duration::from_millis(30)).await;
+        promise.poll_state();
+        assert_eq!(promise.take_value(), Some(3));
+        assert_eq!(promise.current_query(), None);
+    }
+}
@@ -0,0 +1,189 @@
+use std::cell::RefCell;
+use std::fmt::{self, Debug, Formatter};
+use std::ops::{Deref, DerefMut};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use crate::{DirectCacheAccess, LockedCacheAccess, SnapshotPoll, StateSnapshot};
+
+thread_local! {
+    /// The addresses of the [`SyncPromise`]s locked by this thread, to detect re-entrant locking
+    static LOCKED: RefCell<Vec<usize>> = const { RefCell::new(Vec::new()) };
+}
+
+/// # A promise which can be polled and read from several threads
+/// The promises need `&mut self` for polling, so sharing one between e.g. a render thread and a coordinator thread
+/// requires a lock. This wrapper keeps the promise behind a mutex, is `Sync` for any `Send` promise and polls via
+/// `&self`. Reading the value works like for the other locks, see [`LockedCacheAccess`].
+/// ```rust
+/// use std::sync::Arc;
+/// use lazy_async_promise::{LockedCacheAccess, StateTag, SyncPromise, ThreadPromise};
+/// let shared = Arc::new(SyncPromise::new(ThreadPromise::new(|| Ok(34))));
+/// let coordinator = shared.clone();
+/// std::thread::spawn(move || while coordinator.poll_state().state == StateTag::Updating {})
+///     .join()
+///     .unwrap();
+/// assert_eq!(shared.with_value(|value| *value), Some(34));
+/// assert_eq!(shared.take_value(), Some(34));
+/// ```
+/// ### Deadlocks
+/// The lock is held for a single poll or for the duration of a closure given to [`LockedCacheAccess`] or
+/// [`Self::with_promise`], never longer. Calling back into the same `SyncPromise` from such a closure would deadlock,
+/// so it panics instead. Locking two `SyncPromise`s from closures in different orders on two threads can still
+/// deadlock, like with any two mutexes.
+/// A panic in a closure doesn't poison the promise, it stays usable.
+pub struct SyncPromise<P> {
+    promise: Mutex<P>,
+}
+
+/// Unmarks the promise as locked by this thread when dropped
+struct Guard<'a, P> {
+    guard: MutexGuard<'a, P>,
+    address: usize,
+}
+
+impl<P> Deref for Guard<'_, P> {
+    type Target = P;
+    fn deref(&self) -> &P {
+        &self.guard
+    }
+}
+
+impl<P> DerefMut for Guard<'_, P> {
+    fn deref_mut(&mut self) -> &mut P {
+        &mut self.guard
+    }
+}
+
+impl<P> Drop for Guard<'_, P> {
+    fn drop(&mut self) {
+        LOCKED.with(|locked| {
+            locked
+                .borrow_mut()
+                .retain(|address| *address != self.address)
+        });
+    }
+}
+
+impl<P> SyncPromise<P> {
+    /// Wrap the promise
+    pub fn new(promise: P) -> Self {
+        SyncPromise {
+            promise: Mutex::new(promise),
+        }
+    }
+
+    /// Unwrap the promise
+    pub fn into_inner(self) -> P {
+        self.promise
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Poll the promise and take a snapshot of its state, see [`SnapshotPoll`]
+    pub fn poll_state(&self) -> StateSnapshot<P::Tag>
+    where
+        P: SnapshotPoll,
+    {
+        self.lock().poll_snapshot()
+    }
+
+    /// Lock and call `f` with the promise, e.g. to start an update. `f` must not use this `SyncPromise` again.
+    pub fn with_promise<R>(&self, f: impl FnOnce(&mut P) -> R) -> R {
+        f(&mut self.lock())
+    }
+
+    fn lock(&self) -> Guard<'_, P> {
+        let address = self as *const Self as usize;
+        LOCKED.with(|locked| {
+            let mut locked = locked.borrow_mut();
+            assert!(
+                !locked.contains(&address),
+                "SyncPromise used again while locked by the same thread, e.g. from the closure of `with_value`"
+            );
+            locked.push(address);
+        });
+        let guard = self.promise.lock();
+        let guard = guard.unwrap_or_else(PoisonError::into_inner);
+        Guard { guard, address }
+    }
+}
+
+impl<T, E, P: DirectCacheAccess<T, E>> LockedCacheAccess<T, E> for SyncPromise<P> {
+    fn with_value<R>(&self, f: impl FnOnce(&T) -> R) -> Option<R> {
+        self.lock().get_value().map(f)
+    }
+    fn with_value_mut<R>(&self, f: impl FnOnce(&mut T) -> R) -> Option<R> {
+        self.lock().get_value_mut().map(f)
+    }
+    fn with_result<R>(&self, f: impl FnOnce(Result<&T, &E>) -> R) -> Option<R> {
+        self.lock().get_result().map(f)
+    }
+    fn take_value(&self) -> Option<T> {
+        self.lock().take_value()
+    }
+    fn take_result(&self) -> Option<Result<T, E>> {
+        self.lock().take_result()
+    }
+}
+
+impl<P: Debug> Debug for SyncPromise<P> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.promise.try_lock() {
+            Ok(promise) => f.debug_tuple("SyncPromise").field(&*promise).finish(),
+            Err(_) => f.write_str("SyncPromise(<locked>)"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    use super::SyncPromise;
+    use crate::{LockedCacheAccess, StateTag, ThreadPromise};
+
+    #[test]
+    fn two_threads_polling_and_reading() {
+        let promise = ThreadPromise::new(|| {
+            thread::sleep(Duration::from_millis(20));
+            Ok((0..1000).collect::<Vec<u32>>())
+        });
+        let shared = Arc::new(SyncPromise::new(promise));
+        let threads: Vec<_> = (0..2)
+            .map(|_| {
+                let shared = shared.clone();
+                thread::spawn(move || {
+                    let mut reads = 0;
+                    while reads < 1000 {
+                        if shared.poll_state().state == StateTag::Success {
+                            reads += 1;
+                            let sum = shared.with_value(|values| values.iter().sum::<u32>());
+                            assert_eq!(sum, Some(499_500));
+                        }
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert_eq!(shared.take_value().map(|values| values.len()), Some(1000));
+        assert_eq!(shared.poll_state().state, StateTag::Empty);
+    }
+
+    #[test]
+    fn reentrant_use_panics_instead_of_deadlocking() {
+        let shared = SyncPromise::new(ThreadPromise::new(|| Ok(34)));
+        thread::sleep(Duration::from_millis(20));
+        shared.poll_state();
+        let reentrant = catch_unwind(AssertUnwindSafe(|| {
+            shared.with_value(|_| shared.poll_state());
+        }));
+        assert!(reentrant.is_err());
+        // the promise is still usable afterwards
+        assert_eq!(shared.take_value(), Some(34));
+    }
+}
@@ -0,0 +1,241 @@
+//! Assertion and wait helpers for tests of code using promises, behind the `test-util` feature
+use std::fmt::Debug;
+#[cfg(feature = "tokio")]
+use std::time::Duration;
+
+#[cfg(feature = "tokio")]
+use crate::runtime::sleep;
+#[cfg(feature = "tokio")]
+use crate::{clock, ImmediateValuePromise};
+use crate::{
+    BoxedSendError, DataState, DirectCacheAccess, ImmediateValueState, ProgressTracked, Promise,
+    ThreadPromise, TrackedPromise,
+};
+
+/// How long the wait helpers sleep between polls. Without the `async-std` feature, paused tokio time advances
+/// automatically while sleeping.
+#[cfg(feature = "tokio")]
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// # Promises the test helpers can drive
+/// Implemented for all promises of this crate, including [`crate::MockPromise`]
+pub trait TestablePromise<T>: DirectCacheAccess<T, BoxedSendError> {
+    /// Poll the promise and return the name of the state variant, e.g. `"Updating"` or `"UpToDate"`
+    fn poll_variant(&mut self) -> &'static str;
+    /// The current state and the latest status message if there is one, for panic messages
+    fn describe(&self) -> String;
+}
+
+fn data_state_variant(state: &DataState) -> &'static str {
+    match state {
+        DataState::Uninitialized => "Uninitialized",
+        DataState::UpToDate => "UpToDate",
+        DataState::Updating(_) => "Updating",
+        DataState::Error(_) => "Error",
+        DataState::Cancelled => "Cancelled",
+    }
+}
+
+fn immediate_state_variant<T>(state: &ImmediateValueState<T>) -> &'static str {
+    match state {
+        ImmediateValueState::Updating => "Updating",
+        ImmediateValueState::Success(_) => "Success",
+        ImmediateValueState::Error(_) => "Error",
+        ImmediateValueState::Empty => "Empty",
+    }
+}
+
+fn with_status(state: String, message: Option<&impl Debug>) -> String {
+    match message {
+        Some(message) => format!("{state}, last status {message:?}"),
+        None => state,
+    }
+}
+
+impl<T, P: Promise + DirectCacheAccess<T, BoxedSendError>> TestablePromise<T> for P {
+    fn poll_variant(&mut self) -> &'static str {
+        data_state_variant(self.poll_state())
+    }
+    fn describe(&self) -> String {
+        self.state().to_string()
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<T: Send + 'static> TestablePromise<T> for ImmediateValuePromise<T> {
+    fn poll_variant(&mut self) -> &'static str {
+        immediate_state_variant(self.poll_state())
+    }
+    fn describe(&self) -> String {
+        self.get_state().to_string()
+    }
+}
+
+impl<T: Send + 'static> TestablePromise<T> for ThreadPromise<T> {
+    fn poll_variant(&mut self) -> &'static str {
+        immediate_state_variant(self.poll_state())
+    }
+    fn describe(&self) -> String {
+        self.get_state().to_string()
+    }
+}
+
+impl<P: TrackedPromise, M: Debug> TestablePromise<P::Value> for ProgressTracked<P, M> {
+    fn poll_variant(&mut self) -> &'static str {
+        immediate_state_variant(self.poll_state())
+    }
+    fn describe(&self) -> String {
+        let message = self.last_status().map(|status| &status.message);
+        with_status(self.get_state().to_string(), message)
+    }
+}
+
+#[macro_export]
+/// Poll the promise and assert the name of its state variant, e.g. `assert_state!(promise, Updating)`.
+/// Works for all [`TestablePromise`](crate::TestablePromise)s, the panic message describes the observed state.
+macro_rules! assert_state {
+    ($promise: expr, $state: ident) => {{
+        let promise = &mut $promise;
+        let variant = $crate::TestablePromise::poll_variant(promise);
+        assert_eq!(
+            variant,
+            ::std::stringify!($state),
+            "unexpected promise state: {}",
+            $crate::TestablePromise::describe(promise)
+        );
+    }};
+}
+
+/// Poll until the promise has a value and return it. Panics if it fails or the timeout passes first.
+#[cfg(feature = "tokio")]
+pub async fn wait_for_success<'a, T: 'a, P: TestablePromise<T>>(
+    promise: &'a mut P,
+    timeout: Duration,
+) -> &'a T {
+    wait_for(promise, timeout, "a value", |result| result.is_ok()).await;
+    promise.get_value().expect("value checked while waiting")
+}
+
+/// Poll until the promise failed and return the error. Panics if it succeeds or the timeout passes first.
+#[cfg(feature = "tokio")]
+pub async fn wait_for_error<'a, T: 'a, P: TestablePromise<T>>(
+    promise: &'a mut P,
+    timeout: Duration,
+) -> &'a BoxedSendError {
+    wait_for(promise, timeout, "an error", |result| result.is_err()).await;
+    match promise.get_result() {
+        Some(Err(error)) => error,
+        _ => unreachable!("error checked while waiting"),
+    }
+}
+
+/// Poll a lazy promise until it is [`DataState::UpToDate`]. Panics if it fails or the timeout passes first.
+#[cfg(feature = "tokio")]
+pub async fn wait_until_up_to_date<T, P: Promise + TestablePromise<T>>(
+    promise: &mut P,
+    timeout: Duration,
+) {
+    wait_for(promise, timeout, "up to date", |result| result.is_ok()).await;
+}
+
+#[cfg(feature = "tokio")]
+async fn wait_for<T, P: TestablePromise<T>>(
+    promise: &mut P,
+    timeout: Duration,
+    expected: &str,
+    is_expected: impl Fn(Result<&T, &BoxedSendError>) -> bool,
+) {
+    let deadline = clock::now() + timeout;
+    loop {
+        promise.poll_variant();
+        match promise.get_result() {
+            Some(result) if is_expected(result) => return,
+            Some(_) => panic!("expected {expected}, got {}", promise.describe()),
+            None if clock::now() >= deadline => panic!(
+                "expected {expected} within {timeout:?}, last observed {}",
+                promise.describe()
+            ),
+            None => sleep(POLL_INTERVAL).await,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "tokio"))]
+mod test {
+    use std::time::Duration;
+
+    use tokio::sync::mpsc::Sender;
+
+    use super::{wait_for_error, wait_for_success, wait_until_up_to_date};
+    use crate::api_macros::*;
+    use crate::runtime::sleep;
+    use crate::{
+        BoxedSendError, DataState, ImmediateValuePromise, LazyVecPromise, Message, MockPromise,
+        ProgressTrackedImValProm, ThreadPromise,
+    };
+
+    #[tokio::test]
+    async fn waits_for_all_promise_types() {
+        let mut immediate = ImmediateValuePromise::new(async {
+            sleep(Duration::from_millis(10)).await;
+            Ok(34)
+        });
+        assert_state!(immediate, Updating);
+        assert_eq!(
+            wait_for_success(&mut immediate, Duration::from_secs(1)).await,
+            &34
+        );
+        assert_state!(immediate, Success);
+
+        let updater = |tx: Sender<Message<i32>>| async move {
+            send_data!(1, tx);
+            set_finished!(tx);
+        };
+        let mut lazy = LazyVecPromise::new(updater, 6);
+        wait_until_up_to_date(&mut lazy, Duration::from_secs(1)).await;
+        assert_state!(lazy, UpToDate);
+
+        let mut thread = ThreadPromise::<()>::new(|| Err(BoxedSendError::msg("offline")));
+        let error = wait_for_error(&mut thread, Duration::from_secs(1)).await;
+        assert_eq!(error.to_string(), "offline");
+
+        let mut mock = MockPromise::<i32>::new();
+        assert_state!(mock, Uninitialized);
+        mock.resolve(1);
+        assert_eq!(
+            wait_for_success(&mut mock, Duration::from_secs(1)).await,
+            &1
+        );
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "expected a value within 20ms, last observed updating (50%)")]
+    async fn timeout_describes_last_state() {
+        let mut promise = MockPromise::<i32>::new();
+        promise.set_updating(50);
+        wait_for_success(&mut promise, Duration::from_millis(20)).await;
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "expected a value, got error: offline, last status \"connecting\"")]
+    async fn failure_describes_status() {
+        let mut promise = ProgressTrackedImValProm::new(
+            |s| {
+                ImmediateValuePromise::<i32>::new(async move {
+                    send_status!(s, 10, "connecting");
+                    Err(BoxedSendError::msg("offline"))
+                })
+            },
+            10,
+        );
+        wait_for_success(&mut promise, Duration::from_secs(1)).await;
+    }
+
+    #[test]
+    #[should_panic(expected = "unexpected promise state: up to date")]
+    fn assert_state_describes_state() {
+        let mut promise = MockPromise::<i32>::new();
+        promise.resolve(1);
+        assert_state!(promise, Updating);
+    }
+}
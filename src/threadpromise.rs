@@ -0,0 +1,304 @@
+use std::fmt::{self, Debug, Formatter};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::mpsc::{channel, sync_channel, Receiver, Sender, TryRecvError};
+
+use crate::immediatevalueprogress::StatusReceiver;
+use crate::{
+    display_error, panic_error, report_error, BoxedSendError, DirectCacheAccess, ErrorDisplay,
+    ErrorHook, ErrorKind, FutureResult, ImmediateValueState, ProgressTracked, StateSnapshot,
+    Status, TrackedPromise,
+};
+
+/// # A promise backed by a plain thread
+/// Runs a closure on [`std::thread::spawn`] and exposes the result like [`crate::ImmediateValuePromise`] does,
+/// without needing any async runtime. This is the promise to use if the crate is built without the `tokio` feature.
+/// Panics of the closure are turned into an [`ImmediateValueState::Error`].
+/// ```rust
+/// use std::time::Duration;
+/// use lazy_async_promise::{ImmediateValueState, ThreadPromise};
+/// let mut promise = ThreadPromise::new(|| Ok(34));
+/// std::thread::sleep(Duration::from_millis(50));
+/// assert!(matches!(promise.poll_state(), ImmediateValueState::Success(34)));
+/// ```
+pub struct ThreadPromise<T> {
+    receiver: Option<Receiver<FutureResult<T>>>,
+    state: ImmediateValueState<T>,
+    on_error: Option<ErrorHook>,
+    error_display: Option<ErrorDisplay>,
+}
+
+impl<T: Send + 'static> ThreadPromise<T> {
+    /// Spawns a thread running `job`
+    pub fn new(job: impl FnOnce() -> FutureResult<T> + Send + 'static) -> Self {
+        let (sender, receiver) = sync_channel(1);
+        std::thread::spawn(move || {
+            let result = catch_unwind(AssertUnwindSafe(job))
+                .unwrap_or_else(|panic| Err(panic_error(panic.as_ref())));
+            let _ = sender.send(result);
+        });
+        ThreadPromise {
+            receiver: Some(receiver),
+            state: ImmediateValueState::Updating,
+            on_error: None,
+            error_display: None,
+        }
+    }
+
+    /// Set a hook which is called once with the error if the job fails, during the `poll_state` observing it.
+    /// Replaces the default hook set by [`crate::set_default_on_error`] for this promise.
+    pub fn with_on_error(mut self, hook: impl FnMut(&BoxedSendError) + Send + 'static) -> Self {
+        self.on_error = Some(Box::new(hook));
+        self
+    }
+
+    /// Set a mapper turning errors into user-facing messages for [`ThreadPromise::display_error`]
+    pub fn with_error_display(
+        mut self,
+        mapper: impl Fn(&BoxedSendError) -> String + Send + Sync + 'static,
+    ) -> Self {
+        self.error_display = Some(Box::new(mapper));
+        self
+    }
+
+    /// The user-facing message if in error state. Without a mapper, this is the error's `Display` output.
+    pub fn display_error(&self) -> Option<String> {
+        match &self.state {
+            ImmediateValueState::Error(error) => Some(display_error(&self.error_display, error)),
+            _ => None,
+        }
+    }
+
+    /// Poll the state, taking the result from the thread if it's finished
+    pub fn poll_state(&mut self) -> &ImmediateValueState<T> {
+        let result = match self.receiver.as_ref().map(Receiver::try_recv) {
+            Some(Ok(result)) => Some(result),
+            Some(Err(TryRecvError::Disconnected)) => {
+                let error = BoxedSendError::msg("thread finished without a result");
+                Some(Err(error.with_kind(ErrorKind::ChannelClosed)))
+            }
+            Some(Err(TryRecvError::Empty)) | None => None,
+        };
+        if let Some(result) = result {
+            match result {
+                Ok(value) => self.state = ImmediateValueState::Success(value),
+                Err(error) => {
+                    report_error(&mut self.on_error, &error);
+                    self.state = ImmediateValueState::Error(error);
+                }
+            }
+            self.receiver = None;
+        }
+        &self.state
+    }
+
+    /// Poll the state, return a mutable ref to to the state
+    pub fn poll_state_mut(&mut self) -> &mut ImmediateValueState<T> {
+        let _ = self.poll_state();
+        &mut self.state
+    }
+
+    /// Poll the state like `poll_state`, returning an owned [`StateSnapshot`] which doesn't borrow the promise
+    pub fn poll_snapshot(&mut self) -> StateSnapshot {
+        let state = self.poll_state().tag();
+        StateSnapshot {
+            state,
+            error: self.display_error(),
+            has_value: self.has_value(),
+        }
+    }
+
+    /// Get the current state without polling
+    pub fn get_state(&self) -> &ImmediateValueState<T> {
+        &self.state
+    }
+}
+
+/// Prints the state and whether a value is cached, without requiring `T: Debug`
+impl<T> Debug for ThreadPromise<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ThreadPromise")
+            .field("state", &self.state.tag())
+            .field("has_value", &self.state.is_success())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: Send + 'static> DirectCacheAccess<T, BoxedSendError> for ThreadPromise<T> {
+    fn get_value_mut(&mut self) -> Option<&mut T> {
+        self.state.get_value_mut()
+    }
+    fn get_value(&self) -> Option<&T> {
+        self.state.get_value()
+    }
+    fn get_result(&self) -> Option<Result<&T, &BoxedSendError>> {
+        self.state.get_result()
+    }
+    fn get_result_mut(&mut self) -> Option<Result<&mut T, &mut BoxedSendError>> {
+        self.state.get_result_mut()
+    }
+    fn take_value(&mut self) -> Option<T> {
+        self.state.take_value()
+    }
+    fn take_result(&mut self) -> Option<Result<T, BoxedSendError>> {
+        self.state.take_result()
+    }
+    fn is_pending(&self) -> bool {
+        self.state.is_pending()
+    }
+    /// Injects a value into the cache. If the thread is still running, its result will overwrite
+    /// the injected value once it lands during a later [`ThreadPromise::poll_state`].
+    fn set_value(&mut self, value: T) -> Option<T> {
+        self.state.set_value(value)
+    }
+}
+
+/// Blocking sender for [`Status`] updates from a worker thread, see [`ProgressTrackedThreadPromise`]
+pub struct StatusSender<M>(Sender<Status<M>>);
+
+impl<M> Clone for StatusSender<M> {
+    fn clone(&self) -> Self {
+        StatusSender(self.0.clone())
+    }
+}
+
+impl<M> StatusSender<M> {
+    /// Send a status, returns it back if the promise is gone
+    pub fn send(&self, status: Status<M>) -> Result<(), Status<M>> {
+        self.0.send(status).map_err(|error| error.0)
+    }
+}
+
+/// # A progress and status enabling wrapper for [`ThreadPromise`]
+/// The thread counterpart of [`crate::ProgressTrackedImValProm`]:
+/// ```rust
+/// use std::time::Duration;
+/// use lazy_async_promise::{ImmediateValueState, Progress, ProgressTrackedThreadPromise, StringStatus, ThreadPromise};
+/// let mut promise = ProgressTrackedThreadPromise::new(|status| ThreadPromise::new(move || {
+///     let _ = status.send(StringStatus::from_str(Progress::from_percent(50), "halfway"));
+///     Ok(34)
+/// }));
+/// std::thread::sleep(Duration::from_millis(50));
+/// assert!(matches!(promise.poll_state(), ImmediateValueState::Success(34)));
+/// assert_eq!(promise.last_status().unwrap().message, "halfway");
+/// ```
+pub type ProgressTrackedThreadPromise<T, M> = ProgressTracked<ThreadPromise<T>, M>;
+
+impl<T: Send + 'static> TrackedPromise for ThreadPromise<T> {
+    type Value = T;
+    fn poll_state(&mut self) -> &ImmediateValueState<T> {
+        ThreadPromise::poll_state(self)
+    }
+    fn get_state(&self) -> &ImmediateValueState<T> {
+        ThreadPromise::get_state(self)
+    }
+    fn display_error(&self) -> Option<String> {
+        ThreadPromise::display_error(self)
+    }
+    fn with_on_error(self, hook: impl FnMut(&BoxedSendError) + Send + 'static) -> Self {
+        ThreadPromise::with_on_error(self, hook)
+    }
+    fn with_error_display(
+        self,
+        mapper: impl Fn(&BoxedSendError) -> String + Send + Sync + 'static,
+    ) -> Self {
+        ThreadPromise::with_error_display(self, mapper)
+    }
+}
+
+impl<T: Send + 'static, M> ProgressTrackedThreadPromise<T, M> {
+    /// create a new progress tracked thread promise
+    pub fn new(creator: impl FnOnce(StatusSender<M>) -> ThreadPromise<T>) -> Self {
+        let (sender, receiver) = channel();
+        let promise = creator(StatusSender(sender));
+        ProgressTracked::wrap(promise, StatusReceiver::Thread(receiver))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{Progress, StringStatus};
+
+    #[test]
+    fn thread_promise() {
+        let mut promise = ThreadPromise::new(|| {
+            sleep(Duration::from_millis(20));
+            Ok(34)
+        });
+        assert!(promise.poll_state().is_updating());
+        sleep(Duration::from_millis(100));
+        assert!(matches!(
+            promise.poll_state(),
+            ImmediateValueState::Success(34)
+        ));
+        assert_eq!(promise.take_value(), Some(34));
+        assert!(promise.poll_state().is_empty());
+
+        let mut panicking = ThreadPromise::<i32>::new(|| panic!("boom"));
+        sleep(Duration::from_millis(50));
+        assert_eq!(
+            panicking
+                .poll_state()
+                .get_result()
+                .unwrap()
+                .err()
+                .unwrap()
+                .to_string(),
+            "updater panicked: boom"
+        );
+    }
+
+    #[test]
+    fn progress_tracked_thread_promise() {
+        let mut promise = ProgressTrackedThreadPromise::new(|status| {
+            ThreadPromise::new(move || {
+                for percent in [0, 50, 100] {
+                    let _ = status.send(StringStatus::from_str(
+                        Progress::from_percent(percent),
+                        "working",
+                    ));
+                    sleep(Duration::from_millis(10));
+                }
+                Ok("done")
+            })
+        });
+        assert!(!promise.finished());
+        sleep(Duration::from_millis(100));
+        assert!(promise.poll_state().is_success());
+        assert_eq!(*promise.get_progress(), 1.0);
+        assert_eq!(promise.status_history().len(), 3);
+        assert!(promise.finished());
+        assert_eq!(
+            format!("{promise:?}"),
+            "ProgressTracked { promise: ThreadPromise { state: Success, has_value: true, .. }, \
+             progress: Progress(1.0), statuses: 3, .. }"
+        );
+        promise.clear_status_history();
+        promise.poll_state();
+        assert_eq!(*promise.get_progress(), 1.0);
+    }
+
+    #[test]
+    fn progress_coalescing() {
+        let mut promise = ProgressTrackedThreadPromise::new(|status| {
+            ThreadPromise::new(move || {
+                for percent in [0, 50, 100] {
+                    let _ = status.send(StringStatus::from_str(
+                        Progress::from_percent(percent),
+                        "working",
+                    ));
+                }
+                Ok("done")
+            })
+        })
+        .with_progress_coalescing();
+        sleep(Duration::from_millis(50));
+        assert!(promise.poll_state().is_success());
+        assert_eq!(promise.status_history().len(), 1);
+        assert_eq!(promise.coalesced_count(), 2);
+        assert_eq!(*promise.get_progress(), 1.0);
+    }
+}
@@ -0,0 +1,122 @@
+//! Opt-in logs of the state transitions of a promise, see `transition_log` on the promises.
+//! Off by default, a promise without a log only keeps an empty `Option`.
+use std::fmt::{self, Display, Formatter};
+use std::mem::{self, Discriminant};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::clock;
+
+/// How many transitions a log keeps, older ones are dropped
+pub const TRANSITION_LOG_CAPACITY: usize = 32;
+
+static ENABLED_BY_DEFAULT: AtomicBool = AtomicBool::new(false);
+
+/// Enable the transition log for all promises created from now on, e.g. in debug builds. Promises can also enable
+/// it one by one with `with_transition_log`.
+pub fn set_transition_log_default(enabled: bool) {
+    ENABLED_BY_DEFAULT.store(enabled, Ordering::Relaxed);
+}
+
+/// What made a promise change its state, see [`Transition`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransitionCause {
+    /// An update or a new future was started
+    Update,
+    /// An automatic retry of a failed update was started
+    Retry,
+    /// The updater or future reported progress, a result or an error
+    Updater,
+    /// The updater ended without finishing, e.g. it panicked
+    UpdaterEnded,
+    /// The update was cancelled
+    Cancelled,
+    /// The value or error was taken out of the promise
+    Taken,
+    /// The value was set directly
+    ValueSet,
+}
+
+impl Display for TransitionCause {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TransitionCause::Update => "update",
+            TransitionCause::Retry => "retry",
+            TransitionCause::Updater => "updater",
+            TransitionCause::UpdaterEnded => "updater ended",
+            TransitionCause::Cancelled => "cancelled",
+            TransitionCause::Taken => "taken",
+            TransitionCause::ValueSet => "value set",
+        })
+    }
+}
+
+/// An entry of a transition log, printed like `+1.2ms update -> updating (0%)`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Transition {
+    /// Time since the log was created with the promise
+    pub elapsed: Duration,
+    /// The state entered, as printed by its `Display` implementation
+    pub state: String,
+    /// What caused the transition
+    pub cause: TransitionCause,
+}
+
+impl Display for Transition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "+{:?} {} -> {}", self.elapsed, self.cause, self.state)
+    }
+}
+
+/// The last [`TRANSITION_LOG_CAPACITY`] transitions between the variants of the state `S`
+pub(crate) struct TransitionLog<S> {
+    created: Instant,
+    entries: Vec<Transition>,
+    last: Discriminant<S>,
+}
+
+impl<S: Display> TransitionLog<S> {
+    /// A log if enabled by [`set_transition_log_default`]
+    pub(crate) fn by_default(state: &S) -> Option<Box<Self>> {
+        ENABLED_BY_DEFAULT
+            .load(Ordering::Relaxed)
+            .then(|| Self::enabled(state))
+    }
+
+    /// An empty log, `state` is the current state which is not logged itself
+    pub(crate) fn enabled(state: &S) -> Box<Self> {
+        Box::new(TransitionLog {
+            created: clock::now(),
+            entries: Vec::new(),
+            last: mem::discriminant(state),
+        })
+    }
+
+    pub(crate) fn entries(log: &Option<Box<Self>>) -> &[Transition] {
+        log.as_ref().map_or(&[], |log| &log.entries)
+    }
+}
+
+/// Log `state` if the log is enabled and the state changed its variant
+pub(crate) fn record<S: Display>(
+    log: &mut Option<Box<TransitionLog<S>>>,
+    state: &S,
+    cause: TransitionCause,
+) {
+    let Some(log) = log else {
+        return;
+    };
+    let variant = mem::discriminant(state);
+    if log.last == variant {
+        return;
+    }
+    log.last = variant;
+    if log.entries.len() == TRANSITION_LOG_CAPACITY {
+        log.entries.remove(0);
+    }
+    log.entries.push(Transition {
+        elapsed: clock::since(log.created),
+        state: state.to_string(),
+        cause,
+    });
+}
@@ -0,0 +1,205 @@
+//! Transition times and debug records of lazy promise updates. Without the `log` feature, only the times are kept.
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::clock;
+use crate::{BoxedSendError, DataState, Progress};
+
+/// When the latest update of a lazy promise went through its states, see `transitions` on the lazy promises.
+/// Times are taken from tokio's clock, so paused time applies in tests.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TransitionTimes {
+    /// When the update was triggered, retries keep the time of the first try
+    pub update_started: Option<Instant>,
+    /// When the first item of the update arrived
+    pub first_item: Option<Instant>,
+    /// When the update finished with [`crate::DataState::UpToDate`] or [`crate::DataState::Error`]
+    pub settled: Option<Instant>,
+    /// The wall clock time of `settled`, e.g. to show "refreshed at 14:32"
+    pub settled_at: Option<SystemTime>,
+    /// When the last successful update finished, kept across updates
+    pub last_updated: Option<Instant>,
+    /// The wall clock time of `last_updated`, or of the restored cache, see `LazyVecPromise::restore_cache`
+    pub last_updated_at: Option<SystemTime>,
+}
+
+impl TransitionTimes {
+    /// How long the latest update took until it settled
+    pub fn duration(&self) -> Option<Duration> {
+        Some(
+            self.settled?
+                .saturating_duration_since(self.update_started?),
+        )
+    }
+}
+
+/// Tracks the current update of a lazy promise for its transition times and log records
+pub(crate) struct UpdateLog {
+    pub(crate) times: TransitionTimes,
+    /// how long an update is reported as updating at least, see `with_min_updating_display`
+    pub(crate) min_updating: Duration,
+    /// reported instead of a settled state which is held back
+    held: DataState,
+    #[cfg(feature = "log")]
+    started: Option<Instant>,
+    #[cfg(feature = "log")]
+    items: usize,
+    #[cfg(feature = "log")]
+    pub(crate) progress_step: f64,
+    #[cfg(feature = "log")]
+    next_milestone: f64,
+}
+
+#[cfg(feature = "log")]
+fn label(name: Option<&str>) -> &str {
+    name.unwrap_or("unnamed promise")
+}
+
+#[cfg_attr(not(feature = "log"), allow(unused_variables))]
+impl UpdateLog {
+    pub(crate) fn new() -> Self {
+        UpdateLog {
+            times: TransitionTimes::default(),
+            min_updating: Duration::ZERO,
+            held: DataState::Updating(Progress::from_percent(100)),
+            #[cfg(feature = "log")]
+            started: None,
+            #[cfg(feature = "log")]
+            items: 0,
+            #[cfg(feature = "log")]
+            progress_step: 0.25,
+            #[cfg(feature = "log")]
+            next_milestone: 0.0,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn update_triggered(&mut self, name: Option<&str>, retry: u32) {
+        if retry == 0 {
+            self.times.update_started = Some(clock::now());
+        }
+        self.times.first_item = None;
+        self.times.settled = None;
+        self.times.settled_at = None;
+        #[cfg(feature = "log")]
+        {
+            self.started = Some(clock::now());
+            self.items = 0;
+            self.next_milestone = self.progress_step;
+            if retry > 0 {
+                log::debug!("{}: update triggered, retry {retry}", label(name));
+            } else {
+                log::debug!("{}: update triggered", label(name));
+            }
+        }
+    }
+
+    #[inline]
+    pub(crate) fn item_received(&mut self, name: Option<&str>) {
+        if self.times.first_item.is_none() {
+            self.times.first_item = Some(clock::now());
+        }
+        #[cfg(feature = "log")]
+        {
+            self.items += 1;
+            if self.items == 1 {
+                log::debug!(
+                    "{}: first item received after {:?}",
+                    label(name),
+                    self.elapsed()
+                );
+            }
+        }
+    }
+
+    #[inline]
+    pub(crate) fn progress(&mut self, name: Option<&str>, progress: Progress) {
+        #[cfg(feature = "log")]
+        if self.progress_step > 0.0 && *progress >= self.next_milestone {
+            log::debug!("{}: progress {:.0}%", label(name), *progress * 100.0);
+            self.next_milestone =
+                ((*progress / self.progress_step).floor() + 1.0) * self.progress_step;
+        }
+    }
+
+    #[inline]
+    pub(crate) fn error(&mut self, name: Option<&str>, error: &BoxedSendError) {
+        self.settle();
+        #[cfg(feature = "log")]
+        log::debug!(
+            "{}: error after {:?}: {}",
+            label(name),
+            self.elapsed(),
+            error.full_message()
+        );
+    }
+
+    #[inline]
+    pub(crate) fn finished(&mut self, name: Option<&str>) {
+        self.times.last_updated = Some(self.settle());
+        self.times.last_updated_at = self.times.settled_at;
+        #[cfg(feature = "log")]
+        log::debug!(
+            "{}: finished with {} items after {:?}",
+            label(name),
+            self.items,
+            self.elapsed()
+        );
+    }
+
+    /// When the latest update is reported as settled, not before it ran for the minimum updating duration
+    fn shown(&self) -> Option<Instant> {
+        let started = self.times.update_started?;
+        Some(self.times.settled?.max(started + self.min_updating))
+    }
+
+    /// The state to report for `state`, a settled state is reported as updating until it is shown
+    pub(crate) fn reported<'a>(&'a self, state: &'a DataState) -> &'a DataState {
+        let settled = matches!(state, DataState::UpToDate | DataState::Error(_));
+        match self.shown() {
+            Some(shown) if settled && clock::now() < shown => &self.held,
+            _ => state,
+        }
+    }
+
+    /// How long `state` has been reported as settled
+    pub(crate) fn settled_for(&self, state: &DataState) -> Option<Duration> {
+        if !matches!(
+            self.reported(state),
+            DataState::UpToDate | DataState::Error(_)
+        ) {
+            return None;
+        }
+        clock::now().checked_duration_since(self.shown()?)
+    }
+
+    fn settle(&mut self) -> Instant {
+        let now = clock::now();
+        self.times.settled = Some(now);
+        self.times.settled_at = Some(SystemTime::now());
+        now
+    }
+
+    #[cfg(feature = "log")]
+    fn elapsed(&self) -> Duration {
+        self.started.map(clock::since).unwrap_or_default()
+    }
+}
+
+#[cfg(all(test, feature = "log"))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn progress_milestones() {
+        let mut log = UpdateLog::new();
+        log.update_triggered(Some("test"), 0);
+        assert_eq!(log.next_milestone, 0.25);
+        log.progress(None, Progress::from_percent(10));
+        assert_eq!(log.next_milestone, 0.25);
+        log.progress(None, Progress::from_percent(60));
+        assert_eq!(log.next_milestone, 0.75);
+        log.item_received(None);
+        log.item_received(None);
+        assert_eq!(log.items, 2);
+    }
+}
@@ -0,0 +1,113 @@
+//! Reporting progress with static status messages must not allocate per message,
+//! the tokio channel reuses its blocks and the history only grows amortized
+// with async-std, the updater runs on another thread and its allocations aren't counted
+#![cfg(all(feature = "tokio", not(feature = "async-std")))]
+use lazy_async_promise::{ImmediateValuePromise, Progress, ProgressTrackedImValProm, StringStatus};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use tokio::sync::mpsc::Sender;
+
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+fn count_allocation() {
+    let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+}
+
+// SAFETY: forwards everything to the system allocator, only counting the calls of the current thread
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count_allocation();
+        System.alloc(layout)
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count_allocation();
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+const ROUNDS: usize = 1000;
+const BATCH: usize = 16;
+
+async fn report(sender: Sender<StringStatus>, message: fn() -> StringStatus) {
+    for _ in 0..ROUNDS {
+        for _ in 0..BATCH {
+            sender.send(message()).await.unwrap();
+        }
+        tokio::task::yield_now().await;
+    }
+}
+
+/// Runs the sender and the polling loop on the current thread and counts the allocations of the steady state,
+/// returns them together with the length of the final status history
+fn allocations_while_reporting(message: fn() -> StringStatus, coalesce: bool) -> (usize, usize) {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    runtime.block_on(async {
+        let mut promise = ProgressTrackedImValProm::new(
+            |s| {
+                ImmediateValuePromise::new(async move {
+                    report(s, message).await;
+                    Ok(())
+                })
+            },
+            BATCH,
+        );
+        if coalesce {
+            promise = promise.with_progress_coalescing();
+        }
+        // spawning and the first blocks of the channel are not part of the steady state
+        let _ = promise.poll_state();
+        tokio::task::yield_now().await;
+        let _ = promise.poll_state();
+        let before = ALLOCATIONS.with(Cell::get);
+        for _ in 2..ROUNDS {
+            tokio::task::yield_now().await;
+            let _ = promise.poll_state();
+        }
+        let allocations = ALLOCATIONS.with(Cell::get) - before;
+        while promise.poll_state().is_updating() {
+            tokio::task::yield_now().await;
+        }
+        (allocations, promise.status_history().len())
+    })
+}
+
+fn static_status() -> StringStatus {
+    StringStatus::from_str(Progress::from_percent(50), "hashing")
+}
+
+fn owned_status() -> StringStatus {
+    StringStatus::from_string(Progress::from_percent(50), "hashing".to_string())
+}
+
+#[test]
+fn static_messages_dont_allocate_per_message() {
+    let (allocations, history) = allocations_while_reporting(static_status, false);
+    assert_eq!(history, ROUNDS * BATCH);
+    // only the amortized growth of the history is left
+    assert!(allocations < 32, "{allocations} allocations");
+
+    let (allocations, history) = allocations_while_reporting(static_status, true);
+    assert!(history <= ROUNDS);
+    assert!(allocations < 32, "{allocations} allocations");
+}
+
+#[test]
+fn owned_messages_allocate_per_message() {
+    let (allocations, _) = allocations_while_reporting(owned_status, false);
+    assert!(
+        allocations >= (ROUNDS - 2) * BATCH,
+        "{allocations} allocations"
+    );
+}
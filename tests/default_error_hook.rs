@@ -0,0 +1,30 @@
+//! The default error hook is process-wide, so it is tested in its own binary where no other tests report errors
+#![cfg(feature = "tokio")]
+use std::sync::Mutex;
+use std::time::Duration;
+
+use lazy_async_promise::{
+    clear_default_on_error, set_default_on_error, BoxedSendError, ImmediateValuePromise,
+};
+
+static MESSAGES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+#[tokio::test]
+async fn default_on_error_hook() {
+    set_default_on_error(|error| MESSAGES.lock().unwrap().push(error.to_string()));
+    let mut own_hook =
+        ImmediateValuePromise::<()>::new(async { Err(BoxedSendError::msg("own hook")) })
+            .with_on_error(|_| {});
+    let mut default_hook =
+        ImmediateValuePromise::<()>::new(async { Err(BoxedSendError::msg("default hook")) });
+    while own_hook.poll_state().is_updating() || default_hook.poll_state().is_updating() {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    clear_default_on_error();
+    let mut after_clear =
+        ImmediateValuePromise::<()>::new(async { Err(BoxedSendError::msg("cleared")) });
+    while after_clear.poll_state().is_updating() {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    assert_eq!(*MESSAGES.lock().unwrap(), ["default hook"]);
+}